@@ -0,0 +1,305 @@
+use std::{
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use crate::{
+    compiler_ir::IR,
+    env::{self, Env},
+    error::Error,
+    formatter, graph, ir_builder,
+    layout::{self, Report},
+    location::Location,
+    parser, unchecked_env,
+};
+
+/// Splits arguments into flags and positional values.
+/// Flags may carry a value with `--flag=value`.
+struct Args<'a> {
+    flags: Vec<&'a str>,
+    values: Vec<&'a str>,
+}
+
+impl<'a> Args<'a> {
+    fn new(args: &'a [String]) -> Self {
+        let (flags, values) = args
+            .iter()
+            .map(|a| a.as_str())
+            .partition(|a| a.starts_with("--"));
+        Self { flags, values }
+    }
+
+    fn has_flag(&self, flag: &str) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// Returns the value of a `--flag=value` argument.
+    fn flag_value(&self, flag: &str) -> Option<&'a str> {
+        self.flags.iter().find_map(|f| match f.split_once('=') {
+            Some((name, value)) if name == flag => Some(value),
+            _ => None,
+        })
+    }
+
+    /// Returns an error for the first flag that isn't in the allowed list.
+    fn check_flags(&self, allowed: &[&str]) -> Result<(), String> {
+        let is_unknown = |f: &&&str| !allowed.contains(&f.split('=').next().unwrap_or_default());
+        match self.flags.iter().find(is_unknown) {
+            Some(flag) => Err(format!("Unknown flag '{}'", flag)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Formats the given files in place. Formats stdin to stdout if no files are given.
+/// With `--check` nothing is written and a failure is returned if any file is not formatted.
+pub fn fmt(args: &[String]) -> ExitCode {
+    let args = Args::new(args);
+    if let Err(e) = args.check_flags(&["--check"]) {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
+    let check = args.has_flag("--check");
+
+    if args.values.is_empty() {
+        let mut text = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut text) {
+            eprintln!("Unable to read stdin: {}", e);
+            return ExitCode::FAILURE;
+        }
+
+        let asts = match parser::parse(&text, Location::default()) {
+            Ok(asts) => asts,
+            Err(errors) => {
+                print_errors(&errors);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let formatted = formatter::format(&asts);
+        if check {
+            return if formatted == text {
+                ExitCode::SUCCESS
+            } else {
+                eprintln!("<stdin> is not formatted");
+                ExitCode::FAILURE
+            };
+        }
+
+        print!("{}", formatted);
+        return ExitCode::SUCCESS;
+    }
+
+    let mut success = true;
+    for path in args.values.iter().map(PathBuf::from) {
+        let (text, asts) = match load(&path) {
+            Ok(loaded) => loaded,
+            Err(errors) => {
+                print_errors(&errors);
+                success = false;
+                continue;
+            }
+        };
+
+        let formatted = formatter::format(&asts);
+        if formatted == text {
+            continue;
+        }
+
+        if check {
+            eprintln!("{} is not formatted", path.display());
+            success = false;
+        } else if let Err(e) = std::fs::write(&path, formatted) {
+            eprintln!("Unable to write {}: {}", path.display(), e);
+            success = false;
+        }
+    }
+
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Prints the memory layout of every type and component store in the given files.
+/// With `--json` the report is printed as JSON. With `--max-bytes=N` a failure is returned
+/// if the world and its rollback snapshots need more than N bytes.
+pub fn layout(args: &[String]) -> ExitCode {
+    let args = Args::new(args);
+    if let Err(e) = args.check_flags(&["--json", "--max-bytes"]) {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
+
+    let max_bytes = match args.flag_value("--max-bytes").map(|v| v.parse::<usize>()) {
+        Some(Ok(max_bytes)) => Some(max_bytes),
+        Some(Err(_)) => {
+            eprintln!("--max-bytes must be a number of bytes");
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+
+    if args.values.is_empty() {
+        eprintln!("No input files");
+        return ExitCode::FAILURE;
+    }
+
+    let (env, ir) = match build(&args.values) {
+        Ok(built) => built,
+        Err(errors) => {
+            print_errors(&errors);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let report = Report::new(&env, &ir);
+    if args.has_flag("--json") {
+        println!("{}", report.to_json().to_pretty_string());
+    } else {
+        print!("{}", report.to_table());
+    }
+
+    match max_bytes {
+        Some(max_bytes) if report.total > max_bytes => {
+            eprintln!(
+                "World needs {} bytes which exceeds the budget of {} bytes",
+                report.total, max_bytes
+            );
+            ExitCode::FAILURE
+        }
+        _ => ExitCode::SUCCESS,
+    }
+}
+
+/// Prints the JSON schema of every struct and component in the given files.
+pub fn schema(args: &[String]) -> ExitCode {
+    let args = Args::new(args);
+    if let Err(e) = args.check_flags(&[]) {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
+
+    if args.values.is_empty() {
+        eprintln!("No input files");
+        return ExitCode::FAILURE;
+    }
+
+    match build(&args.values) {
+        Ok((env, _)) => {
+            println!("{}", env::schema(&env).to_pretty_string());
+            ExitCode::SUCCESS
+        }
+        Err(errors) => {
+            print_errors(&errors);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Prints the world schedule and the components each system reads and writes as a graph.
+/// Prints Graphviz DOT by default, or a Mermaid flowchart with `--mermaid`.
+/// Worlds that fail the checks, e.g. with conflicting systems in a stage, are still drawn with the
+/// conflicts highlighted. Their errors are printed afterwards and a failure is returned.
+pub fn graph(args: &[String]) -> ExitCode {
+    let args = Args::new(args);
+    if let Err(e) = args.check_flags(&["--mermaid"]) {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
+
+    if args.values.is_empty() {
+        eprintln!("No input files");
+        return ExitCode::FAILURE;
+    }
+
+    let asts = match load_all(&args.values) {
+        Ok(asts) => asts,
+        Err(errors) => {
+            print_errors(&errors);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (env, errors) = env::build_partial(unchecked_env::build(asts));
+    if let Some(env) = env {
+        if args.has_flag("--mermaid") {
+            print!("{}", graph::to_mermaid(&env));
+        } else {
+            print!("{}", graph::to_dot(&env));
+        }
+    }
+
+    if errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        print_errors(&errors);
+        ExitCode::FAILURE
+    }
+}
+
+/// Loads all files and builds them into a checked env and IR.
+fn build(paths: &[&str]) -> Result<(Env, Vec<IR>), Vec<Error>> {
+    let asts = load_all(paths)?;
+    let env = env::build(unchecked_env::build(asts))?;
+    let mut ir = ir_builder::build(env.clone())?;
+    layout::reorder_fields(&mut ir);
+    Ok((env, ir))
+}
+
+/// Reads and parses all files, returning the errors of every file that fails.
+fn load_all(paths: &[&str]) -> Result<Vec<parser::Ast>, Vec<Error>> {
+    let mut asts = vec![];
+    let mut errors = vec![];
+    for path in paths {
+        match load(Path::new(path)) {
+            Ok((_, mut file_asts)) => asts.append(&mut file_asts),
+            Err(mut file_errors) => errors.append(&mut file_errors),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(asts)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Reads and parses the file at the given path.
+fn load(path: &Path) -> Result<(String, Vec<parser::Ast>), Vec<Error>> {
+    let start_location = Location::File {
+        path: path.to_path_buf(),
+        line: 0,
+        column: 0,
+    };
+
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        Error::new(
+            format!("Unable to read file: {}", e),
+            start_location.clone(),
+        )
+    })?;
+
+    let asts = parser::parse(&text, start_location)?;
+    Ok((text, asts))
+}
+
+/// Prints errors as `file:line:column: message`, with one based lines and columns.
+fn print_errors(errors: &[Error]) {
+    for error in errors {
+        let file = match error.location.get_file() {
+            Some(path) => format!("{}:", path.display()),
+            None => String::new(),
+        };
+
+        eprintln!(
+            "{}{}:{}: error: {}",
+            file,
+            error.location.line() + 1,
+            error.location.column() + 1,
+            error.message
+        );
+    }
+}
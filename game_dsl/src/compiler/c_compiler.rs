@@ -2,7 +2,9 @@ use super::{Artifact, Compiler, OutputBuilder};
 use crate::{
     compiler::Target,
     compiler_ir::*,
-    parser::{ListType, Listable, Primitive, PrimitiveType},
+    parser::{
+        is_fractional, ListMethod, ListType, Primitive, PrimitiveType, UnaryOp, TARGET_C_IMPL_ID,
+    },
 };
 
 pub struct CCompiler;
@@ -11,16 +13,23 @@ impl Compiler for CCompiler {
     fn compile_artifacts(
         &self,
         structs: Vec<Commentable<Struct>>,
-        expressions: Vec<Commentable<Expression>>,
+        stores: Vec<Store>,
+        systems: Vec<Commentable<System>>,
+        world: Option<World>,
     ) -> Vec<Artifact> {
-        let mut output = OutputBuilder::new("C", "\t", "//");
+        let mut output = OutputBuilder::new("C", "\t", "//")
+            .with_doc_comment_style(Target::C.doc_comment_style());
 
         // Build out includes
         output.add_section("Includes");
 
+        output.push_line("#include <stddef.h>");
         output.push_line("#include <stdint.h>");
         output.push_line("#include <stdbool.h>");
         output.push_line("#include <stdio.h>");
+        output.push_line("#include <string.h>");
+        output.push_line("#include <stdlib.h>");
+        output.push_line("#include <math.h>");
 
         output.add_line();
 
@@ -28,60 +37,36 @@ impl Compiler for CCompiler {
         output.add_section("Forward declarations");
 
         for s in structs.iter() {
-            output.add_comments(&s.comments);
             let s = &s.value;
-            output.push_line(&format!("struct {};", s.id));
+            output.push_line(&format!("{} {};", c_keyword(s), s.id));
         }
 
         output.add_line();
 
-        // // Build out forward declarations for expressions
-        // for e in expressions.iter() {
-        //     output.add_comments(&e.comments);
-
-        //     let e = &e.value;
-        //     let ret_type = primitive_type(&e.return_type);
-        //     let id = &e.id;
-        //     let args = e
-        //         .args
-        //         .iter()
-        //         .map(|arg| format!("{} {}", primitive_type(&arg.ty), arg.id))
-        //         .collect::<Vec<_>>()
-        //         .join(", ");
-        //     output.push_line(&format!("{ret_type} {id}({args});"));
-        //     output.add_line();
-        // }
-
         output.add_line();
 
         // Build out structs
         output.add_section("Structs");
 
         for s in structs.iter() {
-            output.add_comments(&s.comments);
+            add_enum_tags(&s.value, &mut output);
+            output.add_doc_comments(&s.comments);
 
             let s = &s.value;
 
-            output.push_line(&format!("typedef struct {} {{", s.id));
+            output.push_line(&format!("typedef {} {} {{", c_keyword(s), s.id));
             output.indent();
             for field in s.fields.iter() {
-                let mut ptr_value = String::default();
-                let array_type = {
-                    match &field.ty {
-                        ListType::List { ty, max_size } => {
-                            if !ty.is_identifier() {
-                                ptr_value = "*".to_string();
-                            }
-                            format!("[{}]", max_size)
-                        }
-                        ListType::Single(_) => "".to_string(),
-                    }
+                output.add_doc_comments(&field.comments);
+
+                let array_type = match &field.ty {
+                    ListType::List { max_size, .. } => format!("[{}]", max_size),
+                    ListType::Single(_) => "".to_string(),
                 };
 
                 output.push_line(&format!(
-                    "{ty}{ptr_value} {id}{array_type};",
+                    "{ty} {id}{array_type};",
                     ty = primitive_type(&field.ty),
-                    ptr_value = ptr_value,
                     id = field.id,
                     array_type = array_type
                 ));
@@ -93,125 +78,2739 @@ impl Compiler for CCompiler {
 
         output.add_line();
 
-        // // Build out expressions
-        // output.add_section("Expressions");
-        // for e in expressions.iter() {
-        //     output.add_comments(&e.comments);
-        //     let e = &e.value;
-
-        //     let ret_type = primitive_type(&e.return_type);
-        //     let id = &e.id;
-        //     let args = e
-        //         .args
-        //         .iter()
-        //         .map(|arg| format!("{} {}", primitive_type(&arg.ty), arg.id))
-        //         .collect::<Vec<_>>()
-        //         .join(", ");
-        //     output.push_line(&format!(
-        //         "{ret_type} {id}({args}) {{",
-        //         ret_type = ret_type,
-        //         id = id,
-        //         args = args
-        //     ));
-        //     output.indent();
-        //     // Body
-        //     {
-        //         for expression in e.body.iter() {
-        //             if let ExpressionStatement::Comment(comment) = expression {
-        //                 output.add_comment(comment);
-        //             } else {
-        //                 output.add_indentation();
-        //                 eval_expression(expression, &mut output);
-        //                 output.push(";");
-        //                 output.add_line();
-        //             }
-        //         }
-        //     }
-        //     output.unindent();
-        //     output.push_line("}");
-        //     output.add_line();
-        // }
+        output.add_section("List operations");
+        for s in structs.iter().filter(|s| s.value.list.is_some()) {
+            add_list_operations(&s.value, &mut output);
+        }
+
+        // Build out reflection tables
+        output.add_section("Reflection");
+        add_reflection(&structs, &stores, &mut output);
+
+        // Build out component store accessors
+        output.add_section("Component store accessors");
+
+        for store in stores.iter() {
+            if store.event {
+                add_event_accessors(store, &mut output);
+            } else if store.single {
+                add_single_accessors(store, &mut output);
+            } else {
+                add_store_accessors(store, &mut output);
+                add_store_membership(store, &mut output);
+            }
+        }
+
+        // Build out JSON import and export
+        output.add_section("JSON import and export");
+        for line in JSON_HELPERS.lines() {
+            output.push_line(line);
+        }
+        output.add_line();
+
+        for s in structs.iter() {
+            let s = &s.value;
+            // Unions are written by the enum holding them, which knows the variant
+            if s.union {
+                continue;
+            }
+            // Stores and the world only write their live entries, which needs the accessors
+            let is_store = stores.iter().any(|store| store.id == s.id);
+            let is_world = world.as_ref().is_some_and(|w| w.id == s.id);
+            if is_store || is_world {
+                continue;
+            }
+            if s.list.is_some() {
+                add_list_json(s, &mut output);
+            } else if s.variants.is_empty() {
+                add_struct_json(s, &mut output);
+            } else {
+                add_enum_json(s, &mut output);
+            }
+            add_json_functions(&s.id, FIELDS_JSON_NOTE, &mut output);
+        }
+
+        if let Some(world) = &world {
+            for store in stores.iter().filter(|s| !s.event) {
+                add_store_json(store, &mut output);
+            }
+            add_world_json(world, &stores, &mut output);
+            add_json_functions(&world.id, WORLD_JSON_NOTE, &mut output);
+        }
+
+        if let Some(world) = &world {
+            output.add_section("World init");
+            add_world_init(world, &mut output);
+
+            output.add_section("World single components");
+            for store in stores.iter().filter(|s| s.single) {
+                add_world_single(world, store, &mut output);
+            }
+
+            output.add_section("World save and load");
+            add_save_helpers(&mut output);
+            add_world_save(world, &mut output);
+            add_world_load(world, &stores, &mut output);
+
+            output.add_section("World snapshots");
+            add_world_snapshots(world, &mut output);
+
+            output.add_section("Systems");
+            for system in systems.iter() {
+                add_system(system, world, &mut output);
+            }
+
+            output.add_section("World dispatch");
+            add_world_dispatch(world, &stores, &mut output);
+        }
+
+        // Host files are part of this translation unit so they can use every generated type
+        let impls = c_impls(&systems);
+        if world.is_some() && !impls.is_empty() {
+            output.add_section("External systems");
+            for (path, _) in impls.iter() {
+                output.push_line(&format!("#include \"{}\"", path));
+            }
+            output.add_line();
+        }
 
         // TODO: remove
         {
-            // Add simple 'main' for compilation purposes
+            // Add simple 'main' for compilation purposes, hosts with their own define D_NO_MAIN
+            output.push_line(&format!("#ifndef {NO_MAIN_ID}"));
             output.push_line("int main() { printf(\"Hello, world!\"); }");
+            output.push_line("#endif");
         }
 
         let text = output.build();
 
-        vec![Artifact {
+        let mut artifacts = vec![Artifact {
             target: Target::C,
             path: "main.c".into(),
             contents: text,
-        }]
+            stub: false,
+        }];
+        if let Some(world) = &world {
+            for (path, systems) in impls {
+                artifacts.push(external_stub(path, &systems, world));
+            }
+        }
+
+        artifacts
     }
 }
 
-fn eval_expression(expression: &ExpressionStatement, output: &mut OutputBuilder) {
-    match expression {
-        ExpressionStatement::Literal(literal) => match literal {
-            Literal::Int(i) => output.push(&format!("{}", i)),
-            Literal::Bool(b) => output.push(&format!("{}", b)),
-            Literal::String(s) => output.push(&format!("\"{}\";", s)),
-            Literal::Identifier(id) => output.push(&format!("{}", id)),
-        },
-        ExpressionStatement::Return(expression) => {
-            output.push("return ");
-            eval_expression(expression, output);
-        }
-        ExpressionStatement::Call { id, args } => {
-            output.push(&format!("{}(", id));
-            for (i, arg) in args.iter().enumerate() {
-                eval_expression(arg, output);
-                if i != args.len() - 1 {
-                    output.push(", ");
-                }
+fn c_keyword(s: &Struct) -> &'static str {
+    if s.union {
+        "union"
+    } else {
+        "struct"
+    }
+}
+
+/// Adds the constants of the tags of an enum's variants.
+fn add_enum_tags(s: &Struct, output: &mut OutputBuilder) {
+    if s.variants.is_empty() {
+        return;
+    }
+
+    output.push_line("enum {");
+    output.indent();
+    for (tag, variant) in s.variants.iter().enumerate() {
+        output.add_doc_comments(&variant.comments);
+        output.push_line(&format!("{} = {tag},", variant.tag));
+    }
+    output.unindent();
+    output.push_line("};");
+    output.add_line();
+}
+
+/// Returns the type and max size of the items of a list struct.
+fn list_items(s: &Struct) -> (&PrimitiveType, usize) {
+    let items = s.fields.iter().find_map(|f| match &f.ty {
+        ListType::List { ty, max_size } => Some((&ty.primitive_type, *max_size)),
+        ListType::Single(_) => None,
+    });
+    items.unwrap_or((&PrimitiveType::U32, 0))
+}
+
+/// Adds the functions behind the methods of a list. Lists never grow past their max size, so
+/// pushing onto a full list leaves it unchanged and returns false.
+fn add_list_operations(s: &Struct, output: &mut OutputBuilder) {
+    let id = &s.id;
+    let (ty, max_size) = list_items(s);
+    let item = base_primitive(ty);
+
+    output.add_doc_comments(&[
+        "Adds the item to the end of the list. Returns false if the list is full.".to_string(),
+    ]);
+    output.push_line(&format!(
+        "static inline bool {id}_push({id}* list, {item} item) {{"
+    ));
+    output.indent();
+    output.push_line(&format!("if (list->length >= {max_size}) {{"));
+    output.indent();
+    output.push_line("return false;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("list->items[list->length++] = item;");
+    output.push_line("return true;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(&[
+        "Removes the last item and returns it, or zero if the list is empty.".to_string(),
+    ]);
+    output.push_line(&format!("static inline {item} {id}_pop({id}* list) {{"));
+    output.indent();
+    output.push_line("if (list->length == 0) {");
+    output.indent();
+    output.push_line(&format!("{item} zero = {{ 0 }};"));
+    output.push_line("return zero;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("return list->items[--list->length];");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.push_line(&format!("static inline void {id}_clear({id}* list) {{"));
+    output.indent();
+    output.push_line("list->length = 0;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(&[
+        "Removes the item at the index, moving the items after it down.".to_string(),
+        "Returns false if the index is past the end of the list.".to_string(),
+    ]);
+    output.push_line(&format!(
+        "static inline bool {id}_remove_at({id}* list, uint32_t index) {{"
+    ));
+    output.indent();
+    output.push_line("if (index >= list->length) {");
+    output.indent();
+    output.push_line("return false;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("memmove(&list->items[index], &list->items[index + 1], (list->length - index - 1) * sizeof(list->items[0]));");
+    output.push_line("list->length--;");
+    output.push_line("return true;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    let key = match &s.list {
+        Some(ItemEquality::Value) => "",
+        Some(ItemEquality::Field(field)) => &format!(".{field}"),
+        _ => return,
+    };
+    output.push_line(&format!(
+        "static inline bool {id}_contains(const {id}* list, {item} item) {{"
+    ));
+    output.indent();
+    output.push_line("for (uint32_t i = 0; i < list->length; i++) {");
+    output.indent();
+    output.push_line(&format!("if (list->items[i]{key} == item{key}) {{"));
+    output.indent();
+    output.push_line("return true;");
+    output.unindent();
+    output.push_line("}");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("return false;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+/// Adds a type info table per struct describing its fields, along with a list of the component
+/// types so inspectors can enumerate and edit any component without knowing it ahead of time.
+fn add_reflection(structs: &[Commentable<Struct>], stores: &[Store], output: &mut OutputBuilder) {
+    for line in REFLECTION_TYPES.lines() {
+        output.push_line(line);
+    }
+    output.add_line();
+
+    // Structs are in dependency order, so nested type infos are always declared first
+    for s in structs.iter() {
+        let s = &s.value;
+        let id = &s.id;
+
+        let fields = if s.fields.is_empty() {
+            "NULL".to_string()
+        } else {
+            output.push_line(&format!("static const D_FIELD_INFO {id}_field_info[] = {{"));
+            output.indent();
+            for field in s.fields.iter() {
+                let primitive = &field.ty.inner_ty().primitive_type;
+                let tag = type_tag(primitive);
+                let ty = match primitive {
+                    PrimitiveType::Identifier(ty) => format!("&{ty}_type_info"),
+                    _ => "NULL".to_string(),
+                };
+                let length = match &field.ty {
+                    ListType::List { max_size, .. } => *max_size,
+                    ListType::Single(_) => 0,
+                };
+                output.push_line(&format!(
+                    "{{ \"{}\", {tag}, {ty}, offsetof({id}, {}), {length} }},",
+                    field.id, field.id
+                ));
             }
-            output.push(")");
-        }
-        ExpressionStatement::NativeExpression(native) => {
-            //
-            match native {
-                NativeExpression::Add { lhs, rhs } => {
-                    output.push("(");
-                    eval_expression(lhs, output);
-                    output.push(" + ");
-                    eval_expression(rhs, output);
-                    output.push(")");
-                }
-                NativeExpression::Multiply { lhs, rhs } => {
-                    output.push("(");
-                    eval_expression(lhs, output);
-                    output.push(" * ");
-                    eval_expression(rhs, output);
-                    output.push(")");
+            output.unindent();
+            output.push_line("};");
+            format!("{id}_field_info")
+        };
+
+        output.push_line(&format!(
+            "const D_TYPE_INFO {id}_type_info = {{ \"{}\", sizeof({id}), {}, {fields} }};",
+            s.name,
+            s.fields.len()
+        ));
+        output.add_line();
+    }
+
+    output.add_doc_comments(&["The type info of every component.".to_string()]);
+    let components: Vec<String> = stores
+        .iter()
+        .map(|store| format!("&{}_type_info", store.component))
+        .collect();
+    // C doesn't allow empty arrays
+    let table = if components.is_empty() {
+        "NULL".to_string()
+    } else {
+        components.join(", ")
+    };
+    output.push_line(&format!(
+        "const D_TYPE_INFO* const d_component_types[] = {{ {table} }};"
+    ));
+    output.push_line(&format!(
+        "const size_t d_component_type_count = {};",
+        components.len()
+    ));
+    output.add_line();
+}
+
+fn type_tag(ty: &PrimitiveType) -> &'static str {
+    match ty {
+        PrimitiveType::U32 => "D_TYPE_U32",
+        PrimitiveType::U64 => "D_TYPE_U64",
+        PrimitiveType::I32 => "D_TYPE_I32",
+        PrimitiveType::I64 => "D_TYPE_I64",
+        PrimitiveType::F32 => "D_TYPE_F32",
+        PrimitiveType::F64 => "D_TYPE_F64",
+        PrimitiveType::Bool => "D_TYPE_BOOL",
+        PrimitiveType::Char => "D_TYPE_CHAR",
+        PrimitiveType::Identifier(_) => "D_TYPE_STRUCT",
+    }
+}
+
+const REFLECTION_TYPES: &str = "typedef enum D_TYPE_TAG {
+\tD_TYPE_U32,
+\tD_TYPE_U64,
+\tD_TYPE_I32,
+\tD_TYPE_I64,
+\tD_TYPE_F32,
+\tD_TYPE_F64,
+\tD_TYPE_BOOL,
+\tD_TYPE_CHAR,
+\tD_TYPE_STRUCT,
+} D_TYPE_TAG;
+
+struct D_TYPE_INFO;
+
+typedef struct D_FIELD_INFO {
+\tconst char* name;
+\tD_TYPE_TAG tag;
+\t/* The type of struct fields, NULL for primitives. */
+\tconst struct D_TYPE_INFO* type;
+\tsize_t offset;
+\t/* The length of array fields, 0 for single values. */
+\tsize_t length;
+} D_FIELD_INFO;
+
+typedef struct D_TYPE_INFO {
+\tconst char* name;
+\tsize_t size;
+\tsize_t field_count;
+\tconst D_FIELD_INFO* fields;
+} D_TYPE_INFO;";
+
+/// Adds functions to get and set the component at an index of the store.
+/// SoA stores gather and scatter the component's fields across their arrays.
+fn add_store_accessors(store: &Store, output: &mut OutputBuilder) {
+    // Tag components have no data to access
+    if store.fields.is_empty() {
+        return;
+    }
+
+    let Store { id, component, .. } = store;
+
+    output.add_doc_comments(&["Returns a copy of the component at the given index.".to_string()]);
+    output.push_line(&format!(
+        "static inline {component} {id}_get(const {id}* store, uint64_t index) {{"
+    ));
+    output.indent();
+    if store.soa {
+        output.push_line(&format!("{component} component;"));
+        for field in store.fields.iter() {
+            output.push_line(&format!(
+                "component.{} = store->{}[index];",
+                field.id, field.array
+            ));
+        }
+        output.push_line("return component;");
+    } else {
+        output.push_line("return store->components[index];");
+    }
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(&[
+        "Sets the component at the given index, flagging it as changed.".to_string(),
+    ]);
+    output.push_line(&format!(
+        "static inline void {id}_set({id}* store, uint64_t index, {component} component) {{"
+    ));
+    output.indent();
+    if store.soa {
+        for field in store.fields.iter() {
+            output.push_line(&format!(
+                "store->{}[index] = component.{};",
+                field.array, field.id
+            ));
+        }
+    } else {
+        output.push_line("store->components[index] = component;");
+    }
+    output.push_line("store->changed[index] = true;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+/// Adds functions to get and set the instance of a single component.
+fn add_single_accessors(store: &Store, output: &mut OutputBuilder) {
+    // Tag components have no data to access
+    if store.fields.is_empty() {
+        return;
+    }
+
+    let Store { id, component, .. } = store;
+
+    output.add_doc_comments(&["Returns a copy of the single component.".to_string()]);
+    output.push_line(&format!(
+        "static inline {component} {id}_get(const {id}* store) {{"
+    ));
+    output.indent();
+    output.push_line("return store->instance;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(&["Sets the single component, flagging it as changed.".to_string()]);
+    output.push_line(&format!(
+        "static inline void {id}_set({id}* store, {component} component) {{"
+    ));
+    output.indent();
+    output.push_line("store->instance = component;");
+    output.push_line("store->changed = true;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+/// Adds functions for systems and the host to push and read the events of a queue.
+/// The queue is a ring, so pushing onto a full queue drops the oldest event.
+fn add_event_accessors(store: &Store, output: &mut OutputBuilder) {
+    let Store {
+        id,
+        component,
+        capacity,
+        ..
+    } = store;
+    let is_tag = store.fields.is_empty();
+
+    let (doc, event_param) = if is_tag {
+        (
+            "Pushes the event onto the queue.".to_string(),
+            String::new(),
+        )
+    } else {
+        (
+            "Pushes the event onto the queue, dropping the oldest event if it's full.".to_string(),
+            format!(", {component} event"),
+        )
+    };
+    output.add_doc_comments(&[
+        doc,
+        format!(
+            "Returns false if the queue already held {} events this tick.",
+            capacity
+        ),
+    ]);
+    output.push_line(&format!(
+        "static inline bool {id}_push({id}* store{event_param}) {{"
+    ));
+    output.indent();
+    output.push_line(&format!("bool is_full = store->count == {capacity};"));
+    if !is_tag {
+        output.push_line(&format!(
+            "store->events[(store->start + store->count) % {capacity}] = event;"
+        ));
+    }
+    output.push_line("if (is_full) {");
+    output.indent();
+    if !is_tag {
+        output.push_line(&format!("store->start = (store->start + 1) % {capacity};"));
+    }
+    output.push_line("return false;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("store->count++;");
+    output.push_line("return true;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    if !is_tag {
+        output.add_doc_comments(&[
+            "Returns a copy of the event at the given position, starting from the oldest."
+                .to_string(),
+        ]);
+        output.push_line(&format!(
+            "static inline {component} {id}_get(const {id}* store, uint64_t index) {{"
+        ));
+        output.indent();
+        output.push_line(&format!(
+            "return store->events[(store->start + index) % {capacity}];"
+        ));
+        output.unindent();
+        output.push_line("}");
+        output.add_line();
+    }
+
+    output.add_doc_comments(&["Empties the queue.".to_string()]);
+    output.push_line(&format!("static inline void {id}_clear({id}* store) {{"));
+    output.indent();
+    output.push_line("store->start = 0;");
+    output.push_line("store->count = 0;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+/// Adds functions to find, add and remove the component of an entity through the store's indices.
+/// Removing moves the last component into the freed slot so the components stay packed.
+fn add_store_membership(store: &Store, output: &mut OutputBuilder) {
+    let Store {
+        id,
+        component,
+        capacity,
+        entity,
+        max_entities,
+        ..
+    } = store;
+    let is_tag = store.fields.is_empty();
+
+    output.add_doc_comments(&[
+        "Returns the index of the entity's component, or the capacity of the store if it has none."
+            .to_string(),
+    ]);
+    output.push_line(&format!(
+        "static inline uint64_t {id}_index(const {id}* store, {entity} entity) {{"
+    ));
+    output.indent();
+    output.push_line(&format!(
+        "if (entity.value >= {max_entities} || store->indices[entity.value] == 0) {{"
+    ));
+    output.indent();
+    output.push_line(&format!("return {capacity};"));
+    output.unindent();
+    output.push_line("}");
+    output.push_line("return store->indices[entity.value] - 1;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(&[
+        "Returns whether the entity has a component in the store.".to_string()
+    ]);
+    output.push_line(&format!(
+        "static inline bool {id}_has(const {id}* store, {entity} entity) {{"
+    ));
+    output.indent();
+    output.push_line(&format!("return {id}_index(store, entity) < {capacity};"));
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    let (doc, component_param) = if is_tag {
+        ("Adds the tag to the entity.".to_string(), String::new())
+    } else {
+        (
+            "Adds the component to the entity, replacing the one it already has.".to_string(),
+            format!(", {component} component"),
+        )
+    };
+    output.add_doc_comments(&[
+        doc,
+        format!(
+            "Returns false if the store is full or the entity id is {} or above.",
+            max_entities
+        ),
+    ]);
+    output.push_line(&format!(
+        "static inline bool {id}_add({id}* store, {entity} entity{component_param}) {{"
+    ));
+    output.indent();
+    output.push_line(&format!("uint64_t index = {id}_index(store, entity);"));
+    output.push_line(&format!("if (index == {capacity}) {{"));
+    output.indent();
+    output.push_line(&format!(
+        "if (entity.value >= {max_entities} || store->active_components == {capacity}) {{"
+    ));
+    output.indent();
+    output.push_line("return false;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("index = store->active_components++;");
+    output.push_line("store->entities[index] = entity;");
+    output.push_line("store->indices[entity.value] = (uint32_t)(index + 1);");
+    output.unindent();
+    output.push_line("}");
+    if is_tag {
+        output.push_line("store->changed[index] = true;");
+    } else {
+        output.push_line(&format!("{id}_set(store, index, component);"));
+    }
+    output.push_line("return true;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(&[
+        "Removes the entity's component, moving the last component into its place.".to_string(),
+        "Returns false if the entity has none.".to_string(),
+    ]);
+    output.push_line(&format!(
+        "static inline bool {id}_remove({id}* store, {entity} entity) {{"
+    ));
+    output.indent();
+    output.push_line(&format!("uint64_t index = {id}_index(store, entity);"));
+    output.push_line(&format!("if (index == {capacity}) {{"));
+    output.indent();
+    output.push_line("return false;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("uint64_t last = --store->active_components;");
+    output.push_line("if (index != last) {");
+    output.indent();
+    output.push_line(&format!("{entity} moved = store->entities[last];"));
+    output.push_line("store->entities[index] = moved;");
+    if !is_tag {
+        output.push_line(&format!("{id}_set(store, index, {id}_get(store, last));"));
+    }
+    output.push_line("store->changed[index] = store->changed[last];");
+    output.push_line("store->indices[moved.value] = (uint32_t)(index + 1);");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("store->indices[entity.value] = 0;");
+    output.push_line("return true;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(&[
+        "Rebuilds the indices from the entities of the store.".to_string(),
+        "Returns false if an entity can't be indexed or has more than one component.".to_string(),
+    ]);
+    output.push_line(&format!("static inline bool {id}_reindex({id}* store) {{"));
+    output.indent();
+    output.push_line("memset(store->indices, 0, sizeof(store->indices));");
+    output.push_line("for (uint64_t i = 0; i < store->active_components; i++) {");
+    output.indent();
+    output.push_line("uint64_t value = store->entities[i].value;");
+    output.push_line(&format!(
+        "if (value >= {max_entities} || store->indices[value] != 0) {{"
+    ));
+    output.indent();
+    output.push_line("return false;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("store->indices[value] = (uint32_t)(i + 1);");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("return true;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+/// Adds functions to write the struct as JSON and read it back, using caller provided buffers.
+/// Fields are keyed by their id and `char` arrays are written as strings.
+fn add_struct_json(s: &Struct, output: &mut OutputBuilder) {
+    let id = &s.id;
+
+    output.push_line(&format!(
+        "static void {id}_write_json(D_JSON_WRITER* writer, const {id}* value) {{"
+    ));
+    output.indent();
+    if s.fields.is_empty() {
+        output.push_line("(void)value;");
+    }
+    output.push_line("d_json_write_char(writer, '{');");
+    for (i, field) in s.fields.iter().enumerate() {
+        let separator = if i == 0 { "" } else { "," };
+        output.push_line(&format!(
+            "d_json_write_raw(writer, \"{separator}\\\"{}\\\":\");",
+            field.id
+        ));
+        add_json_write(field, output);
+    }
+    output.push_line("d_json_write_char(writer, '}');");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    let key_size = s
+        .fields
+        .iter()
+        .map(|f| f.id.len())
+        .max()
+        .unwrap_or_default()
+        + 1;
+    output.push_line(&format!(
+        "static void {id}_read_json(D_JSON_READER* reader, {id}* value) {{"
+    ));
+    output.indent();
+    if s.fields.is_empty() {
+        output.push_line("(void)value;");
+    }
+    output.push_line(&format!("char key[{key_size}] = {{ 0 }};"));
+    output.push_line("if (!d_json_expect(reader, '{') || d_json_skip(reader, '}')) {");
+    output.indent();
+    output.push_line("return;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("do {");
+    output.indent();
+    output.push_line("// Keys that don't fit can't be a field");
+    output.push_line("if (d_json_read_string(reader, key, sizeof(key) - 1) >= sizeof(key)) {");
+    output.indent();
+    output.push_line("key[0] = '\\0';");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("d_json_expect(reader, ':');");
+    for (i, field) in s.fields.iter().enumerate() {
+        let branch = if i == 0 { "if" } else { "} else if" };
+        output.push_line(&format!("{branch} (strcmp(key, \"{}\") == 0) {{", field.id));
+        output.indent();
+        add_json_read(field, output);
+        output.unindent();
+    }
+    if s.fields.is_empty() {
+        output.push_line("d_json_skip_value(reader);");
+    } else {
+        output.push_line("} else {");
+        output.indent();
+        output.push_line("d_json_skip_value(reader);");
+        output.unindent();
+        output.push_line("}");
+    }
+    output.unindent();
+    output.push_line("} while (reader->ok && d_json_skip(reader, ','));");
+    output.push_line("d_json_expect(reader, '}');");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+const FIELDS_JSON_NOTE: &str =
+    "Fields missing from the JSON keep their value and unknown fields are ignored.";
+const WORLD_JSON_NOTE: &str =
+    "The world is cleared first, so stores missing from the JSON are empty. Unknown fields are ignored.";
+
+/// Adds the public functions converting a struct to and from JSON.
+/// The note explains what reading does with fields that are missing or unknown.
+fn add_json_functions(id: &str, note: &str, output: &mut OutputBuilder) {
+    output.add_doc_comments(&[
+        "Writes the value as null terminated JSON into the buffer.".to_string(),
+        "Returns the length of the JSON, or 0 if the buffer is too small.".to_string(),
+    ]);
+    output.push_line(&format!(
+        "size_t {id}_to_json(const {id}* value, char* buffer, size_t capacity) {{"
+    ));
+    output.indent();
+    output.push_line("D_JSON_WRITER writer = { buffer, capacity, 0, true };");
+    output.push_line(&format!("{id}_write_json(&writer, value);"));
+    output.push_line("if (!writer.ok) {");
+    output.indent();
+    output.push_line("return 0;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("buffer[writer.offset] = '\\0';");
+    output.push_line("return writer.offset;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(&[
+        "Reads JSON written by the matching 'to_json' function into the value.".to_string(),
+        note.to_string(),
+        "Returns false if the JSON is invalid, in which case the value is unspecified.".to_string(),
+    ]);
+    output.push_line(&format!(
+        "bool {id}_from_json({id}* value, const char* json, size_t length) {{"
+    ));
+    output.indent();
+    output.push_line("D_JSON_READER reader = { json, length, 0, true };");
+    output.push_line(&format!("{id}_read_json(&reader, value);"));
+    output.push_line("return reader.ok && d_json_peek(&reader) == '\\0';");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+/// Component stores are written as an object of the live components keyed by the id of their entity,
+/// e.g. `{"3":{"current":5}}`, and single component stores as their instance.
+/// Event queues only last a tick, so like saves they aren't written.
+fn add_store_json(store: &Store, output: &mut OutputBuilder) {
+    let Store {
+        id,
+        component,
+        entity,
+        ..
+    } = store;
+    let is_tag = store.fields.is_empty();
+
+    output.push_line(&format!(
+        "static void {id}_write_json(D_JSON_WRITER* writer, const {id}* store) {{"
+    ));
+    output.indent();
+    if store.single {
+        output.push_line(&format!(
+            "{component}_write_json(writer, &store->instance);"
+        ));
+    } else {
+        output.push_line("d_json_write_char(writer, '{');");
+        output.push_line("for (uint64_t i = 0; i < store->active_components; i++) {");
+        output.indent();
+        output.push_line("d_json_write_raw(writer, i == 0 ? \"\\\"\" : \",\\\"\");");
+        output.push_line("d_json_write_u64(writer, store->entities[i].value);");
+        output.push_line("d_json_write_raw(writer, \"\\\":\");");
+        if is_tag {
+            output.push_line("d_json_write_raw(writer, \"{}\");");
+        } else {
+            output.push_line(&format!("{component} component = {id}_get(store, i);"));
+            output.push_line(&format!("{component}_write_json(writer, &component);"));
+        }
+        output.unindent();
+        output.push_line("}");
+        output.push_line("d_json_write_char(writer, '}');");
+    }
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.push_line(&format!(
+        "static void {id}_read_json(D_JSON_READER* reader, {id}* store) {{"
+    ));
+    output.indent();
+    if store.single {
+        output.push_line(&format!("{component}_read_json(reader, &store->instance);"));
+        output.unindent();
+        output.push_line("}");
+        output.add_line();
+        return;
+    }
+    output.push_line("if (!d_json_expect(reader, '{') || d_json_skip(reader, '}')) {");
+    output.indent();
+    output.push_line("return;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("do {");
+    output.indent();
+    output.push_line(&format!("{entity} entity = {{ 0 }};"));
+    output.push_line("d_json_expect(reader, '\"');");
+    output.push_line("entity.value = d_json_read_u64(reader, UINT64_MAX);");
+    output.push_line("d_json_expect(reader, '\"');");
+    output.push_line("d_json_expect(reader, ':');");
+    output.push_line(&format!("{component} component;"));
+    output.push_line("memset(&component, 0, sizeof(component));");
+    output.push_line(&format!("{component}_read_json(reader, &component);"));
+    let component_arg = if is_tag { "" } else { ", component" };
+    output.push_line(
+        "// Ids past the entity limit and more components than the store holds are invalid",
+    );
+    output.push_line(&format!(
+        "if (reader->ok && !{id}_add(store, entity{component_arg})) {{"
+    ));
+    output.indent();
+    output.push_line("reader->ok = false;");
+    output.unindent();
+    output.push_line("}");
+    output.unindent();
+    output.push_line("} while (reader->ok && d_json_skip(reader, ','));");
+    output.push_line("d_json_expect(reader, '}');");
+    output.push_line("// Like loading a save, reading doesn't count as a change");
+    output.push_line("memset(store->changed, 0, sizeof(store->changed));");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+/// The world is written as the next entity id followed by its stores, keyed by component name.
+fn add_world_json(world: &World, stores: &[Store], output: &mut OutputBuilder) {
+    let id = &world.id;
+    let stores: Vec<&Store> = stores.iter().filter(|s| !s.event).collect();
+
+    output.push_line(&format!(
+        "static void {id}_write_json(D_JSON_WRITER* writer, const {id}* world) {{"
+    ));
+    output.indent();
+    output.push_line(&format!(
+        "d_json_write_raw(writer, \"{{\\\"{WORLD_NEXT_ENTITY_ID}\\\":\");"
+    ));
+    output.push_line(&format!(
+        "d_json_write_u64(writer, world->{WORLD_NEXT_ENTITY_ID});"
+    ));
+    for store in stores.iter() {
+        output.push_line(&format!(
+            "d_json_write_raw(writer, \",\\\"{}\\\":\");",
+            store.name
+        ));
+        output.push_line(&format!(
+            "{}_write_json(writer, &world->{});",
+            store.id, store.name
+        ));
+    }
+    output.push_line("d_json_write_char(writer, '}');");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    let key_size = stores
+        .iter()
+        .map(|s| s.name.len())
+        .chain([WORLD_NEXT_ENTITY_ID.len()])
+        .max()
+        .unwrap_or_default()
+        + 1;
+    output.push_line(&format!(
+        "static void {id}_read_json(D_JSON_READER* reader, {id}* world) {{"
+    ));
+    output.indent();
+    output.push_line(&format!("char key[{key_size}] = {{ 0 }};"));
+    output.push_line("memset(world, 0, sizeof(*world));");
+    output.push_line("if (!d_json_expect(reader, '{') || d_json_skip(reader, '}')) {");
+    output.indent();
+    output.push_line("return;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("do {");
+    output.indent();
+    output.push_line("// Keys that don't fit can't be a field");
+    output.push_line("if (d_json_read_string(reader, key, sizeof(key) - 1) >= sizeof(key)) {");
+    output.indent();
+    output.push_line("key[0] = '\\0';");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("d_json_expect(reader, ':');");
+    output.push_line(&format!(
+        "if (strcmp(key, \"{WORLD_NEXT_ENTITY_ID}\") == 0) {{"
+    ));
+    output.indent();
+    output.push_line(&format!(
+        "world->{WORLD_NEXT_ENTITY_ID} = d_json_read_u64(reader, UINT64_MAX);"
+    ));
+    output.unindent();
+    for store in stores.iter() {
+        output.push_line(&format!(
+            "}} else if (strcmp(key, \"{}\") == 0) {{",
+            store.name
+        ));
+        output.indent();
+        output.push_line(&format!(
+            "{}_read_json(reader, &world->{});",
+            store.id, store.name
+        ));
+        output.unindent();
+    }
+    output.push_line("} else {");
+    output.indent();
+    output.push_line("d_json_skip_value(reader);");
+    output.unindent();
+    output.push_line("}");
+    output.unindent();
+    output.push_line("} while (reader->ok && d_json_skip(reader, ','));");
+    output.push_line("d_json_expect(reader, '}');");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+/// Enums are written as the name of their variant along with its value, if it holds one,
+/// e.g. `{"variant":"Damaged","value":5}`.
+fn add_enum_json(s: &Struct, output: &mut OutputBuilder) {
+    let id = &s.id;
+
+    output.push_line(&format!(
+        "static void {id}_write_json(D_JSON_WRITER* writer, const {id}* value) {{"
+    ));
+    output.indent();
+    output.push_line("switch (value->tag) {");
+    output.indent();
+    for variant in s.variants.iter() {
+        output.push_line(&format!("case {}:", variant.tag));
+        output.indent();
+        match &variant.payload {
+            Some(payload) => {
+                output.push_line(&format!(
+                    "d_json_write_raw(writer, \"{{\\\"variant\\\":\\\"{}\\\",\\\"value\\\":\");",
+                    variant.name
+                ));
+                add_json_write(&payload_field(variant, payload), output);
+                output.push_line("d_json_write_char(writer, '}');");
+            }
+            None => output.push_line(&format!(
+                "d_json_write_raw(writer, \"{{\\\"variant\\\":\\\"{}\\\"}}\");",
+                variant.name
+            )),
+        }
+        output.push_line("break;");
+        output.unindent();
+    }
+    output.push_line("default:");
+    output.indent();
+    output.push_line("writer->ok = false;");
+    output.push_line("break;");
+    output.unindent();
+    output.unindent();
+    output.push_line("}");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    let name_size = s
+        .variants
+        .iter()
+        .map(|v| v.name.len())
+        .max()
+        .unwrap_or_default()
+        + 1;
+    output.push_line(&format!(
+        "static void {id}_read_json(D_JSON_READER* reader, {id}* value) {{"
+    ));
+    output.indent();
+    output.push_line("char key[8] = { 0 };");
+    output.push_line(&format!("char name[{name_size}] = {{ 0 }};"));
+    output.push_line("if (!d_json_expect(reader, '{') || d_json_skip(reader, '}')) {");
+    output.indent();
+    output.push_line("return;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("do {");
+    output.indent();
+    output.push_line("if (d_json_read_string(reader, key, sizeof(key) - 1) >= sizeof(key)) {");
+    output.indent();
+    output.push_line("key[0] = '\\0';");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("d_json_expect(reader, ':');");
+    output.push_line("if (strcmp(key, \"variant\") == 0) {");
+    output.indent();
+    output.push_line("if (d_json_read_string(reader, name, sizeof(name) - 1) >= sizeof(name)) {");
+    output.indent();
+    output.push_line("name[0] = '\\0';");
+    output.unindent();
+    output.push_line("}");
+    for (i, variant) in s.variants.iter().enumerate() {
+        let branch = if i == 0 { "if" } else { "} else if" };
+        output.push_line(&format!(
+            "{branch} (strcmp(name, \"{}\") == 0) {{",
+            variant.name
+        ));
+        output.indent();
+        output.push_line(&format!("value->tag = {};", variant.tag));
+        output.unindent();
+    }
+    output.push_line("} else {");
+    output.indent();
+    output.push_line("reader->ok = false;");
+    output.unindent();
+    output.push_line("}");
+    output.unindent();
+
+    // The value is read as the variant named before it
+    if s.variants.iter().any(|v| v.payload.is_some()) {
+        output.push_line("} else if (strcmp(key, \"value\") == 0) {");
+        output.indent();
+        output.push_line("switch (value->tag) {");
+        output.indent();
+        for variant in s.variants.iter() {
+            if let Some(payload) = &variant.payload {
+                output.push_line(&format!("case {}:", variant.tag));
+                output.indent();
+                add_json_read(&payload_field(variant, payload), output);
+                output.push_line("break;");
+                output.unindent();
+            }
+        }
+        output.push_line("default:");
+        output.indent();
+        output.push_line("d_json_skip_value(reader);");
+        output.push_line("break;");
+        output.unindent();
+        output.unindent();
+        output.push_line("}");
+        output.unindent();
+    }
+    output.push_line("} else {");
+    output.indent();
+    output.push_line("d_json_skip_value(reader);");
+    output.unindent();
+    output.push_line("}");
+    output.unindent();
+    output.push_line("} while (reader->ok && d_json_skip(reader, ','));");
+    output.push_line("d_json_expect(reader, '}');");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+/// Lists are written as an array of the items in use, or a string for lists of `char`.
+/// Reading fails if the JSON has more items than fit.
+fn add_list_json(s: &Struct, output: &mut OutputBuilder) {
+    let id = &s.id;
+    let (ty, max_size) = list_items(s);
+
+    output.push_line(&format!(
+        "static void {id}_write_json(D_JSON_WRITER* writer, const {id}* value) {{"
+    ));
+    output.indent();
+    if *ty == PrimitiveType::Char {
+        output.push_line("d_json_write_string(writer, value->items, value->length);");
+    } else {
+        output.push_line("d_json_write_char(writer, '[');");
+        output.push_line("for (uint32_t i = 0; i < value->length; i++) {");
+        output.indent();
+        output.push_line("if (i > 0) {");
+        output.indent();
+        output.push_line("d_json_write_char(writer, ',');");
+        output.unindent();
+        output.push_line("}");
+        output.push_line(&json_write_value(ty, "value->items[i]"));
+        output.unindent();
+        output.push_line("}");
+        output.push_line("d_json_write_char(writer, ']');");
+    }
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.push_line(&format!(
+        "static void {id}_read_json(D_JSON_READER* reader, {id}* value) {{"
+    ));
+    output.indent();
+    if *ty == PrimitiveType::Char {
+        output.push_line(&format!(
+            "size_t length = d_json_read_string(reader, value->items, {max_size});"
+        ));
+        output.push_line(&format!("if (length > {max_size}) {{"));
+        output.indent();
+        output.push_line("reader->ok = false;");
+        output.push_line("return;");
+        output.unindent();
+        output.push_line("}");
+        output.push_line("value->length = (uint32_t)length;");
+    } else {
+        output.push_line("value->length = 0;");
+        output.push_line("if (!d_json_expect(reader, '[') || d_json_skip(reader, ']')) {");
+        output.indent();
+        output.push_line("return;");
+        output.unindent();
+        output.push_line("}");
+        output.push_line("do {");
+        output.indent();
+        output.push_line(&format!("if (value->length >= {max_size}) {{"));
+        output.indent();
+        output.push_line("reader->ok = false;");
+        output.push_line("return;");
+        output.unindent();
+        output.push_line("}");
+        add_json_read_value(ty, "value->items[value->length]", output);
+        output.push_line("value->length++;");
+        output.unindent();
+        output.push_line("} while (reader->ok && d_json_skip(reader, ','));");
+        output.push_line("d_json_expect(reader, ']');");
+    }
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+/// Returns the field of the payload union holding the value of the variant.
+fn payload_field(variant: &EnumVariant, payload: &ListType<Primitive>) -> StructField {
+    StructField {
+        id: format!("{}.{}", ENUM_PAYLOAD_ID, variant.name),
+        comments: vec![],
+        ty: payload.clone(),
+    }
+}
+
+fn add_json_write(field: &StructField, output: &mut OutputBuilder) {
+    let value = format!("value->{}", field.id);
+    let ty = &field.ty.inner_ty().primitive_type;
+
+    match &field.ty {
+        ListType::List { max_size, .. } if *ty == PrimitiveType::Char => output.push_line(
+            &format!("d_json_write_string(writer, {value}, {max_size});"),
+        ),
+        ListType::List { max_size, .. } => {
+            output.push_line("d_json_write_char(writer, '[');");
+            output.push_line(&format!("for (size_t i = 0; i < {max_size}; i++) {{"));
+            output.indent();
+            output.push_line("if (i > 0) {");
+            output.indent();
+            output.push_line("d_json_write_char(writer, ',');");
+            output.unindent();
+            output.push_line("}");
+            output.push_line(&json_write_value(ty, &format!("{value}[i]")));
+            output.unindent();
+            output.push_line("}");
+            output.push_line("d_json_write_char(writer, ']');");
+        }
+        ListType::Single(_) => output.push_line(&json_write_value(ty, &value)),
+    }
+}
+
+fn json_write_value(ty: &PrimitiveType, value: &str) -> String {
+    match ty {
+        PrimitiveType::U32 | PrimitiveType::U64 => format!("d_json_write_u64(writer, {value});"),
+        PrimitiveType::I32 | PrimitiveType::I64 => format!("d_json_write_i64(writer, {value});"),
+        PrimitiveType::F32 => format!("d_json_write_f32(writer, {value});"),
+        PrimitiveType::F64 => format!("d_json_write_f64(writer, {value});"),
+        PrimitiveType::Bool => format!("d_json_write_raw(writer, {value} ? \"true\" : \"false\");"),
+        PrimitiveType::Char => format!("d_json_write_string(writer, &{value}, 1);"),
+        PrimitiveType::Identifier(id) => format!("{id}_write_json(writer, &{value});"),
+    }
+}
+
+fn add_json_read(field: &StructField, output: &mut OutputBuilder) {
+    let value = format!("value->{}", field.id);
+    let ty = &field.ty.inner_ty().primitive_type;
+
+    match &field.ty {
+        ListType::List { max_size, .. } if *ty == PrimitiveType::Char => {
+            add_json_read_string(&value, *max_size, output)
+        }
+        ListType::List { max_size, .. } => {
+            output.push_line("d_json_expect(reader, '[');");
+            output.push_line(&format!(
+                "for (size_t i = 0; i < {max_size} && reader->ok; i++) {{"
+            ));
+            output.indent();
+            output.push_line("if (i > 0) {");
+            output.indent();
+            output.push_line("d_json_expect(reader, ',');");
+            output.unindent();
+            output.push_line("}");
+            add_json_read_value(ty, &format!("{value}[i]"), output);
+            output.unindent();
+            output.push_line("}");
+            output.push_line("d_json_expect(reader, ']');");
+        }
+        ListType::Single(_) => add_json_read_value(ty, &value, output),
+    }
+}
+
+fn add_json_read_value(ty: &PrimitiveType, value: &str, output: &mut OutputBuilder) {
+    let read = match ty {
+        PrimitiveType::U32 => "(uint32_t)d_json_read_u64(reader, UINT32_MAX)",
+        PrimitiveType::U64 => "d_json_read_u64(reader, UINT64_MAX)",
+        PrimitiveType::I32 => "(int32_t)d_json_read_i64(reader, INT32_MIN, INT32_MAX)",
+        PrimitiveType::I64 => "d_json_read_i64(reader, INT64_MIN, INT64_MAX)",
+        PrimitiveType::F32 => "(float)d_json_read_f64(reader)",
+        PrimitiveType::F64 => "d_json_read_f64(reader)",
+        PrimitiveType::Bool => "d_json_read_bool(reader)",
+        PrimitiveType::Char => return add_json_read_string(&format!("&{value}"), 1, output),
+        PrimitiveType::Identifier(id) => {
+            return output.push_line(&format!("{id}_read_json(reader, &{value});"))
+        }
+    };
+
+    output.push_line(&format!("{value} = {read};"));
+}
+
+/// Reads a string into a char array, failing if it doesn't fit.
+fn add_json_read_string(text: &str, max_size: usize, output: &mut OutputBuilder) {
+    output.push_line(&format!(
+        "if (d_json_read_string(reader, {text}, {max_size}) > {max_size}) {{"
+    ));
+    output.indent();
+    output.push_line("reader->ok = false;");
+    output.unindent();
+    output.push_line("}");
+}
+
+/// The JSON writer and reader used by the struct JSON functions. Neither allocates.
+const JSON_HELPERS: &str = r#"typedef struct D_JSON_WRITER {
+	char* data;
+	size_t length;
+	size_t offset;
+	bool ok;
+} D_JSON_WRITER;
+
+typedef struct D_JSON_READER {
+	const char* data;
+	size_t length;
+	size_t offset;
+	bool ok;
+} D_JSON_READER;
+
+static inline void d_json_write_char(D_JSON_WRITER* writer, char c) {
+	// Always leave room for the null terminator
+	if (!writer->ok || writer->offset + 1 >= writer->length) {
+		writer->ok = false;
+		return;
+	}
+	writer->data[writer->offset++] = c;
+}
+
+static inline void d_json_write_raw(D_JSON_WRITER* writer, const char* text) {
+	while (*text != '\0') {
+		d_json_write_char(writer, *text++);
+	}
+}
+
+static inline void d_json_write_u64(D_JSON_WRITER* writer, uint64_t value) {
+	char text[32];
+	snprintf(text, sizeof(text), "%llu", (unsigned long long)value);
+	d_json_write_raw(writer, text);
+}
+
+static inline void d_json_write_i64(D_JSON_WRITER* writer, int64_t value) {
+	char text[32];
+	snprintf(text, sizeof(text), "%lld", (long long)value);
+	d_json_write_raw(writer, text);
+}
+
+static inline void d_json_write_float(D_JSON_WRITER* writer, double value, int precision) {
+	// JSON has no infinity or NaN, so they are written as null
+	if (!isfinite(value)) {
+		d_json_write_raw(writer, "null");
+		return;
+	}
+	char text[32];
+	snprintf(text, sizeof(text), "%.*g", precision, value);
+	d_json_write_raw(writer, text);
+}
+
+static inline void d_json_write_f32(D_JSON_WRITER* writer, float value) {
+	d_json_write_float(writer, value, 9);
+}
+
+static inline void d_json_write_f64(D_JSON_WRITER* writer, double value) {
+	d_json_write_float(writer, value, 17);
+}
+
+static inline void d_json_write_string(D_JSON_WRITER* writer, const char* text, size_t max) {
+	d_json_write_char(writer, '"');
+	for (size_t i = 0; i < max && text[i] != '\0'; i++) {
+		unsigned char c = (unsigned char)text[i];
+		if (c == '"' || c == '\\') {
+			d_json_write_char(writer, '\\');
+			d_json_write_char(writer, (char)c);
+		} else if (c < 0x20) {
+			char escaped[8];
+			snprintf(escaped, sizeof(escaped), "\\u%04x", c);
+			d_json_write_raw(writer, escaped);
+		} else {
+			d_json_write_char(writer, (char)c);
+		}
+	}
+	d_json_write_char(writer, '"');
+}
+
+/* Returns the next character after any whitespace, or '\0' at the end. */
+static inline char d_json_peek(D_JSON_READER* reader) {
+	while (reader->offset < reader->length) {
+		char c = reader->data[reader->offset];
+		if (c != ' ' && c != '\t' && c != '\n' && c != '\r') {
+			return reader->ok ? c : '\0';
+		}
+		reader->offset++;
+	}
+	return '\0';
+}
+
+/* Consumes the character if it's next. */
+static inline bool d_json_skip(D_JSON_READER* reader, char c) {
+	if (d_json_peek(reader) != c) {
+		return false;
+	}
+	reader->offset++;
+	return true;
+}
+
+static inline bool d_json_expect(D_JSON_READER* reader, char c) {
+	if (!d_json_skip(reader, c)) {
+		reader->ok = false;
+	}
+	return reader->ok;
+}
+
+static inline bool d_json_skip_literal(D_JSON_READER* reader, const char* literal) {
+	size_t size = strlen(literal);
+	if (d_json_peek(reader) != literal[0] || reader->length - reader->offset < size
+		|| memcmp(reader->data + reader->offset, literal, size) != 0) {
+		return false;
+	}
+	reader->offset += size;
+	return true;
+}
+
+/* Reads a string, keeping up to max characters and zero filling the rest. Returns the full length. */
+static inline size_t d_json_read_string(D_JSON_READER* reader, char* text, size_t max) {
+	size_t count = 0;
+	if (!d_json_expect(reader, '"')) {
+		return 0;
+	}
+	while (true) {
+		if (reader->offset >= reader->length) {
+			reader->ok = false;
+			return 0;
+		}
+		char c = reader->data[reader->offset++];
+		if (c == '"') {
+			break;
+		}
+		if (c == '\\') {
+			if (reader->offset >= reader->length) {
+				reader->ok = false;
+				return 0;
+			}
+			char escaped = reader->data[reader->offset++];
+			switch (escaped) {
+				case '"': case '\\': case '/': c = escaped; break;
+				case 'b': c = '\b'; break;
+				case 'f': c = '\f'; break;
+				case 'n': c = '\n'; break;
+				case 'r': c = '\r'; break;
+				case 't': c = '\t'; break;
+				case 'u': {
+					// Only single byte characters fit in a char
+					unsigned int code = 0;
+					for (int i = 0; i < 4; i++) {
+						char h = reader->offset < reader->length ? reader->data[reader->offset++] : '\0';
+						int digit = h >= '0' && h <= '9' ? h - '0'
+							: h >= 'a' && h <= 'f' ? h - 'a' + 10
+							: h >= 'A' && h <= 'F' ? h - 'A' + 10 : -1;
+						if (digit < 0) {
+							reader->ok = false;
+							return 0;
+						}
+						code = code * 16 + (unsigned int)digit;
+					}
+					if (code > 0x7f) {
+						reader->ok = false;
+						return 0;
+					}
+					c = (char)code;
+					break;
+				}
+				default:
+					reader->ok = false;
+					return 0;
+			}
+		}
+		if (count < max) {
+			text[count] = c;
+		}
+		count++;
+	}
+	for (size_t i = count; i < max; i++) {
+		text[i] = '\0';
+	}
+	return count;
+}
+
+static inline uint64_t d_json_read_u64(D_JSON_READER* reader, uint64_t max) {
+	uint64_t value = 0;
+	size_t digits = 0;
+	d_json_peek(reader);
+	while (reader->offset < reader->length && reader->data[reader->offset] >= '0' && reader->data[reader->offset] <= '9') {
+		uint64_t digit = (uint64_t)(reader->data[reader->offset++] - '0');
+		if (value > (max - digit) / 10) {
+			reader->ok = false;
+			return 0;
+		}
+		value = value * 10 + digit;
+		digits++;
+	}
+	if (digits == 0) {
+		reader->ok = false;
+	}
+	return value;
+}
+
+static inline int64_t d_json_read_i64(D_JSON_READER* reader, int64_t min, int64_t max) {
+	bool negative = d_json_skip(reader, '-');
+	uint64_t magnitude = d_json_read_u64(reader, negative ? (uint64_t)(-(min + 1)) + 1 : (uint64_t)max);
+	if (!reader->ok || magnitude == 0) {
+		return 0;
+	}
+	return negative ? -(int64_t)(magnitude - 1) - 1 : (int64_t)magnitude;
+}
+
+static inline double d_json_read_f64(D_JSON_READER* reader) {
+	if (d_json_skip_literal(reader, "null")) {
+		return NAN;
+	}
+	char text[64];
+	size_t count = 0;
+	d_json_peek(reader);
+	while (reader->offset < reader->length && count < sizeof(text) - 1
+		&& reader->data[reader->offset] != '\0' && strchr("+-.0123456789eE", reader->data[reader->offset]) != NULL) {
+		text[count++] = reader->data[reader->offset++];
+	}
+	text[count] = '\0';
+	char* end;
+	double value = strtod(text, &end);
+	if (count == 0 || *end != '\0') {
+		reader->ok = false;
+	}
+	return value;
+}
+
+static inline bool d_json_read_bool(D_JSON_READER* reader) {
+	if (d_json_skip_literal(reader, "true")) {
+		return true;
+	}
+	if (!d_json_skip_literal(reader, "false")) {
+		reader->ok = false;
+	}
+	return false;
+}
+
+/* Skips over any JSON value, used for fields that don't exist. */
+static void d_json_skip_value(D_JSON_READER* reader) {
+	char c = d_json_peek(reader);
+	if (c == '"') {
+		d_json_read_string(reader, NULL, 0);
+	} else if (c == '{' || c == '[') {
+		char close = c == '{' ? '}' : ']';
+		reader->offset++;
+		if (d_json_skip(reader, close)) {
+			return;
+		}
+		do {
+			if (c == '{') {
+				d_json_read_string(reader, NULL, 0);
+				d_json_expect(reader, ':');
+			}
+			d_json_skip_value(reader);
+		} while (reader->ok && d_json_skip(reader, ','));
+		d_json_expect(reader, close);
+	} else if (!d_json_skip_literal(reader, "true") && !d_json_skip_literal(reader, "false")) {
+		d_json_read_f64(reader);
+	}
+}"#;
+
+/// Adds the little endian writer and reader used by the world save and load functions.
+/// Adds a function that resets the world. Every store starts empty, except for the stores of
+/// single components which always hold their one instance.
+fn add_world_init(world: &World, output: &mut OutputBuilder) {
+    output.add_doc_comments(&[
+        "Resets the world to have no entities.".to_string(),
+        "Each single component is created with all of its fields zeroed.".to_string(),
+    ]);
+    output.push_line(&format!("void world_init({}* world) {{", world.id));
+    output.indent();
+    output.push_line("memset(world, 0, sizeof(*world));");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+/// Adds typed functions for the host to get and set a single component,
+/// e.g. to feed in controller state each frame without knowing the layout of the store.
+fn add_world_single(world: &World, store: &Store, output: &mut OutputBuilder) {
+    // Tag components have no data to set
+    if store.fields.is_empty() {
+        return;
+    }
+
+    let Store {
+        id,
+        component,
+        name,
+        ..
+    } = store;
+
+    output.add_doc_comments(&[format!(
+        "Returns a copy of the '{}' single component.",
+        name
+    )]);
+    output.push_line(&format!(
+        "{component} world_get_{name}(const {}* world) {{",
+        world.id
+    ));
+    output.indent();
+    output.push_line(&format!("return {id}_get(&world->{name});"));
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(&[format!(
+        "Sets the '{}' single component, flagging it as changed.",
+        name
+    )]);
+    output.push_line(&format!(
+        "void world_set_{name}({}* world, const {component}* value) {{",
+        world.id
+    ));
+    output.indent();
+    output.push_line(&format!("{id}_set(&world->{name}, *value);"));
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+fn add_save_helpers(output: &mut OutputBuilder) {
+    for line in SAVE_HELPERS.lines() {
+        output.push_line(line);
+    }
+    output.add_line();
+}
+
+const SAVE_HELPERS: &str = "typedef struct D_WRITER {
+\tuint8_t* data;
+\tsize_t length;
+\tsize_t offset;
+\tbool ok;
+} D_WRITER;
+
+typedef struct D_READER {
+\tconst uint8_t* data;
+\tsize_t length;
+\tsize_t offset;
+\tbool ok;
+} D_READER;
+
+static inline void d_write_bytes(D_WRITER* writer, uint64_t value, size_t size) {
+\tif (!writer->ok || writer->length - writer->offset < size) {
+\t\twriter->ok = false;
+\t\treturn;
+\t}
+\tfor (size_t i = 0; i < size; i++) {
+\t\twriter->data[writer->offset++] = (uint8_t)(value >> (8 * i));
+\t}
+}
+
+static inline uint64_t d_read_bytes(D_READER* reader, size_t size) {
+\tif (!reader->ok || reader->length - reader->offset < size) {
+\t\treader->ok = false;
+\t\treturn 0;
+\t}
+\tuint64_t value = 0;
+\tfor (size_t i = 0; i < size; i++) {
+\t\tvalue |= (uint64_t)reader->data[reader->offset++] << (8 * i);
+\t}
+\treturn value;
+}
+
+static inline void d_write_f32(D_WRITER* writer, float value) {
+\tuint32_t bits;
+\tmemcpy(&bits, &value, sizeof(bits));
+\td_write_bytes(writer, bits, 4);
+}
+
+static inline float d_read_f32(D_READER* reader) {
+\tuint32_t bits = (uint32_t)d_read_bytes(reader, 4);
+\tfloat value;
+\tmemcpy(&value, &bits, sizeof(value));
+\treturn value;
+}
+
+static inline void d_write_f64(D_WRITER* writer, double value) {
+\tuint64_t bits;
+\tmemcpy(&bits, &value, sizeof(bits));
+\td_write_bytes(writer, bits, 8);
+}
+
+static inline double d_read_f64(D_READER* reader) {
+\tuint64_t bits = d_read_bytes(reader, 8);
+\tdouble value;
+\tmemcpy(&value, &bits, sizeof(value));
+\treturn value;
+}";
+
+/// Adds `world_save`, which writes the world into the buffer following the save format.
+fn add_world_save(world: &World, output: &mut OutputBuilder) {
+    output.add_doc_comments(&[
+        "Writes the world into the buffer.".to_string(),
+        "Returns the number of bytes written, or 0 if the buffer is too small.".to_string(),
+    ]);
+    output.push_line(&format!(
+        "size_t world_save(const {}* world, uint8_t* buffer, size_t capacity) {{",
+        world.id
+    ));
+    output.indent();
+    output.push_line("D_WRITER writer = { buffer, capacity, 0, true };");
+    for byte in SAVE_MAGIC {
+        output.push_line(&format!("d_write_bytes(&writer, {}, 1);", byte));
+    }
+    output.push_line(&format!("d_write_bytes(&writer, {}, 4);", SAVE_VERSION));
+    output.push_line(&format!(
+        "d_write_bytes(&writer, {:#x}ULL, 8);",
+        world.save_format.schema_hash
+    ));
+    for op in world.save_format.ops.iter() {
+        add_save_op(op, &WRITER, output);
+    }
+    output.push_line("return writer.ok ? writer.offset : 0;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+/// Where the values of the save operations go, either a save buffer or a checksum.
+struct ValueSink {
+    /// The prefix of the helper functions that consume values.
+    function: &'static str,
+    variable: &'static str,
+}
+
+const WRITER: ValueSink = ValueSink {
+    function: "d_write",
+    variable: "writer",
+};
+
+const HASHER: ValueSink = ValueSink {
+    function: "d_hash",
+    variable: "hasher",
+};
+
+fn add_save_op(op: &SaveOp, sink: &ValueSink, output: &mut OutputBuilder) {
+    let ValueSink { function, variable } = sink;
+
+    match op {
+        SaveOp::Primitive { path, ty } => {
+            let value = save_path(path);
+            let line = match ty {
+                PrimitiveType::F32 => format!("{function}_f32(&{variable}, {value});"),
+                PrimitiveType::F64 => format!("{function}_f64(&{variable}, {value});"),
+                ty => format!(
+                    "{function}_bytes(&{variable}, (uint64_t){value}, {});",
+                    primitive_size(ty)
+                ),
+            };
+            output.push_line(&line);
+        }
+        SaveOp::Repeat { index, count, ops } => {
+            let Count { path, ty, .. } = count;
+            let count = save_path(path);
+            output.push_line(&format!(
+                "{function}_bytes(&{variable}, {count}, {});",
+                primitive_size(ty)
+            ));
+
+            output.push_line(&format!(
+                "for (uint64_t i{index} = 0; i{index} < {count}; i{index}++) {{"
+            ));
+            output.indent();
+            for op in ops {
+                add_save_op(op, sink, output);
+            }
+            output.unindent();
+            output.push_line("}");
+        }
+        SaveOp::Match { path, variants } => {
+            add_variant_switch(path, variants, output, |op, output| {
+                add_save_op(op, sink, output)
+            });
+        }
+    }
+}
+
+/// Adds a switch on the tag at the path with a case for each variant that has operations.
+fn add_variant_switch(
+    path: &[PathSegment],
+    variants: &[Vec<SaveOp>],
+    output: &mut OutputBuilder,
+    add_op: impl Fn(&SaveOp, &mut OutputBuilder),
+) {
+    if variants.iter().all(|ops| ops.is_empty()) {
+        return;
+    }
+
+    output.push_line(&format!("switch ({}) {{", save_path(path)));
+    output.indent();
+    for (tag, ops) in variants.iter().enumerate() {
+        if ops.is_empty() {
+            continue;
+        }
+        output.push_line(&format!("case {tag}:"));
+        output.indent();
+        for op in ops {
+            add_op(op, output);
+        }
+        output.push_line("break;");
+        output.unindent();
+    }
+    output.unindent();
+    output.push_line("}");
+}
+
+/// Adds `world_load`, which reads a save made by `world_save` into the world.
+fn add_world_load(world: &World, stores: &[Store], output: &mut OutputBuilder) {
+    output.add_doc_comments(&[
+        "Reads a save made by 'world_save' into the world.".to_string(),
+        "Returns false if the save is truncated, corrupt or from a different schema.".to_string(),
+        "The contents of the world are unspecified when loading fails.".to_string(),
+    ]);
+    output.push_line(&format!(
+        "bool world_load({}* world, const uint8_t* buffer, size_t length) {{",
+        world.id
+    ));
+    output.indent();
+    output.push_line("D_READER reader = { buffer, length, 0, true };");
+    output.push_line("memset(world, 0, sizeof(*world));");
+    let magic = SAVE_MAGIC
+        .iter()
+        .map(|byte| format!("d_read_bytes(&reader, 1) != {}", byte))
+        .collect::<Vec<_>>()
+        .join(" || ");
+    output.push_line(&format!("if ({}) {{", magic));
+    output.indent();
+    output.push_line("return false;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line(&format!(
+        "if (d_read_bytes(&reader, 4) != {} || d_read_bytes(&reader, 8) != {:#x}ULL) {{",
+        SAVE_VERSION, world.save_format.schema_hash
+    ));
+    output.indent();
+    output.push_line("return false;");
+    output.unindent();
+    output.push_line("}");
+    for op in world.save_format.ops.iter() {
+        add_load_op(op, output);
+    }
+    // The indices aren't saved, so they're rebuilt from the loaded entities
+    for store in stores.iter().filter(|s| !s.single && !s.event) {
+        output.push_line(&format!(
+            "if (!{}_reindex(&world->{})) {{",
+            store.id, store.name
+        ));
+        output.indent();
+        output.push_line("return false;");
+        output.unindent();
+        output.push_line("}");
+    }
+    output.push_line("return reader.ok;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+fn add_load_op(op: &SaveOp, output: &mut OutputBuilder) {
+    match op {
+        SaveOp::Primitive { path, ty } => {
+            let value = save_path(path);
+            let read = match ty {
+                PrimitiveType::F32 => "d_read_f32(&reader)".to_string(),
+                PrimitiveType::F64 => "d_read_f64(&reader)".to_string(),
+                PrimitiveType::Bool => "d_read_bytes(&reader, 1) != 0".to_string(),
+                // Signed values go through their unsigned type so negatives are restored
+                PrimitiveType::I32 => "(int32_t)(uint32_t)d_read_bytes(&reader, 4)".to_string(),
+                PrimitiveType::I64 => "(int64_t)d_read_bytes(&reader, 8)".to_string(),
+                ty => format!(
+                    "({})d_read_bytes(&reader, {})",
+                    base_primitive(ty),
+                    primitive_size(ty)
+                ),
+            };
+            output.push_line(&format!("{} = {};", value, read));
+        }
+        SaveOp::Repeat { index, count, ops } => {
+            let Count { path, ty, max } = count;
+            let count = save_path(path);
+            output.push_line(&format!(
+                "{} = ({})d_read_bytes(&reader, {});",
+                count,
+                base_primitive(ty),
+                primitive_size(ty)
+            ));
+            output.push_line(&format!("if ({} > {}) {{", count, max));
+            output.indent();
+            output.push_line("return false;");
+            output.unindent();
+            output.push_line("}");
+
+            output.push_line(&format!(
+                "for (uint64_t i{index} = 0; i{index} < {count}; i{index}++) {{"
+            ));
+            output.indent();
+            for op in ops {
+                add_load_op(op, output);
+            }
+            output.unindent();
+            output.push_line("}");
+        }
+        SaveOp::Match { path, variants } => {
+            output.push_line(&format!(
+                "if ({} >= {}) {{",
+                save_path(path),
+                variants.len()
+            ));
+            output.indent();
+            output.push_line("return false;");
+            output.unindent();
+            output.push_line("}");
+            add_variant_switch(path, variants, output, add_load_op);
+        }
+    }
+}
+
+/// Adds `world_checksum` along with a ring of snapshots that can be restored for rollback.
+fn add_world_snapshots(world: &World, output: &mut OutputBuilder) {
+    let slots = SNAPSHOT_SLOTS_ID;
+
+    // The FNV prime is filled in so the hash is only defined in the IR
+    let prime = format!("{:#x}ULL", FNV_PRIME);
+    for line in HASH_HELPERS.replace("FNV_PRIME", &prime).lines() {
+        output.push_line(line);
+    }
+    output.add_line();
+
+    output.add_doc_comments(&[
+        "Returns the checksum of the world, which peers compare to detect desyncs.".to_string(),
+        "It only depends on the saved values, so it matches across platforms.".to_string(),
+    ]);
+    output.push_line(&format!(
+        "uint64_t world_checksum(const {}* world) {{",
+        world.id
+    ));
+    output.indent();
+    output.push_line(&format!(
+        "D_HASHER hasher = {{ {:#x}ULL }};",
+        FNV_OFFSET_BASIS
+    ));
+    for op in world.save_format.ops.iter() {
+        add_save_op(op, &HASHER, output);
+    }
+    output.push_line("return hasher.hash;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.push_line(&format!("#ifndef {slots}"));
+    output.push_line(&format!("#define {slots} {}", world.snapshot_slots));
+    output.push_line("#endif");
+    output.add_line();
+    output.push_line("typedef struct D_SNAPSHOT {");
+    output.indent();
+    for (id, ty) in SNAPSHOT_HEADER.iter() {
+        output.push_line(&format!("{} {id};", base_primitive(ty)));
+    }
+    output.push_line(&format!("{} world;", world.id));
+    output.unindent();
+    output.push_line("} D_SNAPSHOT;");
+    output.add_line();
+    output.push_line(&format!("static D_SNAPSHOT d_snapshots[{slots}];"));
+    output.add_line();
+
+    output.add_doc_comments(
+        &[
+            "Copies the world into the snapshot ring. Slots wrap around, so the frame number can be used as the slot.".to_string(),
+            "Returns the checksum of the world.".to_string(),
+        ]
+    );
+    output.push_line(&format!(
+        "uint64_t world_snapshot(const {}* world, uint64_t slot) {{",
+        world.id
+    ));
+    output.indent();
+    output.push_line(&format!(
+        "D_SNAPSHOT* snapshot = &d_snapshots[slot % {slots}];"
+    ));
+    output.push_line("snapshot->taken = true;");
+    output.push_line("snapshot->slot = slot;");
+    output.push_line("snapshot->checksum = world_checksum(world);");
+    output.push_line("memcpy(&snapshot->world, world, sizeof(*world));");
+    output.push_line("return snapshot->checksum;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(
+        &[
+            "Restores the world from the snapshot taken for the slot.".to_string(),
+            "Returns false, leaving the world untouched, if the snapshot was never taken or has been overwritten.".to_string(),
+        ]
+    );
+    output.push_line(&format!(
+        "bool world_restore({}* world, uint64_t slot) {{",
+        world.id
+    ));
+    output.indent();
+    output.push_line(&format!(
+        "const D_SNAPSHOT* snapshot = &d_snapshots[slot % {slots}];"
+    ));
+    output.push_line("if (!snapshot->taken || snapshot->slot != slot) {");
+    output.indent();
+    output.push_line("return false;");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("memcpy(world, &snapshot->world, sizeof(*world));");
+    output.push_line("return true;");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+fn add_system(system: &Commentable<System>, world: &World, output: &mut OutputBuilder) {
+    if let Some(external) = &system.value.external {
+        let mut comments = system.comments.clone();
+        comments.push(match c_impl(&system.value) {
+            Some(path) => format!(
+                "Implemented by the host in '{}', which is included at the end of this file.",
+                path
+            ),
+            None => "Implemented by the host.".to_string(),
+        });
+        if !external.writes.is_empty() {
+            comments.push(format!("Writes: {}", external.writes.join(" ")));
+        }
+        output.add_doc_comments(&comments);
+        output.push_line(&format!("void {}({}* world);", system.value.id, world.id));
+        output.add_line();
+        return;
+    }
+
+    output.add_doc_comments(&system.comments);
+    output.push_line(&format!("void {}({}* world) {{", system.value.id, world.id));
+    output.indent();
+    if system.value.body.is_empty() {
+        output.push_line("(void)world;");
+    }
+    add_statements(&system.value.body, output);
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+}
+
+/// Returns the host file implementing the system in C, if it's an external system that has one.
+fn c_impl(system: &System) -> Option<&str> {
+    system
+        .external
+        .as_ref()?
+        .impls
+        .iter()
+        .find(|i| i.target == TARGET_C_IMPL_ID)
+        .map(|i| i.path.as_str())
+}
+
+/// Returns each host file implementing external systems in C along with the systems it implements,
+/// in the order the files are first used.
+fn c_impls(systems: &[Commentable<System>]) -> Vec<(&str, Vec<&System>)> {
+    let mut impls: Vec<(&str, Vec<&System>)> = vec![];
+    for system in systems.iter() {
+        let path = match c_impl(&system.value) {
+            Some(path) => path,
+            None => continue,
+        };
+        match impls.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, systems)) => systems.push(&system.value),
+            None => impls.push((path, vec![&system.value])),
+        }
+    }
+    impls
+}
+
+/// Returns a stub for the host to implement the external systems of a C file in.
+fn external_stub(path: &str, systems: &[&System], world: &World) -> Artifact {
+    let mut output =
+        OutputBuilder::new("C", "\t", "//").with_doc_comment_style(Target::C.doc_comment_style());
+    output.add_comment("Implements external systems. This file is included by 'main.c'.");
+    output.add_comment("It was generated as a stub and won't be overwritten.");
+    for system in systems {
+        output.add_line();
+        output.add_comment(&format!("The '{}' external system.", system.name));
+        let writes = system
+            .external
+            .as_ref()
+            .map(|e| e.writes.join(" "))
+            .unwrap_or_default();
+        if !writes.is_empty() {
+            output.add_comment(&format!("Writes: {}", writes));
+        }
+        output.push_line(&format!("void {}({}* world) {{", system.id, world.id));
+        output.indent();
+        output.push_line("(void)world;");
+        output.unindent();
+        output.push_line("}");
+    }
+
+    Artifact {
+        target: Target::C,
+        path: path.into(),
+        contents: output.build(),
+        stub: true,
+    }
+}
+
+/// Returns a number literal written for C. Floats always have a fraction and `f32` is suffixed with
+/// `f` so arithmetic between literals isn't integer or double arithmetic.
+fn c_number(number: &str, ty: &PrimitiveType) -> String {
+    match ty {
+        PrimitiveType::F32 | PrimitiveType::F64 => {
+            let mut number = number.to_string();
+            if !is_fractional(&number) {
+                number.push_str(".0");
+            }
+            if *ty == PrimitiveType::F32 {
+                number.push('f');
+            }
+            number
+        }
+        _ => {
+            // Leading zeros would make the number octal
+            let number = match number.trim_start_matches('0') {
+                "" => "0",
+                number => number,
+            };
+            // Decimal numbers past the largest signed integer are only valid as unsigned
+            if number.parse::<i64>().is_err() {
+                format!("{}u", number)
+            } else {
+                number.to_string()
+            }
+        }
+    }
+}
+
+fn add_statements(statements: &[Statement], output: &mut OutputBuilder) {
+    for statement in statements {
+        add_statement(statement, output);
+    }
+}
+
+fn add_statement(statement: &Statement, output: &mut OutputBuilder) {
+    match statement {
+        Statement::Comment(lines) => output.add_comments(lines),
+        Statement::Query {
+            store,
+            index,
+            required,
+            skip,
+            body,
+        } => {
+            output.push_line(&format!(
+                "for (uint64_t {index} = 0; {index} < world->{store}.active_components; {index}++) {{"
+            ));
+            output.indent();
+            for lookup in required {
+                output.push_line(&format!(
+                    "uint64_t {} = {};",
+                    lookup.id,
+                    c_value(&lookup.index)
+                ));
+                output.push_line(&format!("if ({} == {}) {{", lookup.id, lookup.capacity));
+                output.indent();
+                output.push_line("continue;");
+                output.unindent();
+                output.push_line("}");
+            }
+            for condition in skip {
+                output.push_line(&format!("if ({}) {{", c_condition(condition)));
+                output.indent();
+                output.push_line("continue;");
+                output.unindent();
+                output.push_line("}");
+            }
+            add_statements(body, output);
+            output.unindent();
+            output.push_line("}");
+        }
+        Statement::Events {
+            store,
+            store_id,
+            index,
+            event,
+            component,
+            body,
+        } => {
+            output.push_line(&format!(
+                "for (uint64_t {index} = 0; {index} < world->{store}.count; {index}++) {{"
+            ));
+            output.indent();
+            if let Some(event) = event {
+                output.push_line(&format!(
+                    "{component} {event} = {store_id}_get(&world->{store}, {index});"
+                ));
+            }
+            add_statements(body, output);
+            output.unindent();
+            output.push_line("}");
+        }
+        Statement::Iterate {
+            list,
+            index,
+            item,
+            ty,
+            body,
+        } => {
+            let list = c_value(list);
+            output.push_line(&format!(
+                "for (uint32_t {index} = 0; {index} < {list}.{LIST_LENGTH_ID}; {index}++) {{"
+            ));
+            output.indent();
+            output.push_line(&format!(
+                "{} {item} = {list}.{LIST_ITEMS_ID}[{index}];",
+                base_primitive(ty)
+            ));
+            // Loops that only count the items don't use them
+            output.push_line(&format!("(void){item};"));
+            add_statements(body, output);
+            output.unindent();
+            output.push_line("}");
+        }
+        Statement::Emit {
+            store,
+            store_id,
+            component,
+            fields,
+        } => {
+            if fields.is_empty() {
+                output.push_line(&format!("{store_id}_push(&world->{store});"));
+            } else {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|f| format!(".{} = {}", f.id, c_value(&f.value)))
+                    .collect();
+                output.push_line(&format!(
+                    "{store_id}_push(&world->{store}, ({component}){{ {} }});",
+                    fields.join(", ")
+                ));
+            }
+        }
+        Statement::Create {
+            entity,
+            max_entities,
+            components,
+        } => {
+            output.push_line(&format!(
+                "if (world->{WORLD_NEXT_ENTITY_ID} < {max_entities}) {{"
+            ));
+            output.indent();
+            output.push_line(&format!(
+                "{entity} created = {{ world->{WORLD_NEXT_ENTITY_ID}++ }};"
+            ));
+            for created in components {
+                let CreatedComponent {
+                    store,
+                    store_id,
+                    component,
+                    fields,
+                } = created;
+                if fields.is_empty() {
+                    output.push_line(&format!("{store_id}_add(&world->{store}, created);"));
+                } else {
+                    let fields: Vec<String> = fields
+                        .iter()
+                        .map(|f| format!(".{} = {}", f.id, c_value(&f.value)))
+                        .collect();
+                    output.push_line(&format!(
+                        "{store_id}_add(&world->{store}, created, ({component}){{ {} }});",
+                        fields.join(", ")
+                    ));
                 }
             }
+            output.unindent();
+            output.push_line("}");
+        }
+        Statement::If {
+            condition,
+            then,
+            otherwise,
+        } => {
+            output.push_line(&format!("if ({}) {{", c_condition(condition)));
+            add_branch(then, otherwise, output);
+        }
+        Statement::Match {
+            value,
+            arms,
+            otherwise,
+        } => {
+            output.push_line(&format!("switch ({}) {{", c_value(value)));
+            output.indent();
+            let cases = arms
+                .iter()
+                .map(|arm| (format!("case {}:", arm.tag), &arm.body))
+                .chain(otherwise.iter().map(|body| ("default:".to_string(), body)));
+            for (case, body) in cases {
+                output.push_line(&format!("{case} {{"));
+                output.indent();
+                add_statements(body, output);
+                output.push_line("break;");
+                output.unindent();
+                output.push_line("}");
+            }
+            output.unindent();
+            output.push_line("}");
+        }
+        Statement::Assign { target, op, value } => {
+            output.push_line(&format!(
+                "{} {} {};",
+                c_value(target),
+                op.symbol(),
+                c_value(value)
+            ));
+        }
+        Statement::MarkChanged { store, index } => match index {
+            Some(index) => output.push_line(&format!(
+                "world->{store}.changed[{}] = true;",
+                c_value(index)
+            )),
+            None => output.push_line(&format!("world->{store}.changed = true;")),
+        },
+        Statement::Declare { id, ty, value } => {
+            output.push_line(&format!(
+                "{} {} = {};",
+                base_primitive(ty),
+                id,
+                c_value(value)
+            ));
+            // Locals that are only assigned to, or match bindings an arm ignores, aren't read
+            output.push_line(&format!("(void){id};"));
+        }
+        Statement::Call(value) => output.push_line(&format!("{};", c_value(value))),
+    }
+}
+
+/// Adds the statements of an `if` after its opening line. A lone `if` in `otherwise` becomes an `else if`.
+fn add_branch(then: &[Statement], otherwise: &[Statement], output: &mut OutputBuilder) {
+    output.indent();
+    add_statements(then, output);
+    output.unindent();
+
+    match otherwise {
+        [] => output.push_line("}"),
+        [Statement::If {
+            condition,
+            then,
+            otherwise,
+        }] => {
+            output.push_line(&format!("}} else if ({}) {{", c_condition(condition)));
+            add_branch(then, otherwise, output);
         }
-        ExpressionStatement::Comment(_) => {
-            // Do nothing
+        otherwise => {
+            output.push_line("} else {");
+            output.indent();
+            add_statements(otherwise, output);
+            output.unindent();
+            output.push_line("}");
         }
     }
 }
 
+/// Returns the value without the parentheses it would have as an operand.
+fn c_condition(value: &Value) -> String {
+    match value {
+        Value::Has { index, capacity } => format!("{} < {capacity}", c_value(index)),
+        Value::Group(value) => c_value(value),
+        value => c_value(value),
+    }
+}
+
+fn c_value(value: &Value) -> String {
+    match value {
+        Value::Number { value, ty } => c_number(value, ty),
+        Value::Bool(b) => b.to_string(),
+        Value::Local(id) => id.clone(),
+        Value::Entity { store, index } => format!("world->{store}.entities[{index}]"),
+        Value::Index {
+            store,
+            store_id,
+            entity,
+        } => format!("{store_id}_index(&world->{store}, {})", c_value(entity)),
+        Value::Component {
+            store,
+            array,
+            index,
+            fields,
+        } => {
+            let mut access = format!("world->{store}.{array}[{}]", c_value(index));
+            for field in fields {
+                access.push('.');
+                access.push_str(field);
+            }
+            access
+        }
+        Value::Single { store, fields } => {
+            let mut access = format!("world->{store}.instance");
+            for field in fields {
+                access.push('.');
+                access.push_str(field);
+            }
+            access
+        }
+        Value::Gather {
+            store,
+            store_id,
+            index,
+        } => format!("{store_id}_get(&world->{store}, {})", c_value(index)),
+        Value::Changed { store, index } => {
+            format!("world->{store}.changed[{}]", c_value(index))
+        }
+        Value::Has { index, capacity } => format!("({} < {capacity})", c_value(index)),
+        Value::Coalesce {
+            index,
+            capacity,
+            value,
+            default,
+        } => format!(
+            "({} < {capacity} ? {} : {})",
+            c_value(index),
+            c_value(value),
+            c_value(default)
+        ),
+        Value::Variant { id, tag, payload } => match payload {
+            Some((field, value)) => format!(
+                "({id}){{ .{ENUM_TAG_ID} = {tag}, .{ENUM_PAYLOAD_ID}.{field} = {} }}",
+                c_value(value)
+            ),
+            None => format!("({id}){{ .{ENUM_TAG_ID} = {tag} }}"),
+        },
+        Value::Method {
+            list_id,
+            method,
+            list,
+            args,
+        } => {
+            let list = c_value(list);
+            if *method == ListMethod::Len {
+                return format!("{list}.{LIST_LENGTH_ID}");
+            }
+
+            let args: String = args.iter().map(|a| format!(", {}", c_value(a))).collect();
+            format!("{list_id}_{}(&{list}{args})", method.id())
+        }
+        Value::Field { target, field } => format!("{}.{field}", c_value(target)),
+        Value::Unary { op, value } => match value.as_ref() {
+            // The smallest i64 can't be written as a negated literal as its magnitude doesn't fit
+            Value::Number {
+                value: number,
+                ty: PrimitiveType::I64,
+            } if *op == UnaryOp::Negate && number.parse::<i64>().is_err() => {
+                "INT64_MIN".to_string()
+            }
+            // Keeps `- -x` from becoming a decrement
+            Value::Unary { .. } => format!("{}({})", op.symbol(), c_value(value)),
+            value => format!("{}{}", op.symbol(), c_value(value)),
+        },
+        Value::Binary { op, lhs, rhs } => {
+            // Mixed operators are grouped so the C compiler doesn't warn about precedence
+            let operand = |value: &Value| match value {
+                Value::Binary { op: inner, .. } if inner.precedence() != op.precedence() => {
+                    format!("({})", c_value(value))
+                }
+                value => c_value(value),
+            };
+            format!("{} {} {}", operand(lhs), op.symbol(), operand(rhs))
+        }
+        Value::Group(value) => match value.as_ref() {
+            // These are already in parentheses
+            Value::Has { .. } | Value::Coalesce { .. } => c_value(value),
+            value => format!("({})", c_value(value)),
+        },
+    }
+}
+
+/// The macro that leaves out the placeholder `main` so a host can include the generated code.
+const NO_MAIN_ID: &str = "D_NO_MAIN";
+
+/// The macro that enables the pthreads implementation of `world_run_threaded`.
+const PTHREADS_ID: &str = "D_PTHREADS";
+
+/// Adds a dispatch API that leaves threading to the host. Each job of a stage runs one system,
+/// and all jobs of a stage must finish before the next stage starts.
+fn add_world_dispatch(world: &World, stores: &[Store], output: &mut OutputBuilder) {
+    output.add_comment("Stages run in order. Systems within a stage can run in parallel.");
+    for (idx, stage) in world.stages.iter().enumerate() {
+        let names: Vec<&str> = stage.iter().map(|j| j.name.as_str()).collect();
+        output.add_comment(&format!("Stage {}: {}", idx + 1, names.join(" ")));
+    }
+    output.add_line();
+
+    let stage_count = world.stages.len();
+    let max_jobs = world
+        .stages
+        .iter()
+        .map(|s| s.len())
+        .max()
+        .unwrap_or_default();
+
+    output.push_line(&format!(
+        "typedef void (*D_SYSTEM_FN)({}* world);",
+        world.id
+    ));
+    output.add_line();
+
+    if stage_count > 0 {
+        for (idx, stage) in world.stages.iter().enumerate() {
+            let systems: Vec<&str> = stage.iter().map(|j| j.system.as_str()).collect();
+            output.push_line(&format!(
+                "static const D_SYSTEM_FN d_stage_{}_jobs[] = {{{}}};",
+                idx,
+                systems.join(", ")
+            ));
+        }
+
+        let stages: Vec<String> = (0..stage_count)
+            .map(|idx| format!("d_stage_{}_jobs", idx))
+            .collect();
+        output.push_line(&format!(
+            "static const D_SYSTEM_FN* const d_stages[] = {{{}}};",
+            stages.join(", ")
+        ));
+
+        let counts: Vec<String> = world.stages.iter().map(|s| s.len().to_string()).collect();
+        output.push_line(&format!(
+            "static const uint32_t d_stage_job_counts[] = {{{}}};",
+            counts.join(", ")
+        ));
+        output.add_line();
+    }
+
+    output.add_doc_comments(&[
+        "Returns the number of stages, which run one after another.".to_string()
+    ]);
+    output.push_line("uint32_t world_stage_count(void) {");
+    output.indent();
+    output.push_line(&format!("return {};", stage_count));
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(&[
+        "Returns the number of jobs in the stage, or 0 if the stage doesn't exist.".to_string(),
+    ]);
+    output.push_line("uint32_t world_stage_job_count(uint32_t stage) {");
+    output.indent();
+    if stage_count > 0 {
+        output.push_line(&format!(
+            "return stage < {} ? d_stage_job_counts[stage] : 0;",
+            stage_count
+        ));
+    } else {
+        output.push_line("(void)stage;");
+        output.push_line("return 0;");
+    }
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(&[
+        "Runs a single job of a stage. Jobs of the same stage can run on different threads."
+            .to_string(),
+        "Does nothing if the stage or job doesn't exist.".to_string(),
+    ]);
+    output.push_line(&format!(
+        "void world_run_job({}* world, uint32_t stage, uint32_t job) {{",
+        world.id
+    ));
+    output.indent();
+    if stage_count > 0 {
+        output.push_line("if (job < world_stage_job_count(stage)) {");
+        output.indent();
+        output.push_line("d_stages[stage][job](world);");
+        output.unindent();
+        output.push_line("}");
+    } else {
+        output.push_line("(void)world;");
+        output.push_line("(void)stage;");
+        output.push_line("(void)job;");
+    }
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(
+        &[
+            "Clears the changed flags of every component and empties the event queues, ending the tick."
+                .to_string(),
+            "Hosts running jobs themselves call this after the last stage.".to_string(),
+        ]
+    );
+    output.push_line(&format!("void world_clear_changes({}* world) {{", world.id));
+    output.indent();
+    if stores.is_empty() {
+        output.push_line("(void)world;");
+    }
+    for store in stores.iter() {
+        if store.event {
+            output.push_line(&format!("{}_clear(&world->{});", store.id, store.name));
+        } else if store.single {
+            output.push_line(&format!("world->{}.changed = false;", store.name));
+        } else {
+            output.push_line(&format!(
+                "memset(world->{0}.changed, 0, sizeof(world->{0}.changed));",
+                store.name
+            ));
+        }
+    }
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    output.add_doc_comments(&[
+        "Runs every stage in order on the calling thread, then ends the tick.".to_string(),
+    ]);
+    output.push_line(&format!("void world_run({}* world) {{", world.id));
+    output.indent();
+    output.push_line("for (uint32_t stage = 0; stage < world_stage_count(); stage++) {");
+    output.indent();
+    output.push_line("for (uint32_t job = 0; job < world_stage_job_count(stage); job++) {");
+    output.indent();
+    output.push_line("world_run_job(world, stage, job);");
+    output.unindent();
+    output.push_line("}");
+    output.unindent();
+    output.push_line("}");
+    output.push_line("world_clear_changes(world);");
+    output.unindent();
+    output.push_line("}");
+    output.add_line();
+
+    // One job always runs on the calling thread, so at least one slot is needed
+    let helpers = PTHREADS_HELPERS
+        .replace("PTHREADS_ID", PTHREADS_ID)
+        .replace("WORLD_TYPE", &world.id)
+        .replace("MAX_JOBS", &max_jobs.max(1).to_string());
+    for line in helpers.lines() {
+        output.push_line(line);
+    }
+}
+
+/// A reference implementation of `world_run` that runs the jobs of each stage on their own thread.
+/// The first job of a stage runs on the calling thread, and jobs run inline if a thread can't be created.
+const PTHREADS_HELPERS: &str = "#ifdef PTHREADS_ID
+#include <pthread.h>
+
+typedef struct D_JOB {
+	WORLD_TYPE* world;
+	uint32_t stage;
+	uint32_t job;
+} D_JOB;
+
+static void* d_run_job_thread(void* arg) {
+	D_JOB* job = (D_JOB*)arg;
+	world_run_job(job->world, job->stage, job->job);
+	return NULL;
+}
+
+void world_run_threaded(WORLD_TYPE* world) {
+	D_JOB jobs[MAX_JOBS];
+	pthread_t threads[MAX_JOBS];
+	bool started[MAX_JOBS];
+	for (uint32_t stage = 0; stage < world_stage_count(); stage++) {
+		uint32_t count = world_stage_job_count(stage);
+		for (uint32_t job = 1; job < count; job++) {
+			jobs[job] = (D_JOB){world, stage, job};
+			started[job] = pthread_create(&threads[job], NULL, d_run_job_thread, &jobs[job]) == 0;
+			if (!started[job]) {
+				world_run_job(world, stage, job);
+			}
+		}
+		if (count > 0) {
+			world_run_job(world, stage, 0);
+		}
+		for (uint32_t job = 1; job < count; job++) {
+			if (started[job]) {
+				pthread_join(threads[job], NULL);
+			}
+		}
+	}
+	world_clear_changes(world);
+}
+#endif
+";
+
+/// The macro holding the number of snapshots, which can be overridden before compiling.
+const SNAPSHOT_SLOTS_ID: &str = "D_SNAPSHOT_SLOTS";
+
+const HASH_HELPERS: &str = "typedef struct D_HASHER {
+\tuint64_t hash;
+} D_HASHER;
+
+static inline void d_hash_bytes(D_HASHER* hasher, uint64_t value, size_t size) {
+\tfor (size_t i = 0; i < size; i++) {
+\t\thasher->hash ^= (uint8_t)(value >> (8 * i));
+\t\thasher->hash *= FNV_PRIME;
+\t}
+}
+
+static inline void d_hash_f32(D_HASHER* hasher, float value) {
+\tuint32_t bits;
+\tmemcpy(&bits, &value, sizeof(bits));
+\td_hash_bytes(hasher, bits, 4);
+}
+
+static inline void d_hash_f64(D_HASHER* hasher, double value) {
+\tuint64_t bits;
+\tmemcpy(&bits, &value, sizeof(bits));
+\td_hash_bytes(hasher, bits, 8);
+}";
+
+/// Returns the C expression for a value in the world.
+fn save_path(path: &[PathSegment]) -> String {
+    let mut expression = "world".to_string();
+    for (i, segment) in path.iter().enumerate() {
+        match segment {
+            PathSegment::Field(id) if i == 0 => expression.push_str(&format!("->{}", id)),
+            PathSegment::Field(id) => expression.push_str(&format!(".{}", id)),
+            PathSegment::Index(index) => expression.push_str(&format!("[i{}]", index)),
+        }
+    }
+
+    expression
+}
+
+/// The number of bytes a primitive takes up in a save.
+fn primitive_size(ty: &PrimitiveType) -> usize {
+    match ty {
+        PrimitiveType::Bool | PrimitiveType::Char => 1,
+        PrimitiveType::U32 | PrimitiveType::I32 | PrimitiveType::F32 => 4,
+        PrimitiveType::U64 | PrimitiveType::I64 | PrimitiveType::F64 => 8,
+        // Structs are flattened into their primitives by the save format
+        PrimitiveType::Identifier(_) => 0,
+    }
+}
+
 fn base_primitive(primitive_ty: &PrimitiveType) -> String {
     match primitive_ty {
         PrimitiveType::U32 => "uint32_t".to_string(),
         PrimitiveType::U64 => "uint64_t".to_string(),
         PrimitiveType::I32 => "int32_t".to_string(),
         PrimitiveType::I64 => "int64_t".to_string(),
-        PrimitiveType::F32 => todo!(),
-        PrimitiveType::F64 => todo!(),
+        PrimitiveType::F32 => "float".to_string(),
+        PrimitiveType::F64 => "double".to_string(),
         PrimitiveType::Bool => "bool".to_string(),
         PrimitiveType::Char => "char".to_string(),
-        PrimitiveType::Identifier(s) => format!("struct {}*", s),
+        // Structs are embedded by value, so they must be emitted in dependency order
+        PrimitiveType::Identifier(s) => s.to_string(),
     }
 }
 
 fn primitive_type(primitive: &ListType<Primitive>) -> String {
-    match primitive {
-        ListType::List { ty, max_size } => base_primitive(&ty.primitive_type),
-        ListType::Single(ty) => base_primitive(&ty.primitive_type),
-    }
+    base_primitive(&primitive.inner_ty().primitive_type)
 }
@@ -4,30 +4,61 @@ mod c_compiler;
 // mod js_compiler;
 mod output_builder;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::compiler_ir::*;
 pub use c_compiler::CCompiler;
 // pub use cpp_compiler::CppCompiler;
 // pub use csharp_compiler::CSharpCompiler;
 // pub use js_compiler::JSCompiler;
-pub use output_builder::OutputBuilder;
+pub use output_builder::{DocCommentStyle, OutputBuilder};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Artifact {
     pub contents: String,
     pub path: PathBuf,
     pub target: Target,
+    /// Whether the artifact is a starting point for host code, which is never overwritten.
+    pub stub: bool,
 }
 
+/// Writes the artifact to its path within the directory, returning whether it was written.
+/// Stubs are skipped when the file already exists so host implementations are kept.
+pub fn write_artifact(artifact: &Artifact, dir: &Path) -> std::io::Result<bool> {
+    let path = dir.join(&artifact.path);
+    if artifact.stub && path.exists() {
+        return Ok(false);
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, &artifact.contents)?;
+    Ok(true)
+}
+
+// Only the C backend is built. Cpp, JS and CSharp stay for the disabled backends above and
+// pick their doc comment styles, so they're never constructed.
+#[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum Target {
     C,
-    CPP,
+    Cpp,
     JS,
     CSharp,
 }
 
+impl Target {
+    /// The documentation comment syntax of the target language.
+    pub fn doc_comment_style(&self) -> DocCommentStyle {
+        match self {
+            Target::C | Target::JS => DocCommentStyle::Block,
+            Target::Cpp => DocCommentStyle::Line,
+            Target::CSharp => DocCommentStyle::Xml,
+        }
+    }
+}
+
 pub fn build(ir: Vec<IR>) -> Vec<Artifact> {
     let mut artifacts = vec![];
 
@@ -52,25 +83,704 @@ pub trait Compiler {
         // Break out IR into manageable chunks and sort them for consistency.
 
         let mut structs = Vec::new();
-        let mut expressions = Vec::new();
+        let mut stores = Vec::new();
+        let mut systems = Vec::new();
+        let mut world = None;
 
         for ir in ir {
             match ir {
                 IR::Struct(s) => structs.push(s),
-                IR::Expression(e) => expressions.push(e),
+                IR::Store(s) => stores.push(s),
+                IR::System(s) => systems.push(s),
+                IR::World(w) => world = Some(w),
             }
         }
 
         structs.sort_by(|a, b| a.value.id.cmp(&b.value.id));
-        expressions.sort_by(|a, b| a.value.id.cmp(&b.value.id));
+        stores.sort_by(|a, b| a.id.cmp(&b.id));
+        systems.sort_by(|a, b| a.value.id.cmp(&b.value.id));
 
-        self.compile_artifacts(structs, expressions)
+        let structs = sort_by_dependencies(structs);
+
+        self.compile_artifacts(structs, stores, systems, world)
     }
 
     /// Compiles the given artifacts into the target langauge.
     fn compile_artifacts(
         &self,
         structs: Vec<Commentable<Struct>>,
-        expressions: Vec<Commentable<Expression>>,
+        stores: Vec<Store>,
+        systems: Vec<Commentable<System>>,
+        world: Option<World>,
     ) -> Vec<Artifact>;
 }
+
+/// Orders structs so that every struct comes after the structs it contains by value.
+/// Structs that are ready at the same time keep their existing order.
+pub fn sort_by_dependencies(mut structs: Vec<Commentable<Struct>>) -> Vec<Commentable<Struct>> {
+    let mut sorted: Vec<Commentable<Struct>> = Vec::with_capacity(structs.len());
+
+    while !structs.is_empty() {
+        let is_ready = |s: &Commentable<Struct>| {
+            s.value.dependencies().iter().all(|dependency| {
+                *dependency == s.value.id || !structs.iter().any(|s| s.value.id == *dependency)
+            })
+        };
+
+        // Cycles are rejected by the env, but fall back to the existing order just in case
+        let idx = structs.iter().position(is_ready).unwrap_or(0);
+        sorted.push(structs.remove(idx));
+    }
+
+    sorted
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{env, ir_builder, location::Location, parser, unchecked_env};
+
+    fn ir(code: &str) -> Vec<IR> {
+        let ast = parser::parse(code, Location::default()).unwrap();
+        let env = env::build(unchecked_env::build(ast)).unwrap();
+        ir_builder::build(env).unwrap()
+    }
+
+    fn c_output(code: &str) -> String {
+        CCompiler.compile(ir(code)).remove(0).contents
+    }
+
+    /// Builds the generated C with the system compiler and returns its errors.
+    /// Returns None when no C compiler is installed.
+    fn cc(code: &str, flags: &[&str]) -> Option<Result<(), String>> {
+        cc_host(code, flags, None)
+    }
+
+    /// Builds the generated C with a host `main` and runs it, returning its errors or the
+    /// errors of the build. Returns None when no C compiler is installed.
+    pub(crate) fn cc_run(code: &str, host: &str) -> Option<Result<(), String>> {
+        cc_host(code, &[], Some(host))
+    }
+
+    fn cc_host(code: &str, flags: &[&str], host: Option<&str>) -> Option<Result<(), String>> {
+        static BUILDS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let build = BUILDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let dir =
+            std::env::temp_dir().join(format!("game_dsl_cc_{}_{}", std::process::id(), build));
+        for artifact in CCompiler.compile(ir(code)) {
+            write_artifact(&artifact, &dir).unwrap();
+        }
+
+        let source = match host {
+            Some(host) => {
+                let source = format!("#define D_NO_MAIN\n#include \"main.c\"\n\n{host}");
+                std::fs::write(dir.join("host.c"), source).unwrap();
+                "host.c"
+            }
+            None => "main.c",
+        };
+        let output = std::process::Command::new("cc")
+            .args(["-std=c99", "-Wall", "-Wextra", "-Werror"])
+            .args(flags)
+            .args([source, "-o", "main"])
+            .current_dir(&dir)
+            .output()
+            .and_then(|output| match host {
+                Some(_) if output.status.success() => {
+                    std::process::Command::new(dir.join("main")).output()
+                }
+                _ => Ok(output),
+            });
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let output = output.ok()?;
+        if output.status.success() {
+            Some(Ok(()))
+        } else {
+            Some(Err(format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            )))
+        }
+    }
+
+    #[test]
+    fn structs_are_sorted_by_dependencies() {
+        let ir = ir("struct A { C c B b }\nstruct B { C c }\nstruct C;\nstruct D;");
+
+        let structs = ir
+            .into_iter()
+            .filter_map(|ir| match ir {
+                IR::Struct(s) if s.value.id.starts_with("D_STRUCT_") => Some(s),
+                _ => None,
+            })
+            .collect();
+        let ids: Vec<String> = sort_by_dependencies(structs)
+            .into_iter()
+            .map(|s| s.value.id)
+            .collect();
+
+        let position = |id: &str| ids.iter().position(|i| i == id).unwrap();
+        assert!(position("D_STRUCT_C") < position("D_STRUCT_B"));
+        assert!(position("D_STRUCT_B") < position("D_STRUCT_A"));
+    }
+
+    #[test]
+    fn c_embeds_structs_and_arrays_by_value() {
+        let output = c_output("struct Name { [char 10] name f32 scale }\ncomponent Person { Name name [Name 2] aliases }");
+
+        assert!(output.contains("\tD_LIST_CHAR_10 name;\n"));
+        assert!(output.contains("\tfloat scale;\n"));
+        assert!(output.contains("\tD_STRUCT_NAME name;\n"));
+        assert!(output.contains("\tD_LIST_NAME_2 aliases;\n"));
+        assert!(output.contains("\tuint32_t length;\n\tD_STRUCT_NAME items[2];\n} D_LIST_NAME_2;"));
+
+        let name = output.find("} D_STRUCT_NAME;").unwrap();
+        let aliases = output.find("} D_LIST_NAME_2;").unwrap();
+        let person = output.find("} D_COMPONENT_PERSON;").unwrap();
+        assert!(name < aliases);
+        assert!(aliases < person);
+    }
+
+    #[test]
+    fn c_soa_store_accessors_use_field_arrays() {
+        let output = c_output("component Position soa { i32 x i32 y }\ncomponent Hp(i32);");
+
+        assert!(output.contains("\tint32_t components_x[1024];\n"));
+        assert!(output.contains("\tcomponent.y = store->components_y[index];\n"));
+        assert!(output.contains("\tstore->components_x[index] = component.x;\n"));
+        assert!(output.contains(
+            "static inline D_COMPONENT_HP D_STRUCT_HP_STORE_get(const D_STRUCT_HP_STORE* store, uint64_t index) {\n\treturn store->components[index];\n}"
+        ));
+    }
+
+    #[test]
+    fn c_world_save_and_load_follow_save_format() {
+        let output = c_output("component Position soa { f32 x f32 y }\ncomponent Hp(i32);");
+
+        assert!(output.contains("\tD_STRUCT_HP_STORE Hp;\n"));
+        assert!(output.contains(
+            "size_t world_save(const D_WORLD* world, uint8_t* buffer, size_t capacity) {"
+        ));
+        assert!(output.contains("\td_write_bytes(&writer, world->Hp.active_components, 8);\n"));
+        assert!(output.contains("\t\td_write_f32(&writer, world->Position.components_x[i0]);\n"));
+        assert!(output.contains(
+            "\t\tworld->Hp.components[i0].value = (int32_t)(uint32_t)d_read_bytes(&reader, 4);\n"
+        ));
+        assert!(output.contains("\tif (world->Hp.active_components > 1024) {\n"));
+    }
+
+    #[test]
+    fn c_world_checksum_hashes_save_values() {
+        let output = c_output("component Position soa { f32 x f32 y }\ncomponent Hp(i32);");
+
+        assert!(output.contains("\tD_HASHER hasher = { 0xcbf29ce484222325ULL };\n"));
+        assert!(output.contains("\td_hash_bytes(&hasher, world->Hp.active_components, 8);\n"));
+        assert!(output.contains("\t\td_hash_f32(&hasher, world->Position.components_y[i0]);\n"));
+        assert!(output.contains("#define D_SNAPSHOT_SLOTS 8\n"));
+        assert!(output.contains("bool world_restore(D_WORLD* world, uint64_t slot) {"));
+    }
+
+    #[test]
+    fn c_json_uses_field_ids_and_char_strings() {
+        let output = c_output("component Person { [char 8] name char initial [i32 2] scores }");
+
+        assert!(output.contains(
+            "size_t D_COMPONENT_PERSON_to_json(const D_COMPONENT_PERSON* value, char* buffer, size_t capacity) {"
+        ));
+        assert!(output.contains("\td_json_write_raw(writer, \",\\\"initial\\\":\");\n"));
+        assert!(output.contains("\tD_LIST_CHAR_8_write_json(writer, &value->name);\n"));
+        assert!(output.contains("\td_json_write_string(writer, value->items, value->length);\n"));
+        assert!(output.contains("\t\t\tif (d_json_read_string(reader, &value->initial, 1) > 1) {\n\t\t\t\treader->ok = false;\n\t\t\t}\n"));
+        assert!(output.contains(
+            "\t\tvalue->items[value->length] = (int32_t)d_json_read_i64(reader, INT32_MIN, INT32_MAX);\n"
+        ));
+    }
+
+    #[test]
+    fn c_world_lists_scheduled_stages() {
+        let output = c_output(
+            "component Position;\nsystem Move { write [Position] }\nsystem Render { read [Position] }\nsystem Idle {}\nworld auto { [Move Idle Render] }",
+        );
+
+        assert!(output.contains("// Stage 1: Move Idle\n// Stage 2: Render\n"));
+        assert!(output.contains("void d_system_idle(D_WORLD* world) {\n"));
+        assert!(output.contains(
+            "static const D_SYSTEM_FN d_stage_0_jobs[] = {d_system_move, d_system_idle};\n"
+        ));
+        assert!(output.contains("static const uint32_t d_stage_job_counts[] = {2, 1};\n"));
+        assert!(output.contains("\tD_JOB jobs[2];\n"));
+    }
+
+    #[test]
+    fn c_full_example_builds() {
+        let code = "# A name padded to eight characters.
+struct Name([char 8]);
+
+struct Vec2 {
+    f32 x
+    f32 y
+}
+
+enum Effect {
+    None,
+    Burning(i32),
+    Pushed(Vec2),
+}
+
+component Position {
+    f32 x
+    f32 y
+}
+
+component Velocity soa {
+    f32 x
+    f32 y
+}
+
+component Hp(i32);
+component IsDead;
+component Afflicted(Effect);
+component Person {
+    Name name
+    u64 id
+}
+component Collisions([Entity 4]);
+
+single_component Input {
+    bool jump
+    u32 frame
+}
+
+event Hit {
+    Entity target
+    i32 damage
+}
+
+system Move {
+    read [Velocity Input IsDead]
+    write [Position]
+
+    input = single Input
+    for e with Position, Velocity? without IsDead {
+        speed = 2.0
+        if input.jump && e has Velocity {
+            e.Position.x += e.Velocity.x * speed
+        }
+        e.Position.y += (e.Velocity.y ?? 0.5) * 0.5
+    }
+}
+
+system Collide {
+    read [Position IsDead]
+    write [Collisions Hit]
+
+    for e with Position, Collisions changed Position {
+        e.Collisions.clear()
+        for other with Position {
+            if e != other && e.Collisions.contains(other) == false {
+                e.Collisions.push(other)
+                emit Hit(other, 1)
+            }
+        }
+        for c in e.Collisions {
+            if c == e {
+                last = e.Collisions.pop()
+                emit Hit(last, 2)
+            }
+        }
+        if e.Collisions.len() > 2 {
+            e.Collisions.remove_at(0)
+        }
+    }
+}
+
+system Burn {
+    read [Hit]
+    write [Hp Afflicted Position IsDead]
+
+    for ev in events Hit {
+        for e with Hp {
+            if e == ev.target {
+                e.Hp -= ev.damage
+                if e.Hp <= 0 {
+                    create [Position(0, 0) IsDead]
+                }
+            }
+        }
+    }
+    for e with Hp, Afflicted {
+        match e.Afflicted {
+            Burning(damage) {
+                e.Hp -= damage
+            }
+            Pushed(by) {}
+            None {}
+        }
+    }
+}
+
+external_system Render {
+    write [Person]
+
+    target_c_impl \"render.c\"
+}
+
+world auto { [Move Collide Burn Render] }";
+
+        if let Some(result) = cc(code, &[]) {
+            assert_eq!(Ok(()), result);
+        }
+    }
+
+    #[test]
+    fn c_unused_locals_and_extreme_literals_build() {
+        let code = "component Limits { i64 low u64 high i32 n }
+system Reset {
+    write [Limits]
+    for e with Limits {
+        unused = 1
+        e.Limits.low = -9223372036854775808
+        e.Limits.high = 18446744073709551615
+        e.Limits.n = -2147483648
+    }
+}
+world { [Reset] }";
+
+        let output = c_output(code);
+        assert!(output.contains("\t\tint32_t l_unused = 1;\n\t\t(void)l_unused;\n"));
+        assert!(output.contains(".low = INT64_MIN;\n"));
+        assert!(output.contains(".high = 18446744073709551615u;\n"));
+        if let Some(result) = cc(code, &[]) {
+            assert_eq!(Ok(()), result);
+        }
+    }
+
+    #[test]
+    fn c_threaded_dispatch_builds() {
+        let code = "component Position;\ncomponent Velocity;\nsystem Move { write [Position] read [Velocity] }\nsystem Push { write [Velocity] }\nsystem Render { read [Position] }\nworld auto { [Move Push Render] }";
+
+        if let Some(result) = cc(code, &["-DD_PTHREADS", "-pthread"]) {
+            assert_eq!(Ok(()), result);
+        }
+    }
+
+    #[test]
+    fn c_reflection_describes_components() {
+        let output = c_output("struct Name([char 8]);\ncomponent Person { Name name u64 id }");
+
+        assert!(output.contains(
+            "\t{ \"name\", D_TYPE_STRUCT, &D_STRUCT_NAME_type_info, offsetof(D_COMPONENT_PERSON, name), 0 },\n"
+        ));
+        assert!(output.contains(
+            "\t{ \"value\", D_TYPE_STRUCT, &D_LIST_CHAR_8_type_info, offsetof(D_STRUCT_NAME, value), 0 },\n"
+        ));
+        assert!(output
+            .contains("\t{ \"items\", D_TYPE_CHAR, NULL, offsetof(D_LIST_CHAR_8, items), 8 },\n"));
+        assert!(output.contains(
+            "const D_TYPE_INFO D_COMPONENT_PERSON_type_info = { \"Person\", sizeof(D_COMPONENT_PERSON), 2, D_COMPONENT_PERSON_field_info };\n"
+        ));
+        assert!(output.contains(
+            "const D_TYPE_INFO* const d_component_types[] = { &D_COMPONENT_PERSON_type_info };\n"
+        ));
+    }
+
+    #[test]
+    fn c_number_literals_take_on_their_type() {
+        let output = c_output(
+            "component Motion { f32 speed f64 far u64 id i32 hp }\nsystem Move {\n    write [Motion]\n    for e with Motion {\n        e.Motion.speed = 2 / 3\n        e.Motion.far = 1e300\n        e.Motion.id = 18446744073709551615\n        e.Motion.hp = 007\n        half = 0.5\n    }\n}\nworld { [Move] }",
+        );
+
+        assert!(output.contains("].speed = 2.0f / 3.0f;\n"));
+        assert!(output.contains("].far = 1e300;\n"));
+        assert!(output.contains("].id = 18446744073709551615u;\n"));
+        assert!(output.contains("].hp = 7;\n"));
+        assert!(output.contains("\t\tfloat l_half = 0.5f;\n"));
+    }
+
+    #[test]
+    fn c_system_body_checks_optional_components() {
+        let output = c_output(
+            "component Position { f32 x }\ncomponent Velocity soa { f32 x }\ncomponent Boost(f32);\nsystem Move {\n    read [Velocity Boost]\n    write [Position]\n    for e with Position, Velocity, Boost? {\n        if e has Boost {\n            e.Position.x += e.Boost\n        }\n        e.Position.x += e.Velocity.x * (e.Boost ?? 1)\n    }\n}\nworld { [Move] }",
+        );
+
+        assert!(output.contains("\tfor (uint64_t i_e_Position = 0; i_e_Position < world->Position.active_components; i_e_Position++) {\n\t\tuint64_t i_e_Velocity = D_STRUCT_VELOCITY_STORE_index(&world->Velocity, world->Position.entities[i_e_Position]);\n\t\tif (i_e_Velocity == 1024) {\n\t\t\tcontinue;\n\t\t}\n"));
+        assert!(output.contains(
+            "\t\tif (D_STRUCT_BOOST_STORE_index(&world->Boost, world->Position.entities[i_e_Position]) < 1024) {\n"
+        ));
+        assert!(output.contains("world->Position.components[i_e_Position].x += world->Velocity.components_x[i_e_Velocity] * (D_STRUCT_BOOST_STORE_index(&world->Boost, world->Position.entities[i_e_Position]) < 1024 ? world->Boost.components[D_STRUCT_BOOST_STORE_index(&world->Boost, world->Position.entities[i_e_Position])].value : 1.0f);\n"));
+        assert!(output.contains("\tif (!D_STRUCT_BOOST_STORE_reindex(&world->Boost)) {\n"));
+    }
+
+    #[test]
+    fn c_query_filters_skip_excluded_and_unchanged_entities() {
+        let output = c_output(
+            "component Position { f32 x }\ncomponent IsDead;\nsystem Move {\n    read [IsDead]\n    write [Position]\n    for e with Position without IsDead {\n        e.Position.x += 1\n    }\n}\nsystem Render {\n    read [Position]\n    for e with Position changed Position {}\n}\nworld { [Move] [Render] }",
+        );
+
+        assert!(output.contains("\t\tif (D_STRUCT_ISDEAD_STORE_index(&world->IsDead, world->Position.entities[i_e_Position]) < 1024) {\n\t\t\tcontinue;\n\t\t}\n"));
+        assert!(output.contains("\t\tworld->Position.components[i_e_Position].x += 1.0f;\n\t\tworld->Position.changed[i_e_Position] = true;\n"));
+        assert!(output.contains(
+            "\t\tif (!world->Position.changed[i_e_Position]) {\n\t\t\tcontinue;\n\t\t}\n"
+        ));
+        assert!(output.contains("\tstore->changed[index] = store->changed[last];\n"));
+        assert!(output
+            .contains("\tmemset(world->Position.changed, 0, sizeof(world->Position.changed));\n"));
+        assert!(output.contains("\t}\n\tworld_clear_changes(world);\n}\n"));
+    }
+
+    #[test]
+    fn c_single_components_have_one_instance() {
+        let output = c_output(
+            "single_component WorldState { u64 frame }\nsingle_component Score(i32);\nsystem Tick {\n    write [WorldState Score]\n    state = single WorldState\n    state.frame += 1\n    single Score += 2\n}\nworld { [Tick] }",
+        );
+
+        assert!(output.contains("\tD_COMPONENT_WORLDSTATE instance;\n\tbool changed;\n"));
+        assert!(output.contains(
+            "static inline void D_STRUCT_WORLDSTATE_STORE_set(D_STRUCT_WORLDSTATE_STORE* store, D_COMPONENT_WORLDSTATE component) {"
+        ));
+        assert!(!output.contains("D_STRUCT_WORLDSTATE_STORE_add"));
+        assert!(output.contains(
+            "\tworld->WorldState.instance.frame += 1;\n\tworld->WorldState.changed = true;\n"
+        ));
+        assert!(output.contains("\tworld->Score.instance.value += 2;\n"));
+        assert!(output.contains(
+            "\td_write_bytes(&writer, (uint64_t)world->WorldState.instance.frame, 8);\n"
+        ));
+        assert!(output.contains("\tworld->Score.changed = false;\n"));
+    }
+
+    #[test]
+    fn c_single_components_have_world_accessors() {
+        let output =
+            c_output("single_component Input { f32 x f32 y bool jump }\nsingle_component Paused;");
+
+        assert!(output.contains(
+            "D_COMPONENT_INPUT world_get_Input(const D_WORLD* world) {\n\treturn D_STRUCT_INPUT_STORE_get(&world->Input);\n}\n"
+        ));
+        assert!(output.contains(
+            "void world_set_Input(D_WORLD* world, const D_COMPONENT_INPUT* value) {\n\tD_STRUCT_INPUT_STORE_set(&world->Input, *value);\n}\n"
+        ));
+        assert!(!output.contains("world_set_Paused"));
+    }
+
+    #[test]
+    fn c_external_systems_include_host_stubs() {
+        let artifacts = CCompiler.compile(ir(
+            "component Sprite(u32);\nexternal_system Render {\n    write [Sprite]\n    target_c_impl \"graphics.c\"\n}\nworld { [Render] }",
+        ));
+        let output = &artifacts[0].contents;
+
+        assert!(!artifacts[0].stub);
+        assert!(output.contains("void d_system_render(D_WORLD* world);\n"));
+        assert!(output.contains("#include \"graphics.c\"\n"));
+
+        let stub = &artifacts[1];
+        assert!(stub.stub);
+        assert_eq!(Path::new("graphics.c"), stub.path);
+        assert!(stub
+            .contents
+            .contains("void d_system_render(D_WORLD* world) {\n\t(void)world;\n}\n"));
+    }
+
+    #[test]
+    fn c_external_systems_share_host_files() {
+        let artifacts = CCompiler.compile(ir(
+            "component Sound(u32);\nexternal_system Audio {\n    write [Sound]\n    target_c_impl \"host.c\"\n}\nexternal_system Music {\n    target_c_impl \"host.c\"\n}\nworld { [Audio] [Music] }",
+        ));
+        let output = &artifacts[0].contents;
+
+        assert_eq!(1, output.matches("#include \"host.c\"\n").count());
+        assert_eq!(2, artifacts.len());
+        let stub = &artifacts[1].contents;
+        assert!(stub.contains("void d_system_audio(D_WORLD* world) {\n"));
+        assert!(stub.contains("void d_system_music(D_WORLD* world) {\n"));
+    }
+
+    #[test]
+    fn c_events_are_queued_and_cleared() {
+        let output = c_output(
+            "component Hp(i32);\nevent Damage(i32);\nevent Reset;\nsystem Hit {\n    read [Hp]\n    write [Damage Reset]\n    for e with Hp {\n        emit Damage(e.Hp)\n    }\n    emit Reset\n}\nsystem Log {\n    read [Damage]\n    for ev in events Damage {\n        total = ev\n    }\n}\nworld { [Hit] [Log] }",
+        );
+
+        assert!(output.contains("\tD_COMPONENT_DAMAGE events[256];\n"));
+        assert!(output.contains(
+            "static inline bool D_STRUCT_DAMAGE_STORE_push(D_STRUCT_DAMAGE_STORE* store, D_COMPONENT_DAMAGE event) {"
+        ));
+        assert!(output.contains(
+            "static inline bool D_STRUCT_RESET_STORE_push(D_STRUCT_RESET_STORE* store) {"
+        ));
+        assert!(output.contains(
+            "\t\tD_STRUCT_DAMAGE_STORE_push(&world->Damage, (D_COMPONENT_DAMAGE){ .value = world->Hp.components[i_e_Hp].value });\n"
+        ));
+        assert!(output.contains("\tD_STRUCT_RESET_STORE_push(&world->Reset);\n"));
+        assert!(output.contains(
+            "\tfor (uint64_t i_ev_Damage = 0; i_ev_Damage < world->Damage.count; i_ev_Damage++) {\n\t\tD_COMPONENT_DAMAGE l_ev = D_STRUCT_DAMAGE_STORE_get(&world->Damage, i_ev_Damage);\n\t\tint32_t l_total = l_ev.value;\n"
+        ));
+        assert!(output.contains("\tD_STRUCT_DAMAGE_STORE_clear(&world->Damage);\n"));
+        assert!(!output.contains("D_STRUCT_DAMAGE_STORE_reindex"));
+    }
+
+    #[test]
+    fn c_create_adds_a_new_entity() {
+        let output = c_output(
+            "component Position { f32 x f32 y }\ncomponent IsAlive;\nsystem Spawn {\n    write [Position IsAlive]\n    create [Position(1, 2) IsAlive]\n}\nworld { [Spawn] }",
+        );
+
+        assert!(output.contains(
+            "\tif (world->next_entity < 1024) {\n\t\tD_STRUCT_ENTITY created = { world->next_entity++ };\n\t\tD_STRUCT_POSITION_STORE_add(&world->Position, created, (D_COMPONENT_POSITION){ .x = 1.0f, .y = 2.0f });\n\t\tD_STRUCT_ISALIVE_STORE_add(&world->IsAlive, created);\n\t}\n"
+        ));
+    }
+
+    #[test]
+    fn c_create_stops_at_max_entities() {
+        let code = "component Hp(i32);\nsystem Spawn {\n    write [Hp]\n    create [Hp(100)]\n}\nworld { [Spawn] }";
+        let host = "static D_WORLD world;
+
+int main(void) {
+\tworld_init(&world);
+\tfor (int i = 0; i < 1100; i++) {
+\t\tworld_run(&world);
+\t}
+\tif (world.next_entity != 1024 || world.Hp.active_components != 1024) {
+\t\tprintf(\"next_entity %llu, active_components %llu\\n\", (unsigned long long)world.next_entity, (unsigned long long)world.Hp.active_components);
+\t\treturn 1;
+\t}
+\treturn 0;
+}
+";
+
+        if let Some(result) = cc_run(code, host) {
+            assert_eq!(Ok(()), result);
+        }
+    }
+
+    #[test]
+    fn c_world_json_only_writes_live_entities() {
+        let code = "component Hp { i32 current }\ncomponent Frozen;\ncomponent Speed soa { f32 x }\nsingle_component Time(f32);\nevent Hit;\nworld {}";
+        let host = "static D_WORLD world;
+static D_WORLD loaded;
+static char json[256];
+static char again[256];
+
+int main(void) {
+\tworld_init(&world);
+\tD_STRUCT_ENTITY a = { 3 };
+\tD_STRUCT_ENTITY b = { 7 };
+\tworld.next_entity = 8;
+\tD_STRUCT_HP_STORE_add(&world.Hp, a, (D_COMPONENT_HP){ 5 });
+\tD_STRUCT_HP_STORE_add(&world.Hp, b, (D_COMPONENT_HP){ -2 });
+\tD_STRUCT_FROZEN_STORE_add(&world.Frozen, b);
+\tD_STRUCT_SPEED_STORE_add(&world.Speed, a, (D_COMPONENT_SPEED){ 1.5f });
+\tworld.Time.instance.value = 0.25f;
+\tif (D_WORLD_to_json(&world, json, sizeof(json)) == 0) {
+\t\tprintf(\"buffer too small\\n\");
+\t\treturn 1;
+\t}
+\tconst char* expected = \"{\\\"next_entity\\\":8,\\\"Frozen\\\":{\\\"7\\\":{}},\\\"Hp\\\":{\\\"3\\\":{\\\"current\\\":5},\\\"7\\\":{\\\"current\\\":-2}},\\\"Speed\\\":{\\\"3\\\":{\\\"x\\\":1.5}},\\\"Time\\\":{\\\"value\\\":0.25}}\";
+\tif (strcmp(json, expected) != 0) {
+\t\tprintf(\"%s\\n\", json);
+\t\treturn 1;
+\t}
+\tif (!D_WORLD_from_json(&loaded, json, strlen(json)) || !D_STRUCT_FROZEN_STORE_has(&loaded.Frozen, b)
+\t\t|| D_STRUCT_HP_STORE_get(&loaded.Hp, D_STRUCT_HP_STORE_index(&loaded.Hp, b)).current != -2) {
+\t\tprintf(\"round trip failed\\n\");
+\t\treturn 1;
+\t}
+\tD_WORLD_to_json(&loaded, again, sizeof(again));
+\tif (strcmp(json, again) != 0) {
+\t\tprintf(\"%s\\n\", again);
+\t\treturn 1;
+\t}
+\tconst char* past_limit = \"{\\\"Frozen\\\":{\\\"1024\\\":{}}}\";
+\tif (D_WORLD_from_json(&loaded, past_limit, strlen(past_limit))) {
+\t\tprintf(\"accepted an entity past the limit\\n\");
+\t\treturn 1;
+\t}
+\treturn 0;
+}
+";
+
+        let output = c_output(code);
+        assert!(!output.contains("D_STRUCT_HP_STORE_to_json"));
+        if let Some(result) = cc_run(code, host) {
+            assert_eq!(Ok(()), result);
+        }
+    }
+
+    #[test]
+    fn c_stores_index_entities() {
+        let output = c_output("component Frozen;");
+
+        assert!(output.contains("\tuint32_t indices[1024];\n"));
+        assert!(output.contains(
+            "static inline bool D_STRUCT_FROZEN_STORE_add(D_STRUCT_FROZEN_STORE* store, D_STRUCT_ENTITY entity) {"
+        ));
+        assert!(output.contains("\t\tstore->indices[moved.value] = (uint32_t)(index + 1);\n"));
+    }
+
+    #[test]
+    fn c_enums_are_tagged_unions() {
+        let output = c_output(
+            "enum Status { Ok, Damaged(i32) }\ncomponent Health(Status);
+system Heal {
+    write [Health]
+    for e with Health {
+        match e.Health {
+            Damaged(amount) {
+                e.Health = Status.Damaged(amount - 1)
+            }
+            else {}
+        }
+    }
+}
+world { [Heal] }",
+        );
+
+        assert!(output.contains("union D_PAYLOAD_STATUS;\n"));
+        assert!(output.contains("enum {\n\tD_TAG_STATUS_OK = 0,\n\tD_TAG_STATUS_DAMAGED = 1,\n};"));
+        assert!(output
+            .contains("typedef union D_PAYLOAD_STATUS {\n\tint32_t Damaged;\n} D_PAYLOAD_STATUS;"));
+        assert!(output.contains("\tD_PAYLOAD_STATUS payload;\n} D_STRUCT_STATUS;"));
+        assert!(output.contains(
+            "\t\tswitch (world->Health.components[i_e_Health].value.tag) {\n\t\t\tcase D_TAG_STATUS_DAMAGED: {\n\t\t\t\tint32_t l_amount = world->Health.components[i_e_Health].value.payload.Damaged;\n\t\t\t\t(void)l_amount;\n"
+        ));
+        assert!(output.contains(
+            "= (D_STRUCT_STATUS){ .tag = D_TAG_STATUS_DAMAGED, .payload.Damaged = l_amount - 1 };\n"
+        ));
+        assert!(output.contains("\t\t\tdefault: {\n\t\t\t\tbreak;\n\t\t\t}\n"));
+        assert!(
+            output.contains("\t\t\td_json_write_raw(writer, \"{\\\"variant\\\":\\\"Ok\\\"}\");\n")
+        );
+        assert!(output.contains("\t\tif (world->Health.components[i0].value.tag >= 2) {\n"));
+    }
+
+    #[test]
+    fn c_lists_track_their_length() {
+        let output = c_output(
+            "component Scores { [i32 3] values i32 total }
+system Count {
+    write [Scores]
+    for e with Scores {
+        for v in e.Scores.values {
+            e.Scores.total += v
+        }
+        if e.Scores.values.len() < 3 {
+            e.Scores.values.push(e.Scores.total)
+        }
+        last = e.Scores.values.pop()
+    }
+}",
+        );
+
+        assert!(output.contains("\tuint32_t length;\n\tint32_t items[3];\n} D_LIST_I32_3;"));
+        assert!(output.contains(
+            "static inline bool D_LIST_I32_3_push(D_LIST_I32_3* list, int32_t item) {\n\tif (list->length >= 3) {\n\t\treturn false;\n\t}\n"
+        ));
+        assert!(output.contains("\t\tfor (uint32_t i_v_items = 0; i_v_items < world->Scores.components[i_e_Scores].values.length; i_v_items++) {\n\t\t\tint32_t l_v = world->Scores.components[i_e_Scores].values.items[i_v_items];\n"));
+        assert!(output.contains("\t\tif (world->Scores.components[i_e_Scores].values.length < 3) {\n\t\t\tD_LIST_I32_3_push(&world->Scores.components[i_e_Scores].values, world->Scores.components[i_e_Scores].total);\n"));
+        assert!(output.contains("\t\tint32_t l_last = D_LIST_I32_3_pop(&world->Scores.components[i_e_Scores].values);\n"));
+        assert!(output.contains("\t\tif (world->Scores.components[i0].values.length > 3) {\n"));
+    }
+}
@@ -1,8 +1,20 @@
+/// The syntax used for documentation comments.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocCommentStyle {
+    /// `/** ... */` blocks, as used by Doxygen and JSDoc.
+    Block,
+    /// `///` lines.
+    Line,
+    /// `/// <summary>` XML documentation.
+    Xml,
+}
+
 pub struct OutputBuilder {
     output: String,
     indent_symbol: String,
     indent_level: usize,
     comment_symbol: String,
+    doc_comment_style: DocCommentStyle,
 }
 impl OutputBuilder {
     /// Creates a new output builder.
@@ -12,6 +24,7 @@ impl OutputBuilder {
             comment_symbol: comment_symbol.to_string(),
             output: String::new(),
             indent_level: 0,
+            doc_comment_style: DocCommentStyle::Block,
         };
 
         let version = env!("CARGO_PKG_VERSION");
@@ -29,6 +42,12 @@ impl OutputBuilder {
         s
     }
 
+    /// Sets the syntax used by `add_doc_comments`. Defaults to `/** */` blocks.
+    pub fn with_doc_comment_style(mut self, style: DocCommentStyle) -> Self {
+        self.doc_comment_style = style;
+        self
+    }
+
     /// Adds a comment to the code.
     pub fn add_comment(&mut self, comment: &str) {
         self.push_line(&format!("{} {}", self.comment_symbol, comment));
@@ -41,6 +60,41 @@ impl OutputBuilder {
         }
     }
 
+    /// Adds documentation comments in the builder's style. Does nothing if there are no comments.
+    pub fn add_doc_comments(&mut self, comments: &[String]) {
+        if comments.is_empty() {
+            return;
+        }
+
+        match self.doc_comment_style {
+            DocCommentStyle::Block => {
+                self.push_line("/**");
+                for comment in comments {
+                    // A `*/` in the comment would end the block early
+                    let comment = comment.replace("*/", "* /");
+                    self.push_line(format!(" * {}", comment).trim_end());
+                }
+                self.push_line(" */");
+            }
+            DocCommentStyle::Line => {
+                for comment in comments {
+                    self.push_line(format!("/// {}", comment).trim_end());
+                }
+            }
+            DocCommentStyle::Xml => {
+                self.push_line("/// <summary>");
+                for comment in comments {
+                    let comment = comment
+                        .replace('&', "&amp;")
+                        .replace('<', "&lt;")
+                        .replace('>', "&gt;");
+                    self.push_line(format!("/// {}", comment).trim_end());
+                }
+                self.push_line("/// </summary>");
+            }
+        }
+    }
+
     pub fn add_multiline_section(&mut self, sections: &Vec<String>) {
         let terminal_width = 80;
 
@@ -100,11 +154,6 @@ impl OutputBuilder {
         }
     }
 
-    /// Pushes the string to the output.
-    pub fn push(&mut self, s: &str) {
-        self.output.push_str(s);
-    }
-
     /// Pushes the string to the output and adds a new line.
     pub fn push_line(&mut self, line: &str) {
         self.add_indentation();
@@ -122,3 +171,28 @@ impl OutputBuilder {
         self.output
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc_comments(style: DocCommentStyle) -> String {
+        let mut output = OutputBuilder::new("Test", "\t", "//").with_doc_comment_style(style);
+        let start = output.output.len();
+        output.add_doc_comments(&["A <b> & c".to_string(), "".to_string()]);
+        output.build()[start..].to_string()
+    }
+
+    #[test]
+    fn add_doc_comments_uses_the_builder_style() {
+        assert_eq!(
+            "/**\n * A <b> & c\n *\n */\n",
+            doc_comments(DocCommentStyle::Block)
+        );
+        assert_eq!("/// A <b> & c\n///\n", doc_comments(DocCommentStyle::Line));
+        assert_eq!(
+            "/// <summary>\n/// A &lt;b&gt; &amp; c\n///\n/// </summary>\n",
+            doc_comments(DocCommentStyle::Xml)
+        );
+    }
+}
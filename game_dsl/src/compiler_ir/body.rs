@@ -0,0 +1,207 @@
+use crate::parser::{AssignOp, BinaryOp, ListMethod, PrimitiveType, UnaryOp};
+
+/// A statement of a system body. Components are accessed through the stores of the world.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Comment(Vec<String>),
+    /// Runs the body for every component of a store.
+    /// Entities missing a required component of another store are skipped.
+    Query {
+        /// The field of the world with the store to iterate.
+        store: String,
+        /// The local holding the index into the store.
+        index: String,
+        required: Vec<Lookup>,
+        /// Conditions checked after the lookups that skip the entity when true.
+        skip: Vec<Value>,
+        body: Vec<Statement>,
+    },
+    /// Runs the body for every event in a queue, oldest first.
+    Events {
+        /// The field of the world with the queue to read.
+        store: String,
+        store_id: String,
+        /// The local holding the position in the queue.
+        index: String,
+        /// The local holding a copy of the event, unless the event is a tag.
+        event: Option<String>,
+        /// The id of the event struct.
+        component: String,
+        body: Vec<Statement>,
+    },
+    /// Runs the body for every item of a list in order, with a copy of the item in a local.
+    Iterate {
+        list: Value,
+        /// The local holding the position in the list.
+        index: String,
+        item: String,
+        ty: PrimitiveType,
+        body: Vec<Statement>,
+    },
+    /// Pushes an event onto its queue.
+    Emit {
+        store: String,
+        store_id: String,
+        component: String,
+        fields: Vec<FieldValue>,
+    },
+    /// Creates an entity with the world's next entity id and adds each component to it.
+    /// Ids aren't reused, so nothing is created once the ids below `max_entities` are used up.
+    Create {
+        /// The id of the entity struct.
+        entity: String,
+        max_entities: usize,
+        components: Vec<CreatedComponent>,
+    },
+    If {
+        condition: Value,
+        then: Vec<Statement>,
+        otherwise: Vec<Statement>,
+    },
+    /// Runs the arm whose tag is the tag of the enum value, or `otherwise` if no arm has it.
+    Match {
+        value: Value,
+        arms: Vec<MatchArm>,
+        otherwise: Option<Vec<Statement>>,
+    },
+    Assign {
+        target: Value,
+        op: AssignOp,
+        value: Value,
+    },
+    /// Flags the component at an index of a store as written this tick.
+    /// Stores of single components have no index.
+    MarkChanged {
+        store: String,
+        index: Option<Value>,
+    },
+    /// Declares a local with its first value.
+    Declare {
+        id: String,
+        ty: PrimitiveType,
+        value: Value,
+    },
+    /// Computes a value for its effect, e.g. a method that changes a list.
+    Call(Value),
+}
+
+/// An index of an entity's component kept in a local.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lookup {
+    pub id: String,
+    pub index: Value,
+    /// The capacity of the store, which is the index when the entity has no component.
+    pub capacity: usize,
+}
+
+/// An arm of a match. The body starts by declaring the binding of the variant's value, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    /// The constant of the variant's tag.
+    pub tag: String,
+    pub body: Vec<Statement>,
+}
+
+/// A component added to a created entity. Tags have no fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreatedComponent {
+    /// The field of the world with the store to add to.
+    pub store: String,
+    pub store_id: String,
+    pub component: String,
+    pub fields: Vec<FieldValue>,
+}
+
+/// The value given to a field of an emitted event or a created component.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldValue {
+    pub id: String,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A number as written in the source, along with the type it takes on.
+    Number {
+        value: String,
+        ty: PrimitiveType,
+    },
+    Bool(bool),
+    Local(String),
+    /// The entity that owns the component at an index of a store.
+    Entity {
+        store: String,
+        index: String,
+    },
+    /// The index of the entity's component in a store, or the capacity of the store if it has none.
+    Index {
+        store: String,
+        store_id: String,
+        entity: Box<Value>,
+    },
+    /// A component at an index of a store array, followed by the fields accessed on it.
+    Component {
+        store: String,
+        array: String,
+        index: Box<Value>,
+        fields: Vec<String>,
+    },
+    /// The instance of a single component, followed by the fields accessed on it.
+    Single {
+        store: String,
+        fields: Vec<String>,
+    },
+    /// A copy of a whole component from a store that keeps each field in its own array.
+    Gather {
+        store: String,
+        store_id: String,
+        index: Box<Value>,
+    },
+    /// Whether the component at an index of a store was added or written this tick.
+    Changed {
+        store: String,
+        index: Box<Value>,
+    },
+    /// Whether the index refers to a component rather than being the capacity of the store.
+    Has {
+        index: Box<Value>,
+        capacity: usize,
+    },
+    /// The value if the index refers to a component, otherwise the default.
+    Coalesce {
+        index: Box<Value>,
+        capacity: usize,
+        value: Box<Value>,
+        default: Box<Value>,
+    },
+    /// A variant of an enum along with the value it holds, if any.
+    Variant {
+        /// The id of the enum struct.
+        id: String,
+        tag: String,
+        /// The field of the payload union for the variant, and its value.
+        payload: Option<(String, Box<Value>)>,
+    },
+    /// A method called on a list.
+    Method {
+        /// The id of the list struct.
+        list_id: String,
+        method: ListMethod,
+        list: Box<Value>,
+        args: Vec<Value>,
+    },
+    Field {
+        target: Box<Value>,
+        field: String,
+    },
+    Unary {
+        op: UnaryOp,
+        value: Box<Value>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Value>,
+        rhs: Box<Value>,
+    },
+    Group(Box<Value>),
+}
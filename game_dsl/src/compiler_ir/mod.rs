@@ -1,10 +1,39 @@
-use crate::parser::{ListType, Listable, Primitive, PrimitiveType};
+mod body;
+mod save_format;
+
+use crate::parser::{ListType, Primitive, PrimitiveType};
+pub use body::*;
+pub use save_format::*;
+
+/// The field of an enum struct holding the tag of its variant.
+pub const ENUM_TAG_ID: &str = "tag";
+
+/// The field of an enum struct holding the union of its payloads.
+pub const ENUM_PAYLOAD_ID: &str = "payload";
+
+/// The field of a list struct holding the number of items in use.
+pub const LIST_LENGTH_ID: &str = "length";
+
+/// The field of a list struct holding the array of items.
+pub const LIST_ITEMS_ID: &str = "items";
+
+/// The world field holding the id of the next entity to create.
+pub const WORLD_NEXT_ENTITY_ID: &str = "next_entity";
+
+/// The fields each rollback snapshot keeps in front of its copy of the world.
+pub const SNAPSHOT_HEADER: [(&str, PrimitiveType); 3] = [
+    ("taken", PrimitiveType::Bool),
+    ("slot", PrimitiveType::U64),
+    ("checksum", PrimitiveType::U64),
+];
 
 /// Intermediate representation of compiler primitives.
 #[derive(Debug, Clone, PartialEq)]
 pub enum IR {
     Struct(Commentable<Struct>),
-    Expression(Commentable<Expression>),
+    Store(Store),
+    System(Commentable<System>),
+    World(World),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -14,59 +43,139 @@ pub struct Commentable<T> {
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Expression {
+pub struct Struct {
     pub id: String,
-    pub args: Vec<ExpressionArg>,
-    pub body: Vec<ExpressionStatement>,
-    pub return_type: Listable<Primitive>,
+    /// The name declared in the language, used for reflection.
+    pub name: String,
+    pub fields: Vec<StructField>,
+    /// Whether fields must stay in declaration order.
+    pub ordered: bool,
+    /// Whether the fields share the same memory, as the payloads of an enum do.
+    pub union: bool,
+    /// The variants of an enum. Enums are a struct with the tag of the variant and a union of the
+    /// values the variants hold.
+    pub variants: Vec<EnumVariant>,
+    /// Set for lists declared in the language, which are a struct with the `length` in use
+    /// followed by the array of `items`.
+    pub list: Option<ItemEquality>,
+}
+impl Struct {
+    /// Returns the ids of the structs this struct contains by value.
+    pub fn dependencies(&self) -> Vec<&str> {
+        let mut dependencies = vec![];
+        for field in self.fields.iter() {
+            if let PrimitiveType::Identifier(id) = &field.ty.inner_ty().primitive_type {
+                if !dependencies.contains(&id.as_str()) {
+                    dependencies.push(id.as_str());
+                }
+            }
+        }
+
+        dependencies
+    }
 }
 
+/// How `contains` compares the items of a list.
 #[derive(Debug, Clone, PartialEq)]
-pub enum ExpressionStatement {
-    Comment(String),
-    Literal(Literal),
-    Return(Box<ExpressionStatement>),
-    Call {
-        id: String,
-        args: Vec<ExpressionStatement>,
-    },
-    NativeExpression(NativeExpression),
+pub enum ItemEquality {
+    /// The items are compared whole.
+    Value,
+    /// The items are compared by a field, e.g. the id of an entity or the tag of an enum.
+    Field(String),
+    /// The items can't be compared, e.g. structs.
+    None,
 }
 
+/// A variant of an enum. Tags are numbered from 0 in declaration order.
 #[derive(Debug, Clone, PartialEq)]
-pub enum NativeExpression {
-    Add {
-        lhs: Box<ExpressionStatement>,
-        rhs: Box<ExpressionStatement>,
-    },
-    Multiply {
-        lhs: Box<ExpressionStatement>,
-        rhs: Box<ExpressionStatement>,
-    },
+pub struct EnumVariant {
+    /// The name declared in the language, which is also the field of the payload union.
+    pub name: String,
+    /// The id of the constant holding the tag.
+    pub tag: String,
+    pub comments: Vec<String>,
+    /// The type of the value the variant holds, if any.
+    pub payload: Option<ListType<Primitive>>,
 }
 
+/// A component store. The store itself is also emitted as a struct with the same id.
 #[derive(Debug, Clone, PartialEq)]
-pub enum Literal {
-    Int(i32),
-    Bool(bool),
-    String(String),
-    Identifier(String),
+pub struct Store {
+    pub id: String,
+    /// The id of the component struct.
+    pub component: String,
+    /// The component name declared in the language, which is also the field of the store in the world.
+    pub name: String,
+    pub capacity: usize,
+    /// The id of the entity struct.
+    pub entity: String,
+    /// The number of entity ids the store can index.
+    pub max_entities: usize,
+    /// Whether each component field is kept in its own array.
+    pub soa: bool,
+    /// Whether the store holds the one instance of a single component rather than one per entity.
+    pub single: bool,
+    /// Whether the store is a queue of the events emitted this tick.
+    pub event: bool,
+    pub fields: Vec<StoreField>,
 }
 
+/// The world that owns every component store. The world itself is also emitted as a struct with the same id.
 #[derive(Debug, Clone, PartialEq)]
-pub struct ExpressionArg {
+pub struct World {
     pub id: String,
-    pub ty: PrimitiveType,
+    pub save_format: SaveFormat,
+    /// The number of snapshots kept for rollback.
+    pub snapshot_slots: usize,
+    /// The systems each stage runs, in dispatch order.
+    pub stages: Vec<Vec<Job>>,
 }
 
+/// A system that runs over the world.
 #[derive(Debug, Clone, PartialEq)]
-pub struct Struct {
+pub struct System {
     pub id: String,
-    pub fields: Vec<StructField>,
+    /// The name declared in the language.
+    pub name: String,
+    pub body: Vec<Statement>,
+    /// Set for systems implemented by the host instead of a body.
+    pub external: Option<ExternalSystem>,
+}
+
+/// A system implemented by the host, which is only given the world.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalSystem {
+    /// The fields of the world with the stores the system writes.
+    pub writes: Vec<String>,
+    pub impls: Vec<SystemImpl>,
+}
+
+/// The host file implementing an external system for a target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemImpl {
+    /// The section declaring the file, e.g. `target_c_impl`.
+    pub target: String,
+    pub path: String,
+}
+
+/// A run of a system within a stage. Jobs of the same stage can run in parallel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Job {
+    /// The id of the system to run.
+    pub system: String,
+    pub name: String,
+}
+
+/// A component field and the store array that holds it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoreField {
+    pub id: String,
+    pub array: String,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructField {
     pub id: String,
+    pub comments: Vec<String>,
     pub ty: ListType<Primitive>,
 }
@@ -0,0 +1,89 @@
+use crate::parser::PrimitiveType;
+
+/// The first bytes of every save.
+pub const SAVE_MAGIC: [u8; 4] = *b"GDSL";
+
+/// Incremented whenever the layout of the save format changes.
+pub const SAVE_VERSION: u32 = 2;
+
+/// The binary format used to save and load the world. Every backend follows the same format so
+/// a save from one target can be loaded by any other.
+///
+/// A save is laid out as:
+/// - the 4 magic bytes `GDSL`
+/// - the format version as a `u32`
+/// - the schema hash as a `u64`
+/// - the values of each operation in order
+///
+/// All numbers are little endian. Booleans and characters are a single byte and floats are
+/// written as their IEEE 754 bits. Loading fails if the magic, version or schema hash don't match.
+///
+/// The checksum of a world is the FNV-1a hash of the operation values, i.e. a save without its header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveFormat {
+    /// An FNV-1a hash of the saved types, used to reject saves from a different schema.
+    pub schema_hash: u64,
+    pub ops: Vec<SaveOp>,
+}
+
+/// A single step of saving or loading.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveOp {
+    /// Reads or writes the primitive at the path.
+    Primitive {
+        path: Vec<PathSegment>,
+        ty: PrimitiveType,
+    },
+    /// Runs the operations once for every index up to the count.
+    Repeat {
+        /// The index bound by this loop. Nested loops have increasing indexes.
+        index: usize,
+        count: Count,
+        ops: Vec<SaveOp>,
+    },
+    /// Runs the operations of the variant whose tag is the `u32` at the path, which is saved
+    /// before the variant. Loading fails if the saved tag isn't one of the variants.
+    Match {
+        path: Vec<PathSegment>,
+        variants: Vec<Vec<SaveOp>>,
+    },
+}
+
+/// The number of times a repeat runs, which is the number at the path, such as the length of a
+/// list. It's saved before the repeated values and loading fails if it's larger than the max.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Count {
+    pub path: Vec<PathSegment>,
+    pub ty: PrimitiveType,
+    pub max: usize,
+}
+
+/// Part of the path to a value, starting from the world.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Field(String),
+    /// Indexes into a list with the index of the repeat.
+    Index(usize),
+}
+
+pub const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+pub const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Returns the 64 bit FNV-1a hash of the bytes.
+pub fn fnv1a_64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_64_matches_reference_values() {
+        assert_eq!(0xcbf29ce484222325, fnv1a_64(b""));
+        assert_eq!(0xaf63dc4c8601ec8c, fnv1a_64(b"a"));
+        assert_eq!(0x85944171f73967e8, fnv1a_64(b"foobar"));
+    }
+}
@@ -0,0 +1,1865 @@
+use super::{is_event, is_single, Env, ENTITY_ID};
+use crate::{
+    error::Error,
+    location::Location,
+    parser::{
+        is_fractional, AssignOp, BinaryOp, ComponentProperties, Constructor, Enum, Expr, ExprValue,
+        ListMethod, ListType, Listable, MatchArm, Primitive, PrimitiveType, QueryTerm, Reference,
+        Statement, StatementValue, StructProperties, System, UnaryOp, CHANGED_ID, CREATE_ID,
+        ELSE_ID, EMIT_ID, EVENTS_ID, FOR_ID, HAS_ID, IN_ID, MATCH_ID, READ_ID, SINGLE_ID,
+        WITHOUT_ID, WRITE_ID,
+    },
+};
+use std::collections::HashMap;
+
+/// The type of a value in a system body.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    /// A number literal, which takes on the type of the number it's used with.
+    Literal { fractional: bool },
+    /// A primitive or a struct. Entities are the native entity struct.
+    Primitive(PrimitiveType),
+    /// A component with multiple fields.
+    Component(String),
+    /// A list of up to the max size items, which can be changed through its methods but can't
+    /// be stored in locals.
+    List(PrimitiveType, usize),
+}
+
+impl Type {
+    fn is_numeric(&self) -> bool {
+        match self {
+            Type::Literal { .. } => true,
+            Type::Primitive(ty) => is_numeric(ty),
+            _ => false,
+        }
+    }
+
+    fn is_entity(&self) -> bool {
+        matches!(self, Type::Primitive(PrimitiveType::Identifier(id)) if id == ENTITY_ID)
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Literal { fractional: false } => write!(f, "integer"),
+            Type::Literal { fractional: true } => write!(f, "fractional number"),
+            Type::Primitive(ty) => write!(f, "{}", ty),
+            Type::Component(id) => write!(f, "{}", id),
+            Type::List(ty, max_size) => write!(f, "[{} {}]", ty, max_size),
+        }
+    }
+}
+
+fn is_numeric(ty: &PrimitiveType) -> bool {
+    matches!(
+        ty,
+        PrimitiveType::U32
+            | PrimitiveType::U64
+            | PrimitiveType::I32
+            | PrimitiveType::I64
+            | PrimitiveType::F32
+            | PrimitiveType::F64
+    )
+}
+
+fn is_float(ty: &PrimitiveType) -> bool {
+    matches!(ty, PrimitiveType::F32 | PrimitiveType::F64)
+}
+
+/// What an identifier refers to in a system body.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Binding {
+    Local(Type),
+    /// A read only copy of a value, e.g. `x` in `for x in e.Collisions`.
+    Copy(Type),
+    /// An entity matched by a query, along with the components of the query.
+    Entity(Vec<QueryTerm>),
+    /// The instance of a single component, e.g. `state = single WorldState`.
+    Single(String),
+    /// An event read from its queue, e.g. `ev` in `for ev in events Collision`.
+    Event(String),
+}
+
+/// The identifiers visible at a point of a system body. Each block adds a frame.
+#[derive(Debug, Clone)]
+pub struct Scope {
+    frames: Vec<HashMap<String, Binding>>,
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Self {
+            frames: vec![HashMap::new()],
+        }
+    }
+
+    pub fn push(&mut self) {
+        self.frames.push(HashMap::new());
+    }
+
+    pub fn pop(&mut self) {
+        self.frames.pop();
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Binding> {
+        self.frames.iter().rev().find_map(|frame| frame.get(id))
+    }
+
+    pub fn insert(&mut self, id: &str, binding: Binding) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.insert(id.to_string(), binding);
+        }
+    }
+
+    /// Returns the query term of the component if the identifier is an entity of a query.
+    pub fn term(&self, entity: &str, component: &str) -> Option<&QueryTerm> {
+        match self.get(entity) {
+            Some(Binding::Entity(terms)) => terms.iter().find(|t| t.component.id == component),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the type of the local declared by assigning a value of the given type.
+/// Number literals become `i32` or `f32`.
+pub fn local_type(ty: Type) -> Type {
+    match ty {
+        Type::Literal { fractional: false } => Type::Primitive(PrimitiveType::I32),
+        Type::Literal { fractional: true } => Type::Primitive(PrimitiveType::F32),
+        ty => ty,
+    }
+}
+
+/// Returns the entity and component an expression accesses, e.g. `e` and `Position` for `e.Position.x`.
+pub fn component_root<'a>(scope: &Scope, expr: &'a Expr) -> Option<(&'a str, &'a str)> {
+    match &expr.value {
+        ExprValue::Field { target, field } => match &target.value {
+            ExprValue::Identifier(id) if matches!(scope.get(id), Some(Binding::Entity(_))) => {
+                Some((id.as_str(), field.as_str()))
+            }
+            _ => component_root(scope, target),
+        },
+        _ => None,
+    }
+}
+
+/// Returns the single component an expression accesses, e.g. `WorldState` for `state.frame`.
+pub fn single_root(scope: &Scope, expr: &Expr) -> Option<String> {
+    match &expr.value {
+        ExprValue::Single { component } => Some(component.clone()),
+        ExprValue::Identifier(id) => match scope.get(id) {
+            Some(Binding::Single(component)) => Some(component.clone()),
+            _ => None,
+        },
+        ExprValue::Field { target, .. } | ExprValue::Group(target) => single_root(scope, target),
+        _ => None,
+    }
+}
+
+/// Returns the enum and variant a field access or call names, e.g. `Status` and `Dead` for
+/// `Status.Dead` or `Status` and `Damaged` for `Status.Damaged(5)`.
+pub fn enum_variant<'a>(env: &Env, scope: &Scope, expr: &'a Expr) -> Option<(&'a str, &'a str)> {
+    match &expr.value {
+        ExprValue::Field { target, field }
+        | ExprValue::Call {
+            target,
+            method: field,
+            ..
+        } => match &target.value {
+            ExprValue::Identifier(id) if scope.get(id).is_none() && env.enums.contains_key(id) => {
+                Some((id.as_str(), field.as_str()))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the type of the expression, or an error if its operands don't fit together.
+pub fn type_of(env: &Env, scope: &Scope, expr: &Expr) -> Result<Type, Error> {
+    let location = expr.start_location.clone();
+    match &expr.value {
+        ExprValue::Number(n) => Ok(Type::Literal {
+            fractional: is_fractional(n),
+        }),
+        ExprValue::Bool(_) => Ok(Type::Primitive(PrimitiveType::Bool)),
+        ExprValue::Identifier(id) => match scope.get(id) {
+            Some(Binding::Local(ty)) | Some(Binding::Copy(ty)) => Ok(ty.clone()),
+            Some(Binding::Entity(_)) => Ok(Type::Primitive(PrimitiveType::Identifier(
+                ENTITY_ID.to_string(),
+            ))),
+            Some(Binding::Single(component)) => single_type(env, component, location),
+            Some(Binding::Event(component)) => data_type(env, component, location),
+            None => Err(Error::new(format!("Unknown identifier '{}'", id), location)),
+        },
+        ExprValue::Single { component } => single_type(env, component, location),
+        ExprValue::Field { .. } if enum_variant(env, scope, expr).is_some() => {
+            let (enum_id, variant) = enum_variant(env, scope, expr).unwrap_or_default();
+            let payload = variant_payload(env, enum_id, variant, location.clone())?;
+            if payload.is_some() {
+                return Err(Error::new(
+                    format!(
+                        "Variant '{}' of enum '{}' holds a value, use '{}.{}(..)'",
+                        variant, enum_id, enum_id, variant
+                    ),
+                    location,
+                ));
+            }
+
+            Ok(Type::Primitive(PrimitiveType::Identifier(
+                enum_id.to_string(),
+            )))
+        }
+        ExprValue::Call { args, .. } if enum_variant(env, scope, expr).is_some() => {
+            let (enum_id, variant) = enum_variant(env, scope, expr).unwrap_or_default();
+            let payload = match variant_payload(env, enum_id, variant, location.clone())? {
+                Some(payload) => listable_type(payload),
+                None => {
+                    return Err(Error::new(
+                        format!("Variant '{}' of enum '{}' holds no value", variant, enum_id),
+                        location,
+                    ))
+                }
+            };
+            let value = match args.as_slice() {
+                [value] => value,
+                _ => {
+                    return Err(Error::new(
+                        format!(
+                            "Variant '{}' of enum '{}' holds one value, found {}",
+                            variant,
+                            enum_id,
+                            args.len()
+                        ),
+                        location,
+                    ))
+                }
+            };
+            if let Type::List(..) = payload {
+                return Err(Error::new(
+                    format!("Lists can't be constructed, found '{}'", payload),
+                    location,
+                ));
+            }
+
+            let symbol = format!("{}.{}", enum_id, variant);
+            let ty = type_of(env, scope, value)?;
+            match unify(&symbol, payload.clone(), ty, value.start_location.clone())? {
+                ty if ty == payload => Ok(Type::Primitive(PrimitiveType::Identifier(
+                    enum_id.to_string(),
+                ))),
+                ty => Err(Error::new(
+                    format!(
+                        "Mismatched types '{}' and '{}' for '{}'",
+                        payload, ty, symbol
+                    ),
+                    value.start_location.clone(),
+                )),
+            }
+        }
+        ExprValue::Field { target, field } => {
+            if let ExprValue::Identifier(entity) = &target.value {
+                if let Some(Binding::Entity(_)) = scope.get(entity) {
+                    return component_type(env, scope, entity, field, location);
+                }
+            }
+
+            let ty = type_of(env, scope, target)?;
+            field_type(env, &ty, field).ok_or_else(|| {
+                Error::new(format!("Type '{}' has no field '{}'", ty, field), location)
+            })
+        }
+        ExprValue::Call {
+            target,
+            method,
+            args,
+        } => {
+            let list = type_of(env, scope, target)?;
+            let args = args
+                .iter()
+                .map(|arg| Ok((type_of(env, scope, arg)?, arg.start_location.clone())))
+                .collect::<Result<Vec<_>, Error>>()?;
+            method_type(env, &list, method, &args, location.clone())?.ok_or_else(|| {
+                Error::new(
+                    format!(
+                        "Method '{}' has no value and can only be a statement",
+                        method
+                    ),
+                    location,
+                )
+            })
+        }
+        ExprValue::Has { entity, component } => {
+            match scope.get(entity) {
+                Some(Binding::Entity(_)) => {}
+                _ => {
+                    return Err(Error::new(
+                        format!("'{}' is not an entity of a query", entity),
+                        location,
+                    ))
+                }
+            }
+
+            if scope.term(entity, component).is_none() {
+                return Err(not_in_query(entity, component, location));
+            }
+
+            Ok(Type::Primitive(PrimitiveType::Bool))
+        }
+        ExprValue::Unary { op, expr } => {
+            let ty = type_of(env, scope, expr)?;
+            let valid = match op {
+                UnaryOp::Not => ty == Type::Primitive(PrimitiveType::Bool),
+                UnaryOp::Negate => ty.is_numeric(),
+            };
+
+            if valid {
+                Ok(ty)
+            } else {
+                let expected = match op {
+                    UnaryOp::Not => "'bool'",
+                    UnaryOp::Negate => "a number",
+                };
+                Err(Error::new(
+                    format!(
+                        "Operator '{}' requires {}, found '{}'",
+                        op.symbol(),
+                        expected,
+                        ty
+                    ),
+                    location,
+                ))
+            }
+        }
+        ExprValue::Binary { op, lhs, rhs } => {
+            let lhs = type_of(env, scope, lhs)?;
+            let rhs = type_of(env, scope, rhs)?;
+            binary_type(env, *op, lhs, rhs, location)
+        }
+        ExprValue::Group(expr) => type_of(env, scope, expr),
+    }
+}
+
+/// Returns the value held by the variant of the enum, or an error if the enum has no such variant.
+fn variant_payload<'a>(
+    env: &'a Env,
+    enum_id: &str,
+    variant: &str,
+    location: Location,
+) -> Result<Option<&'a Listable<Primitive>>, Error> {
+    match env.enums.get(enum_id).and_then(|e| e.variant(variant)) {
+        Some(variant) => Ok(variant.payload.as_ref()),
+        None => Err(no_variant(enum_id, variant, location)),
+    }
+}
+
+fn no_variant(enum_id: &str, variant: &str, location: Location) -> Error {
+    Error::new(
+        format!("Enum '{}' has no variant '{}'", enum_id, variant),
+        location,
+    )
+}
+
+/// Returns the type of the value a method of a list returns, if it returns one, or an error if
+/// the list has no such method or it's given values that don't fit.
+fn method_type(
+    env: &Env,
+    list: &Type,
+    method: &str,
+    args: &[(Type, Location)],
+    location: Location,
+) -> Result<Option<Type>, Error> {
+    let no_method = || {
+        Error::new(
+            format!("Type '{}' has no method '{}'", list, method),
+            location.clone(),
+        )
+    };
+    let item = match list {
+        Type::List(item, _) => Type::Primitive(item.clone()),
+        _ => return Err(no_method()),
+    };
+    let method = ListMethod::from_id(method).ok_or_else(no_method)?;
+
+    let params = method_params(&item, method);
+    if params.len() != args.len() {
+        return Err(Error::new(
+            format!(
+                "Method '{}' takes {} values, found {}",
+                method.id(),
+                params.len(),
+                args.len()
+            ),
+            location,
+        ));
+    }
+
+    for (param, (arg, location)) in params.into_iter().zip(args) {
+        match unify(method.id(), param.clone(), arg.clone(), location.clone())? {
+            ty if ty == param => {}
+            ty => {
+                return Err(Error::new(
+                    format!(
+                        "Mismatched types '{}' and '{}' for '{}'",
+                        param,
+                        ty,
+                        method.id()
+                    ),
+                    location.clone(),
+                ))
+            }
+        }
+    }
+
+    if method == ListMethod::Contains && !is_comparable(env, &item) {
+        return Err(Error::new(
+            format!("Method '{}' can't compare '{}'", method.id(), item),
+            location,
+        ));
+    }
+
+    Ok(match method {
+        ListMethod::Pop => Some(item),
+        ListMethod::Len => Some(Type::Primitive(PrimitiveType::U32)),
+        ListMethod::Contains => Some(Type::Primitive(PrimitiveType::Bool)),
+        ListMethod::Push | ListMethod::Clear | ListMethod::RemoveAt => None,
+    })
+}
+
+/// Returns the types of the values a method of a list of the item takes.
+fn method_params(item: &Type, method: ListMethod) -> Vec<Type> {
+    match method {
+        ListMethod::Push | ListMethod::Contains => vec![item.clone()],
+        ListMethod::RemoveAt => vec![Type::Primitive(PrimitiveType::U32)],
+        ListMethod::Pop | ListMethod::Clear | ListMethod::Len => vec![],
+    }
+}
+
+/// Returns the types of the values a call takes, i.e. the payload of a variant or the parameters
+/// of a list method. Calls that don't resolve take nothing.
+pub fn param_types(env: &Env, scope: &Scope, expr: &Expr) -> Vec<Type> {
+    if let Some((enum_id, variant)) = enum_variant(env, scope, expr) {
+        return match variant_payload(env, enum_id, variant, expr.start_location.clone()) {
+            Ok(Some(payload)) => vec![listable_type(payload)],
+            _ => vec![],
+        };
+    }
+
+    match &expr.value {
+        ExprValue::Call { target, method, .. } => {
+            match (type_of(env, scope, target), ListMethod::from_id(method)) {
+                (Ok(Type::List(item, _)), Some(method)) => {
+                    method_params(&Type::Primitive(item), method)
+                }
+                _ => vec![],
+            }
+        }
+        _ => vec![],
+    }
+}
+
+/// Returns the type both operands of a binary expression are used as, given the type the
+/// expression is used as. Number literals take on the type of the other operand, the type of an
+/// arithmetic expression they're part of, or else `i32` or `f32`.
+pub fn operand_type(
+    env: &Env,
+    scope: &Scope,
+    op: BinaryOp,
+    lhs: &Expr,
+    rhs: &Expr,
+    ty: &Type,
+) -> Type {
+    let location = lhs.start_location.clone();
+    let operand = match (type_of(env, scope, lhs), type_of(env, scope, rhs)) {
+        (Ok(lhs), Ok(rhs)) => unify(op.symbol(), lhs, rhs, location).ok(),
+        _ => None,
+    };
+
+    let gives_operand_type = !op.is_comparison()
+        && !matches!(
+            op,
+            BinaryOp::Equal | BinaryOp::NotEqual | BinaryOp::And | BinaryOp::Or
+        );
+    match operand {
+        Some(Type::Literal { .. }) if gives_operand_type => local_type(ty.clone()),
+        Some(operand) => local_type(operand),
+        None => local_type(ty.clone()),
+    }
+}
+
+/// Returns the smallest and largest values of an integer type.
+fn integer_range(ty: &PrimitiveType) -> Option<(i128, i128)> {
+    match ty {
+        PrimitiveType::U32 => Some((0, u32::MAX as i128)),
+        PrimitiveType::I32 => Some((i32::MIN as i128, i32::MAX as i128)),
+        PrimitiveType::U64 => Some((0, u64::MAX as i128)),
+        PrimitiveType::I64 => Some((i64::MIN as i128, i64::MAX as i128)),
+        _ => None,
+    }
+}
+
+/// Returns the value of an expression made only of whole number literals, e.g. `2147483647 + 1`.
+/// Returns None if it divides by zero or doesn't fit in an `i128`.
+fn constant(expr: &Expr) -> Option<i128> {
+    match &expr.value {
+        ExprValue::Number(number) if !is_fractional(number) => number.parse().ok(),
+        ExprValue::Group(expr) => constant(expr),
+        ExprValue::Unary {
+            op: UnaryOp::Negate,
+            expr,
+        } => constant(expr)?.checked_neg(),
+        ExprValue::Binary { op, lhs, rhs } => {
+            let (lhs, rhs) = (constant(lhs)?, constant(rhs)?);
+            match op {
+                BinaryOp::Add => lhs.checked_add(rhs),
+                BinaryOp::Subtract => lhs.checked_sub(rhs),
+                BinaryOp::Multiply => lhs.checked_mul(rhs),
+                BinaryOp::Divide => lhs.checked_div(rhs),
+                BinaryOp::Modulo => lhs.checked_rem(rhs),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns an error if the type can't hold the number literal, which is negated if it's the
+/// operand of `-`. Integers must be in range and floats must be finite.
+fn literal_fits(
+    number: &str,
+    negated: bool,
+    ty: &PrimitiveType,
+    location: Location,
+) -> Result<(), Error> {
+    let fits = if is_float(ty) || is_fractional(number) {
+        let value: f64 = number.parse().unwrap_or(f64::INFINITY);
+        match ty {
+            PrimitiveType::F32 => value <= f32::MAX as f64,
+            // Fractional numbers used as integers are mismatched types instead
+            _ => value.is_finite(),
+        }
+    } else {
+        let range = match integer_range(ty) {
+            Some(range) => range,
+            None => return Ok(()),
+        };
+        match number.parse::<i128>() {
+            Ok(value) => {
+                let value = if negated { -value } else { value };
+                range.0 <= value && value <= range.1
+            }
+            Err(_) => false,
+        }
+    };
+
+    if fits {
+        return Ok(());
+    }
+    Err(Error::new(
+        format!(
+            "Number '{}{}' doesn't fit in '{}'",
+            if negated { "-" } else { "" },
+            number,
+            ty
+        ),
+        location,
+    ))
+}
+
+/// Returns whether values of the type can be compared with `==`.
+fn is_comparable(env: &Env, ty: &Type) -> bool {
+    match ty {
+        // Enums holding values would have to compare them too
+        Type::Primitive(PrimitiveType::Identifier(id)) => {
+            ty.is_entity() || env.enums.get(id).is_some_and(Enum::is_plain)
+        }
+        Type::Primitive(_) | Type::Literal { .. } => true,
+        _ => false,
+    }
+}
+
+/// Returns the method if the expression calls one that changes a list, e.g. `e.Collisions.pop()`.
+pub fn list_change(env: &Env, scope: &Scope, expr: &Expr) -> Option<ListMethod> {
+    match &expr.value {
+        ExprValue::Call { method, .. } if enum_variant(env, scope, expr).is_none() => {
+            ListMethod::from_id(method).filter(ListMethod::changes_list)
+        }
+        _ => None,
+    }
+}
+
+fn binary_type(
+    env: &Env,
+    op: BinaryOp,
+    lhs: Type,
+    rhs: Type,
+    location: Location,
+) -> Result<Type, Error> {
+    let requires = |expected: &str, ty: &Type| {
+        Error::new(
+            format!(
+                "Operator '{}' requires {}, found '{}'",
+                op.symbol(),
+                expected,
+                ty
+            ),
+            location.clone(),
+        )
+    };
+
+    match op {
+        BinaryOp::And | BinaryOp::Or => {
+            for ty in [&lhs, &rhs] {
+                if *ty != Type::Primitive(PrimitiveType::Bool) {
+                    return Err(requires("'bool'", ty));
+                }
+            }
+            Ok(lhs)
+        }
+        BinaryOp::Coalesce => unify(op.symbol(), lhs, rhs, location),
+        BinaryOp::Equal | BinaryOp::NotEqual => {
+            let ty = unify(op.symbol(), lhs, rhs, location.clone())?;
+            if !is_comparable(env, &ty) {
+                return Err(Error::new(
+                    format!("Operator '{}' can't compare '{}'", op.symbol(), ty),
+                    location,
+                ));
+            }
+            Ok(Type::Primitive(PrimitiveType::Bool))
+        }
+        _ => {
+            for ty in [&lhs, &rhs] {
+                if !ty.is_numeric() {
+                    return Err(requires("numbers", ty));
+                }
+            }
+
+            let ty = unify(op.symbol(), lhs, rhs, location.clone())?;
+            match &ty {
+                Type::Primitive(primitive) if op == BinaryOp::Modulo && is_float(primitive) => {
+                    return Err(requires("integers", &ty))
+                }
+                _ => {}
+            }
+
+            if op.is_comparison() {
+                Ok(Type::Primitive(PrimitiveType::Bool))
+            } else {
+                Ok(ty)
+            }
+        }
+    }
+}
+
+/// Returns the type both sides of an operator share. Literals take on the type of the other side.
+fn unify(symbol: &str, lhs: Type, rhs: Type, location: Location) -> Result<Type, Error> {
+    let fits =
+        |fractional: bool, ty: &PrimitiveType| is_numeric(ty) && (!fractional || is_float(ty));
+
+    match (&lhs, &rhs) {
+        (Type::Literal { fractional: a }, Type::Literal { fractional: b }) => Ok(Type::Literal {
+            fractional: *a || *b,
+        }),
+        (Type::Literal { fractional }, Type::Primitive(ty))
+        | (Type::Primitive(ty), Type::Literal { fractional })
+            if fits(*fractional, ty) =>
+        {
+            Ok(Type::Primitive(ty.clone()))
+        }
+        _ if lhs == rhs => Ok(lhs),
+        _ => Err(Error::new(
+            format!("Mismatched types '{}' and '{}' for '{}'", lhs, rhs, symbol),
+            location,
+        )),
+    }
+}
+
+fn component_type(
+    env: &Env,
+    scope: &Scope,
+    entity: &str,
+    component: &str,
+    location: Location,
+) -> Result<Type, Error> {
+    if scope.term(entity, component).is_none() {
+        return Err(not_in_query(entity, component, location));
+    }
+
+    data_type(env, component, location)
+}
+
+fn single_type(env: &Env, component: &str, location: Location) -> Result<Type, Error> {
+    if env.components.get(component).is_some_and(|c| !is_single(c)) {
+        return Err(Error::new(
+            format!(
+                "Component '{}' isn't a single component and can't be accessed with '{}'",
+                component, SINGLE_ID
+            ),
+            location,
+        ));
+    }
+
+    data_type(env, component, location)
+}
+
+/// Returns the type of a component's data.
+fn data_type(env: &Env, component: &str, location: Location) -> Result<Type, Error> {
+    match env.components.get(component).map(|c| &c.properties) {
+        Some(ComponentProperties::Value(value)) => Ok(listable_type(value)),
+        Some(ComponentProperties::Multiple(_)) => Ok(Type::Component(component.to_string())),
+        Some(ComponentProperties::None) => Err(Error::new(
+            format!("Component '{}' is a tag and has no data", component),
+            location,
+        )),
+        None => Err(Error::new(
+            format!("Component '{}' does not exist", component),
+            location,
+        )),
+    }
+}
+
+fn field_type(env: &Env, ty: &Type, field: &str) -> Option<Type> {
+    let find = |properties: &[(&str, &Listable<Primitive>)]| {
+        properties
+            .iter()
+            .find(|(id, _)| *id == field)
+            .map(|(_, ty)| listable_type(ty))
+    };
+
+    match ty {
+        Type::Component(id) => match &env.components.get(id)?.properties {
+            ComponentProperties::Multiple(properties) => find(
+                &properties
+                    .iter()
+                    .map(|p| (p.identifier.as_str(), &p.ty))
+                    .collect::<Vec<_>>(),
+            ),
+            _ => None,
+        },
+        Type::Primitive(PrimitiveType::Identifier(id)) => {
+            match &env.structs.get(id)?.strukt().properties {
+                StructProperties::Value(value) => find(&[("value", value)]),
+                StructProperties::Multiple(properties) => find(
+                    &properties
+                        .iter()
+                        .map(|p| (p.identifier.as_str(), &p.ty))
+                        .collect::<Vec<_>>(),
+                ),
+                StructProperties::None => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn listable_type(listable: &Listable<Primitive>) -> Type {
+    match &listable.ty {
+        ListType::Single(primitive) => Type::Primitive(primitive.primitive_type.clone()),
+        ListType::List { ty, max_size } => Type::List(ty.primitive_type.clone(), *max_size),
+    }
+}
+
+fn not_in_query(entity: &str, component: &str, location: Location) -> Error {
+    Error::new(
+        format!(
+            "Component '{}' is not part of the query for '{}'",
+            component, entity
+        ),
+        location,
+    )
+}
+
+/// Checks the statements of the system's body.
+pub(super) fn validate_body(env: &Env, system: &System) -> Vec<Error> {
+    let mut checker = Checker {
+        env,
+        system,
+        scope: Scope::new(),
+        guards: vec![],
+        errors: vec![],
+    };
+    checker.statements(&system.body);
+    checker.errors
+}
+
+struct Checker<'a> {
+    env: &'a Env,
+    system: &'a System,
+    scope: Scope,
+    /// The entities and components checked with `has` around the current statement.
+    guards: Vec<(String, String)>,
+    errors: Vec<Error>,
+}
+
+impl<'a> Checker<'a> {
+    fn statements(&mut self, statements: &[Statement]) {
+        self.scope.push();
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.scope.pop();
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match &statement.value {
+            StatementValue::Comment(_) => {}
+            StatementValue::Query {
+                entity,
+                terms,
+                without,
+                changed,
+                body,
+            } => {
+                if self.scope.get(&entity.id).is_some() {
+                    self.errors.push(Error::new(
+                        format!("'{}' is already defined", entity.id),
+                        entity.start_location.clone(),
+                    ));
+                }
+
+                self.terms(terms);
+                self.filters(terms, without, changed);
+                if terms.iter().all(|t| t.optional) {
+                    self.errors.push(Error::new(
+                        "Queries need at least one component that isn't optional".to_string(),
+                        statement.start_location.clone(),
+                    ));
+                }
+
+                self.scope.push();
+                self.scope
+                    .insert(&entity.id, Binding::Entity(terms.clone()));
+                self.statements(body);
+                self.scope.pop();
+            }
+            StatementValue::Events {
+                event,
+                component,
+                body,
+            } => {
+                if self.scope.get(&event.id).is_some() {
+                    self.errors.push(Error::new(
+                        format!("'{}' is already defined", event.id),
+                        event.start_location.clone(),
+                    ));
+                }
+
+                if self.event(component) {
+                    let is_declared = self
+                        .system
+                        .reads
+                        .iter()
+                        .chain(self.system.writes.iter())
+                        .any(|r| r.id == component.id);
+                    if !is_declared {
+                        self.errors.push(Error::new(
+                            format!(
+                                "Event '{}' must be listed in '{}' or '{}' for system '{}' to read it",
+                                component.id, READ_ID, WRITE_ID, self.system.id
+                            ),
+                            component.start_location.clone(),
+                        ));
+                    }
+                }
+
+                self.scope.push();
+                self.scope
+                    .insert(&event.id, Binding::Event(component.id.clone()));
+                self.statements(body);
+                self.scope.pop();
+            }
+            StatementValue::Iterate { item, list, body } => self.iterate(item, list, body),
+            StatementValue::Emit { component, args } => self.emit(component, args),
+            StatementValue::Create { components } => self.create(components),
+            StatementValue::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                if let Some(ty) = self.expr(condition) {
+                    if ty != Type::Primitive(PrimitiveType::Bool) {
+                        self.errors.push(Error::new(
+                            format!("Conditions must be 'bool', found '{}'", ty),
+                            condition.start_location.clone(),
+                        ));
+                    }
+                }
+
+                let guards = self.guards.len();
+                self.guards.append(&mut has_checks(condition));
+                self.statements(then);
+                self.guards.truncate(guards);
+
+                self.statements(otherwise);
+            }
+            StatementValue::Match { value, arms, .. } => self.match_arms(statement, value, arms),
+            StatementValue::Assign { target, op, value } => self.assign(target, *op, value),
+            StatementValue::Call(call) => {
+                if list_change(self.env, &self.scope, call).is_some() {
+                    self.change(call);
+                } else if self.expr(call).is_some() {
+                    self.errors.push(Error::new(
+                        format!(
+                            "Only methods that change a list can be statements, e.g. '{}'",
+                            ListMethod::Push.id()
+                        ),
+                        call.start_location.clone(),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Checks that a `for .. in` loop iterates over a list and binds a copy of each item.
+    fn iterate(&mut self, item: &Reference, list: &Expr, body: &[Statement]) {
+        if self.scope.get(&item.id).is_some() {
+            self.errors.push(Error::new(
+                format!("'{}' is already defined", item.id),
+                item.start_location.clone(),
+            ));
+        }
+
+        if let ExprValue::Identifier(id) = &list.value {
+            let is_event = self.env.components.get(id).is_some_and(is_event);
+            if is_event && self.scope.get(id).is_none() {
+                self.errors.push(Error::new(
+                    format!(
+                        "Events are read with '{} {} {} {} {}'",
+                        FOR_ID, item.id, IN_ID, EVENTS_ID, id
+                    ),
+                    list.start_location.clone(),
+                ));
+                return;
+            }
+        }
+
+        let ty = match self.expr(list) {
+            Some(Type::List(ty, _)) => ty,
+            Some(ty) => {
+                self.errors.push(Error::new(
+                    format!("Only lists can be iterated, found '{}'", ty),
+                    list.start_location.clone(),
+                ));
+                return;
+            }
+            None => return,
+        };
+
+        self.scope.push();
+        self.scope
+            .insert(&item.id, Binding::Copy(Type::Primitive(ty)));
+        self.statements(body);
+        self.scope.pop();
+    }
+
+    /// Checks a method that changes a list, returning the type of its value if it has one.
+    /// The list must be part of a written component, like the target of an assignment.
+    fn change(&mut self, call: &Expr) -> Option<Type> {
+        let (target, method, args) = match &call.value {
+            ExprValue::Call {
+                target,
+                method,
+                args,
+            } => (target, method, args),
+            _ => return self.expr(call),
+        };
+
+        let list = self.target(target)?;
+        let mut arg_types = vec![];
+        for arg in args {
+            arg_types.push((self.expr(arg)?, arg.start_location.clone()));
+        }
+
+        match method_type(
+            self.env,
+            &list,
+            method,
+            &arg_types,
+            call.start_location.clone(),
+        ) {
+            Ok(ty) => {
+                let params = param_types(self.env, &self.scope, call);
+                for ((arg, (arg_ty, _)), param) in args.iter().zip(arg_types).zip(params) {
+                    self.used_as(arg, &arg_ty, &param);
+                }
+                ty
+            }
+            Err(error) => {
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    /// Checks the value of an assignment, which can also be an item popped off a list.
+    fn assigned(&mut self, value: &Expr) -> Option<Type> {
+        match list_change(self.env, &self.scope, value) {
+            Some(ListMethod::Pop) => self.change(value),
+            _ => self.expr(value),
+        }
+    }
+
+    /// Checks that methods changing a list aren't part of another value, which could run them twice.
+    fn changes(&mut self, expr: &Expr) {
+        match &expr.value {
+            ExprValue::Call { target, args, .. } => {
+                if let Some(method) = list_change(self.env, &self.scope, expr) {
+                    let allowed = match method {
+                        ListMethod::Pop => "a statement or the value of an assignment",
+                        _ => "a statement",
+                    };
+                    self.errors.push(Error::new(
+                        format!(
+                            "Method '{}' changes the list and can only be {}",
+                            method.id(),
+                            allowed
+                        ),
+                        expr.start_location.clone(),
+                    ));
+                }
+
+                self.changes(target);
+                for arg in args {
+                    self.changes(arg);
+                }
+            }
+            ExprValue::Field { target: expr, .. }
+            | ExprValue::Unary { expr, .. }
+            | ExprValue::Group(expr) => self.changes(expr),
+            ExprValue::Binary { lhs, rhs, .. } => {
+                self.changes(lhs);
+                self.changes(rhs);
+            }
+            ExprValue::Number(_)
+            | ExprValue::Bool(_)
+            | ExprValue::Identifier(_)
+            | ExprValue::Has { .. }
+            | ExprValue::Single { .. } => {}
+        }
+    }
+
+    /// Checks that the arms of a `match` are variants of the enum and that every variant is handled.
+    fn match_arms(&mut self, statement: &Statement, value: &Expr, arms: &[MatchArm]) {
+        let env = self.env;
+        let enumeration = match self.expr(value) {
+            Some(Type::Primitive(PrimitiveType::Identifier(id))) if env.enums.contains_key(&id) => {
+                env.enums.get(&id)
+            }
+            Some(ty) => {
+                self.errors.push(Error::new(
+                    format!("'{}' requires an enum, found '{}'", MATCH_ID, ty),
+                    value.start_location.clone(),
+                ));
+                None
+            }
+            None => None,
+        };
+
+        let mut matched: Vec<&str> = vec![];
+        let mut has_else = false;
+        for arm in arms {
+            self.scope.push();
+            if let Some(enumeration) = enumeration {
+                match &arm.variant {
+                    Some(reference) => self.arm(enumeration, reference, arm, &mut matched),
+                    None => {
+                        has_else = true;
+                        if matched.len() == enumeration.variants.len() {
+                            self.errors.push(Error::new(
+                                format!(
+                                    "'{}' is unreachable as every variant of '{}' is matched",
+                                    ELSE_ID, enumeration.id
+                                ),
+                                arm.start_location.clone(),
+                            ));
+                        }
+                    }
+                }
+            }
+            self.statements(&arm.body);
+            self.scope.pop();
+        }
+
+        if let Some(enumeration) = enumeration {
+            let missing: Vec<&str> = enumeration
+                .variants
+                .iter()
+                .map(|v| v.id.as_str())
+                .filter(|id| !matched.contains(id))
+                .collect();
+            if !has_else && !missing.is_empty() {
+                self.errors.push(Error::new(
+                    format!(
+                        "'{}' on '{}' must handle every variant or use '{}', missing: {}",
+                        MATCH_ID,
+                        enumeration.id,
+                        ELSE_ID,
+                        missing.join(", ")
+                    ),
+                    statement.start_location.clone(),
+                ));
+            }
+        }
+    }
+
+    /// Checks the variant of an arm and binds the value it holds.
+    fn arm<'e>(
+        &mut self,
+        enumeration: &'e Enum,
+        reference: &Reference,
+        arm: &MatchArm,
+        matched: &mut Vec<&'e str>,
+    ) {
+        let variant = match enumeration.variant(&reference.id) {
+            Some(variant) => variant,
+            None => {
+                self.errors.push(no_variant(
+                    &enumeration.id,
+                    &reference.id,
+                    reference.start_location.clone(),
+                ));
+                return;
+            }
+        };
+
+        if matched.contains(&variant.id.as_str()) {
+            self.errors.push(Error::new(
+                format!("Variant '{}' is matched more than once", variant.id),
+                reference.start_location.clone(),
+            ));
+        }
+        matched.push(&variant.id);
+
+        let binding = match &arm.binding {
+            Some(binding) => binding,
+            None => return,
+        };
+        let payload = match &variant.payload {
+            Some(payload) => listable_type(payload),
+            None => {
+                self.errors.push(Error::new(
+                    format!(
+                        "Variant '{}' of enum '{}' holds no value to bind",
+                        variant.id, enumeration.id
+                    ),
+                    binding.start_location.clone(),
+                ));
+                return;
+            }
+        };
+
+        if self.scope.get(&binding.id).is_some() {
+            self.errors.push(Error::new(
+                format!("'{}' is already defined", binding.id),
+                binding.start_location.clone(),
+            ));
+        }
+        if let Type::List(..) = payload {
+            self.errors.push(Error::new(
+                format!("Lists can't be stored in locals, found '{}'", payload),
+                binding.start_location.clone(),
+            ));
+        }
+        self.scope.insert(&binding.id, Binding::Copy(payload));
+    }
+
+    fn terms(&mut self, terms: &[QueryTerm]) {
+        for (idx, term) in terms.iter().enumerate() {
+            let component = &term.component;
+            self.declared(component);
+            self.queryable(component);
+
+            if terms[..idx].iter().any(|t| t.component.id == component.id) {
+                self.errors.push(Error::new(
+                    format!(
+                        "Component '{}' is listed more than once in the query",
+                        component.id
+                    ),
+                    component.start_location.clone(),
+                ));
+            }
+        }
+    }
+
+    fn filters(&mut self, terms: &[QueryTerm], without: &[Reference], changed: &[Reference]) {
+        for (section, references) in [(WITHOUT_ID, without), (CHANGED_ID, changed)] {
+            for (idx, reference) in references.iter().enumerate() {
+                if references[..idx].iter().any(|r| r.id == reference.id) {
+                    self.errors.push(Error::new(
+                        format!(
+                            "Component '{}' is listed more than once in '{}'",
+                            reference.id, section
+                        ),
+                        reference.start_location.clone(),
+                    ));
+                }
+            }
+        }
+
+        for reference in without {
+            self.declared(reference);
+            self.queryable(reference);
+            if terms.iter().any(|t| t.component.id == reference.id) {
+                self.errors.push(Error::new(
+                    format!(
+                        "Component '{}' can't be both matched and excluded by the query",
+                        reference.id
+                    ),
+                    reference.start_location.clone(),
+                ));
+            }
+        }
+
+        for reference in changed {
+            let is_required = terms
+                .iter()
+                .any(|t| t.component.id == reference.id && !t.optional);
+            if !is_required {
+                self.errors.push(Error::new(
+                    format!(
+                        "Component '{}' must be a required component of the query to check for changes",
+                        reference.id
+                    ),
+                    reference.start_location.clone(),
+                ));
+            }
+        }
+    }
+
+    /// Checks that a component used by a query is listed in `read` or `write`.
+    fn declared(&mut self, component: &Reference) {
+        let is_declared = self
+            .system
+            .reads
+            .iter()
+            .chain(self.system.writes.iter())
+            .any(|r| r.id == component.id);
+        if !is_declared {
+            self.errors.push(Error::new(
+                format!(
+                    "Component '{}' must be listed in '{}' or '{}' for system '{}' to be queried",
+                    component.id, READ_ID, WRITE_ID, self.system.id
+                ),
+                component.start_location.clone(),
+            ));
+        }
+    }
+
+    /// Checks that the component exists and is an event, returning whether it is.
+    fn event(&mut self, component: &Reference) -> bool {
+        match self.env.components.get(&component.id) {
+            Some(definition) if is_event(definition) => true,
+            Some(_) => {
+                self.errors.push(Error::new(
+                    format!("Component '{}' isn't an event", component.id),
+                    component.start_location.clone(),
+                ));
+                false
+            }
+            None => {
+                self.errors.push(Error::new(
+                    format!("Event '{}' does not exist", component.id),
+                    component.start_location.clone(),
+                ));
+                false
+            }
+        }
+    }
+
+    /// Checks that the event is written by the system and given a value of the right type for each field.
+    fn emit(&mut self, component: &Reference, args: &[Expr]) {
+        if !self.event(component) {
+            return;
+        }
+
+        if !self.system.writes.iter().any(|w| w.id == component.id) {
+            self.errors.push(Error::new(
+                format!(
+                    "Event '{}' must be listed in '{}' for system '{}' to emit it",
+                    component.id, WRITE_ID, self.system.id
+                ),
+                component.start_location.clone(),
+            ));
+        }
+
+        self.field_values(EMIT_ID, "Event", component, args);
+    }
+
+    /// Checks that every created component can be attached to entities, is written by the system
+    /// and is given a value of the right type for each field.
+    fn create(&mut self, components: &[Constructor]) {
+        for (i, constructor) in components.iter().enumerate() {
+            let component = &constructor.component;
+            let definition = match self.env.components.get(&component.id) {
+                Some(definition) => definition,
+                None => {
+                    self.errors.push(Error::new(
+                        format!("Component '{}' does not exist", component.id),
+                        component.start_location.clone(),
+                    ));
+                    continue;
+                }
+            };
+
+            if is_single(definition) {
+                self.errors.push(Error::new(
+                    format!(
+                        "Single component '{}' has one instance created with the world and can't be added to entities",
+                        component.id
+                    ),
+                    component.start_location.clone(),
+                ));
+                continue;
+            }
+
+            if is_event(definition) {
+                self.errors.push(Error::new(
+                    format!(
+                        "Event '{}' isn't attached to entities, use '{} {}' instead",
+                        component.id, EMIT_ID, component.id
+                    ),
+                    component.start_location.clone(),
+                ));
+                continue;
+            }
+
+            if components[..i]
+                .iter()
+                .any(|c| c.component.id == component.id)
+            {
+                self.errors.push(Error::new(
+                    format!("Component '{}' is created more than once", component.id),
+                    component.start_location.clone(),
+                ));
+            }
+
+            if !self.system.writes.iter().any(|w| w.id == component.id) {
+                self.errors.push(Error::new(
+                    format!(
+                        "Component '{}' must be listed in '{}' for system '{}' to create it",
+                        component.id, WRITE_ID, self.system.id
+                    ),
+                    component.start_location.clone(),
+                ));
+            }
+
+            self.field_values(CREATE_ID, "Component", component, &constructor.args);
+        }
+    }
+
+    /// Checks that a component is given a value of the right type for each of its fields.
+    fn field_values(&mut self, op: &str, kind: &str, component: &Reference, args: &[Expr]) {
+        let fields: Vec<Type> = match self
+            .env
+            .components
+            .get(&component.id)
+            .map(|c| &c.properties)
+        {
+            Some(ComponentProperties::Value(value)) => vec![listable_type(value)],
+            Some(ComponentProperties::Multiple(properties)) => {
+                properties.iter().map(|p| listable_type(&p.ty)).collect()
+            }
+            _ => vec![],
+        };
+        if fields.len() != args.len() {
+            self.errors.push(Error::new(
+                format!(
+                    "{} '{}' takes {} values, found {}",
+                    kind,
+                    component.id,
+                    fields.len(),
+                    args.len()
+                ),
+                component.start_location.clone(),
+            ));
+            return;
+        }
+
+        for (field_ty, arg) in fields.into_iter().zip(args) {
+            let arg_ty = match self.expr(arg) {
+                Some(ty) => ty,
+                None => continue,
+            };
+            self.used_as(arg, &arg_ty, &field_ty);
+
+            match unify(op, field_ty.clone(), arg_ty, arg.start_location.clone()) {
+                Ok(ty) if ty == field_ty => {}
+                Ok(ty) => self.errors.push(Error::new(
+                    format!("Mismatched types '{}' and '{}' for '{}'", field_ty, ty, op),
+                    arg.start_location.clone(),
+                )),
+                Err(error) => self.errors.push(error),
+            }
+        }
+    }
+
+    /// Checks that a component used by a query can be attached to entities.
+    fn queryable(&mut self, component: &Reference) {
+        if self.env.components.get(&component.id).is_some_and(is_event) {
+            self.errors.push(Error::new(
+                format!(
+                    "Event '{}' isn't attached to entities, use '{} ev {} {} {}' instead",
+                    component.id, FOR_ID, IN_ID, EVENTS_ID, component.id
+                ),
+                component.start_location.clone(),
+            ));
+        }
+
+        if self
+            .env
+            .components
+            .get(&component.id)
+            .is_some_and(is_single)
+        {
+            self.errors.push(Error::new(
+                format!(
+                    "Single component '{}' isn't attached to entities, use '{} {}' instead",
+                    component.id, SINGLE_ID, component.id
+                ),
+                component.start_location.clone(),
+            ));
+        }
+    }
+
+    /// Checks that the single components accessed by the expression are listed in `read` or `write`.
+    fn singles(&mut self, expr: &Expr) {
+        match &expr.value {
+            ExprValue::Single { component } => {
+                let is_declared = self
+                    .system
+                    .reads
+                    .iter()
+                    .chain(self.system.writes.iter())
+                    .any(|r| &r.id == component);
+                if !is_declared {
+                    self.errors.push(Error::new(
+                        format!(
+                            "Component '{}' must be listed in '{}' or '{}' for system '{}' to be accessed",
+                            component, READ_ID, WRITE_ID, self.system.id
+                        ),
+                        expr.start_location.clone(),
+                    ));
+                }
+            }
+            ExprValue::Call { target, args, .. } => {
+                self.singles(target);
+                for arg in args {
+                    self.singles(arg);
+                }
+            }
+            ExprValue::Field { target: expr, .. }
+            | ExprValue::Unary { expr, .. }
+            | ExprValue::Group(expr) => self.singles(expr),
+            ExprValue::Binary { lhs, rhs, .. } => {
+                self.singles(lhs);
+                self.singles(rhs);
+            }
+            ExprValue::Number(_)
+            | ExprValue::Bool(_)
+            | ExprValue::Identifier(_)
+            | ExprValue::Has { .. } => {}
+        }
+    }
+
+    fn assign(&mut self, target: &Expr, op: AssignOp, value: &Expr) {
+        // Assigning to an unknown identifier declares a local
+        if let ExprValue::Identifier(id) = &target.value {
+            if op == AssignOp::Assign && self.scope.get(id).is_none() {
+                if let Some(ty) = self.assigned(value) {
+                    // Single components are bound rather than copied so they can be written to
+                    if let ExprValue::Single { component } = &value.value {
+                        self.scope.insert(id, Binding::Single(component.clone()));
+                        return;
+                    }
+
+                    if let Type::List(..) = ty {
+                        self.errors.push(Error::new(
+                            format!("Lists can't be stored in locals, found '{}'", ty),
+                            value.start_location.clone(),
+                        ));
+                    }
+                    self.used_as(value, &ty, &local_type(ty.clone()));
+                    self.scope.insert(id, Binding::Local(local_type(ty)));
+                }
+                return;
+            }
+        }
+
+        let target_ty = match self.target(target) {
+            Some(ty) => ty,
+            None => return,
+        };
+        let value_ty = match self.assigned(value) {
+            Some(ty) => ty,
+            None => return,
+        };
+        self.used_as(value, &value_ty, &target_ty);
+
+        if op != AssignOp::Assign && !target_ty.is_numeric() {
+            self.errors.push(Error::new(
+                format!(
+                    "Operator '{}' requires a number, found '{}'",
+                    op.symbol(),
+                    target_ty
+                ),
+                target.start_location.clone(),
+            ));
+            return;
+        }
+
+        let is_integer = matches!(&target_ty, Type::Primitive(ty) if integer_range(ty).is_some());
+        if op == AssignOp::Divide && is_integer && constant(value) == Some(0) {
+            self.errors.push(Error::new(
+                format!("Operator '{}' divides by zero", op.symbol()),
+                value.start_location.clone(),
+            ));
+        }
+
+        let fits = unify(
+            op.symbol(),
+            target_ty.clone(),
+            value_ty,
+            value.start_location.clone(),
+        );
+        match fits {
+            Ok(ty) if ty == target_ty => {}
+            Ok(ty) => self.errors.push(Error::new(
+                format!(
+                    "Mismatched types '{}' and '{}' for '{}'",
+                    target_ty,
+                    ty,
+                    op.symbol()
+                ),
+                value.start_location.clone(),
+            )),
+            Err(error) => self.errors.push(error),
+        }
+    }
+
+    /// Checks that the target of an assignment is part of a local or of a written component.
+    fn target(&mut self, target: &Expr) -> Option<Type> {
+        if let Some(component) = single_root(&self.scope, target) {
+            if !self.system.writes.iter().any(|w| w.id == component) {
+                self.errors.push(Error::new(
+                    format!(
+                        "Component '{}' is read only for system '{}'",
+                        component, self.system.id
+                    ),
+                    target.start_location.clone(),
+                ));
+                return None;
+            }
+            return self.expr(target);
+        }
+
+        if let Some(id) = self.copy_root(target) {
+            self.errors.push(Error::new(
+                format!("'{}' is a read only copy and can't be assigned to", id),
+                target.start_location.clone(),
+            ));
+            return None;
+        }
+
+        let component = match component_root(&self.scope, target) {
+            Some((_, component)) => component,
+            None if self.is_local(target) => return self.expr(target),
+            // Reports the unknown identifier
+            None if matches!(&target.value, ExprValue::Identifier(id) if self.scope.get(id).is_none()) => {
+                return self.expr(target)
+            }
+            None => {
+                self.errors.push(Error::new(
+                    "Only locals and component fields can be assigned to".to_string(),
+                    target.start_location.clone(),
+                ));
+                return None;
+            }
+        };
+
+        if !self.system.writes.iter().any(|w| w.id == component) {
+            self.errors.push(Error::new(
+                format!(
+                    "Component '{}' is read only for system '{}'",
+                    component, self.system.id
+                ),
+                target.start_location.clone(),
+            ));
+            return None;
+        }
+
+        // The fields of soa components aren't stored together, so there's no whole component to assign to
+        let is_whole = matches!(
+            &target.value,
+            ExprValue::Field { target, .. } if matches!(target.value, ExprValue::Identifier(_))
+        );
+        let is_soa = self.env.components.get(component).is_some_and(|c| {
+            super::is_soa(c) && matches!(c.properties, ComponentProperties::Multiple(_))
+        });
+        if is_whole && is_soa {
+            self.errors.push(Error::new(
+                format!(
+                    "Fields of 'soa' component '{}' must be assigned one at a time",
+                    component
+                ),
+                target.start_location.clone(),
+            ));
+            return None;
+        }
+
+        self.expr(target)
+    }
+
+    /// Returns whether the expression is a local or one of its fields.
+    fn is_local(&self, expr: &Expr) -> bool {
+        match &expr.value {
+            ExprValue::Identifier(id) => matches!(self.scope.get(id), Some(Binding::Local(_))),
+            ExprValue::Field { target, .. } => self.is_local(target),
+            _ => false,
+        }
+    }
+
+    /// Returns the read only copy the expression is or is a field of, if any.
+    fn copy_root<'e>(&self, expr: &'e Expr) -> Option<&'e str> {
+        match &expr.value {
+            ExprValue::Identifier(id) => {
+                matches!(self.scope.get(id), Some(Binding::Copy(_))).then_some(id.as_str())
+            }
+            ExprValue::Field { target, .. } => self.copy_root(target),
+            _ => None,
+        }
+    }
+
+    /// Checks the expression, returning its type if it's valid.
+    fn expr(&mut self, expr: &Expr) -> Option<Type> {
+        let errors = self.errors.len();
+        self.singles(expr);
+        self.changes(expr);
+        self.optional_access(expr, &self.guards.clone());
+        if self.errors.len() > errors {
+            return None;
+        }
+
+        match type_of(self.env, &self.scope, expr) {
+            Ok(ty) => {
+                // Literals on their own take on the type of where they're used, see `used_as`
+                if !matches!(ty, Type::Literal { .. }) {
+                    self.literals(expr, &ty);
+                }
+                Some(ty)
+            }
+            Err(error) => {
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    /// Checks the number literals of a value that's used as the type, e.g. `x = 1` for a `u64` `x`.
+    fn used_as(&mut self, value: &Expr, value_ty: &Type, ty: &Type) {
+        if let Type::Literal { .. } = value_ty {
+            self.literals(value, ty);
+        }
+    }
+
+    /// Checks that the number literals of an expression fit the types they take on, given the type
+    /// the expression is used as.
+    fn literals(&mut self, expr: &Expr, ty: &Type) {
+        match &expr.value {
+            ExprValue::Number(number) => self.literal(number, false, ty, expr),
+            ExprValue::Unary {
+                op: UnaryOp::Negate,
+                expr: operand,
+            } => match &operand.value {
+                ExprValue::Number(number) => self.literal(number, true, ty, expr),
+                _ => self.literals(operand, ty),
+            },
+            ExprValue::Unary { expr, .. } | ExprValue::Group(expr) => self.literals(expr, ty),
+            ExprValue::Binary { op, lhs, rhs } => {
+                let operand = operand_type(self.env, &self.scope, *op, lhs, rhs, ty);
+                // Literals only have a type once it's known where they're used, e.g. `3 % 2` for an `f32`
+                match &operand {
+                    Type::Primitive(primitive)
+                        if *op == BinaryOp::Modulo && is_float(primitive) =>
+                    {
+                        self.errors.push(Error::new(
+                            format!(
+                                "Operator '{}' requires integers, found '{}'",
+                                op.symbol(),
+                                operand
+                            ),
+                            expr.start_location.clone(),
+                        ));
+                    }
+                    _ => {}
+                }
+                if let Type::Primitive(primitive) = &operand {
+                    self.constant_arithmetic(*op, lhs, rhs, primitive, expr);
+                }
+                self.literals(lhs, &operand);
+                self.literals(rhs, &operand);
+            }
+            ExprValue::Call { args, .. } => {
+                let params = param_types(self.env, &self.scope, expr);
+                for (arg, param) in args.iter().zip(params) {
+                    self.literals(arg, &param);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks that integer arithmetic doesn't divide by zero, and that arithmetic on literals gives
+    /// a value that fits the type, e.g. `2147483647 + 1` for an `i32`.
+    fn constant_arithmetic(
+        &mut self,
+        op: BinaryOp,
+        lhs: &Expr,
+        rhs: &Expr,
+        ty: &PrimitiveType,
+        expr: &Expr,
+    ) {
+        let range = match integer_range(ty) {
+            Some(range) => range,
+            None => return,
+        };
+        let location = expr.start_location.clone();
+        let (lhs, rhs) = (constant(lhs), constant(rhs));
+        if matches!(op, BinaryOp::Divide | BinaryOp::Modulo) && rhs == Some(0) {
+            self.errors.push(Error::new(
+                format!("Operator '{}' divides by zero", op.symbol()),
+                location,
+            ));
+            return;
+        }
+
+        let is_arithmetic = matches!(
+            op,
+            BinaryOp::Add
+                | BinaryOp::Subtract
+                | BinaryOp::Multiply
+                | BinaryOp::Divide
+                | BinaryOp::Modulo
+        );
+        let fits = |value: i128| range.0 <= value && value <= range.1;
+        // Operands that don't fit are reported on their own
+        let (lhs, rhs) = match (lhs, rhs) {
+            (Some(lhs), Some(rhs)) if is_arithmetic && fits(lhs) && fits(rhs) => (lhs, rhs),
+            _ => return,
+        };
+        let message = match constant(expr) {
+            Some(value) if fits(value) => return,
+            Some(value) => format!("is {}, which doesn't fit in '{}'", value, ty),
+            None => format!("doesn't fit in '{}'", ty),
+        };
+        self.errors.push(Error::new(
+            format!("'{} {} {}' {}", lhs, op.symbol(), rhs, message),
+            location,
+        ));
+    }
+
+    fn literal(&mut self, number: &str, negated: bool, ty: &Type, expr: &Expr) {
+        if let Type::Primitive(ty) = local_type(ty.clone()) {
+            let location = expr.start_location.clone();
+            if let Err(error) = literal_fits(number, negated, &ty, location) {
+                self.errors.push(error);
+            }
+        }
+    }
+
+    /// Checks that optional components are only accessed after a `has` check or with a default.
+    fn optional_access(&mut self, expr: &Expr, guards: &[(String, String)]) {
+        match &expr.value {
+            ExprValue::Field { target, .. } => {
+                let (entity, component) = match component_root(&self.scope, expr) {
+                    Some(root) => root,
+                    None => return self.optional_access(target, guards),
+                };
+
+                let is_optional = self
+                    .scope
+                    .term(entity, component)
+                    .is_some_and(|t| t.optional);
+                let is_guarded = guards.iter().any(|(e, c)| e == entity && c == component);
+                if is_optional && !is_guarded {
+                    self.errors.push(Error::new(
+                        format!(
+                            "Optional component '{}' must be checked with '{} {} {}' or given a default with '??'",
+                            component, entity, HAS_ID, component
+                        ),
+                        expr.start_location.clone(),
+                    ));
+                }
+            }
+            ExprValue::Binary {
+                op: BinaryOp::Coalesce,
+                lhs,
+                rhs,
+            } => {
+                let is_optional = component_root(&self.scope, lhs)
+                    .and_then(|(entity, component)| self.scope.term(entity, component))
+                    .is_some_and(|t| t.optional);
+                if !is_optional {
+                    self.errors.push(Error::new(
+                        "The left side of '??' must access an optional component".to_string(),
+                        lhs.start_location.clone(),
+                    ));
+                }
+                self.optional_access(rhs, guards);
+            }
+            ExprValue::Binary {
+                op: BinaryOp::And,
+                lhs,
+                rhs,
+            } => {
+                self.optional_access(lhs, guards);
+                let mut guards = guards.to_vec();
+                guards.append(&mut has_checks(lhs));
+                self.optional_access(rhs, &guards);
+            }
+            ExprValue::Binary { lhs, rhs, .. } => {
+                self.optional_access(lhs, guards);
+                self.optional_access(rhs, guards);
+            }
+            ExprValue::Call { target, args, .. } => {
+                self.optional_access(target, guards);
+                for arg in args {
+                    self.optional_access(arg, guards);
+                }
+            }
+            ExprValue::Unary { expr, .. } | ExprValue::Group(expr) => {
+                self.optional_access(expr, guards)
+            }
+            ExprValue::Number(_)
+            | ExprValue::Bool(_)
+            | ExprValue::Identifier(_)
+            | ExprValue::Has { .. }
+            | ExprValue::Single { .. } => {}
+        }
+    }
+}
+
+/// Returns the `has` checks that must all pass for the condition to be true.
+fn has_checks(condition: &Expr) -> Vec<(String, String)> {
+    match &condition.value {
+        ExprValue::Has { entity, component } => vec![(entity.clone(), component.clone())],
+        ExprValue::Binary {
+            op: BinaryOp::And,
+            lhs,
+            rhs,
+        } => {
+            let mut checks = has_checks(lhs);
+            checks.append(&mut has_checks(rhs));
+            checks
+        }
+        ExprValue::Group(expr) => has_checks(expr),
+        _ => vec![],
+    }
+}
@@ -1,3 +1,6 @@
+mod body;
+mod schema;
+mod system;
 #[cfg(test)]
 mod tests;
 
@@ -5,17 +8,51 @@ use crate::{
     error::Error,
     location::Location,
     parser::{
-        is_reserved_word, Component, ComponentProperties, ListType, Listable, Primitive,
-        PrimitiveType, Struct, StructProperties, StructProperty,
+        is_reserved_word, Attribute, Component, ComponentProperties, ComponentType, Enum, ListType,
+        Listable, Primitive, PrimitiveType, Struct, StructProperties, StructProperty, System,
+        World,
     },
     unchecked_env::UncheckedEnv,
 };
 use std::collections::HashMap;
 
+pub use body::{
+    component_root, enum_variant, list_change, local_type, operand_type, param_types, single_root,
+    type_of, Binding, Scope, Type,
+};
+pub use schema::schema;
+pub use system::{conflicts, schedule};
+
+/// The id of the native entity struct.
+pub const ENTITY_ID: &str = "Entity";
+
+/// The number of components each component store can hold.
+pub const MAX_COMPONENTS: usize = 1024;
+
+/// The number of entity ids each component store can index.
+pub const MAX_ENTITIES: usize = MAX_COMPONENTS;
+
+/// The number of events each event queue can hold in a tick.
+pub const MAX_EVENTS: usize = 256;
+
+// Properties of component stores
+pub const STORE_ACTIVE_ID: &str = "active_components";
+pub const STORE_COMPONENTS_ID: &str = "components";
+pub const STORE_ENTITIES_ID: &str = "entities";
+pub const STORE_INDICES_ID: &str = "indices";
+pub const STORE_CHANGED_ID: &str = "changed";
+pub const STORE_INSTANCE_ID: &str = "instance";
+pub const STORE_EVENTS_ID: &str = "events";
+pub const STORE_START_ID: &str = "start";
+pub const STORE_COUNT_ID: &str = "count";
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Env {
     pub components: HashMap<String, Component>,
     pub structs: HashMap<String, StructValue>,
+    pub enums: HashMap<String, Enum>,
+    pub systems: HashMap<String, System>,
+    pub world: Option<World>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -32,6 +69,47 @@ impl StructValue {
     }
 }
 
+/// Returns the id of the store struct for the given component id.
+pub fn store_id(component_id: &str) -> String {
+    format!("{}_Store", component_id)
+}
+
+/// Returns the number of components the store for the given component can hold.
+pub fn store_capacity(component: &Component) -> usize {
+    match component.component_type {
+        ComponentType::Component => MAX_COMPONENTS,
+        ComponentType::SingleComponent => 1,
+        ComponentType::Event => MAX_EVENTS,
+    }
+}
+
+/// Returns whether the component has exactly one instance instead of one per entity.
+pub fn is_single(component: &Component) -> bool {
+    component.component_type == ComponentType::SingleComponent
+}
+
+/// Returns whether the component is an event that's queued rather than attached to entities.
+pub fn is_event(component: &Component) -> bool {
+    component.component_type == ComponentType::Event
+}
+
+/// Returns whether the component keeps each field in its own array.
+/// Single components and events always keep their fields together.
+pub fn is_soa(component: &Component) -> bool {
+    component.attributes.contains(&Attribute::Soa)
+        && component.component_type == ComponentType::Component
+}
+
+/// Returns the id of the store array that holds the given component field.
+/// Stores that aren't SoA hold all fields in a single array of components.
+pub fn store_array_id(component: &Component, field: &str) -> String {
+    if is_soa(component) {
+        format!("{}_{}", STORE_COMPONENTS_ID, field)
+    } else {
+        STORE_COMPONENTS_ID.to_string()
+    }
+}
+
 fn finalize_errors(mut errors: Vec<Error>) -> Vec<Error> {
     errors.dedup();
     errors.sort_by(|a, b| {
@@ -50,9 +128,22 @@ fn finalize_errors(mut errors: Vec<Error>) -> Vec<Error> {
 // Build out a checked environment from an unchecked environment.
 // Return a list of errors.
 pub fn build(unchecked: UncheckedEnv) -> Result<Env, Vec<Error>> {
+    match build_partial(unchecked) {
+        (Some(env), errors) if errors.is_empty() => Ok(env),
+        (_, errors) => Err(errors),
+    }
+}
+
+/// Builds the environment, returning it along with every error found.
+/// The environment is only missing when its declarations couldn't be assembled, so it may fail later checks
+/// such as conflicts between systems in a stage.
+pub fn build_partial(unchecked: UncheckedEnv) -> (Option<Env>, Vec<Error>) {
     let mut env = Env {
         components: HashMap::new(),
         structs: HashMap::new(),
+        enums: HashMap::new(),
+        systems: HashMap::new(),
+        world: None,
     };
 
     let mut errors = vec![];
@@ -99,32 +190,123 @@ pub fn build(unchecked: UncheckedEnv) -> Result<Env, Vec<Error>> {
         }
     }
 
+    // Assemble enums
+    for e in unchecked.enums {
+        if let Some(existing) = env.enums.get(&e.id) {
+            let mut error = Error {
+                message: format!("Multiple enum definitions for '{}'", e.id),
+                location: e.start_location,
+            };
+            errors.push(error.clone());
+            error.location = existing.start_location.clone();
+            errors.push(error);
+        } else if is_reserved_word(&e.id) {
+            errors.push(Error {
+                message: format!("Enum id '{}' is a reserved word", e.id),
+                location: e.start_location,
+            });
+        } else {
+            env.enums.insert(e.id.clone(), e);
+        }
+    }
+
     // TODO: look into abstracting the above checks into a method
-    // TODO: assemble systems
-    // TODO: assemble world
+
+    // Assemble systems
+    for system in unchecked.systems {
+        if let Some(existing) = env.systems.get(&system.id) {
+            let mut error = Error {
+                message: format!("Multiple system definitions for '{}'", system.id),
+                location: system.start_location,
+            };
+            errors.push(error.clone());
+            error.location = existing.start_location.clone();
+            errors.push(error);
+        } else if is_reserved_word(&system.id) {
+            errors.push(Error {
+                message: format!("System id '{}' is a reserved word", system.id),
+                location: system.start_location,
+            });
+        } else {
+            env.systems.insert(system.id.clone(), system);
+        }
+    }
+
+    // Assemble world
+    for world in unchecked.worlds {
+        if let Some(existing) = &env.world {
+            let mut error = Error {
+                message: "Only one world may exist".to_string(),
+                location: world.start_location,
+            };
+            errors.push(error.clone());
+            error.location = existing.start_location.clone();
+            errors.push(error);
+        } else {
+            env.world = Some(world);
+        }
+    }
+
     // TODO: assemble expressions
 
     // No point to attempting to validate further as we need properly declarations for additional type checking..
     if !errors.is_empty() {
-        return Err(finalize_errors(errors));
+        return (None, finalize_errors(errors));
     }
 
     // Build out default implementations
-    build_native_structures(&mut env)?;
-    build_native_expressions(&mut env)?;
+    if let Err(errors) = build_native_structures(&mut env) {
+        return (None, errors);
+    }
+    if let Err(errors) = build_native_expressions(&mut env) {
+        return (None, errors);
+    }
 
     if !errors.is_empty() {
-        return Err(finalize_errors(errors));
+        return (None, finalize_errors(errors));
     }
 
     // Validate components
     for component in env.components.values() {
+        if component.attributes.contains(&Attribute::Auto) {
+            errors.push(Error {
+                message: format!(
+                    "Attribute '{}' is only allowed on the world, found on component '{}'",
+                    Attribute::Auto,
+                    component.id
+                ),
+                location: component.start_location.clone(),
+            });
+        }
+
+        if is_single(component) && component.attributes.contains(&Attribute::Soa) {
+            errors.push(Error {
+                message: format!(
+                    "Attribute '{}' is only allowed on components, found on single component '{}'",
+                    Attribute::Soa,
+                    component.id
+                ),
+                location: component.start_location.clone(),
+            });
+        }
+
+        if is_event(component) && component.attributes.contains(&Attribute::Soa) {
+            errors.push(Error {
+                message: format!(
+                    "Attribute '{}' is only allowed on components, found on event '{}'",
+                    Attribute::Soa,
+                    component.id
+                ),
+                location: component.start_location.clone(),
+            });
+        }
+
         match &component.properties {
             ComponentProperties::None => {}
             ComponentProperties::Value(value) => {
                 // Check that the type for value exists if it's an identifier
                 if let PrimitiveType::Identifier(id) = &value.inner_ty().primitive_type {
-                    if !env.structs.contains_key(id) {
+                    if !type_exists(&env, id) {
                         errors.push(Error {
                             message: format!(
                                 "Referenced struct type '{}' does not exist for value component '{}'",
@@ -168,7 +350,7 @@ pub fn build(unchecked: UncheckedEnv) -> Result<Env, Vec<Error>> {
                     // Check that the type for value exists if it's an identifier
                     let prop_type = &property.ty.inner_ty().primitive_type;
                     if let PrimitiveType::Identifier(ty_id) = prop_type {
-                        if !env.structs.contains_key(ty_id) {
+                        if !type_exists(&env, ty_id) {
                             errors.push(Error {
                                 message: format!(
                                     "Referenced struct type '{}' does not exist for property '{}' for component '{}'",
@@ -185,18 +367,45 @@ pub fn build(unchecked: UncheckedEnv) -> Result<Env, Vec<Error>> {
 
     // Validate structs
     for strukt in env.structs.values() {
-        let check_id = match strukt {
-            StructValue::Struct(_) => struct_exists,
-            StructValue::ComponentStore(_) => component_exists,
+        // Only the components of a component store refer to a component, everything else is a struct
+        let is_store = matches!(strukt, StructValue::ComponentStore(_));
+        let check_id = |property: &str, id: &str| {
+            if is_store && is_component_property(property) {
+                component_exists(&env, id)
+            } else {
+                type_exists(&env, id)
+            }
         };
         let strukt = strukt.strukt();
 
+        if strukt.attributes.contains(&Attribute::Auto) {
+            errors.push(Error {
+                message: format!(
+                    "Attribute '{}' is only allowed on the world, found on struct '{}'",
+                    Attribute::Auto,
+                    strukt.id
+                ),
+                location: strukt.start_location.clone(),
+            });
+        }
+
+        if strukt.attributes.contains(&Attribute::Soa) {
+            errors.push(Error {
+                message: format!(
+                    "Attribute '{}' is only allowed on components, found on struct '{}'",
+                    Attribute::Soa,
+                    strukt.id
+                ),
+                location: strukt.start_location.clone(),
+            });
+        }
+
         match &strukt.properties {
             StructProperties::None => {}
             StructProperties::Value(value) => {
                 // Check that the type for value exists if it's an identifier
                 if let PrimitiveType::Identifier(id) = &value.inner_ty().primitive_type {
-                    if !check_id(&env, id) {
+                    if !check_id("value", id) {
                         errors.push(Error {
                             message: format!(
                                 "Referenced struct type '{}' does not exist for value struct '{}'",
@@ -240,7 +449,7 @@ pub fn build(unchecked: UncheckedEnv) -> Result<Env, Vec<Error>> {
                     // Check that the type for value exists if it's an identifier
                     let prop_type = &property.ty.inner_ty().primitive_type;
                     if let PrimitiveType::Identifier(ty_id) = prop_type {
-                        if !check_id(&env, ty_id) {
+                        if !check_id(&property.identifier, ty_id) {
                             errors.push(Error {
                                 message: format!(
                                     "Referenced struct type '{}' does not exist for property '{}' for struct '{}'",
@@ -255,19 +464,232 @@ pub fn build(unchecked: UncheckedEnv) -> Result<Env, Vec<Error>> {
         }
     }
 
+    // Validate enums
+    for e in env.enums.values() {
+        if env.structs.contains_key(&e.id) {
+            errors.push(Error {
+                message: format!("Enum '{}' has the same id as a struct", e.id),
+                location: e.start_location.clone(),
+            });
+        }
+
+        if e.variants.is_empty() {
+            errors.push(Error {
+                message: format!("Enum '{}' must have at least one variant", e.id),
+                location: e.start_location.clone(),
+            });
+        }
+
+        for (idx, variant) in e.variants.iter().enumerate() {
+            // Check that duplicate variant ids don't exist
+            if e.variants[..idx].iter().any(|v| v.id == variant.id) {
+                errors.push(Error {
+                    message: format!(
+                        "Duplicate variant identifier '{}' for enum '{}'",
+                        variant.id, e.id
+                    ),
+                    location: variant.start_location.clone(),
+                });
+            }
+
+            // Check that the type for the payload exists if it's an identifier
+            let payload = variant
+                .payload
+                .as_ref()
+                .map(|p| &p.inner_ty().primitive_type);
+            if let Some(PrimitiveType::Identifier(ty_id)) = payload {
+                if !type_exists(&env, ty_id) {
+                    errors.push(Error {
+                        message: format!(
+                            "Referenced struct type '{}' does not exist for variant '{}' for enum '{}'",
+                            ty_id, variant.id, e.id
+                        ),
+                        location: variant.start_location.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    errors.append(&mut check_struct_cycles(&env));
+
     // TODO: validate expressions
-    // TODO: validate systems
-    // TODO: validate world
+    errors.append(&mut system::validate_systems(&env));
+    errors.append(&mut system::validate_world(&env));
 
-    if errors.is_empty() {
-        Ok(env)
-    } else {
-        return Err(finalize_errors(errors));
+    (Some(env), finalize_errors(errors))
+}
+
+/// A by-value reference from a struct property to another struct.
+#[derive(Debug, Clone, Copy)]
+struct StructEdge<'a> {
+    id: &'a str,
+    property: &'a str,
+    ty: &'a str,
+    location: &'a Location,
+}
+
+/// Returns the struct types a struct contains by value, including lists.
+fn struct_edges(strukt: &Struct) -> Vec<StructEdge<'_>> {
+    let mut edges = vec![];
+    match &strukt.properties {
+        StructProperties::None => {}
+        StructProperties::Value(value) => {
+            if let PrimitiveType::Identifier(ty) = &value.inner_ty().primitive_type {
+                edges.push(StructEdge {
+                    id: &strukt.id,
+                    property: "value",
+                    ty,
+                    location: &value.start_location,
+                });
+            }
+        }
+        StructProperties::Multiple(properties) => {
+            for property in properties {
+                if let PrimitiveType::Identifier(ty) = &property.ty.inner_ty().primitive_type {
+                    edges.push(StructEdge {
+                        id: &strukt.id,
+                        property: &property.identifier,
+                        ty,
+                        location: &property.start_location,
+                    });
+                }
+            }
+        }
+    }
+
+    edges
+}
+
+/// Returns the types the variants of an enum hold by value, including lists.
+fn enum_edges(e: &Enum) -> Vec<StructEdge<'_>> {
+    let mut edges = vec![];
+    for variant in e.variants.iter() {
+        let payload = variant
+            .payload
+            .as_ref()
+            .map(|p| &p.inner_ty().primitive_type);
+        if let Some(PrimitiveType::Identifier(ty)) = payload {
+            edges.push(StructEdge {
+                id: &e.id,
+                property: &variant.id,
+                ty,
+                location: &variant.start_location,
+            });
+        }
+    }
+
+    edges
+}
+
+/// Checks that no struct contains itself by value, as it would have an infinite size.
+/// Reports an error for each cycle with the full path of properties that form it.
+fn check_struct_cycles(env: &Env) -> Vec<Error> {
+    let mut ids: Vec<&String> = env
+        .structs
+        .iter()
+        .filter(|(_, s)| matches!(s, StructValue::Struct(_)))
+        .map(|(id, _)| id)
+        .chain(env.enums.keys())
+        .collect();
+    ids.sort();
+
+    let mut cycles = vec![];
+    let mut finished = vec![];
+    for id in ids {
+        find_struct_cycles(env, id, &mut vec![], &mut finished, &mut cycles);
+    }
+
+    cycles
+        .into_iter()
+        .map(|cycle| {
+            let path = cycle
+                .iter()
+                .map(|edge| {
+                    format!(
+                        "{}.{} ({})",
+                        edge.id,
+                        edge.property,
+                        edge.location.pretty_print_line_column()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" -> ");
+
+            let first = cycle[0];
+            let kind = if env.enums.contains_key(first.id) {
+                "Enum"
+            } else {
+                "Struct"
+            };
+            Error {
+                message: format!(
+                    "{} '{}' contains itself by value: {} -> {}",
+                    kind, first.id, path, first.id
+                ),
+                location: first.location.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Depth first search over struct properties and enum variants that records each cycle it finds.
+/// Cycles are rotated to start at their smallest id so each one is only reported once.
+fn find_struct_cycles<'a>(
+    env: &'a Env,
+    id: &'a str,
+    path: &mut Vec<StructEdge<'a>>,
+    finished: &mut Vec<&'a str>,
+    cycles: &mut Vec<Vec<StructEdge<'a>>>,
+) {
+    if finished.contains(&id) {
+        return;
+    }
+
+    let edges = match (env.structs.get(id), env.enums.get(id)) {
+        (Some(StructValue::Struct(strukt)), _) => struct_edges(strukt),
+        (None, Some(e)) => enum_edges(e),
+        _ => return,
+    };
+
+    for edge in edges {
+        path.push(edge);
+
+        match path.iter().position(|e| e.id == edge.ty) {
+            Some(start) => {
+                let mut cycle = path[start..].to_vec();
+                let smallest = (0..cycle.len()).min_by_key(|i| cycle[*i].id).unwrap_or(0);
+                cycle.rotate_left(smallest);
+
+                let is_new = !cycles.iter().any(|existing: &Vec<StructEdge>| {
+                    existing.len() == cycle.len()
+                        && existing
+                            .iter()
+                            .zip(cycle.iter())
+                            .all(|(a, b)| a.id == b.id && a.property == b.property)
+                });
+
+                if is_new {
+                    cycles.push(cycle);
+                }
+            }
+            None => find_struct_cycles(env, edge.ty, path, finished, cycles),
+        }
+
+        path.pop();
     }
+
+    finished.push(id);
 }
 
-fn struct_exists(env: &Env, id: &str) -> bool {
-    env.structs.contains_key(id)
+/// Returns whether the property of a component store holds components rather than structs.
+pub fn is_component_property(property: &str) -> bool {
+    property == STORE_COMPONENTS_ID || property == STORE_INSTANCE_ID || property == STORE_EVENTS_ID
+}
+
+/// Returns whether the id is a struct or an enum, which can both be used as property types.
+fn type_exists(env: &Env, id: &str) -> bool {
+    env.structs.contains_key(id) || env.enums.contains_key(id)
 }
 
 fn component_exists(env: &Env, id: &str) -> bool {
@@ -276,7 +698,10 @@ fn component_exists(env: &Env, id: &str) -> bool {
 
 fn build_native_structures(env: &mut Env) -> Result<(), Vec<Error>> {
     let entity = StructValue::Struct(Struct {
-        id: "Entity".to_string(),
+        id: ENTITY_ID.to_string(),
+        comments: vec!["A unique identifier for an entity.".to_string()],
+        trailing_comments: vec![],
+        attributes: vec![],
         properties: StructProperties::Value(Listable {
             ty: ListType::Single(Primitive {
                 primitive_type: PrimitiveType::U64,
@@ -295,17 +720,106 @@ fn build_native_structures(env: &mut Env) -> Result<(), Vec<Error>> {
 
     // Build out component stores
     for component in env.components.values() {
+        // The entity struct is native, so a component can't share its name
+        if component.id == ENTITY_ID {
+            errors.push(Error {
+                message: format!("Component id '{}' is reserved for native struct", ENTITY_ID),
+                location: component.start_location.clone(),
+            });
+            continue;
+        }
+
+        // Single components have one instance that isn't attached to an entity
+        // properties:
+        // instance - the component, unless it is a tag
+        // changed - whether the instance was written this tick
+        if is_single(component) {
+            let mut properties = vec![];
+            if !matches!(component.properties, ComponentProperties::None) {
+                properties.push((STORE_INSTANCE_ID, component.id.as_str()).into());
+            }
+            properties.push((STORE_CHANGED_ID, PrimitiveType::Bool).into());
+
+            system_structs.push(StructValue::ComponentStore(Struct {
+                id: store_id(&component.id),
+                comments: vec![format!(
+                    "Storage for the single '{}' component.",
+                    component.id
+                )],
+                trailing_comments: vec![],
+                attributes: vec![],
+                properties: StructProperties::Multiple(properties),
+                start_location: Location::SystemDefined,
+                end_location: Location::SystemDefined,
+            }));
+            continue;
+        }
+
+        // Events are queued in a ring that's emptied at the end of each tick
+        // properties:
+        // start - the index of the oldest event
+        // count - the number of events in the queue
+        // events - the ring of events, unless they're tags
+        if is_event(component) {
+            let mut properties = vec![
+                (STORE_START_ID, PrimitiveType::U64).into(),
+                (STORE_COUNT_ID, PrimitiveType::U64).into(),
+            ];
+            if !matches!(component.properties, ComponentProperties::None) {
+                properties.push(
+                    (
+                        STORE_EVENTS_ID,
+                        component.id.as_str(),
+                        store_capacity(component),
+                    )
+                        .into(),
+                );
+            }
+
+            system_structs.push(StructValue::ComponentStore(Struct {
+                id: store_id(&component.id),
+                comments: vec![format!(
+                    "Queue of the '{}' events emitted this tick.",
+                    component.id
+                )],
+                trailing_comments: vec![],
+                attributes: vec![],
+                properties: StructProperties::Multiple(properties),
+                start_location: Location::SystemDefined,
+                end_location: Location::SystemDefined,
+            }));
+            continue;
+        }
+
         // properties:
-        // active_components - usize
-        // components - a list of components
+        // active_components - the number of components in use
+        // components - a list of components, or a list per field for SoA components
         // entities - a list of entities that maps to the components
-        let max_components = 1024;
+        // indices - the index of each entity's component plus one, or zero if it has none
+        // changed - whether each component was added or written this tick
+        let capacity = store_capacity(component);
+        let mut properties = vec![(STORE_ACTIVE_ID, PrimitiveType::U64).into()];
+        if is_soa(component) {
+            match soa_properties(component, capacity) {
+                Ok(mut soa) => properties.append(&mut soa),
+                Err(mut soa_errors) => {
+                    errors.append(&mut soa_errors);
+                    continue;
+                }
+            }
+        } else {
+            properties.push((STORE_COMPONENTS_ID, component.id.as_str(), capacity).into());
+        }
+        properties.push((STORE_ENTITIES_ID, ENTITY_ID, capacity).into());
+        properties.push((STORE_INDICES_ID, PrimitiveType::U32, MAX_ENTITIES).into());
+        properties.push((STORE_CHANGED_ID, PrimitiveType::Bool, capacity).into());
+
         let component_store = Struct {
-            id: format!("{}_Store", component.id),
-            properties: StructProperties::Multiple(vec![
-                ("active_components", PrimitiveType::U64).into(),
-                ("components", &component.id).into(),
-            ]),
+            id: store_id(&component.id),
+            comments: vec![format!("Storage for all '{}' components.", component.id)],
+            trailing_comments: vec![],
+            attributes: vec![],
+            properties: StructProperties::Multiple(properties),
             start_location: Location::SystemDefined,
             end_location: Location::SystemDefined,
         };
@@ -335,6 +849,58 @@ fn build_native_structures(env: &mut Env) -> Result<(), Vec<Error>> {
     }
 }
 
-fn build_native_expressions(env: &mut Env) -> Result<(), Vec<Error>> {
+/// Returns a list property per component field. Fields that are lists can't be stored this way.
+fn soa_properties(
+    component: &Component,
+    capacity: usize,
+) -> Result<Vec<StructProperty>, Vec<Error>> {
+    let fields = match &component.properties {
+        ComponentProperties::None => vec![],
+        ComponentProperties::Value(value) => vec![("value", value)],
+        ComponentProperties::Multiple(properties) => properties
+            .iter()
+            .map(|p| (p.identifier.as_str(), &p.ty))
+            .collect(),
+    };
+
+    let mut properties = vec![];
+    let mut errors = vec![];
+    for (field, ty) in fields {
+        match &ty.ty {
+            ListType::Single(primitive) => properties.push(StructProperty {
+                identifier: store_array_id(component, field),
+                comments: vec![],
+                trailing_comment: None,
+                ty: Listable {
+                    ty: ListType::List {
+                        ty: primitive.clone(),
+                        max_size: capacity,
+                    },
+                    start_location: Location::SystemDefined,
+                    end_location: Location::SystemDefined,
+                },
+                start_location: Location::SystemDefined,
+                end_location: Location::SystemDefined,
+            }),
+            ListType::List { .. } => errors.push(Error {
+                message: format!(
+                    "List property '{}' can't be stored in '{}' component '{}'",
+                    field,
+                    Attribute::Soa,
+                    component.id
+                ),
+                location: ty.start_location.clone(),
+            }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(properties)
+    } else {
+        Err(errors)
+    }
+}
+
+fn build_native_expressions(_env: &mut Env) -> Result<(), Vec<Error>> {
     Ok(())
 }
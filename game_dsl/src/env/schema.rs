@@ -0,0 +1,345 @@
+//! The schema is a JSON description of every type in a checked env, so tools can discover
+//! types without parsing the language. Everything is sorted by id so the output is stable.
+//!
+//! ```json
+//! {
+//!   "version": 2,
+//!   "structs": [
+//!     { "id": "Name", "comments": [], "ordered": false, "fields": [FIELD] }
+//!   ],
+//!   "enums": [
+//!     { "id": "Status", "comments": [], "variants": [FIELD] }
+//!   ],
+//!   "components": [
+//!     {
+//!       "id": "Hp", "comments": [], "kind": "component", "storage": "aos",
+//!       "capacity": 1024, "fields": [FIELD]
+//!     }
+//!   ],
+//!   "systems": [
+//!     { "id": "Move", "comments": [], "read": ["Velocity"], "write": ["Position"] }
+//!   ],
+//!   "world": {
+//!     "comments": [], "auto": false,
+//!     "stages": [{ "comments": [], "systems": ["Move"] }],
+//!     "schedule": [["Move"]]
+//!   }
+//! }
+//! ```
+//!
+//! - `kind` is either `component`, `single_component` or `event`.
+//! - `storage` is either `aos` or `soa`.
+//! - `capacity` is the number of components a store can hold.
+//!
+//! A `FIELD` is `{ "id": "name", "comments": [], "type": "char", "length": 10 }`. The `type` is
+//! either a primitive or the id of a struct. The `length` of a list is its size and is `null`
+//! for single values. Value types like `component Hp(i32);` have a single field named `value`.
+//! Each variant of an enum is a `FIELD` with the value it holds, or a `null` type if it holds none.
+//!
+//! Stages keep the order they're declared in and `world` is `null` if none is declared.
+//! The `schedule` is the stages that are dispatched, which `auto` worlds derive from the systems.
+
+use super::{is_soa, schedule, store_capacity, Env, StructValue};
+use crate::{
+    json::Json,
+    parser::{
+        Attribute, Component, ComponentProperties, ComponentType, Enum, ListType, Listable,
+        Primitive, Reference, Struct, StructProperties, System, World, COMPONENT_ID, EVENT_ID,
+        READ_ID, SINGLE_COMPONENT_ID, WRITE_ID,
+    },
+};
+
+/// Incremented whenever the layout of the schema changes.
+pub const SCHEMA_VERSION: usize = 2;
+
+/// Returns the schema of every type and system in the env.
+pub fn schema(env: &Env) -> Json {
+    let mut structs: Vec<&Struct> = env
+        .structs
+        .values()
+        .filter_map(|s| match s {
+            StructValue::Struct(s) => Some(s),
+            // Stores are generated, so they aren't part of the schema
+            StructValue::ComponentStore(_) => None,
+        })
+        .collect();
+    structs.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut enums: Vec<&Enum> = env.enums.values().collect();
+    enums.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut components: Vec<&Component> = env.components.values().collect();
+    components.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut systems: Vec<&System> = env.systems.values().collect();
+    systems.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Json::object(vec![
+        ("version", SCHEMA_VERSION.into()),
+        (
+            "structs",
+            structs
+                .into_iter()
+                .map(struct_schema)
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+        (
+            "enums",
+            enums
+                .into_iter()
+                .map(enum_schema)
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+        (
+            "components",
+            components
+                .into_iter()
+                .map(component_schema)
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+        (
+            "systems",
+            systems
+                .into_iter()
+                .map(system_schema)
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+        (
+            "world",
+            env.world
+                .as_ref()
+                .map(|w| world_schema(w, schedule(env)))
+                .unwrap_or(Json::Null),
+        ),
+    ])
+}
+
+fn struct_schema(strukt: &Struct) -> Json {
+    let fields = match &strukt.properties {
+        StructProperties::None => vec![],
+        StructProperties::Value(value) => vec![field_schema("value", &[], value)],
+        StructProperties::Multiple(properties) => properties
+            .iter()
+            .map(|p| field_schema(&p.identifier, &p.comments, &p.ty))
+            .collect(),
+    };
+
+    Json::object(vec![
+        ("id", strukt.id.as_str().into()),
+        ("comments", comments(&strukt.comments)),
+        (
+            "ordered",
+            strukt.attributes.contains(&Attribute::Ordered).into(),
+        ),
+        ("fields", fields.into()),
+    ])
+}
+
+fn enum_schema(e: &Enum) -> Json {
+    let variants = e
+        .variants
+        .iter()
+        .map(|v| match &v.payload {
+            Some(payload) => field_schema(&v.id, &v.comments, payload),
+            None => Json::object(vec![
+                ("id", v.id.as_str().into()),
+                ("comments", comments(&v.comments)),
+                ("type", Json::Null),
+                ("length", Json::Null),
+            ]),
+        })
+        .collect::<Vec<_>>();
+
+    Json::object(vec![
+        ("id", e.id.as_str().into()),
+        ("comments", comments(&e.comments)),
+        ("variants", variants.into()),
+    ])
+}
+
+fn component_schema(component: &Component) -> Json {
+    let kind = match component.component_type {
+        ComponentType::Component => COMPONENT_ID,
+        ComponentType::SingleComponent => SINGLE_COMPONENT_ID,
+        ComponentType::Event => EVENT_ID,
+    };
+    let storage = if is_soa(component) { "soa" } else { "aos" };
+
+    let fields = match &component.properties {
+        ComponentProperties::None => vec![],
+        ComponentProperties::Value(value) => vec![field_schema("value", &[], value)],
+        ComponentProperties::Multiple(properties) => properties
+            .iter()
+            .map(|p| field_schema(&p.identifier, &p.comments, &p.ty))
+            .collect(),
+    };
+
+    Json::object(vec![
+        ("id", component.id.as_str().into()),
+        ("comments", comments(&component.comments)),
+        ("kind", kind.into()),
+        ("storage", storage.into()),
+        ("capacity", store_capacity(component).into()),
+        ("fields", fields.into()),
+    ])
+}
+
+fn system_schema(system: &System) -> Json {
+    Json::object(vec![
+        ("id", system.id.as_str().into()),
+        ("comments", comments(&system.comments)),
+        (READ_ID, references(&system.reads)),
+        (WRITE_ID, references(&system.writes)),
+    ])
+}
+
+fn world_schema(world: &World, schedule: Vec<Vec<&str>>) -> Json {
+    let stages: Vec<Json> = world
+        .stages
+        .iter()
+        .map(|stage| {
+            Json::object(vec![
+                ("comments", comments(&stage.comments)),
+                ("systems", references(&stage.systems)),
+            ])
+        })
+        .collect();
+
+    let schedule: Vec<Json> = schedule
+        .into_iter()
+        .map(|stage| stage.into_iter().map(Json::from).collect::<Vec<_>>().into())
+        .collect();
+
+    Json::object(vec![
+        ("comments", comments(&world.comments)),
+        ("auto", world.attributes.contains(&Attribute::Auto).into()),
+        ("stages", stages.into()),
+        ("schedule", schedule.into()),
+    ])
+}
+
+fn references(references: &[Reference]) -> Json {
+    references
+        .iter()
+        .map(|r| r.id.as_str().into())
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn field_schema(id: &str, field_comments: &[String], ty: &Listable<Primitive>) -> Json {
+    let length = match &ty.ty {
+        ListType::Single(_) => Json::Null,
+        ListType::List { max_size, .. } => (*max_size).into(),
+    };
+
+    Json::object(vec![
+        ("id", id.into()),
+        ("comments", comments(field_comments)),
+        ("type", ty.ty.inner_ty().to_string().into()),
+        ("length", length),
+    ])
+}
+
+fn comments(comments: &[String]) -> Json {
+    comments
+        .iter()
+        .map(|c| c.as_str().into())
+        .collect::<Vec<_>>()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env, location::Location, parser, unchecked_env};
+
+    fn schema(code: &str) -> Json {
+        let ast = parser::parse(code, Location::default()).unwrap();
+        let env = env::build(unchecked_env::build(ast)).unwrap();
+        super::schema(&env)
+    }
+
+    #[test]
+    fn schema_describes_components() {
+        let schema = schema(
+            "# Where it is.\ncomponent Position soa { i32 x i32 y }\nsingle_component Title([char 16]);",
+        );
+
+        let components = schema.get("components").unwrap().as_array().unwrap();
+        let position = &components[0];
+        assert_eq!(Some("Position"), position.get("id").unwrap().as_str());
+        assert_eq!(Some("soa"), position.get("storage").unwrap().as_str());
+        assert_eq!(Some(1024), position.get("capacity").unwrap().as_usize());
+        assert_eq!(
+            Some("Where it is."),
+            position.get("comments").unwrap().as_array().unwrap()[0].as_str()
+        );
+
+        let title = &components[1];
+        assert_eq!(
+            Some("single_component"),
+            title.get("kind").unwrap().as_str()
+        );
+        assert_eq!(Some(1), title.get("capacity").unwrap().as_usize());
+        let value = &title.get("fields").unwrap().as_array().unwrap()[0];
+        assert_eq!(
+            "{\"id\":\"value\",\"comments\":[],\"type\":\"char\",\"length\":16}",
+            value.to_compact_string()
+        );
+    }
+
+    #[test]
+    fn schema_leaves_out_stores() {
+        let schema = schema("struct Name([char 4]);\ncomponent Person { Name name i32 age }");
+
+        let ids: Vec<&str> = schema
+            .get("structs")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s.get("id").unwrap().as_str().unwrap())
+            .collect();
+        assert_eq!(vec!["Entity", "Name"], ids);
+
+        let age = &schema.path(&["components"]).unwrap().as_array().unwrap()[0]
+            .get("fields")
+            .unwrap()
+            .as_array()
+            .unwrap()[1];
+        assert_eq!(Some("i32"), age.get("type").unwrap().as_str());
+        assert_eq!(Some(&Json::Null), age.get("length"));
+        assert_eq!(Some(&Json::Null), schema.get("world"));
+    }
+
+    #[test]
+    fn schema_describes_enums() {
+        let schema = schema("enum Status { Ok, Damaged([char 2]) }");
+
+        assert_eq!(
+            "[{\"id\":\"Status\",\"comments\":[],\"variants\":[{\"id\":\"Ok\",\"comments\":[],\"type\":null,\"length\":null},{\"id\":\"Damaged\",\"comments\":[],\"type\":\"char\",\"length\":2}]}]",
+            schema.get("enums").unwrap().to_compact_string()
+        );
+    }
+
+    #[test]
+    fn schema_describes_systems_and_world() {
+        let schema = schema(
+            "component Position;\ncomponent Velocity;\nsystem Move { read [Velocity] write [Position] }\nworld {\n    [Move]\n}",
+        );
+
+        let system = &schema.get("systems").unwrap().as_array().unwrap()[0];
+        assert_eq!(
+            "{\"id\":\"Move\",\"comments\":[],\"read\":[\"Velocity\"],\"write\":[\"Position\"]}",
+            system.to_compact_string()
+        );
+        assert_eq!(
+            "{\"comments\":[],\"auto\":false,\"stages\":[{\"comments\":[],\"systems\":[\"Move\"]}],\"schedule\":[[\"Move\"]]}",
+            schema.get("world").unwrap().to_compact_string()
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use super::{body, Env};
+use crate::{
+    compiler_ir::WORLD_NEXT_ENTITY_ID,
+    error::Error,
+    parser::{
+        Attribute, Reference, Statement, StatementValue, System, SystemType, World, READ_ID,
+        TARGET_C_IMPL_ID, WRITE_ID,
+    },
+};
+use std::path::{Component, Path};
+
+/// Returns the components that keep two systems from running at the same time, sorted by id.
+/// Systems conflict when one writes a component the other reads or writes,
+/// and systems that both create entities conflict on the id of the next entity.
+pub fn conflicts<'a>(a: &'a System, b: &'a System) -> Vec<&'a str> {
+    let accesses = |s: &'a System| s.reads.iter().chain(s.writes.iter()).map(|r| r.id.as_str());
+
+    let mut components: Vec<&str> = a
+        .writes
+        .iter()
+        .map(|r| r.id.as_str())
+        .filter(|id| accesses(b).any(|other| other == *id))
+        .chain(
+            b.writes
+                .iter()
+                .map(|r| r.id.as_str())
+                .filter(|id| accesses(a).any(|other| other == *id)),
+        )
+        .collect();
+    if creates(&a.body) && creates(&b.body) {
+        components.push(WORLD_NEXT_ENTITY_ID);
+    }
+    components.sort();
+    components.dedup();
+    components
+}
+
+/// Returns whether any of the statements, or the statements nested in them, create an entity.
+fn creates(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match &statement.value {
+        StatementValue::Create { .. } => true,
+        StatementValue::Query { body, .. }
+        | StatementValue::Events { body, .. }
+        | StatementValue::Iterate { body, .. } => creates(body),
+        StatementValue::If {
+            then, otherwise, ..
+        } => creates(then) || creates(otherwise),
+        StatementValue::Match { arms, .. } => arms.iter().any(|arm| creates(&arm.body)),
+        StatementValue::Comment(_)
+        | StatementValue::Emit { .. }
+        | StatementValue::Assign { .. }
+        | StatementValue::Call(_) => false,
+    })
+}
+
+/// Returns the ids of the systems each stage of the world runs.
+/// For `auto` worlds every system is placed in the earliest stage after the systems declared
+/// before it that it conflicts with, so conflicting systems keep their declared order.
+pub fn schedule(env: &Env) -> Vec<Vec<&str>> {
+    let world = match &env.world {
+        Some(world) => world,
+        None => return vec![],
+    };
+
+    if !is_auto(world) {
+        return world
+            .stages
+            .iter()
+            .map(|stage| stage.systems.iter().map(|r| r.id.as_str()).collect())
+            .collect();
+    }
+
+    let mut placed: Vec<(usize, &System)> = vec![];
+    let declared = world.stages.iter().flat_map(|stage| stage.systems.iter());
+    for reference in declared {
+        let system = match env.systems.get(&reference.id) {
+            Some(system) => system,
+            None => continue,
+        };
+
+        // Running the same system twice conflicts on everything it touches, so it's ordered as well
+        let stage = placed
+            .iter()
+            .filter(|(_, other)| other.id == system.id || !conflicts(other, system).is_empty())
+            .map(|(stage, _)| stage + 1)
+            .max()
+            .unwrap_or_default();
+        placed.push((stage, system));
+    }
+
+    let stage_count = placed
+        .iter()
+        .map(|(stage, _)| stage + 1)
+        .max()
+        .unwrap_or_default();
+    (0..stage_count)
+        .map(|idx| {
+            placed
+                .iter()
+                .filter(|(stage, _)| *stage == idx)
+                .map(|(_, system)| system.id.as_str())
+                .collect()
+        })
+        .collect()
+}
+
+fn is_auto(world: &World) -> bool {
+    world.attributes.contains(&Attribute::Auto)
+}
+
+/// Checks that systems only refer to existing components, each at most once, and checks their bodies.
+pub(super) fn validate_systems(env: &Env) -> Vec<Error> {
+    let mut errors = vec![];
+
+    for system in env.systems.values() {
+        let sections = [(READ_ID, &system.reads), (WRITE_ID, &system.writes)];
+        for (section, references) in sections {
+            for (idx, reference) in references.iter().enumerate() {
+                if !env.components.contains_key(&reference.id) {
+                    errors.push(Error {
+                        message: format!(
+                            "Referenced component '{}' does not exist for system '{}'",
+                            reference.id, system.id
+                        ),
+                        location: reference.start_location.clone(),
+                    });
+                }
+
+                if references[..idx].iter().any(|r| r.id == reference.id) {
+                    errors.push(Error {
+                        message: format!(
+                            "Component '{}' is listed more than once in '{}' for system '{}'",
+                            reference.id, section, system.id
+                        ),
+                        location: reference.start_location.clone(),
+                    });
+                }
+            }
+        }
+
+        for reference in system.reads.iter() {
+            if system.writes.iter().any(|w| w.id == reference.id) {
+                errors.push(Error {
+                    message: format!(
+                        "Component '{}' is both read and written by system '{}', '{}' already allows reading",
+                        reference.id, system.id, WRITE_ID
+                    ),
+                    location: reference.start_location.clone(),
+                });
+            }
+        }
+
+        // The host's code isn't checked, so anything it can see might be mutated
+        if system.system_type == SystemType::ExternalSystem {
+            for reference in system.reads.iter() {
+                errors.push(Error {
+                    message: format!(
+                        "External system '{}' can't read components as its code isn't checked, list '{}' in '{}' instead",
+                        system.id, reference.id, WRITE_ID
+                    ),
+                    location: reference.start_location.clone(),
+                });
+            }
+
+            // C is the only generated target, so the system would never be defined otherwise
+            if !system.impls.iter().any(|i| i.target == TARGET_C_IMPL_ID) {
+                errors.push(Error {
+                    message: format!(
+                        "External system '{}' must set '{}' to the file implementing it",
+                        system.id, TARGET_C_IMPL_ID
+                    ),
+                    location: system.start_location.clone(),
+                });
+            }
+        }
+
+        // Stubs are written to these paths, so they can't point outside the output directory
+        for target_impl in system.impls.iter() {
+            let path = Path::new(&target_impl.path);
+            let inside = path.components().next().is_some()
+                && path
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+            if !inside {
+                errors.push(Error {
+                    message: format!(
+                        "Path '{}' of '{}' must be relative and can't leave the output directory",
+                        target_impl.path, target_impl.target
+                    ),
+                    location: target_impl.start_location.clone(),
+                });
+            }
+        }
+
+        errors.append(&mut body::validate_body(env, system));
+    }
+
+    errors
+}
+
+/// Checks that every stage of the world refers to existing systems that can run at the same time.
+pub(super) fn validate_world(env: &Env) -> Vec<Error> {
+    let mut errors = vec![];
+    let world = match &env.world {
+        Some(world) => world,
+        None => return errors,
+    };
+
+    for attribute in world.attributes.iter() {
+        let allowed_on = match attribute {
+            Attribute::Auto => continue,
+            Attribute::Ordered => "structs and components",
+            Attribute::Soa => "components",
+        };
+        errors.push(Error {
+            message: format!(
+                "Attribute '{}' is only allowed on {}, found on world",
+                attribute, allowed_on
+            ),
+            location: world.start_location.clone(),
+        });
+    }
+
+    for stage in world.stages.iter() {
+        if stage.systems.is_empty() {
+            errors.push(Error {
+                message: "Stages must contain at least one system".to_string(),
+                location: stage.start_location.clone(),
+            });
+        }
+
+        for (idx, reference) in stage.systems.iter().enumerate() {
+            let system = match env.systems.get(&reference.id) {
+                Some(system) => system,
+                None => {
+                    errors.push(Error {
+                        message: format!(
+                            "Referenced system '{}' does not exist for world",
+                            reference.id
+                        ),
+                        location: reference.start_location.clone(),
+                    });
+                    continue;
+                }
+            };
+
+            // Auto worlds only declare an order, so systems are free to repeat and conflict
+            if is_auto(world) {
+                continue;
+            }
+
+            for previous in stage.systems[..idx].iter() {
+                if previous.id == reference.id {
+                    errors.push(Error {
+                        message: format!(
+                            "System '{}' is listed more than once in a stage",
+                            reference.id
+                        ),
+                        location: reference.start_location.clone(),
+                    });
+                    continue;
+                }
+
+                if let Some(error) = env
+                    .systems
+                    .get(&previous.id)
+                    .and_then(|other| conflict_error(other, system, reference))
+                {
+                    errors.push(error);
+                }
+            }
+        }
+    }
+
+    errors
+}
+
+fn conflict_error(a: &System, b: &System, reference: &Reference) -> Option<Error> {
+    let components = conflicts(a, b);
+    if components.is_empty() {
+        return None;
+    }
+
+    let components: Vec<String> = components.iter().map(|c| format!("'{}'", c)).collect();
+    Some(Error {
+        message: format!(
+            "Systems '{}' and '{}' can't run in the same stage as they conflict on {}",
+            a.id,
+            b.id,
+            components.join(", ")
+        ),
+        location: reference.start_location.clone(),
+    })
+}
@@ -215,3 +215,1169 @@ fn struct_struct_property_id_duplicate_returns_err() {
 
     assert_eq!(expected, result);
 }
+
+#[test]
+fn self_referencing_struct_returns_err() {
+    let input = "struct A { i32 x A a }";
+    let result = build(input);
+    let expected = Err(vec![Error {
+        message: "Struct 'A' contains itself by value: A.a (0:17) -> A".to_string(),
+        location: (0, 17).into(),
+    }]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn struct_cycle_returns_err_with_path() {
+    let input = "struct B { C c }\nstruct A { B b }\nstruct C([A 4]);";
+    let result = build(input);
+    let expected = Err(vec![Error {
+        message:
+            "Struct 'A' contains itself by value: A.b (1:11) -> B.c (0:11) -> C.value (2:9) -> A"
+                .to_string(),
+        location: (1, 11).into(),
+    }]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn shared_struct_without_cycle_returns_ok() {
+    let input = "struct Vec2 { i32 x i32 y }\nstruct Aabb { Vec2 min Vec2 max }\ncomponent Bounds { Aabb box Vec2 center }";
+    assert!(build(input).is_ok());
+}
+
+#[test]
+fn entity_component_returns_err() {
+    let input = "component Entity(u32);";
+    let result = build(input);
+    let expected = Err(vec![Error {
+        message: "Component id 'Entity' is reserved for native struct".to_string(),
+        location: (0, 0).into(),
+    }]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn soa_component_store_has_array_per_field() {
+    let env =
+        build("struct Vec2 { f32 x f32 y }\ncomponent Motion soa { Vec2 velocity bool grounded }")
+            .unwrap();
+    let store = env.structs.get(&store_id("Motion")).unwrap().strukt();
+
+    let properties = match &store.properties {
+        StructProperties::Multiple(properties) => properties,
+        properties => panic!("Expected multiple properties, got {:?}", properties),
+    };
+    let ids: Vec<&str> = properties.iter().map(|p| p.identifier.as_str()).collect();
+    assert_eq!(
+        vec![
+            "active_components",
+            "components_velocity",
+            "components_grounded",
+            "entities",
+            "indices",
+            "changed"
+        ],
+        ids
+    );
+    assert_eq!(
+        (&PrimitiveType::Identifier("Vec2".to_string()), 1024),
+        match &properties[1].ty.ty {
+            ListType::List { ty, max_size } => (&ty.primitive_type, *max_size),
+            ty => panic!("Expected list, got {:?}", ty),
+        }
+    );
+}
+
+#[test]
+fn soa_component_list_property_returns_err() {
+    let input = "component Names soa {\n    [char 10] name\n}";
+    let result = build(input);
+    let expected = Err(vec![Error {
+        message: "List property 'name' can't be stored in 'soa' component 'Names'".to_string(),
+        location: (1, 4).into(),
+    }]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn soa_struct_returns_err() {
+    let input = "struct Vec2 soa { f32 x f32 y }";
+    let result = build(input);
+    let expected = Err(vec![Error {
+        message: "Attribute 'soa' is only allowed on components, found on struct 'Vec2'"
+            .to_string(),
+        location: (0, 0).into(),
+    }]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn system_unknown_component_returns_err() {
+    let input = "component Position;\nsystem Move {\n    read [Velocity]\n    write [Position]\n}";
+    let result = build(input);
+    let expected = Err(vec![Error {
+        message: "Referenced component 'Velocity' does not exist for system 'Move'".to_string(),
+        location: (2, 10).into(),
+    }]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn system_read_and_write_same_component_returns_err() {
+    let input = "component Position;\nsystem Move { read [Position] write [Position Position] }";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Component 'Position' is both read and written by system 'Move', 'write' already allows reading".to_string(),
+            location: (1, 20).into(),
+        },
+        Error {
+            message: "Component 'Position' is listed more than once in 'write' for system 'Move'"
+                .to_string(),
+            location: (1, 46).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn multiple_worlds_returns_err() {
+    let input = "world {}\nworld {}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Only one world may exist".to_string(),
+            location: (0, 0).into(),
+        },
+        Error {
+            message: "Only one world may exist".to_string(),
+            location: (1, 0).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn world_conflicting_stage_returns_err() {
+    let input = "component Position;
+system Move { write [Position] }
+system Render { read [Position] }
+system Idle {}
+world {
+    [Move Idle Render]
+    [Render Idle]
+    [Missing]
+    []
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Systems 'Move' and 'Render' can't run in the same stage as they conflict on 'Position'".to_string(),
+            location: (5, 15).into(),
+        },
+        Error {
+            message: "Referenced system 'Missing' does not exist for world".to_string(),
+            location: (7, 5).into(),
+        },
+        Error {
+            message: "Stages must contain at least one system".to_string(),
+            location: (8, 4).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn world_with_stages_builds() {
+    let input = "component Position;
+system Move { write [Position] }
+system Render { read [Position] }
+world {
+    [Move]
+    [Render]
+}";
+    let env = build(input).unwrap();
+
+    assert_eq!(2, env.systems.len());
+    assert_eq!(2, env.world.unwrap().stages.len());
+}
+
+#[test]
+fn auto_world_schedules_by_conflicts() {
+    let input = "component Position;
+component Velocity;
+component Sprite;
+system Gravity { write [Velocity] }
+system Move { read [Velocity] write [Position] }
+system Animate { write [Sprite] }
+system Render { read [Position Sprite] }
+world auto {
+    [Gravity Move Animate Render Gravity]
+}";
+    let env = build(input).unwrap();
+
+    assert_eq!(
+        vec![
+            vec!["Gravity", "Animate"],
+            vec!["Move"],
+            vec!["Render", "Gravity"]
+        ],
+        schedule(&env)
+    );
+}
+
+#[test]
+fn auto_world_orders_systems_that_create() {
+    let input = "component Position;
+component Bullet;
+system Spawn {
+    write [Position]
+    create [Position]
+}
+system Shoot {
+    write [Bullet]
+    for e with Bullet {
+        if true {
+            create [Bullet]
+        }
+    }
+}
+system Render { read [Position] }
+world auto {
+    [Spawn Shoot Render]
+}";
+    let env = build(input).unwrap();
+
+    assert_eq!(vec![vec!["Spawn"], vec!["Shoot", "Render"]], schedule(&env));
+}
+
+#[test]
+fn systems_that_create_in_one_stage_return_err() {
+    let input = "component Position;
+component Bullet;
+system Spawn {
+    write [Position]
+    create [Position]
+}
+system Shoot {
+    write [Bullet]
+    create [Bullet]
+}
+world {
+    [Spawn Shoot]
+}";
+    let result = build(input);
+    let expected = Err(vec![Error {
+        message: "Systems 'Spawn' and 'Shoot' can't run in the same stage as they conflict on 'next_entity'".to_string(),
+        location: (11, 11).into(),
+    }]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn auto_attribute_outside_world_returns_err() {
+    let input = "struct A auto;\ncomponent B auto;\nworld soa {}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Attribute 'auto' is only allowed on the world, found on struct 'A'"
+                .to_string(),
+            location: (0, 0).into(),
+        },
+        Error {
+            message: "Attribute 'auto' is only allowed on the world, found on component 'B'"
+                .to_string(),
+            location: (1, 0).into(),
+        },
+        Error {
+            message: "Attribute 'soa' is only allowed on components, found on world".to_string(),
+            location: (2, 0).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn system_body_with_optional_components_builds() {
+    let input = "component Position { f32 x f32 y }\ncomponent SpeedModifier(f32);
+system Move {
+    read [SpeedModifier]
+    write [Position]
+
+    for e with Position, SpeedModifier? {
+        speed = 1 + (e.SpeedModifier ?? 0)
+        if e has SpeedModifier && e.SpeedModifier > 2.5 {
+            speed *= e.SpeedModifier
+        }
+        e.Position.x += speed
+    }
+}";
+    assert!(build(input).is_ok());
+}
+
+#[test]
+fn fractional_literals_declare_float_locals() {
+    let input = "component Speed(f32);
+system Move {
+    write [Speed]
+    for e with Speed {
+        speed = 2.0
+        speed *= 0.5
+        e.Speed = speed
+    }
+}";
+    assert!(build(input).is_ok());
+}
+
+#[test]
+fn literals_that_dont_fit_return_err() {
+    let input = "component Big { u64 id u32 count f32 speed f64 far }
+event Hit(i32);
+system Move {
+    write [Big Hit]
+    for e with Big {
+        e.Big.id = 18446744073709551615
+        e.Big.id = 18446744073709551616
+        e.Big.count = -1
+        e.Big.count += 4294967296
+        e.Big.speed = 1e300
+        e.Big.far = 1e300
+        small = -2147483648
+        large = 2147483648
+        if e.Big.count < 5000000000 {}
+        emit Hit(3000000000)
+    }
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Number '18446744073709551616' doesn't fit in 'u64'".to_string(),
+            location: (6, 19).into(),
+        },
+        Error {
+            message: "Number '-1' doesn't fit in 'u32'".to_string(),
+            location: (7, 22).into(),
+        },
+        Error {
+            message: "Number '4294967296' doesn't fit in 'u32'".to_string(),
+            location: (8, 23).into(),
+        },
+        Error {
+            message: "Number '1e300' doesn't fit in 'f32'".to_string(),
+            location: (9, 22).into(),
+        },
+        Error {
+            message: "Number '2147483648' doesn't fit in 'i32'".to_string(),
+            location: (12, 16).into(),
+        },
+        Error {
+            message: "Number '5000000000' doesn't fit in 'u32'".to_string(),
+            location: (13, 25).into(),
+        },
+        Error {
+            message: "Number '3000000000' doesn't fit in 'i32'".to_string(),
+            location: (14, 17).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn modulo_of_literals_used_as_floats_returns_err() {
+    let input = "component M { f32 f i32 n }
+system Wrap {
+    write [M]
+    for e with M {
+        e.M.f = 3 % 2
+        e.M.f += (7 % 4) * 2
+        half = 1.5 % 2
+        e.M.n = 7 % 4
+    }
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Operator '%' requires integers, found 'f32'".to_string(),
+            location: (4, 16).into(),
+        },
+        Error {
+            message: "Operator '%' requires integers, found 'f32'".to_string(),
+            location: (5, 18).into(),
+        },
+        Error {
+            message: "Operator '%' requires integers, found 'f32'".to_string(),
+            location: (6, 15).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn constant_arithmetic_that_doesnt_fit_returns_err() {
+    let input = "component M { i32 n u32 u i64 big f32 f }
+system Wrap {
+    write [M]
+    for e with M {
+        e.M.n = 2147483647 + 1
+        e.M.n = 7 / 0
+        e.M.n /= 0
+        e.M.u = 1 - 2
+        e.M.big = 9223372036854775807 * 9223372036854775807
+        x = (2147483647 + 1) - 1
+        e.M.n = -2147483647 - 1
+        e.M.big = -9223372036854775808
+        e.M.f = 7 / 0
+    }
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "'2147483647 + 1' is 2147483648, which doesn't fit in 'i32'".to_string(),
+            location: (4, 16).into(),
+        },
+        Error {
+            message: "Operator '/' divides by zero".to_string(),
+            location: (5, 16).into(),
+        },
+        Error {
+            message: "Operator '/=' divides by zero".to_string(),
+            location: (6, 17).into(),
+        },
+        Error {
+            message: "'1 - 2' is -1, which doesn't fit in 'u32'".to_string(),
+            location: (7, 16).into(),
+        },
+        Error {
+            message: "'9223372036854775807 * 9223372036854775807' is 85070591730234615847396907784232501249, which doesn't fit in 'i64'".to_string(),
+            location: (8, 18).into(),
+        },
+        Error {
+            message: "'2147483647 + 1' is 2147483648, which doesn't fit in 'i32'".to_string(),
+            location: (9, 13).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn unguarded_optional_component_returns_err() {
+    let input = "component Position { f32 x }\ncomponent SpeedModifier(f32);
+system Move {
+    read [SpeedModifier]
+    write [Position]
+
+    for e with Position, SpeedModifier? {
+        e.Position.x += e.SpeedModifier
+        e.Position.x = e.Position.x ?? 1
+    }
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Optional component 'SpeedModifier' must be checked with 'e has SpeedModifier' or given a default with '??'".to_string(),
+            location: (7, 24).into(),
+        },
+        Error {
+            message: "The left side of '??' must access an optional component".to_string(),
+            location: (8, 23).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn system_body_type_errors_return_err() {
+    let input = "component Position { f32 x }\ncomponent Hp(i32);
+system Heal {
+    read [Position]
+    write [Hp]
+
+    for e with Hp, Position, Hp {
+        e.Position.x = 1
+        e.Hp += e.Position.x
+        if e.Hp { e.Hp = true }
+        e.Hp.value = 2
+        count += 1
+    }
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Component 'Hp' is listed more than once in the query".to_string(),
+            location: (6, 29).into(),
+        },
+        Error {
+            message: "Component 'Position' is read only for system 'Heal'".to_string(),
+            location: (7, 8).into(),
+        },
+        Error {
+            message: "Mismatched types 'i32' and 'f32' for '+='".to_string(),
+            location: (8, 16).into(),
+        },
+        Error {
+            message: "Conditions must be 'bool', found 'i32'".to_string(),
+            location: (9, 11).into(),
+        },
+        Error {
+            message: "Mismatched types 'i32' and 'bool' for '='".to_string(),
+            location: (9, 25).into(),
+        },
+        Error {
+            message: "Type 'i32' has no field 'value'".to_string(),
+            location: (10, 8).into(),
+        },
+        Error {
+            message: "Unknown identifier 'count'".to_string(),
+            location: (11, 8).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn query_components_must_be_declared() {
+    let input = "component Position;\ncomponent Frozen;\nsystem Move {\n    read [Position]\n    for e with Frozen? {}\n}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Queries need at least one component that isn't optional".to_string(),
+            location: (4, 4).into(),
+        },
+        Error {
+            message: "Component 'Frozen' must be listed in 'read' or 'write' for system 'Move' to be queried".to_string(),
+            location: (4, 15).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn query_filters_return_err() {
+    let input = "component Position;\ncomponent IsDead;\ncomponent Hp(i32);
+system Render {
+    read [Position Hp]
+    for e with Position, Hp? without IsDead, Position changed Hp, Velocity, Hp {}
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Component 'IsDead' must be listed in 'read' or 'write' for system 'Render' to be queried".to_string(),
+            location: (5, 37).into(),
+        },
+        Error {
+            message: "Component 'Position' can't be both matched and excluded by the query"
+                .to_string(),
+            location: (5, 45).into(),
+        },
+        Error {
+            message: "Component 'Hp' must be a required component of the query to check for changes".to_string(),
+            location: (5, 62).into(),
+        },
+        Error {
+            message: "Component 'Velocity' must be a required component of the query to check for changes".to_string(),
+            location: (5, 66).into(),
+        },
+        Error {
+            message: "Component 'Hp' is listed more than once in 'changed'".to_string(),
+            location: (5, 76).into(),
+        },
+        Error {
+            message: "Component 'Hp' must be a required component of the query to check for changes".to_string(),
+            location: (5, 76).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn single_component_access_builds() {
+    let input =
+        "component Position { f32 x }\nsingle_component WorldState { u64 frame f32 gravity }
+system Fall {
+    read [WorldState]
+    write [Position]
+    state = single WorldState
+    for e with Position {
+        e.Position.x += state.gravity
+    }
+}
+system Tick {
+    write [WorldState]
+    state = single WorldState
+    state.frame += 1
+}";
+    assert!(build(input).is_ok());
+}
+
+#[test]
+fn single_component_misuse_returns_err() {
+    let input = "component Hp(i32);\nsingle_component WorldState { u64 frame }\nsingle_component Paused soa;
+system Tick {
+    read [Hp WorldState]
+    state = single WorldState
+    state.frame += 1
+    hp = single Hp
+    for e with Hp without WorldState {}
+    frame = single Paused
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Attribute 'soa' is only allowed on components, found on single component 'Paused'".to_string(),
+            location: (2, 0).into(),
+        },
+        Error {
+            message: "Component 'WorldState' is read only for system 'Tick'".to_string(),
+            location: (6, 4).into(),
+        },
+        Error {
+            message: "Component 'Hp' isn't a single component and can't be accessed with 'single'"
+                .to_string(),
+            location: (7, 9).into(),
+        },
+        Error {
+            message: "Single component 'WorldState' isn't attached to entities, use 'single WorldState' instead".to_string(),
+            location: (8, 26).into(),
+        },
+        Error {
+            message: "Component 'Paused' must be listed in 'read' or 'write' for system 'Tick' to be accessed".to_string(),
+            location: (9, 12).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn create_builds() {
+    let input = "component Position { f32 x f32 y }\ncomponent Hp(i32);\ncomponent IsAlive;
+system Spawn {
+    read [Hp]
+    write [Position IsAlive]
+    for e with Hp {
+        if e.Hp > 100 {
+            create [Position(0.5, 2) IsAlive]
+        }
+    }
+}";
+    assert!(build(input).is_ok());
+}
+
+#[test]
+fn create_misuse_returns_err() {
+    let input = "component Position { f32 x f32 y }\ncomponent Hp(i32);\nsingle_component WorldState { u64 frame }\nevent Hit;
+system Spawn {
+    read [Hp]
+    write [Position WorldState Hit]
+    for e with Hp {
+        create [WorldState(1) Hit Hp(1) Missing]
+        create [Position(1) Position(1, true)]
+    }
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Single component 'WorldState' has one instance created with the world and can't be added to entities".to_string(),
+            location: (8, 16).into(),
+        },
+        Error {
+            message: "Event 'Hit' isn't attached to entities, use 'emit Hit' instead".to_string(),
+            location: (8, 30).into(),
+        },
+        Error {
+            message: "Component 'Hp' must be listed in 'write' for system 'Spawn' to create it"
+                .to_string(),
+            location: (8, 34).into(),
+        },
+        Error {
+            message: "Component 'Missing' does not exist".to_string(),
+            location: (8, 40).into(),
+        },
+        Error {
+            message: "Component 'Position' takes 2 values, found 1".to_string(),
+            location: (9, 16).into(),
+        },
+        Error {
+            message: "Component 'Position' is created more than once".to_string(),
+            location: (9, 28).into(),
+        },
+        Error {
+            message: "Mismatched types 'f32' and 'bool' for 'create'".to_string(),
+            location: (9, 40).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn external_system_reads_return_err() {
+    let input = "component Sprite(u32);\ncomponent Position { f32 x }
+external_system Render {
+    read [Position]
+    write [Sprite]
+    target_c_impl \"graphics.c\"
+}";
+    let result = build(input);
+    let expected = Err(vec![Error {
+        message: "External system 'Render' can't read components as its code isn't checked, list 'Position' in 'write' instead".to_string(),
+        location: (3, 10).into(),
+    }]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn external_system_without_c_impl_returns_err() {
+    let input = "component Sprite(u32);\nexternal_system Render {\n    write [Sprite]\n}";
+    let result = build(input);
+    let expected = Err(vec![Error {
+        message: "External system 'Render' must set 'target_c_impl' to the file implementing it"
+            .to_string(),
+        location: (1, 0).into(),
+    }]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn external_system_paths_outside_output_return_err() {
+    let input = "external_system A {\n    target_c_impl \"/tmp/a.c\"\n}
+external_system B {\n    target_c_impl \"../b.c\"\n}
+external_system C {\n    target_c_impl \"host/c.c\"\n    target_js_impl \"host/../../c.js\"\n}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Path '/tmp/a.c' of 'target_c_impl' must be relative and can't leave the output directory"
+                .to_string(),
+            location: (1, 4).into(),
+        },
+        Error {
+            message: "Path '../b.c' of 'target_c_impl' must be relative and can't leave the output directory"
+                .to_string(),
+            location: (4, 4).into(),
+        },
+        Error {
+            message: "Path 'host/../../c.js' of 'target_js_impl' must be relative and can't leave the output directory"
+                .to_string(),
+            location: (8, 4).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn events_build() {
+    let input = "component Hp(i32);\nevent Collision { Entity a Entity b }\nevent Reset;
+system Collide {
+    read [Hp]
+    write [Collision Reset]
+    for e with Hp {
+        for o with Hp {
+            emit Collision(e, o)
+        }
+    }
+    emit Reset
+}
+system Hurt {
+    read [Collision]
+    write [Hp]
+    for ev in events Collision {
+        for e with Hp {
+            if e == ev.a {
+                e.Hp -= 1
+            }
+        }
+    }
+}";
+    assert!(build(input).is_ok());
+}
+
+#[test]
+fn event_misuse_returns_err() {
+    let input = "component Hp(i32);\nevent Damage(i32);\nevent Tick soa;
+system Hurt {
+    read [Hp Damage]
+    emit Damage(1)
+    for ev in events Hp {}
+    for e with Damage {}
+    for ev in events Damage {
+        ev = 2
+    }
+}
+system Heal {
+    write [Damage]
+    emit Damage(1, 2)
+    emit Damage(true)
+    for ev in events Tick {}
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Attribute 'soa' is only allowed on components, found on event 'Tick'"
+                .to_string(),
+            location: (2, 0).into(),
+        },
+        Error {
+            message: "Event 'Damage' must be listed in 'write' for system 'Hurt' to emit it"
+                .to_string(),
+            location: (5, 9).into(),
+        },
+        Error {
+            message: "Component 'Hp' isn't an event".to_string(),
+            location: (6, 21).into(),
+        },
+        Error {
+            message:
+                "Event 'Damage' isn't attached to entities, use 'for ev in events Damage' instead"
+                    .to_string(),
+            location: (7, 15).into(),
+        },
+        Error {
+            message: "Only locals and component fields can be assigned to".to_string(),
+            location: (9, 8).into(),
+        },
+        Error {
+            message: "Event 'Damage' takes 1 values, found 2".to_string(),
+            location: (14, 9).into(),
+        },
+        Error {
+            message: "Mismatched types 'i32' and 'bool' for 'emit'".to_string(),
+            location: (15, 16).into(),
+        },
+        Error {
+            message:
+                "Event 'Tick' must be listed in 'read' or 'write' for system 'Heal' to read it"
+                    .to_string(),
+            location: (16, 21).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn enums_build() {
+    let input = "enum Status { Ok, Damaged(i32), Dead }\nenum Mood { Calm, Angry }
+component Health { Status status Mood mood }
+system Heal {
+    write [Health]
+    for e with Health {
+        match e.Health.status {
+            Damaged(amount) {
+                if amount > 1 {
+                    e.Health.status = Status.Damaged(amount - 1)
+                } else {
+                    e.Health.status = Status.Ok
+                }
+            }
+            else {}
+        }
+        if e.Health.mood == Mood.Angry {
+            e.Health.mood = Mood.Calm
+        }
+    }
+}";
+    assert!(build(input).is_ok());
+}
+
+#[test]
+fn enum_definition_errors_return_err() {
+    let input = "enum Empty {}\nenum Twice { A, A }\nenum Loop { Next(Loop) }\nenum Missing { A(Gone) }\nstruct Twice;";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Enum 'Empty' must have at least one variant".to_string(),
+            location: (0, 0).into(),
+        },
+        Error {
+            message: "Enum 'Twice' has the same id as a struct".to_string(),
+            location: (1, 0).into(),
+        },
+        Error {
+            message: "Duplicate variant identifier 'A' for enum 'Twice'".to_string(),
+            location: (1, 16).into(),
+        },
+        Error {
+            message: "Enum 'Loop' contains itself by value: Loop.Next (2:12) -> Loop".to_string(),
+            location: (2, 12).into(),
+        },
+        Error {
+            message:
+                "Referenced struct type 'Gone' does not exist for variant 'A' for enum 'Missing'"
+                    .to_string(),
+            location: (3, 15).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn enum_misuse_returns_err() {
+    let input = "enum Status { Ok, Damaged(i32), Dead }\ncomponent Health(Status);
+system Heal {
+    write [Health]
+    for e with Health {
+        match e.Health {
+            Ok(x) {}
+            Damaged(e) {}
+            Damaged {}
+            Burning {}
+        }
+        match e.Health {
+            Ok {}
+            Damaged {}
+            Dead {}
+            else {}
+        }
+        match 1 {}
+        e.Health = Status.Damaged
+        e.Health = Status.Ok(1)
+        e.Health = Status.Damaged(true)
+        e.Health = Status.Gone
+        if e.Health == Status.Ok {}
+    }
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "'match' on 'Status' must handle every variant or use 'else', missing: Dead"
+                .to_string(),
+            location: (5, 8).into(),
+        },
+        Error {
+            message: "Variant 'Ok' of enum 'Status' holds no value to bind".to_string(),
+            location: (6, 15).into(),
+        },
+        Error {
+            message: "'e' is already defined".to_string(),
+            location: (7, 20).into(),
+        },
+        Error {
+            message: "Variant 'Damaged' is matched more than once".to_string(),
+            location: (8, 12).into(),
+        },
+        Error {
+            message: "Enum 'Status' has no variant 'Burning'".to_string(),
+            location: (9, 12).into(),
+        },
+        Error {
+            message: "'else' is unreachable as every variant of 'Status' is matched".to_string(),
+            location: (15, 12).into(),
+        },
+        Error {
+            message: "'match' requires an enum, found 'integer'".to_string(),
+            location: (17, 14).into(),
+        },
+        Error {
+            message: "Variant 'Damaged' of enum 'Status' holds a value, use 'Status.Damaged(..)'"
+                .to_string(),
+            location: (18, 19).into(),
+        },
+        Error {
+            message: "Variant 'Ok' of enum 'Status' holds no value".to_string(),
+            location: (19, 19).into(),
+        },
+        Error {
+            message: "Mismatched types 'i32' and 'bool' for 'Status.Damaged'".to_string(),
+            location: (20, 34).into(),
+        },
+        Error {
+            message: "Enum 'Status' has no variant 'Gone'".to_string(),
+            location: (21, 19).into(),
+        },
+        Error {
+            message: "Operator '==' can't compare 'Status'".to_string(),
+            location: (22, 11).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn assigning_to_match_bindings_returns_err() {
+    let input = "struct Push { f32 x f32 y }\nenum Status { Damaged(i32), Pushed(Push) }\ncomponent Health(Status);
+system Heal {
+    write [Health]
+    for e with Health {
+        match e.Health {
+            Damaged(amount) {
+                amount = 3
+            }
+            Pushed(by) {
+                by.x = 1.0
+            }
+        }
+    }
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "'amount' is a read only copy and can't be assigned to".to_string(),
+            location: (8, 16).into(),
+        },
+        Error {
+            message: "'by' is a read only copy and can't be assigned to".to_string(),
+            location: (11, 16).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn lists_build() {
+    let input = "component Collisions([Entity 4]);\ncomponent Scores { [i32 8] values i32 total }
+system Collide {
+    write [Collisions Scores]
+    for e with Collisions, Scores {
+        e.Collisions.clear()
+        for other with Scores {
+            if e.Collisions.contains(other) == false {
+                e.Collisions.push(other)
+            }
+        }
+        for v in e.Scores.values {
+            e.Scores.total += v
+        }
+        last = e.Scores.values.pop()
+        e.Scores.values.remove_at(e.Scores.values.len() - 1)
+    }
+}";
+    assert!(build(input).is_ok());
+}
+
+#[test]
+fn list_misuse_returns_err() {
+    let input =
+        "struct Point { i32 x }\ncomponent Scores { [i32 8] values [Point 2] points i32 total }
+event Hit;
+system Count {
+    read [Hit]
+    write [Scores]
+    for e with Scores {
+        for v in e.Scores.total {}
+        for h in Hit {}
+        e.Scores.values.len()
+        e.Scores.total = e.Scores.values.pop() + 1
+        e.Scores.total.len()
+        e.Scores.values.push(true)
+        e.Scores.values.remove_at()
+        e.Scores.values.sort()
+        if e.Scores.points.contains(e.Scores.points.pop()) {}
+        point = e.Scores.points.pop()
+        if e.Scores.points.contains(point) {}
+    }
+}
+system Peek {
+    read [Scores]
+    for e with Scores {
+        e.Scores.values.clear()
+    }
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "Only lists can be iterated, found 'i32'".to_string(),
+            location: (7, 17).into(),
+        },
+        Error {
+            message: "Events are read with 'for h in events Hit'".to_string(),
+            location: (8, 17).into(),
+        },
+        Error {
+            message: "Only methods that change a list can be statements, e.g. 'push'".to_string(),
+            location: (9, 8).into(),
+        },
+        Error {
+            message: "Method 'pop' changes the list and can only be a statement or the value of an assignment".to_string(),
+            location: (10, 25).into(),
+        },
+        Error {
+            message: "Type 'i32' has no method 'len'".to_string(),
+            location: (11, 8).into(),
+        },
+        Error {
+            message: "Mismatched types 'i32' and 'bool' for 'push'".to_string(),
+            location: (12, 29).into(),
+        },
+        Error {
+            message: "Method 'remove_at' takes 1 values, found 0".to_string(),
+            location: (13, 8).into(),
+        },
+        Error {
+            message: "Type '[i32 8]' has no method 'sort'".to_string(),
+            location: (14, 8).into(),
+        },
+        Error {
+            message: "Method 'pop' changes the list and can only be a statement or the value of an assignment".to_string(),
+            location: (15, 36).into(),
+        },
+        Error {
+            message: "Method 'contains' can't compare 'Point'".to_string(),
+            location: (17, 11).into(),
+        },
+        Error {
+            message: "Component 'Scores' is read only for system 'Peek'".to_string(),
+            location: (23, 8).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn assigning_to_list_items_returns_err() {
+    let input = "struct Point { i32 x }\ncomponent Scores { [i32 8] values [Point 2] points }
+system Count {
+    write [Scores]
+    for e with Scores {
+        for v in e.Scores.values {
+            v = 3
+            v += 1
+        }
+        for p in e.Scores.points {
+            p.x = 1
+        }
+    }
+}";
+    let result = build(input);
+    let expected = Err(vec![
+        Error {
+            message: "'v' is a read only copy and can't be assigned to".to_string(),
+            location: (6, 12).into(),
+        },
+        Error {
+            message: "'v' is a read only copy and can't be assigned to".to_string(),
+            location: (7, 12).into(),
+        },
+        Error {
+            message: "'p' is a read only copy and can't be assigned to".to_string(),
+            location: (10, 12).into(),
+        },
+    ]);
+
+    assert_eq!(expected, result);
+}
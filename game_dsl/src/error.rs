@@ -1,5 +1,3 @@
-use std::cmp::Ordering;
-
 use crate::location::Location;
 
 #[derive(Clone, PartialEq, Debug)]
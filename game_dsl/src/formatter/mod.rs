@@ -0,0 +1,745 @@
+use crate::{
+    location::Location,
+    parser::{
+        Ast, Attribute, Comment, Component, ComponentProperties, ComponentType, Enum, Expr,
+        ExprValue, Listable, MatchArm, Primitive, Reference, Statement, StatementValue, Struct,
+        StructProperties, System, SystemType, World, CHANGED_ID, COMPONENT_ID, CREATE_ID, ELSE_ID,
+        EMIT_ID, ENUM_ID, EVENTS_ID, EVENT_ID, EXTERNAL_SYSTEM_ID, FOR_ID, HAS_ID, IF_ID, IN_ID,
+        MATCH_ID, READ_ID, SINGLE_COMPONENT_ID, SINGLE_ID, STRUCT_ID, SYSTEM_ID, WITHOUT_ID,
+        WITH_ID, WORLD_ID, WRITE_ID,
+    },
+};
+
+const INDENT: &str = "    ";
+
+/// Pretty prints the ASTs into canonical source code.
+/// Declarations keep their grouping from the original source, with runs of blank lines collapsed into one.
+pub fn format(asts: &[Ast]) -> String {
+    let mut output = String::new();
+    let mut previous: Option<&Ast> = None;
+
+    for ast in asts.iter() {
+        if let Some(previous) = previous {
+            let (_, previous_end) = locations(previous);
+            let (start, _) = locations(ast);
+
+            if let Ast::Comment(comment) = ast {
+                // Trailing comments stay on the line they were written on
+                let is_trailing = !matches!(previous, Ast::Comment(_))
+                    && comment.lines.len() == 1
+                    && start.line() == previous_end.line();
+
+                if is_trailing {
+                    output.push(' ');
+                    output.push_str(&comment_line(&comment.lines[0]));
+                    continue;
+                }
+            }
+
+            output.push('\n');
+            if start.line() > previous_end.line() + 1 {
+                output.push('\n');
+            }
+        }
+
+        match ast {
+            Ast::Comment(comment) => format_comment(comment, &mut output),
+            Ast::Component(component) => format_component(component, &mut output),
+            Ast::Struct(strukt) => format_struct(strukt, &mut output),
+            Ast::Enum(enumeration) => format_enum(enumeration, &mut output),
+            Ast::System(system) => format_system(system, &mut output),
+            Ast::World(world) => format_world(world, &mut output),
+        }
+
+        previous = Some(ast);
+    }
+
+    if !output.is_empty() {
+        output.push('\n');
+    }
+
+    output
+}
+
+fn locations(ast: &Ast) -> (&Location, &Location) {
+    match ast {
+        Ast::Comment(c) => (&c.start_location, &c.end_location),
+        Ast::Component(c) => (&c.start_location, &c.end_location),
+        Ast::Struct(s) => (&s.start_location, &s.end_location),
+        Ast::Enum(e) => (&e.start_location, &e.end_location),
+        Ast::System(s) => (&s.start_location, &s.end_location),
+        Ast::World(w) => (&w.start_location, &w.end_location),
+    }
+}
+
+fn format_comment(comment: &Comment, output: &mut String) {
+    let lines: Vec<String> = comment.lines.iter().map(|l| comment_line(l)).collect();
+    output.push_str(&lines.join("\n"));
+}
+
+/// Comment text is trimmed by the lexer, so a single space is used after the `#`.
+fn comment_line(line: &str) -> String {
+    if line.is_empty() {
+        "#".to_string()
+    } else {
+        format!("# {}", line)
+    }
+}
+
+fn format_component(component: &Component, output: &mut String) {
+    let keyword = match component.component_type {
+        ComponentType::Component => COMPONENT_ID,
+        ComponentType::SingleComponent => SINGLE_COMPONENT_ID,
+        ComponentType::Event => EVENT_ID,
+    };
+
+    let fields = match &component.properties {
+        ComponentProperties::None => Fields::None,
+        ComponentProperties::Value(value) => Fields::Value(value),
+        ComponentProperties::Multiple(properties) => Fields::Multiple(
+            properties
+                .iter()
+                .map(|p| Field {
+                    ty: &p.ty,
+                    identifier: &p.identifier,
+                    comments: &p.comments,
+                    trailing_comment: p.trailing_comment.as_deref(),
+                })
+                .collect(),
+        ),
+    };
+
+    let name = declaration_name(&component.id, &component.attributes);
+    format_declaration(
+        keyword,
+        &name,
+        &component.comments,
+        &component.trailing_comments,
+        fields,
+        output,
+    );
+}
+
+fn format_struct(strukt: &Struct, output: &mut String) {
+    let fields = match &strukt.properties {
+        StructProperties::None => Fields::None,
+        StructProperties::Value(value) => Fields::Value(value),
+        StructProperties::Multiple(properties) => Fields::Multiple(
+            properties
+                .iter()
+                .map(|p| Field {
+                    ty: &p.ty,
+                    identifier: &p.identifier,
+                    comments: &p.comments,
+                    trailing_comment: p.trailing_comment.as_deref(),
+                })
+                .collect(),
+        ),
+    };
+
+    let name = declaration_name(&strukt.id, &strukt.attributes);
+    format_declaration(
+        STRUCT_ID,
+        &name,
+        &strukt.comments,
+        &strukt.trailing_comments,
+        fields,
+        output,
+    );
+}
+
+/// Every variant is on its own line followed by a comma.
+fn format_enum(enumeration: &Enum, output: &mut String) {
+    for comment in enumeration.comments.iter() {
+        output.push_str(&comment_line(comment));
+        output.push('\n');
+    }
+
+    if enumeration.variants.is_empty() {
+        output.push_str(&format!("{} {} {{}}", ENUM_ID, enumeration.id));
+        return;
+    }
+
+    output.push_str(&format!("{} {} {{\n", ENUM_ID, enumeration.id));
+    for variant in enumeration.variants.iter() {
+        for comment in variant.comments.iter() {
+            output.push_str(&format!("{}{}\n", INDENT, comment_line(comment)));
+        }
+        match &variant.payload {
+            Some(payload) => output.push_str(&format!("{}{}({}),", INDENT, variant.id, payload.ty)),
+            None => output.push_str(&format!("{}{},", INDENT, variant.id)),
+        }
+        push_trailing_comment(variant.trailing_comment.as_deref(), output);
+        output.push('\n');
+    }
+    output.push('}');
+}
+
+fn format_system(system: &System, output: &mut String) {
+    for comment in system.comments.iter() {
+        output.push_str(&comment_line(comment));
+        output.push('\n');
+    }
+
+    let sections: Vec<(&str, &[Reference], &[String])> = [
+        (READ_ID, &system.reads, &system.read_comments),
+        (WRITE_ID, &system.writes, &system.write_comments),
+    ]
+    .into_iter()
+    .filter(|(_, references, _)| !references.is_empty())
+    .map(|(section, references, comments)| (section, references.as_slice(), comments.as_slice()))
+    .collect();
+
+    let keyword = match system.system_type {
+        SystemType::System => SYSTEM_ID,
+        SystemType::ExternalSystem => EXTERNAL_SYSTEM_ID,
+    };
+
+    if sections.is_empty() && system.body.is_empty() && system.impls.is_empty() {
+        output.push_str(&format!("{} {} {{}}", keyword, system.id));
+        return;
+    }
+
+    output.push_str(&format!("{} {} {{\n", keyword, system.id));
+    for (section, references, comments) in sections.iter() {
+        for comment in comments.iter() {
+            output.push_str(&format!("{}{}\n", INDENT, comment_line(comment)));
+        }
+        output.push_str(&format!(
+            "{}{} {}\n",
+            INDENT,
+            section,
+            reference_list(references)
+        ));
+    }
+
+    // Target implementations are set apart from the components
+    if !sections.is_empty() && !system.impls.is_empty() {
+        output.push('\n');
+    }
+    for target_impl in system.impls.iter() {
+        for comment in target_impl.comments.iter() {
+            output.push_str(&format!("{}{}\n", INDENT, comment_line(comment)));
+        }
+        output.push_str(&format!(
+            "{}{} \"{}\"\n",
+            INDENT, target_impl.target, target_impl.path
+        ));
+    }
+
+    // The body is always set apart from the components
+    if !sections.is_empty() && !system.body.is_empty() {
+        output.push('\n');
+    }
+    format_statements(&system.body, 1, output);
+    output.push('}');
+}
+
+/// Writes each statement on its own lines at the given depth, ending with a new line.
+/// Blank lines between statements are kept, with runs of them collapsed into one.
+fn format_statements(statements: &[Statement], depth: usize, output: &mut String) {
+    let indent = INDENT.repeat(depth);
+    let mut previous: Option<&Statement> = None;
+
+    for statement in statements {
+        if let Some(previous) = previous {
+            if let StatementValue::Comment(lines) = &statement.value {
+                // Trailing comments stay on the line they were written on
+                let is_trailing = !matches!(previous.value, StatementValue::Comment(_))
+                    && lines.len() == 1
+                    && statement.start_location.line() == previous.end_location.line();
+
+                if is_trailing {
+                    output.pop();
+                    output.push(' ');
+                    output.push_str(&comment_line(&lines[0]));
+                    output.push('\n');
+                    continue;
+                }
+            }
+
+            if statement.start_location.line() > previous.end_location.line() + 1 {
+                output.push('\n');
+            }
+        }
+
+        output.push_str(&indent);
+        format_statement(statement, depth, output);
+        output.push('\n');
+        previous = Some(statement);
+    }
+}
+
+fn format_statement(statement: &Statement, depth: usize, output: &mut String) {
+    let indent = INDENT.repeat(depth);
+    match &statement.value {
+        StatementValue::Comment(lines) => {
+            let lines: Vec<String> = lines.iter().map(|l| comment_line(l)).collect();
+            output.push_str(&lines.join(&format!("\n{}", indent)));
+        }
+        StatementValue::Query {
+            entity,
+            terms,
+            without,
+            changed,
+            body,
+        } => {
+            let terms: Vec<String> = terms
+                .iter()
+                .map(|t| {
+                    let optional = if t.optional { "?" } else { "" };
+                    format!("{}{}", t.component.id, optional)
+                })
+                .collect();
+            output.push_str(&format!(
+                "{} {} {} {} ",
+                FOR_ID,
+                entity.id,
+                WITH_ID,
+                terms.join(", ")
+            ));
+            for (keyword, references) in [(WITHOUT_ID, without), (CHANGED_ID, changed)] {
+                if !references.is_empty() {
+                    let ids: Vec<&str> = references.iter().map(|r| r.id.as_str()).collect();
+                    output.push_str(&format!("{} {} ", keyword, ids.join(", ")));
+                }
+            }
+            format_block(body, depth, output);
+        }
+        StatementValue::Events {
+            event,
+            component,
+            body,
+        } => {
+            output.push_str(&format!(
+                "{} {} {} {} {} ",
+                FOR_ID, event.id, IN_ID, EVENTS_ID, component.id
+            ));
+            format_block(body, depth, output);
+        }
+        StatementValue::Iterate { item, list, body } => {
+            output.push_str(&format!(
+                "{} {} {} {} ",
+                FOR_ID,
+                item.id,
+                IN_ID,
+                format_expr(list)
+            ));
+            format_block(body, depth, output);
+        }
+        StatementValue::Emit { component, args } => {
+            output.push_str(&format!(
+                "{} {}",
+                EMIT_ID,
+                format_constructor(component, args)
+            ));
+        }
+        StatementValue::Create { components } => {
+            let components: Vec<String> = components
+                .iter()
+                .map(|c| format_constructor(&c.component, &c.args))
+                .collect();
+            output.push_str(&format!("{} [{}]", CREATE_ID, components.join(" ")));
+        }
+        StatementValue::If {
+            condition,
+            then,
+            otherwise,
+        } => {
+            output.push_str(&format!("{} {} ", IF_ID, format_expr(condition)));
+            format_block(then, depth, output);
+
+            match otherwise.as_slice() {
+                [] => {}
+                [statement @ Statement {
+                    value: StatementValue::If { .. },
+                    ..
+                }] => {
+                    output.push_str(&format!(" {} ", ELSE_ID));
+                    format_statement(statement, depth, output);
+                }
+                otherwise => {
+                    output.push_str(&format!(" {} ", ELSE_ID));
+                    format_block(otherwise, depth, output);
+                }
+            }
+        }
+        StatementValue::Match {
+            value,
+            arms,
+            comments,
+        } => {
+            output.push_str(&format!("{} {} ", MATCH_ID, format_expr(value)));
+            if arms.is_empty() && comments.is_empty() {
+                output.push_str("{}");
+                return;
+            }
+
+            output.push_str("{\n");
+            let arm_indent = INDENT.repeat(depth + 1);
+            for arm in arms.iter() {
+                for comment in arm.comments.iter() {
+                    output.push_str(&format!("{}{}\n", arm_indent, comment_line(comment)));
+                }
+                output.push_str(&arm_indent);
+                format_arm(arm, depth + 1, output);
+                output.push('\n');
+            }
+            for comment in comments.iter() {
+                output.push_str(&format!("{}{}\n", arm_indent, comment_line(comment)));
+            }
+            output.push_str(&indent);
+            output.push('}');
+        }
+        StatementValue::Assign { target, op, value } => {
+            output.push_str(&format!(
+                "{} {} {}",
+                format_expr(target),
+                op.symbol(),
+                format_expr(value)
+            ));
+        }
+        StatementValue::Call(call) => output.push_str(&format_expr(call)),
+    }
+}
+
+fn format_arm(arm: &MatchArm, depth: usize, output: &mut String) {
+    match (&arm.variant, &arm.binding) {
+        (Some(variant), Some(binding)) => {
+            output.push_str(&format!("{}({}) ", variant.id, binding.id))
+        }
+        (Some(variant), None) => output.push_str(&format!("{} ", variant.id)),
+        (None, _) => output.push_str(&format!("{} ", ELSE_ID)),
+    }
+    format_block(&arm.body, depth, output);
+}
+
+fn format_block(statements: &[Statement], depth: usize, output: &mut String) {
+    if statements.is_empty() {
+        output.push_str("{}");
+        return;
+    }
+
+    output.push_str("{\n");
+    format_statements(statements, depth + 1, output);
+    output.push_str(&INDENT.repeat(depth));
+    output.push('}');
+}
+
+/// Formats a component with its values, leaving out the parentheses when there are none.
+fn format_constructor(component: &Reference, args: &[Expr]) -> String {
+    if args.is_empty() {
+        return component.id.clone();
+    }
+
+    let args: Vec<String> = args.iter().map(format_expr).collect();
+    format!("{}({})", component.id, args.join(", "))
+}
+
+fn format_expr(expr: &Expr) -> String {
+    match &expr.value {
+        ExprValue::Number(n) => n.clone(),
+        ExprValue::Bool(b) => b.to_string(),
+        ExprValue::Identifier(id) => id.clone(),
+        ExprValue::Field { target, field } => format!("{}.{}", format_expr(target), field),
+        ExprValue::Call {
+            target,
+            method,
+            args,
+        } => {
+            let args: Vec<String> = args.iter().map(format_expr).collect();
+            format!("{}.{}({})", format_expr(target), method, args.join(", "))
+        }
+        ExprValue::Has { entity, component } => format!("{} {} {}", entity, HAS_ID, component),
+        ExprValue::Single { component } => format!("{} {}", SINGLE_ID, component),
+        ExprValue::Unary { op, expr } => format!("{}{}", op.symbol(), format_expr(expr)),
+        ExprValue::Binary { op, lhs, rhs } => {
+            format!("{} {} {}", format_expr(lhs), op.symbol(), format_expr(rhs))
+        }
+        ExprValue::Group(expr) => format!("({})", format_expr(expr)),
+    }
+}
+
+/// Stages keep their comments, while comments of the world itself are placed before it.
+fn format_world(world: &World, output: &mut String) {
+    for comment in world.comments.iter() {
+        output.push_str(&comment_line(comment));
+        output.push('\n');
+    }
+
+    let name = declaration_name(WORLD_ID, &world.attributes);
+    if world.stages.is_empty() {
+        output.push_str(&format!("{} {{}}", name));
+        return;
+    }
+
+    output.push_str(&format!("{} {{\n", name));
+    for stage in world.stages.iter() {
+        for comment in stage.comments.iter() {
+            output.push_str(&format!("{}{}\n", INDENT, comment_line(comment)));
+        }
+        output.push_str(&format!("{}{}\n", INDENT, reference_list(&stage.systems)));
+    }
+    output.push('}');
+}
+
+fn reference_list(references: &[Reference]) -> String {
+    let ids: Vec<&str> = references.iter().map(|r| r.id.as_str()).collect();
+    format!("[{}]", ids.join(" "))
+}
+
+/// Returns the id followed by its attributes.
+fn declaration_name(id: &str, attributes: &[Attribute]) -> String {
+    let mut name = id.to_string();
+    for attribute in attributes {
+        name.push_str(&format!(" {}", attribute));
+    }
+
+    name
+}
+
+/// Comments written after a property or variant stay on its line.
+fn push_trailing_comment(comment: Option<&str>, output: &mut String) {
+    if let Some(comment) = comment {
+        output.push(' ');
+        output.push_str(&comment_line(comment));
+    }
+}
+
+/// The shared shape of struct and component properties.
+enum Fields<'a> {
+    None,
+    Value(&'a Listable<Primitive>),
+    Multiple(Vec<Field<'a>>),
+}
+
+struct Field<'a> {
+    ty: &'a Listable<Primitive>,
+    identifier: &'a str,
+    comments: &'a [String],
+    trailing_comment: Option<&'a str>,
+}
+
+/// Comments before the declaration are placed above it, and trailing comments at the end of its body.
+fn format_declaration(
+    keyword: &str,
+    id: &str,
+    comments: &[String],
+    trailing_comments: &[String],
+    fields: Fields,
+    output: &mut String,
+) {
+    for comment in comments {
+        output.push_str(&comment_line(comment));
+        output.push('\n');
+    }
+
+    // A body with only comments keeps its braces so the comments stay inside it
+    let fields = match fields {
+        Fields::None if !trailing_comments.is_empty() => Fields::Multiple(vec![]),
+        fields => fields,
+    };
+
+    match fields {
+        Fields::None => output.push_str(&format!("{} {};", keyword, id)),
+        Fields::Value(value) => output.push_str(&format!("{} {}({});", keyword, id, value.ty)),
+        Fields::Multiple(properties) => {
+            output.push_str(&format!("{} {} {{\n", keyword, id));
+            for field in properties {
+                for comment in field.comments {
+                    output.push_str(&format!("{}{}\n", INDENT, comment_line(comment)));
+                }
+                output.push_str(&format!("{}{} {}", INDENT, field.ty.ty, field.identifier));
+                push_trailing_comment(field.trailing_comment, output);
+                output.push('\n');
+            }
+            for comment in trailing_comments {
+                output.push_str(&format!("{}{}\n", INDENT, comment_line(comment)));
+            }
+            output.push('}');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn fmt(code: &str) -> String {
+        format(&parse(code, Location::default()).unwrap())
+    }
+
+    #[test]
+    fn empty_input_returns_empty() {
+        assert_eq!("", fmt(""));
+    }
+
+    #[test]
+    fn normalizes_declarations() {
+        let input =
+            "struct   Empty {}\ncomponent Hp (  i32 ) ;\nsingle_component  Names([char  10]);";
+        let expected = "struct Empty;\ncomponent Hp(i32);\nsingle_component Names([char 10]);\n";
+        assert_eq!(expected, fmt(input));
+    }
+
+    #[test]
+    fn normalizes_properties() {
+        let input =
+            "struct Aabb {   i32 width  i32\n\n\n height }\ncomponent Boxes{[Aabb 256] boxes}";
+        let expected = "struct Aabb {\n    i32 width\n    i32 height\n}\ncomponent Boxes {\n    [Aabb 256] boxes\n}\n";
+        assert_eq!(expected, fmt(input));
+    }
+
+    #[test]
+    fn collapses_blank_lines() {
+        let input = "struct A;\n\n\n\nstruct B;\n\ncomponent C;";
+        let expected = "struct A;\n\nstruct B;\n\ncomponent C;\n";
+        assert_eq!(expected, fmt(input));
+    }
+
+    #[test]
+    fn splits_declarations_on_one_line() {
+        let input = "struct A; struct B;";
+        let expected = "struct A;\nstruct B;\n";
+        assert_eq!(expected, fmt(input));
+    }
+
+    #[test]
+    fn normalizes_systems_and_world() {
+        let input = "system Idle { }\nsystem Move { write [ Position ]   read [Velocity\n Mass] }\n\nworld   auto {\n# Physics first.\n[Move   Idle]\n}";
+        let expected = "system Idle {}\nsystem Move {\n    read [Velocity Mass]\n    write [Position]\n}\n\nworld auto {\n    # Physics first.\n    [Move Idle]\n}\n";
+        assert_eq!(expected, fmt(input));
+        assert_eq!(expected, fmt(expected));
+    }
+
+    #[test]
+    fn normalizes_system_bodies() {
+        let input = "system Move { write [Position] read [Mod]\nfor e with Position,Mod? {\n  if e has Mod&&(e.Mod>0) { e.Position.x+=-e.Mod }\n  else if !done {}else{\n\n\n x=1.5 # Half\n y = 2 }\n}}";
+        let expected = "system Move {\n    read [Mod]\n    write [Position]\n\n    for e with Position, Mod? {\n        if e has Mod && (e.Mod > 0) {\n            e.Position.x += -e.Mod\n        } else if !done {} else {\n            x = 1.5 # Half\n            y = 2\n        }\n    }\n}\n";
+        assert_eq!(expected, fmt(input));
+        assert_eq!(expected, fmt(expected));
+    }
+
+    #[test]
+    fn normalizes_query_filters() {
+        let input = "system Render { read [Position IsDead Frozen]\nfor e with Position changed Position without IsDead,Frozen {} }";
+        let expected = "system Render {\n    read [Position IsDead Frozen]\n\n    for e with Position without IsDead, Frozen changed Position {}\n}\n";
+        assert_eq!(expected, fmt(input));
+        assert_eq!(expected, fmt(expected));
+    }
+
+    #[test]
+    fn normalizes_events() {
+        let input = "event Collision{Entity a Entity b}\nevent Reset;\nsystem Hit { read [Collision] write [Reset]\nfor ev in   events Collision { emit Reset()\nemit Reset } }";
+        let expected = "event Collision {\n    Entity a\n    Entity b\n}\nevent Reset;\nsystem Hit {\n    read [Collision]\n    write [Reset]\n\n    for ev in events Collision {\n        emit Reset\n        emit Reset\n    }\n}\n";
+        assert_eq!(expected, fmt(input));
+        assert_eq!(expected, fmt(expected));
+    }
+
+    #[test]
+    fn normalizes_create() {
+        let input =
+            "system Spawn { write [Position IsAlive]\ncreate[Position( 1,2 )   IsAlive()] }";
+        let expected = "system Spawn {\n    write [Position IsAlive]\n\n    create [Position(1, 2) IsAlive]\n}\n";
+        assert_eq!(expected, fmt(input));
+        assert_eq!(expected, fmt(expected));
+    }
+
+    #[test]
+    fn normalizes_external_systems() {
+        let input = "external_system Render{write[Sprite] target_c_impl   \"graphics.c\"}";
+        let expected =
+            "external_system Render {\n    write [Sprite]\n\n    target_c_impl \"graphics.c\"\n}\n";
+        assert_eq!(expected, fmt(input));
+        assert_eq!(expected, fmt(expected));
+    }
+
+    #[test]
+    fn normalizes_enums_and_match() {
+        let input = "enum Status{Ok,\n# Lost hp\nDamaged( i32 )}\nsystem Heal { write [Hp]\nfor e with Hp { match e.Hp {Ok{}\nDamaged(n){ e.Hp=Status.Damaged(n-1) } else {}\n# Done\n} } }";
+        let expected = "enum Status {\n    Ok,\n    # Lost hp\n    Damaged(i32),\n}\nsystem Heal {\n    write [Hp]\n\n    for e with Hp {\n        match e.Hp {\n            Ok {}\n            Damaged(n) {\n                e.Hp = Status.Damaged(n - 1)\n            }\n            else {}\n            # Done\n        }\n    }\n}\n";
+        assert_eq!(expected, fmt(input));
+        assert_eq!(expected, fmt(expected));
+    }
+
+    #[test]
+    fn normalizes_list_methods_and_iteration() {
+        let input = "system Clean { write [Hits]\nfor e with Hits { for hit in e.Hits{ if hit<0{e.Hits.remove_at( 0 )} }\ne.Hits.push( 1 )\nlast=e.Hits.pop() } }";
+        let expected = "system Clean {\n    write [Hits]\n\n    for e with Hits {\n        for hit in e.Hits {\n            if hit < 0 {\n                e.Hits.remove_at(0)\n            }\n        }\n        e.Hits.push(1)\n        last = e.Hits.pop()\n    }\n}\n";
+        assert_eq!(expected, fmt(input));
+        assert_eq!(expected, fmt(expected));
+    }
+
+    #[test]
+    fn keeps_numbers_as_written() {
+        let input = "system Move { write [Speed]\nfor e with Speed { e.Speed *= 2.0\ne.Speed += 1e3 - 0.05 } }";
+        let expected = "system Move {\n    write [Speed]\n\n    for e with Speed {\n        e.Speed *= 2.0\n        e.Speed += 1e3 - 0.05\n    }\n}\n";
+        assert_eq!(expected, fmt(input));
+    }
+
+    #[test]
+    fn keeps_comments_attached() {
+        let input = "    # Position of an entity.\n    # In world units.\n  component Position { i32 x i32 y }\n\n# Detached\n\nstruct A;";
+        let expected = "# Position of an entity.\n# In world units.\ncomponent Position {\n    i32 x\n    i32 y\n}\n\n# Detached\n\nstruct A;\n";
+        assert_eq!(expected, fmt(input));
+    }
+
+    #[test]
+    fn keeps_trailing_comments() {
+        let input = "component IsAlive;   # A tag\nstruct A;";
+        let expected = "component IsAlive; # A tag\nstruct A;\n";
+        assert_eq!(expected, fmt(input));
+    }
+
+    #[test]
+    fn keeps_field_comments() {
+        let input = "struct Aabb {\n  # Horizontal size\n  i32 width i32 height # Vertical size\n}";
+        let expected = "struct Aabb {\n    # Horizontal size\n    i32 width\n    i32 height # Vertical size\n}\n";
+        assert_eq!(expected, fmt(input));
+    }
+
+    #[test]
+    fn keeps_body_comments_in_the_body() {
+        let input = "struct Aabb {\n    i32 width\n    # Height comes later\n}\ncomponent Todo {\n    # Nothing yet\n}\nsystem Move {\n    write [Position]\n    # Readers\n    read [Velocity]\n    # Done\n}\nexternal_system Draw {\n    # For C\n    target_c_impl \"draw.c\"\n}";
+        let expected = "struct Aabb {\n    i32 width\n    # Height comes later\n}\ncomponent Todo {\n    # Nothing yet\n}\nsystem Move {\n    # Readers\n    read [Velocity]\n    write [Position]\n\n    # Done\n}\nexternal_system Draw {\n    # For C\n    target_c_impl \"draw.c\"\n}\n";
+        assert_eq!(expected, fmt(input));
+        assert_eq!(expected, fmt(expected));
+    }
+
+    #[test]
+    fn keeps_attributes() {
+        let input = "struct Header   ordered{u32 magic}\ncomponent Hp ordered (i32);";
+        let expected = "struct Header ordered {\n    u32 magic\n}\ncomponent Hp ordered(i32);\n";
+        assert_eq!(expected, fmt(input));
+    }
+
+    #[test]
+    fn trailing_comments_stay_on_their_line() {
+        let input = "component Position {\n    f32 x # Left to right\n    # Up\n    f32 y\n}\nenum Status {\n    Ok, # Fine\n    Damaged(i32),   # Lost hp\n}";
+        let expected = "component Position {\n    f32 x # Left to right\n    # Up\n    f32 y\n}\nenum Status {\n    Ok, # Fine\n    Damaged(i32), # Lost hp\n}\n";
+        let once = fmt(input);
+        assert_eq!(expected, once);
+        assert_eq!(once, fmt(&once));
+
+        // Formatting doesn't turn the trailing comment into documentation
+        let comments = |code: &str| match &parse(code, Location::default()).unwrap()[0] {
+            Ast::Component(Component {
+                properties: ComponentProperties::Multiple(properties),
+                ..
+            }) => properties
+                .iter()
+                .map(|p| (p.comments.clone(), p.trailing_comment.clone()))
+                .collect::<Vec<_>>(),
+            ast => panic!("Expected component with properties, got {:?}", ast),
+        };
+        assert_eq!(comments(input), comments(&once));
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        let input = "# Structs\nstruct Name { [char 10] name }\n\n\ncomponent Person { Name name i32 age } # People\n";
+        let once = fmt(input);
+        assert_eq!(once, fmt(&once));
+    }
+}
@@ -0,0 +1,274 @@
+//! Renders the world schedule as a graph so large pipelines can be reviewed visually.
+//! The stages of `auto` worlds are the ones computed by the compiler.
+//! Each stage is a cluster of the systems it runs, with edges from the components a system
+//! reads and to the components it writes. Conflicts between systems in different stages are
+//! highlighted in red, as they're the reason those systems can't share a stage.
+//! Conflicts within a stage are errors, but the graph is still drawn for them with bold red
+//! edges in both directions so they're easy to find.
+//! Systems that aren't part of the world are drawn outside of the stages.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    env::{conflicts, schedule, Env},
+    parser::System,
+};
+
+/// A system as it's placed in the schedule. Systems may run in multiple stages.
+struct SystemNode<'a> {
+    stage: Option<usize>,
+    system: &'a System,
+}
+
+impl SystemNode<'_> {
+    fn id(&self) -> String {
+        match self.stage {
+            Some(stage) => format!("{}:{}", stage + 1, self.system.id),
+            None => self.system.id.clone(),
+        }
+    }
+}
+
+/// An ordering constraint between two system nodes, by index.
+struct Conflict<'a> {
+    from: usize,
+    to: usize,
+    components: Vec<&'a str>,
+    /// Whether both systems are in the same stage, which the checks reject.
+    same_stage: bool,
+}
+
+struct Graph<'a> {
+    systems: Vec<SystemNode<'a>>,
+    stage_count: usize,
+    components: Vec<&'a str>,
+    conflicts: Vec<Conflict<'a>>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(env: &'a Env) -> Self {
+        let mut systems = vec![];
+        let stages = schedule(env);
+        for (idx, stage) in stages.iter().enumerate() {
+            for id in stage.iter() {
+                if let Some(system) = env.systems.get(*id) {
+                    systems.push(SystemNode {
+                        stage: Some(idx),
+                        system,
+                    });
+                }
+            }
+        }
+
+        let mut unscheduled: Vec<&System> = env
+            .systems
+            .values()
+            .filter(|s| !systems.iter().any(|n| n.system.id == s.id))
+            .collect();
+        unscheduled.sort_by(|a, b| a.id.cmp(&b.id));
+        systems.extend(unscheduled.into_iter().map(|system| SystemNode {
+            stage: None,
+            system,
+        }));
+
+        let mut components: Vec<&str> = systems
+            .iter()
+            .flat_map(|n| n.system.reads.iter().chain(n.system.writes.iter()))
+            .map(|r| r.id.as_str())
+            .collect();
+        components.sort();
+        components.dedup();
+
+        Self {
+            conflicts: conflict_edges(&systems),
+            systems,
+            stage_count: stages.len(),
+            components,
+        }
+    }
+
+    fn stage(&self, stage: usize) -> impl Iterator<Item = &SystemNode<'a>> {
+        self.systems.iter().filter(move |n| n.stage == Some(stage))
+    }
+
+    fn unscheduled(&self) -> impl Iterator<Item = &SystemNode<'a>> {
+        self.systems.iter().filter(|n| n.stage.is_none())
+    }
+}
+
+/// Links each scheduled system to the next run of every system it conflicts with,
+/// and to the systems after it in its own stage that it conflicts with.
+fn conflict_edges<'a>(systems: &[SystemNode<'a>]) -> Vec<Conflict<'a>> {
+    let mut edges = vec![];
+    for (from, node) in systems.iter().enumerate() {
+        let stage = match node.stage {
+            Some(stage) => stage,
+            None => continue,
+        };
+
+        let mut linked: Vec<&str> = vec![];
+        for (to, other) in systems.iter().enumerate() {
+            let same_stage = other.stage == Some(stage) && to > from;
+            let is_later = matches!(other.stage, Some(s) if s > stage)
+                && !linked.contains(&other.system.id.as_str());
+            if !same_stage && !is_later {
+                continue;
+            }
+
+            let components = conflicts(node.system, other.system);
+            if components.is_empty() {
+                continue;
+            }
+
+            if is_later {
+                linked.push(&other.system.id);
+            }
+            edges.push(Conflict {
+                from,
+                to,
+                components,
+                same_stage,
+            });
+        }
+    }
+
+    edges
+}
+
+/// Returns the graph as Graphviz DOT.
+pub fn to_dot(env: &Env) -> String {
+    let graph = Graph::new(env);
+    let mut lines = vec![
+        "digraph world {".to_string(),
+        "    rankdir=LR;".to_string(),
+        "    node [shape=box];".to_string(),
+    ];
+
+    for stage in 0..graph.stage_count {
+        lines.push(format!("    subgraph cluster_stage_{} {{", stage + 1));
+        lines.push(format!("        label=\"Stage {}\";", stage + 1));
+        for node in graph.stage(stage) {
+            lines.push(format!(
+                "        \"{}\" [label=\"{}\"];",
+                node.id(),
+                node.system.id
+            ));
+        }
+        lines.push("    }".to_string());
+    }
+
+    for node in graph.unscheduled() {
+        lines.push(format!(
+            "    \"{}\" [label=\"{}\", style=dotted];",
+            node.id(),
+            node.system.id
+        ));
+    }
+
+    for component in graph.components.iter() {
+        lines.push(format!(
+            "    \"c:{}\" [label=\"{}\", shape=ellipse];",
+            component, component
+        ));
+    }
+
+    for node in graph.systems.iter() {
+        for read in node.system.reads.iter() {
+            lines.push(format!(
+                "    \"c:{}\" -> \"{}\" [style=dashed];",
+                read.id,
+                node.id()
+            ));
+        }
+        for write in node.system.writes.iter() {
+            lines.push(format!("    \"{}\" -> \"c:{}\";", node.id(), write.id));
+        }
+    }
+
+    for conflict in graph.conflicts.iter() {
+        let style = if conflict.same_stage {
+            ", style=bold, dir=both"
+        } else {
+            ""
+        };
+        lines.push(format!(
+            "    \"{}\" -> \"{}\" [color=red, fontcolor=red{}, label=\"{}\"];",
+            graph.systems[conflict.from].id(),
+            graph.systems[conflict.to].id(),
+            style,
+            conflict.components.join(" ")
+        ));
+    }
+
+    lines.push("}".to_string());
+    lines.join("\n") + "\n"
+}
+
+/// Returns the graph as a Mermaid flowchart.
+pub fn to_mermaid(env: &Env) -> String {
+    let graph = Graph::new(env);
+    // Mermaid ids can't contain ':', so stages are part of the id instead
+    let node_id = |node: &SystemNode| match node.stage {
+        Some(stage) => format!("s{}_{}", stage + 1, node.system.id),
+        None => format!("s_{}", node.system.id),
+    };
+
+    let mut lines = vec!["flowchart LR".to_string()];
+    for stage in 0..graph.stage_count {
+        lines.push(format!(
+            "    subgraph stage_{}[\"Stage {}\"]",
+            stage + 1,
+            stage + 1
+        ));
+        for node in graph.stage(stage) {
+            lines.push(format!("        {}[\"{}\"]", node_id(node), node.system.id));
+        }
+        lines.push("    end".to_string());
+    }
+
+    for node in graph.unscheduled() {
+        lines.push(format!("    {}[\"{}\"]", node_id(node), node.system.id));
+    }
+
+    for component in graph.components.iter() {
+        lines.push(format!("    c_{}([\"{}\"])", component, component));
+    }
+
+    // Links are styled by their index, so they're counted as they're added
+    let mut link_count = 0;
+    for node in graph.systems.iter() {
+        for read in node.system.reads.iter() {
+            lines.push(format!("    c_{} -.-> {}", read.id, node_id(node)));
+            link_count += 1;
+        }
+        for write in node.system.writes.iter() {
+            lines.push(format!("    {} --> c_{}", node_id(node), write.id));
+            link_count += 1;
+        }
+    }
+
+    let mut conflict_links = vec![];
+    for conflict in graph.conflicts.iter() {
+        // Conflicts within a stage are thick and point both ways
+        let arrow = if conflict.same_stage { "<==>" } else { "-->" };
+        lines.push(format!(
+            "    {} {}|\"{}\"| {}",
+            node_id(&graph.systems[conflict.from]),
+            arrow,
+            conflict.components.join(" "),
+            node_id(&graph.systems[conflict.to])
+        ));
+        conflict_links.push(link_count.to_string());
+        link_count += 1;
+    }
+
+    if !conflict_links.is_empty() {
+        lines.push(format!(
+            "    linkStyle {} stroke:red,color:red",
+            conflict_links.join(",")
+        ));
+    }
+
+    lines.join("\n") + "\n"
+}
@@ -0,0 +1,129 @@
+use super::*;
+use crate::{env, location::Location, parser, unchecked_env};
+
+const WORLD: &str = "component Position;
+component Velocity;
+component Sprite;
+system Move { read [Velocity] write [Position] }
+system Render { read [Position] write [Sprite] }
+system Idle {}
+world {
+    [Move]
+    [Render]
+}";
+
+fn build(code: &str) -> Env {
+    let ast = parser::parse(code, Location::default()).unwrap();
+    env::build(unchecked_env::build(ast)).unwrap()
+}
+
+#[test]
+fn dot_groups_stages_and_highlights_conflicts() {
+    let expected = r#"digraph world {
+    rankdir=LR;
+    node [shape=box];
+    subgraph cluster_stage_1 {
+        label="Stage 1";
+        "1:Move" [label="Move"];
+    }
+    subgraph cluster_stage_2 {
+        label="Stage 2";
+        "2:Render" [label="Render"];
+    }
+    "Idle" [label="Idle", style=dotted];
+    "c:Position" [label="Position", shape=ellipse];
+    "c:Sprite" [label="Sprite", shape=ellipse];
+    "c:Velocity" [label="Velocity", shape=ellipse];
+    "c:Velocity" -> "1:Move" [style=dashed];
+    "1:Move" -> "c:Position";
+    "c:Position" -> "2:Render" [style=dashed];
+    "2:Render" -> "c:Sprite";
+    "1:Move" -> "2:Render" [color=red, fontcolor=red, label="Position"];
+}
+"#;
+    assert_eq!(expected, to_dot(&build(WORLD)));
+}
+
+#[test]
+fn mermaid_styles_conflict_links() {
+    let expected = r#"flowchart LR
+    subgraph stage_1["Stage 1"]
+        s1_Move["Move"]
+    end
+    subgraph stage_2["Stage 2"]
+        s2_Render["Render"]
+    end
+    s_Idle["Idle"]
+    c_Position(["Position"])
+    c_Sprite(["Sprite"])
+    c_Velocity(["Velocity"])
+    c_Velocity -.-> s1_Move
+    s1_Move --> c_Position
+    c_Position -.-> s2_Render
+    s2_Render --> c_Sprite
+    s1_Move -->|"Position"| s2_Render
+    linkStyle 4 stroke:red,color:red
+"#;
+    assert_eq!(expected, to_mermaid(&build(WORLD)));
+}
+
+#[test]
+fn systems_running_twice_link_to_their_next_run() {
+    let env = build(
+        "component Position;
+system Move { write [Position] }
+system Render { read [Position] }
+world {
+    [Move]
+    [Render]
+    [Move]
+}",
+    );
+
+    let dot = to_dot(&env);
+    let conflicts: Vec<&str> = dot
+        .lines()
+        .filter(|l| l.contains("color=red"))
+        .map(|l| l.trim())
+        .collect();
+    assert_eq!(
+        vec![
+            r#""1:Move" -> "2:Render" [color=red, fontcolor=red, label="Position"];"#,
+            r#""1:Move" -> "3:Move" [color=red, fontcolor=red, label="Position"];"#,
+            r#""2:Render" -> "3:Move" [color=red, fontcolor=red, label="Position"];"#,
+        ],
+        conflicts
+    );
+}
+
+#[test]
+fn conflicts_within_a_stage_are_drawn_for_invalid_worlds() {
+    let code = "component Position;
+system Move { write [Position] }
+system Render { read [Position] }
+system Idle {}
+world {
+    [Move Idle Render]
+}";
+    let ast = parser::parse(code, Location::default()).unwrap();
+    let (env, errors) = env::build_partial(unchecked_env::build(ast));
+    assert_eq!(1, errors.len());
+    let env = env.unwrap();
+
+    let dot = to_dot(&env);
+    let conflicts: Vec<&str> = dot
+        .lines()
+        .filter(|l| l.contains("color=red"))
+        .map(|l| l.trim())
+        .collect();
+    assert_eq!(
+        vec![
+            r#""1:Move" -> "1:Render" [color=red, fontcolor=red, style=bold, dir=both, label="Position"];"#
+        ],
+        conflicts
+    );
+
+    let mermaid = to_mermaid(&env);
+    assert!(mermaid.contains("    s1_Move <==>|\"Position\"| s1_Render\n"));
+    assert!(mermaid.ends_with("    linkStyle 2 stroke:red,color:red\n"));
+}
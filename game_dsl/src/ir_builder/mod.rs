@@ -1,18 +1,31 @@
-use std::error;
+mod system;
+mod world;
 
 use crate::{
-    compiler_ir::{Commentable, Struct, StructField, IR},
-    env::{Env, StructValue},
+    compiler_ir::{
+        Commentable, EnumVariant, ItemEquality, Store, StoreField, Struct, StructField,
+        ENUM_PAYLOAD_ID, ENUM_TAG_ID, IR, LIST_ITEMS_ID, LIST_LENGTH_ID,
+    },
+    env::{self, Env, StructValue},
     error::Error,
-    parser::{ComponentProperties, ListType, Primitive, PrimitiveType, StructProperties},
+    location::Location,
+    parser::{
+        Attribute, ComponentProperties, ListType, Primitive, PrimitiveType, StructProperties,
+    },
 };
+use system::build_systems;
+use world::build_world;
 
 pub fn build(env: Env) -> Result<Vec<IR>, Vec<Error>> {
     let mut ir = vec![];
-    let mut errors = vec![];
+    let errors = vec![];
 
+    build_lists(&env, &mut ir);
     build_components(&env, &mut ir);
     build_structs(&env, &mut ir);
+    build_enums(&env, &mut ir);
+    build_systems(&env, &mut ir);
+    build_world(&env, &mut ir);
 
     if errors.is_empty() {
         Ok(ir)
@@ -23,10 +36,8 @@ pub fn build(env: Env) -> Result<Vec<IR>, Vec<Error>> {
 
 fn build_structs(env: &Env, ir: &mut Vec<IR>) {
     for (id, strukt) in env.structs.iter() {
-        let use_components = match strukt {
-            StructValue::ComponentStore(_) => true,
-            StructValue::Struct(_) => false,
-        };
+        // Only the components of a component store refer to a component
+        let is_store = matches!(strukt, StructValue::ComponentStore(_));
         let strukt = strukt.strukt();
         let mut fields = vec![];
 
@@ -35,23 +46,204 @@ fn build_structs(env: &Env, ir: &mut Vec<IR>) {
             StructProperties::Value(v) => {
                 fields.push(StructField {
                     id: "value".to_string(),
-                    ty: build_list_type(&v.ty, use_components),
+                    comments: vec![],
+                    ty: build_field_type(&v.ty),
                 });
             }
             StructProperties::Multiple(properties) => {
                 for prop in properties {
                     let id = prop.identifier.to_string();
-                    let ty = build_list_type(&prop.ty.ty, use_components);
-                    fields.push(StructField { id, ty });
+                    let comments = prop.comments.clone();
+                    // The arrays of a store stay plain arrays, as the store keeps their length
+                    let ty = if is_store {
+                        let use_components = env::is_component_property(&prop.identifier);
+                        build_list_type(&prop.ty.ty, use_components)
+                    } else {
+                        build_field_type(&prop.ty.ty)
+                    };
+                    fields.push(StructField { id, comments, ty });
                 }
             }
         }
 
         ir.push(IR::Struct(Commentable {
-            comments: vec![],
+            comments: strukt.comments.clone(),
+            value: Struct {
+                id: struct_id(id),
+                name: id.to_string(),
+                fields,
+                ordered: strukt.attributes.contains(&Attribute::Ordered),
+                union: false,
+                variants: vec![],
+                list: None,
+            },
+        }));
+    }
+}
+
+/// Builds a struct for every list declared in the language, holding the length in use along
+/// with the items. Lists with the same type and max size share their struct.
+fn build_lists(env: &Env, ir: &mut Vec<IR>) {
+    let mut lists: Vec<(Primitive, usize)> = vec![];
+    let mut add = |ty: &ListType<Primitive>| {
+        if let ListType::List { ty, max_size } = ty {
+            let id = list_id(&ty.primitive_type, *max_size);
+            if !lists
+                .iter()
+                .any(|(t, m)| list_id(&t.primitive_type, *m) == id)
+            {
+                lists.push((ty.clone(), *max_size));
+            }
+        }
+    };
+
+    for strukt in env.structs.values() {
+        if let StructValue::Struct(strukt) = strukt {
+            match &strukt.properties {
+                StructProperties::None => {}
+                StructProperties::Value(v) => add(&v.ty),
+                StructProperties::Multiple(properties) => {
+                    properties.iter().for_each(|p| add(&p.ty.ty))
+                }
+            }
+        }
+    }
+    for component in env.components.values() {
+        match &component.properties {
+            ComponentProperties::None => {}
+            ComponentProperties::Value(v) => add(&v.ty),
+            ComponentProperties::Multiple(properties) => {
+                properties.iter().for_each(|p| add(&p.ty.ty))
+            }
+        }
+    }
+    for e in env.enums.values() {
+        e.variants
+            .iter()
+            .filter_map(|v| v.payload.as_ref())
+            .for_each(|p| add(&p.ty));
+    }
+
+    for (ty, max_size) in lists {
+        let equality = match &ty.primitive_type {
+            PrimitiveType::Identifier(id) if id == env::ENTITY_ID => {
+                ItemEquality::Field("value".to_string())
+            }
+            PrimitiveType::Identifier(id) if env.enums.get(id).is_some_and(|e| e.is_plain()) => {
+                ItemEquality::Field(ENUM_TAG_ID.to_string())
+            }
+            PrimitiveType::Identifier(_) => ItemEquality::None,
+            _ => ItemEquality::Value,
+        };
+
+        ir.push(IR::Struct(Commentable {
+            comments: vec![format!(
+                "A list of up to {} '{}'. Only the first 'length' items are in use.",
+                max_size, ty.primitive_type
+            )],
+            value: Struct {
+                id: list_id(&ty.primitive_type, max_size),
+                name: format!("[{} {}]", ty.primitive_type, max_size),
+                fields: vec![
+                    StructField {
+                        id: LIST_LENGTH_ID.to_string(),
+                        comments: vec![],
+                        ty: ListType::Single(Primitive {
+                            primitive_type: PrimitiveType::U32,
+                            start_location: Location::SystemDefined,
+                            end_location: Location::SystemDefined,
+                        }),
+                    },
+                    StructField {
+                        id: LIST_ITEMS_ID.to_string(),
+                        comments: vec![],
+                        ty: ListType::List {
+                            ty: build_primitive(&ty, false),
+                            max_size,
+                        },
+                    },
+                ],
+                ordered: true,
+                union: false,
+                variants: vec![],
+                list: Some(equality),
+            },
+        }));
+    }
+}
+
+/// Builds each enum as a struct with the tag of its variant, followed by a union of the values
+/// the variants hold if any do.
+fn build_enums(env: &Env, ir: &mut Vec<IR>) {
+    for (id, e) in env.enums.iter() {
+        let mut fields = vec![StructField {
+            id: ENUM_TAG_ID.to_string(),
+            comments: vec!["The variant of the value.".to_string()],
+            ty: ListType::Single(Primitive {
+                primitive_type: PrimitiveType::U32,
+                start_location: Location::SystemDefined,
+                end_location: Location::SystemDefined,
+            }),
+        }];
+
+        let payloads: Vec<StructField> = e
+            .variants
+            .iter()
+            .filter_map(|v| {
+                Some(StructField {
+                    id: v.id.clone(),
+                    comments: v.comments.clone(),
+                    ty: build_field_type(&v.payload.as_ref()?.ty),
+                })
+            })
+            .collect();
+
+        // C doesn't allow empty unions
+        if !payloads.is_empty() {
+            fields.push(StructField {
+                id: ENUM_PAYLOAD_ID.to_string(),
+                comments: vec!["The value held by the variant, if any.".to_string()],
+                ty: ListType::Single(Primitive {
+                    primitive_type: PrimitiveType::Identifier(payload_id(id)),
+                    start_location: Location::SystemDefined,
+                    end_location: Location::SystemDefined,
+                }),
+            });
+
+            ir.push(IR::Struct(Commentable {
+                comments: vec![format!("The values held by the variants of '{}'.", id)],
+                value: Struct {
+                    id: payload_id(id),
+                    name: format!("{}Payload", id),
+                    fields: payloads,
+                    ordered: true,
+                    union: true,
+                    variants: vec![],
+                    list: None,
+                },
+            }));
+        }
+
+        ir.push(IR::Struct(Commentable {
+            comments: e.comments.clone(),
             value: Struct {
-                id: struct_id(&id),
+                id: struct_id(id),
+                name: id.to_string(),
                 fields,
+                // The tag comes first so every variant starts at the same place
+                ordered: true,
+                union: false,
+                variants: e
+                    .variants
+                    .iter()
+                    .map(|v| EnumVariant {
+                        name: v.id.clone(),
+                        tag: tag_id(id, &v.id),
+                        comments: v.comments.clone(),
+                        payload: v.payload.as_ref().map(|p| build_field_type(&p.ty)),
+                    })
+                    .collect(),
+                list: None,
             },
         }));
     }
@@ -66,32 +258,75 @@ fn build_components(env: &Env, ir: &mut Vec<IR>) {
             ComponentProperties::Value(v) => {
                 fields.push(StructField {
                     id: "value".to_string(),
-                    ty: build_list_type(&v.ty, false),
+                    comments: vec![],
+                    ty: build_field_type(&v.ty),
                 });
             }
             ComponentProperties::Multiple(properties) => {
                 for prop in properties {
                     let id = prop.identifier.to_string();
-                    let ty = build_list_type(&prop.ty.ty, false);
-                    fields.push(StructField { id, ty });
+                    let comments = prop.comments.clone();
+                    let ty = build_field_type(&prop.ty.ty);
+                    fields.push(StructField { id, comments, ty });
                 }
             }
         }
 
         let s = Struct {
-            id: component_id(&id),
+            id: component_id(id),
+            name: id.to_string(),
             fields,
+            ordered: component.attributes.contains(&Attribute::Ordered),
+            union: false,
+            variants: vec![],
+            list: None,
         };
         ir.push(IR::Struct(Commentable {
-            comments: vec![],
+            comments: component.comments.clone(),
             value: s,
         }));
 
-        // Build out ComponentManager IR class
+        let fields = match &component.properties {
+            ComponentProperties::None => vec![],
+            ComponentProperties::Value(_) => vec!["value"],
+            ComponentProperties::Multiple(properties) => {
+                properties.iter().map(|p| p.identifier.as_str()).collect()
+            }
+        };
+
+        ir.push(IR::Store(Store {
+            id: struct_id(&env::store_id(id)),
+            component: component_id(id),
+            name: id.to_string(),
+            capacity: env::store_capacity(component),
+            entity: struct_id(env::ENTITY_ID),
+            max_entities: env::MAX_ENTITIES,
+            soa: env::is_soa(component),
+            single: env::is_single(component),
+            event: env::is_event(component),
+            fields: fields
+                .into_iter()
+                .map(|field| StoreField {
+                    id: field.to_string(),
+                    array: env::store_array_id(component, field),
+                })
+                .collect(),
+        }));
     }
 }
 
-fn build_list_type(ty: &ListType<Primitive>, use_components: bool) -> ListType<Primitive> {
+/// Builds the type of a field declared in the language, where lists are their list struct.
+pub fn build_field_type(ty: &ListType<Primitive>) -> ListType<Primitive> {
+    match ty {
+        ListType::Single(ty) => ListType::Single(build_primitive(ty, false)),
+        ListType::List { ty, max_size } => ListType::Single(Primitive {
+            primitive_type: PrimitiveType::Identifier(list_id(&ty.primitive_type, *max_size)),
+            ..ty.clone()
+        }),
+    }
+}
+
+pub fn build_list_type(ty: &ListType<Primitive>, use_components: bool) -> ListType<Primitive> {
     match ty {
         ListType::Single(ty) => ListType::Single(build_primitive(ty, use_components)),
         ListType::List { ty, max_size } => ListType::List {
@@ -117,10 +352,29 @@ fn build_primitive(primitive: &Primitive, use_components: bool) -> Primitive {
     }
 }
 
-fn component_id(id: &str) -> String {
+pub fn component_id(id: &str) -> String {
     format!("D_COMPONENT_{}", id.to_uppercase())
 }
 
-fn struct_id(id: &str) -> String {
+pub fn struct_id(id: &str) -> String {
     format!("D_STRUCT_{}", id.to_uppercase())
 }
+
+/// Returns the id of the struct of a list declared in the language, e.g. `D_LIST_ENTITY_256`.
+pub fn list_id(ty: &PrimitiveType, max_size: usize) -> String {
+    format!("D_LIST_{}_{}", ty.to_string().to_uppercase(), max_size)
+}
+
+/// Returns the id of the union holding the payloads of an enum.
+pub fn payload_id(id: &str) -> String {
+    format!("D_PAYLOAD_{}", id.to_uppercase())
+}
+
+/// Returns the id of the constant holding the tag of an enum variant.
+pub fn tag_id(id: &str, variant: &str) -> String {
+    format!("D_TAG_{}_{}", id.to_uppercase(), variant.to_uppercase())
+}
+
+pub fn system_id(id: &str) -> String {
+    format!("d_system_{}", id.to_lowercase())
+}
@@ -0,0 +1,697 @@
+use super::{component_id, list_id, struct_id, system_id, tag_id};
+use crate::{
+    compiler_ir::{
+        Commentable, CreatedComponent, ExternalSystem, FieldValue, Lookup, MatchArm, Statement,
+        System, SystemImpl, Value, ENUM_PAYLOAD_ID, ENUM_TAG_ID, IR, LIST_ITEMS_ID,
+    },
+    env::{self, Binding, Env, Scope, Type, ENTITY_ID},
+    parser::{
+        self, AssignOp, BinaryOp, ComponentProperties, Expr, ExprValue, ListMethod, PrimitiveType,
+        Reference, SystemType, UnaryOp,
+    },
+};
+use std::collections::HashMap;
+
+pub fn build_systems(env: &Env, ir: &mut Vec<IR>) {
+    for system in env.systems.values() {
+        let mut lowering = Lowering {
+            env,
+            scope: Scope::new(),
+            indices: HashMap::new(),
+        };
+
+        let external = match system.system_type {
+            SystemType::System => None,
+            SystemType::ExternalSystem => Some(ExternalSystem {
+                writes: system.writes.iter().map(|w| w.id.clone()).collect(),
+                impls: system
+                    .impls
+                    .iter()
+                    .map(|i| SystemImpl {
+                        target: i.target.clone(),
+                        path: i.path.clone(),
+                    })
+                    .collect(),
+            }),
+        };
+
+        ir.push(IR::System(Commentable {
+            comments: system.comments.clone(),
+            value: System {
+                id: system_id(&system.id),
+                name: system.id.clone(),
+                body: lowering.statements(&system.body),
+                external,
+            },
+        }));
+    }
+}
+
+/// Returns the id of the local for a variable of a system body.
+fn local_id(id: &str) -> String {
+    format!("l_{}", id)
+}
+
+/// Returns the id of the local holding the index of an entity's component.
+fn index_id(entity: &str, component: &str) -> String {
+    format!("i_{}_{}", entity, component)
+}
+
+/// Lowers checked system bodies, resolving every component access to its store.
+struct Lowering<'a> {
+    env: &'a Env,
+    scope: Scope,
+    /// The index of each component of the query entities in scope, keyed by entity then component.
+    indices: HashMap<String, HashMap<String, Value>>,
+}
+
+impl<'a> Lowering<'a> {
+    fn statements(&mut self, statements: &[parser::Statement]) -> Vec<Statement> {
+        self.scope.push();
+        let mut lowered = vec![];
+        for statement in statements {
+            if self.bind_single(statement) {
+                continue;
+            }
+            lowered.push(self.statement(statement));
+            lowered.append(&mut self.mark_changed(statement));
+        }
+        self.scope.pop();
+        lowered
+    }
+
+    /// Binds a name to a single component if the statement is `x = single Component`.
+    /// Accesses through the name lower to the store, so there's nothing to emit.
+    fn bind_single(&mut self, statement: &parser::Statement) -> bool {
+        if let parser::StatementValue::Assign {
+            target,
+            op: AssignOp::Assign,
+            value,
+        } = &statement.value
+        {
+            if let (ExprValue::Identifier(id), ExprValue::Single { component }) =
+                (&target.value, &value.value)
+            {
+                if self.scope.get(id).is_none() {
+                    self.scope.insert(id, Binding::Single(component.clone()));
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Returns the statements that flag components as changed if the statement writes to them,
+    /// either by assigning to them or by changing one of their lists.
+    fn mark_changed(&self, statement: &parser::Statement) -> Vec<Statement> {
+        let mut targets = vec![];
+        match &statement.value {
+            parser::StatementValue::Assign { target, value, .. } => {
+                targets.push(target);
+                if env::list_change(self.env, &self.scope, value).is_some() {
+                    targets.extend(call_target(value));
+                }
+            }
+            parser::StatementValue::Call(call) => targets.extend(call_target(call)),
+            _ => {}
+        }
+
+        targets
+            .into_iter()
+            .filter_map(|target| {
+                if let Some(component) = env::single_root(&self.scope, target) {
+                    return Some(Statement::MarkChanged {
+                        store: component,
+                        index: None,
+                    });
+                }
+
+                let (entity, component) = env::component_root(&self.scope, target)?;
+                Some(Statement::MarkChanged {
+                    store: component.to_string(),
+                    index: Some(self.index(entity, component)),
+                })
+            })
+            .collect()
+    }
+
+    /// Lowers the values given to each field of a component, in declaration order.
+    fn field_values(&mut self, component: &Reference, args: &[Expr]) -> Vec<FieldValue> {
+        let fields = match self
+            .env
+            .components
+            .get(&component.id)
+            .map(|c| &c.properties)
+        {
+            Some(ComponentProperties::Value(value)) => vec![("value".to_string(), value)],
+            Some(ComponentProperties::Multiple(properties)) => properties
+                .iter()
+                .map(|p| (p.identifier.clone(), &p.ty))
+                .collect(),
+            _ => vec![],
+        };
+
+        fields
+            .into_iter()
+            .zip(args)
+            .map(|((id, ty), arg)| FieldValue {
+                id,
+                value: self.value_as(arg, &Type::Primitive(ty.inner_ty().primitive_type.clone())),
+            })
+            .collect()
+    }
+
+    fn statement(&mut self, statement: &parser::Statement) -> Statement {
+        match &statement.value {
+            parser::StatementValue::Comment(lines) => Statement::Comment(lines.clone()),
+            parser::StatementValue::Query {
+                entity,
+                terms,
+                without,
+                changed,
+                body,
+            } => {
+                // The env ensures there's a required term to iterate
+                let driver = terms.iter().find(|t| !t.optional).unwrap_or(&terms[0]);
+                let driver_index = index_id(&entity.id, &driver.component.id);
+                let owner = Value::Entity {
+                    store: driver.component.id.clone(),
+                    index: driver_index.clone(),
+                };
+
+                let mut indices = HashMap::new();
+                let mut required = vec![];
+                for term in terms.iter() {
+                    let component = &term.component.id;
+                    let index = if component == &driver.component.id {
+                        Value::Local(driver_index.clone())
+                    } else {
+                        let lookup = Value::Index {
+                            store: component.clone(),
+                            store_id: store_struct_id(component),
+                            entity: Box::new(owner.clone()),
+                        };
+
+                        // Optional components are looked up where they're used
+                        if term.optional {
+                            lookup
+                        } else {
+                            let id = index_id(&entity.id, component);
+                            required.push(Lookup {
+                                id: id.clone(),
+                                index: lookup,
+                                capacity: self.capacity(component),
+                            });
+                            Value::Local(id)
+                        }
+                    };
+                    indices.insert(component.clone(), index);
+                }
+
+                let mut skip = vec![];
+                for component in without.iter() {
+                    skip.push(Value::Has {
+                        index: Box::new(Value::Index {
+                            store: component.id.clone(),
+                            store_id: store_struct_id(&component.id),
+                            entity: Box::new(owner.clone()),
+                        }),
+                        capacity: self.capacity(&component.id),
+                    });
+                }
+                for component in changed.iter() {
+                    // The env ensures changed components are required terms
+                    let index = indices
+                        .get(&component.id)
+                        .cloned()
+                        .unwrap_or_else(|| Value::Local(index_id(&entity.id, &component.id)));
+                    skip.push(Value::Unary {
+                        op: UnaryOp::Not,
+                        value: Box::new(Value::Changed {
+                            store: component.id.clone(),
+                            index: Box::new(index),
+                        }),
+                    });
+                }
+
+                self.indices.insert(entity.id.clone(), indices);
+                self.scope.push();
+                self.scope
+                    .insert(&entity.id, Binding::Entity(terms.clone()));
+                let body = self.statements(body);
+                self.scope.pop();
+                self.indices.remove(&entity.id);
+
+                Statement::Query {
+                    store: driver.component.id.clone(),
+                    index: driver_index,
+                    required,
+                    skip,
+                    body,
+                }
+            }
+            parser::StatementValue::Events {
+                event,
+                component,
+                body,
+            } => {
+                let is_tag = self
+                    .env
+                    .components
+                    .get(&component.id)
+                    .is_none_or(|c| matches!(c.properties, ComponentProperties::None));
+
+                self.scope.push();
+                self.scope
+                    .insert(&event.id, Binding::Event(component.id.clone()));
+                let body = self.statements(body);
+                self.scope.pop();
+
+                Statement::Events {
+                    store: component.id.clone(),
+                    store_id: store_struct_id(&component.id),
+                    index: index_id(&event.id, &component.id),
+                    event: (!is_tag).then(|| local_id(&event.id)),
+                    component: component_id(&component.id),
+                    body,
+                }
+            }
+            parser::StatementValue::Iterate { item, list, body } => {
+                let ty = match env::type_of(self.env, &self.scope, list) {
+                    Ok(Type::List(ty, _)) => Type::Primitive(ty),
+                    _ => Type::Primitive(PrimitiveType::I32),
+                };
+                let list = self.value(list);
+
+                self.scope.push();
+                self.scope.insert(&item.id, Binding::Copy(ty.clone()));
+                let body = self.statements(body);
+                self.scope.pop();
+
+                Statement::Iterate {
+                    list,
+                    index: index_id(&item.id, LIST_ITEMS_ID),
+                    item: local_id(&item.id),
+                    ty: primitive_type(&ty),
+                    body,
+                }
+            }
+            parser::StatementValue::Emit { component, args } => Statement::Emit {
+                store: component.id.clone(),
+                store_id: store_struct_id(&component.id),
+                component: component_id(&component.id),
+                fields: self.field_values(component, args),
+            },
+            parser::StatementValue::Create { components } => Statement::Create {
+                entity: struct_id(ENTITY_ID),
+                max_entities: env::MAX_ENTITIES,
+                components: components
+                    .iter()
+                    .map(|c| CreatedComponent {
+                        store: c.component.id.clone(),
+                        store_id: store_struct_id(&c.component.id),
+                        component: component_id(&c.component.id),
+                        fields: self.field_values(&c.component, &c.args),
+                    })
+                    .collect(),
+            },
+            parser::StatementValue::If {
+                condition,
+                then,
+                otherwise,
+            } => Statement::If {
+                condition: self.value(condition),
+                then: self.statements(then),
+                otherwise: self.statements(otherwise),
+            },
+            parser::StatementValue::Match { value, arms, .. } => {
+                let enum_id = match env::type_of(self.env, &self.scope, value) {
+                    Ok(Type::Primitive(PrimitiveType::Identifier(id))) => id,
+                    _ => String::new(),
+                };
+                let enumeration = self.env.enums.get(&enum_id);
+                let lowered = self.value(value);
+
+                let mut otherwise = None;
+                let mut lowered_arms = vec![];
+                for arm in arms {
+                    let variant = match &arm.variant {
+                        Some(variant) => variant,
+                        None => {
+                            otherwise = Some(self.statements(&arm.body));
+                            continue;
+                        }
+                    };
+
+                    // The binding is declared in the arm's body from the variant's field of the payload
+                    self.scope.push();
+                    let payload = enumeration
+                        .and_then(|e| e.variant(&variant.id))
+                        .and_then(|v| v.payload.as_ref());
+                    let mut body = vec![];
+                    if let (Some(binding), Some(payload)) = (&arm.binding, payload) {
+                        let ty = Type::Primitive(payload.inner_ty().primitive_type.clone());
+                        body.push(Statement::Declare {
+                            id: local_id(&binding.id),
+                            ty: primitive_type(&ty),
+                            value: Value::Field {
+                                target: Box::new(Value::Field {
+                                    target: Box::new(lowered.clone()),
+                                    field: ENUM_PAYLOAD_ID.to_string(),
+                                }),
+                                field: variant.id.clone(),
+                            },
+                        });
+                        self.scope.insert(&binding.id, Binding::Copy(ty));
+                    }
+                    body.append(&mut self.statements(&arm.body));
+                    self.scope.pop();
+
+                    lowered_arms.push(MatchArm {
+                        tag: tag_id(&enum_id, &variant.id),
+                        body,
+                    });
+                }
+
+                Statement::Match {
+                    value: tag_of(lowered),
+                    arms: lowered_arms,
+                    otherwise,
+                }
+            }
+            parser::StatementValue::Assign { target, op, value } => {
+                if let ExprValue::Identifier(id) = &target.value {
+                    if *op == AssignOp::Assign && self.scope.get(id).is_none() {
+                        let ty = match env::type_of(self.env, &self.scope, value) {
+                            Ok(ty) => env::local_type(ty),
+                            Err(_) => Type::Primitive(PrimitiveType::I32),
+                        };
+                        let value = self.value_as(value, &ty);
+                        self.scope.insert(id, Binding::Local(ty.clone()));
+
+                        return Statement::Declare {
+                            id: local_id(id),
+                            ty: primitive_type(&ty),
+                            value,
+                        };
+                    }
+                }
+
+                let ty = env::type_of(self.env, &self.scope, target)
+                    .unwrap_or(Type::Primitive(PrimitiveType::I32));
+                Statement::Assign {
+                    target: self.value(target),
+                    op: *op,
+                    value: self.value_as(value, &ty),
+                }
+            }
+            parser::StatementValue::Call(call) => Statement::Call(self.value(call)),
+        }
+    }
+
+    fn value(&self, expr: &Expr) -> Value {
+        let ty = env::type_of(self.env, &self.scope, expr)
+            .unwrap_or(Type::Primitive(PrimitiveType::I32));
+        self.value_as(expr, &ty)
+    }
+
+    /// Lowers the expression given the type it's used as, which number literals take on.
+    fn value_as(&self, expr: &Expr, ty: &Type) -> Value {
+        match &expr.value {
+            ExprValue::Number(n) => Value::Number {
+                value: n.clone(),
+                ty: primitive_type(&env::local_type(ty.clone())),
+            },
+            ExprValue::Bool(b) => Value::Bool(*b),
+            ExprValue::Identifier(id) => match self.scope.get(id) {
+                Some(Binding::Entity(terms)) => {
+                    let driver = terms.iter().find(|t| !t.optional).unwrap_or(&terms[0]);
+                    Value::Entity {
+                        store: driver.component.id.clone(),
+                        index: index_id(id, &driver.component.id),
+                    }
+                }
+                Some(Binding::Single(_)) => self.single(expr),
+                Some(Binding::Event(component)) => {
+                    let local = Value::Local(local_id(id));
+                    let is_value = self
+                        .env
+                        .components
+                        .get(component)
+                        .is_some_and(|c| matches!(c.properties, ComponentProperties::Value(_)));
+                    if is_value {
+                        id_of(local)
+                    } else {
+                        local
+                    }
+                }
+                _ => Value::Local(local_id(id)),
+            },
+            ExprValue::Single { .. } => self.single(expr),
+            ExprValue::Field { .. } if env::enum_variant(self.env, &self.scope, expr).is_some() => {
+                let (enum_id, variant) =
+                    env::enum_variant(self.env, &self.scope, expr).unwrap_or_default();
+                Value::Variant {
+                    id: struct_id(enum_id),
+                    tag: tag_id(enum_id, variant),
+                    payload: None,
+                }
+            }
+            ExprValue::Call { args, .. }
+                if env::enum_variant(self.env, &self.scope, expr).is_some() =>
+            {
+                let (enum_id, variant) =
+                    env::enum_variant(self.env, &self.scope, expr).unwrap_or_default();
+                Value::Variant {
+                    id: struct_id(enum_id),
+                    tag: tag_id(enum_id, variant),
+                    payload: args
+                        .iter()
+                        .zip(env::param_types(self.env, &self.scope, expr))
+                        .next()
+                        .map(|(value, ty)| {
+                            (variant.to_string(), Box::new(self.value_as(value, &ty)))
+                        }),
+                }
+            }
+            ExprValue::Call {
+                target,
+                method,
+                args,
+            } => {
+                let list_id = match env::type_of(self.env, &self.scope, target) {
+                    Ok(Type::List(ty, max_size)) => list_id(&ty, max_size),
+                    _ => String::new(),
+                };
+                Value::Method {
+                    list_id,
+                    // The env ensures only list methods are called
+                    method: ListMethod::from_id(method).unwrap_or(ListMethod::Len),
+                    list: Box::new(self.value(target)),
+                    args: args
+                        .iter()
+                        .zip(env::param_types(self.env, &self.scope, expr))
+                        .map(|(arg, ty)| self.value_as(arg, &ty))
+                        .collect(),
+                }
+            }
+            ExprValue::Field { .. } if env::single_root(&self.scope, expr).is_some() => {
+                self.single(expr)
+            }
+            ExprValue::Field { target, field } => match env::component_root(&self.scope, expr) {
+                Some((entity, component)) => self.component(entity, component, expr),
+                None => Value::Field {
+                    target: Box::new(self.value(target)),
+                    field: field.clone(),
+                },
+            },
+            ExprValue::Has { entity, component } => Value::Has {
+                index: Box::new(self.index(entity, component)),
+                capacity: self.capacity(component),
+            },
+            ExprValue::Unary { op, expr } => Value::Unary {
+                op: *op,
+                value: Box::new(self.value_as(expr, ty)),
+            },
+            ExprValue::Binary {
+                op: BinaryOp::Coalesce,
+                lhs,
+                rhs,
+            } => {
+                let (entity, component) = env::component_root(&self.scope, lhs).unwrap_or_default();
+                let operand =
+                    env::operand_type(self.env, &self.scope, BinaryOp::Coalesce, lhs, rhs, ty);
+                Value::Coalesce {
+                    index: Box::new(self.index(entity, component)),
+                    capacity: self.capacity(component),
+                    value: Box::new(self.value_as(lhs, &operand)),
+                    default: Box::new(self.value_as(rhs, &operand)),
+                }
+            }
+            ExprValue::Binary { op, lhs, rhs } => {
+                let operand = env::operand_type(self.env, &self.scope, *op, lhs, rhs, ty);
+                let (is_entity, is_enum) = match &operand {
+                    Type::Primitive(PrimitiveType::Identifier(id)) => {
+                        (id == env::ENTITY_ID, self.env.enums.contains_key(id))
+                    }
+                    _ => (false, false),
+                };
+
+                let mut lhs = self.value_as(lhs, &operand);
+                let mut rhs = self.value_as(rhs, &operand);
+
+                // Entities are compared by their ids and enums by their tags
+                if is_entity {
+                    lhs = id_of(lhs);
+                    rhs = id_of(rhs);
+                } else if is_enum {
+                    lhs = tag_of(lhs);
+                    rhs = tag_of(rhs);
+                }
+
+                Value::Binary {
+                    op: *op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                }
+            }
+            ExprValue::Group(expr) => Value::Group(Box::new(self.value_as(expr, ty))),
+        }
+    }
+
+    /// Lowers an access of a query entity's component, along with any fields accessed on it.
+    fn component(&self, entity: &str, component: &str, expr: &Expr) -> Value {
+        let mut fields = vec![];
+        let mut current = expr;
+        while let ExprValue::Field { target, field } = &current.value {
+            fields.push(field.clone());
+            current = target;
+        }
+        fields.reverse();
+        // The first field is the component itself
+        fields.remove(0);
+
+        let index = Box::new(self.index(entity, component));
+        let definition = match self.env.components.get(component) {
+            Some(definition) => definition,
+            None => return Value::Local(local_id(component)),
+        };
+
+        if let ComponentProperties::Value(_) = definition.properties {
+            fields.insert(0, "value".to_string());
+        }
+
+        if !env::is_soa(definition) {
+            return Value::Component {
+                store: component.to_string(),
+                array: env::STORE_COMPONENTS_ID.to_string(),
+                index,
+                fields,
+            };
+        }
+
+        if fields.is_empty() {
+            return Value::Gather {
+                store: component.to_string(),
+                store_id: store_struct_id(component),
+                index,
+            };
+        }
+
+        let field = fields.remove(0);
+        Value::Component {
+            store: component.to_string(),
+            array: env::store_array_id(definition, &field),
+            index,
+            fields,
+        }
+    }
+
+    /// Lowers an access of a single component, along with any fields accessed on it.
+    fn single(&self, expr: &Expr) -> Value {
+        let mut fields = vec![];
+        let mut current = expr;
+        loop {
+            match &current.value {
+                ExprValue::Field { target, field } => {
+                    fields.push(field.clone());
+                    current = target;
+                }
+                ExprValue::Group(target) => current = target,
+                _ => break,
+            }
+        }
+        fields.reverse();
+
+        let component = env::single_root(&self.scope, expr).unwrap_or_default();
+        let is_value = self
+            .env
+            .components
+            .get(&component)
+            .is_some_and(|c| matches!(c.properties, ComponentProperties::Value(_)));
+        if is_value {
+            fields.insert(0, "value".to_string());
+        }
+
+        Value::Single {
+            store: component,
+            fields,
+        }
+    }
+
+    fn index(&self, entity: &str, component: &str) -> Value {
+        self.indices
+            .get(entity)
+            .and_then(|indices| indices.get(component))
+            .cloned()
+            .unwrap_or_else(|| Value::Local(index_id(entity, component)))
+    }
+
+    fn capacity(&self, component: &str) -> usize {
+        self.env
+            .components
+            .get(component)
+            .map(env::store_capacity)
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the list a method is called on.
+fn call_target(call: &Expr) -> Option<&Expr> {
+    match &call.value {
+        ExprValue::Call { target, .. } => Some(target),
+        _ => None,
+    }
+}
+
+/// Returns the id of an entity value.
+fn id_of(value: Value) -> Value {
+    Value::Field {
+        target: Box::new(value),
+        field: "value".to_string(),
+    }
+}
+
+/// Returns the tag of an enum value.
+fn tag_of(value: Value) -> Value {
+    Value::Field {
+        target: Box::new(value),
+        field: ENUM_TAG_ID.to_string(),
+    }
+}
+
+fn store_struct_id(component: &str) -> String {
+    struct_id(&env::store_id(component))
+}
+
+/// Returns the type of a local, using the ids of the emitted structs.
+fn primitive_type(ty: &Type) -> PrimitiveType {
+    match ty {
+        Type::Primitive(PrimitiveType::Identifier(id)) => PrimitiveType::Identifier(struct_id(id)),
+        Type::Primitive(ty) => ty.clone(),
+        Type::Component(id) => PrimitiveType::Identifier(component_id(id)),
+        Type::Literal { fractional: true } => PrimitiveType::F32,
+        Type::Literal { fractional: false } | Type::List(..) => PrimitiveType::I32,
+    }
+}
@@ -0,0 +1,491 @@
+use super::{struct_id, system_id};
+use crate::{
+    compiler_ir::{
+        fnv1a_64, Commentable, Count, Job, PathSegment, SaveFormat, SaveOp, Struct, StructField,
+        World, ENUM_PAYLOAD_ID, ENUM_TAG_ID, IR, LIST_ITEMS_ID, LIST_LENGTH_ID,
+        WORLD_NEXT_ENTITY_ID,
+    },
+    env::{self, Env, ENTITY_ID, STORE_ACTIVE_ID, STORE_ENTITIES_ID, STORE_INSTANCE_ID},
+    location::Location,
+    parser::{
+        Component, ComponentProperties, ListType, Primitive, PrimitiveType, StructProperties,
+    },
+};
+
+/// The id of the world struct.
+const WORLD_ID: &str = "D_WORLD";
+
+/// The name of the world in reflection data.
+const WORLD_NAME: &str = "World";
+
+/// The number of frames that can be rolled back by default.
+const SNAPSHOT_SLOTS: usize = 8;
+
+/// Builds the world struct, which has a field with the store of each component, and its save format.
+pub fn build_world(env: &Env, ir: &mut Vec<IR>) {
+    let mut components: Vec<&Component> = env.components.values().collect();
+    components.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut fields = vec![StructField {
+        id: WORLD_NEXT_ENTITY_ID.to_string(),
+        comments: vec![
+            "The id given to the next created entity.".to_string(),
+            format!(
+                "Ids aren't reused, and 'create' does nothing once it reaches {}.",
+                env::MAX_ENTITIES
+            ),
+        ],
+        ty: ListType::Single(primitive(PrimitiveType::U64)),
+    }];
+
+    for component in components.iter() {
+        fields.push(StructField {
+            id: component.id.clone(),
+            comments: vec![],
+            ty: ListType::Single(primitive(PrimitiveType::Identifier(struct_id(
+                &env::store_id(&component.id),
+            )))),
+        });
+    }
+
+    ir.push(IR::Struct(Commentable {
+        comments: vec!["All component stores along with the entities that own them.".to_string()],
+        value: Struct {
+            id: WORLD_ID.to_string(),
+            name: WORLD_NAME.to_string(),
+            fields,
+            ordered: false,
+            union: false,
+            variants: vec![],
+            list: None,
+        },
+    }));
+
+    let mut builder = SaveFormatBuilder {
+        env,
+        schema: String::new(),
+        depth: 0,
+    };
+
+    let mut ops = vec![];
+    builder.primitive(
+        &PrimitiveType::U64,
+        vec![field(WORLD_NEXT_ENTITY_ID)],
+        WORLD_NEXT_ENTITY_ID,
+        &mut ops,
+    );
+    for component in components {
+        builder.store(component, &mut ops);
+    }
+
+    ir.push(IR::World(World {
+        id: WORLD_ID.to_string(),
+        save_format: SaveFormat {
+            schema_hash: fnv1a_64(builder.schema.as_bytes()),
+            ops,
+        },
+        snapshot_slots: SNAPSHOT_SLOTS,
+        stages: env::schedule(env)
+            .into_iter()
+            .map(|stage| {
+                stage
+                    .into_iter()
+                    .map(|name| Job {
+                        system: system_id(name),
+                        name: name.to_string(),
+                    })
+                    .collect()
+            })
+            .collect(),
+    }));
+}
+
+fn primitive(primitive_type: PrimitiveType) -> Primitive {
+    Primitive {
+        primitive_type,
+        start_location: Location::SystemDefined,
+        end_location: Location::SystemDefined,
+    }
+}
+
+fn field(id: &str) -> PathSegment {
+    PathSegment::Field(id.to_string())
+}
+
+/// Returns the id and type of each field of the component.
+fn component_fields(component: &Component) -> Vec<(&str, &ListType<Primitive>)> {
+    match &component.properties {
+        ComponentProperties::None => vec![],
+        ComponentProperties::Value(value) => vec![("value", &value.ty)],
+        ComponentProperties::Multiple(properties) => properties
+            .iter()
+            .map(|p| (p.identifier.as_str(), &p.ty.ty))
+            .collect(),
+    }
+}
+
+/// Walks the stores in declaration order, building the save operations along with a description
+/// of the schema. The schema only depends on the saved types so it doesn't change with the storage.
+struct SaveFormatBuilder<'a> {
+    env: &'a Env,
+    schema: String,
+    depth: usize,
+}
+
+impl<'a> SaveFormatBuilder<'a> {
+    /// Saves the active entities of the store followed by their components.
+    /// Single component stores only save their instance, and event queues aren't saved
+    /// as they're emptied at the end of every tick.
+    fn store(&mut self, component: &Component, ops: &mut Vec<SaveOp>) {
+        if env::is_event(component) {
+            return;
+        }
+
+        let store = field(&component.id);
+        if env::is_single(component) {
+            self.schema.push_str(&format!("{}{{", component.id));
+            for (id, ty) in component_fields(component) {
+                let path = vec![store.clone(), field(STORE_INSTANCE_ID), field(id)];
+                self.list(ty, path, id, ops);
+            }
+            self.schema.push('}');
+            return;
+        }
+
+        let capacity = env::store_capacity(component);
+        self.schema
+            .push_str(&format!("{}<{}>{{", component.id, capacity));
+
+        let index = self.depth;
+        self.depth += 1;
+
+        let mut store_ops = vec![];
+        self.strukt(
+            ENTITY_ID,
+            vec![
+                store.clone(),
+                field(STORE_ENTITIES_ID),
+                PathSegment::Index(index),
+            ],
+            STORE_ENTITIES_ID,
+            &mut store_ops,
+        );
+
+        for (id, ty) in component_fields(component) {
+            let array = env::store_array_id(component, id);
+            let path = if env::is_soa(component) {
+                vec![store.clone(), field(&array), PathSegment::Index(index)]
+            } else {
+                vec![
+                    store.clone(),
+                    field(&array),
+                    PathSegment::Index(index),
+                    field(id),
+                ]
+            };
+
+            self.list(ty, path, id, &mut store_ops);
+        }
+
+        self.depth -= 1;
+        self.schema.push('}');
+
+        ops.push(SaveOp::Repeat {
+            index,
+            count: Count {
+                path: vec![store, field(STORE_ACTIVE_ID)],
+                ty: PrimitiveType::U64,
+                max: capacity,
+            },
+            ops: store_ops,
+        });
+    }
+
+    fn strukt(&mut self, id: &str, path: Vec<PathSegment>, name: &str, ops: &mut Vec<SaveOp>) {
+        if self.env.enums.contains_key(id) {
+            return self.enumeration(id, path, name, ops);
+        }
+
+        let strukt = match self.env.structs.get(id) {
+            Some(strukt) => strukt.strukt(),
+            None => return,
+        };
+
+        self.schema.push_str(&format!("{}:{}{{", name, id));
+        match &strukt.properties {
+            StructProperties::None => {}
+            StructProperties::Value(value) => {
+                let mut path = path;
+                path.push(field("value"));
+                self.list(&value.ty, path, "value", ops);
+            }
+            StructProperties::Multiple(properties) => {
+                for property in properties {
+                    let mut path = path.clone();
+                    path.push(field(&property.identifier));
+                    self.list(&property.ty.ty, path, &property.identifier, ops);
+                }
+            }
+        }
+        self.schema.push('}');
+    }
+
+    /// Saves the tag of the enum followed by the value of its variant, if it holds one.
+    fn enumeration(&mut self, id: &str, path: Vec<PathSegment>, name: &str, ops: &mut Vec<SaveOp>) {
+        let e = match self.env.enums.get(id) {
+            Some(e) => e,
+            None => return,
+        };
+
+        self.schema.push_str(&format!("{}:{}<", name, id));
+        let mut tag_path = path.clone();
+        tag_path.push(field(ENUM_TAG_ID));
+        ops.push(SaveOp::Primitive {
+            path: tag_path.clone(),
+            ty: PrimitiveType::U32,
+        });
+
+        let mut variants = vec![];
+        for variant in e.variants.iter() {
+            self.schema.push_str(&format!("{}(", variant.id));
+            let mut variant_ops = vec![];
+            if let Some(payload) = &variant.payload {
+                let mut path = path.clone();
+                path.push(field(ENUM_PAYLOAD_ID));
+                path.push(field(&variant.id));
+                self.list(&payload.ty, path, &variant.id, &mut variant_ops);
+            }
+            self.schema.push_str(");");
+            variants.push(variant_ops);
+        }
+        self.schema.push('>');
+
+        ops.push(SaveOp::Match {
+            path: tag_path,
+            variants,
+        });
+    }
+
+    /// Saves the length of a list followed by the items in use.
+    fn list(
+        &mut self,
+        ty: &ListType<Primitive>,
+        path: Vec<PathSegment>,
+        name: &str,
+        ops: &mut Vec<SaveOp>,
+    ) {
+        match ty {
+            ListType::Single(ty) => self.primitive(&ty.primitive_type, path, name, ops),
+            ListType::List { ty, max_size } => {
+                let index = self.depth;
+                self.depth += 1;
+                self.schema.push_str(&format!("[{}", max_size));
+
+                let mut length = path.clone();
+                length.push(field(LIST_LENGTH_ID));
+                let mut items = path;
+                items.push(field(LIST_ITEMS_ID));
+                items.push(PathSegment::Index(index));
+                let mut list_ops = vec![];
+                self.primitive(&ty.primitive_type, items, name, &mut list_ops);
+
+                self.depth -= 1;
+                self.schema.push(']');
+
+                ops.push(SaveOp::Repeat {
+                    index,
+                    count: Count {
+                        path: length,
+                        ty: PrimitiveType::U32,
+                        max: *max_size,
+                    },
+                    ops: list_ops,
+                });
+            }
+        }
+    }
+
+    fn primitive(
+        &mut self,
+        ty: &PrimitiveType,
+        path: Vec<PathSegment>,
+        name: &str,
+        ops: &mut Vec<SaveOp>,
+    ) {
+        match ty {
+            PrimitiveType::Identifier(id) => self.strukt(id, path, name, ops),
+            ty => {
+                self.schema.push_str(&format!("{}:{};", name, ty));
+                ops.push(SaveOp::Primitive {
+                    path,
+                    ty: ty.clone(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser, unchecked_env};
+
+    fn world(code: &str) -> World {
+        let ast = parser::parse(code, Location::default()).unwrap();
+        let env = env::build(unchecked_env::build(ast)).unwrap();
+        let mut ir = vec![];
+        build_world(&env, &mut ir);
+
+        ir.into_iter()
+            .find_map(|ir| match ir {
+                IR::World(world) => Some(world),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn path(segments: &[&str]) -> Vec<PathSegment> {
+        segments
+            .iter()
+            .map(|s| match s.parse::<usize>() {
+                Ok(index) => PathSegment::Index(index),
+                Err(_) => field(s),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn save_format_walks_stores_in_declaration_order() {
+        let world = world("component Hp(i32);\ncomponent Tag;");
+        let ops = world.save_format.ops;
+
+        let entity = |store: &str| SaveOp::Primitive {
+            path: path(&[store, "entities", "0", "value"]),
+            ty: PrimitiveType::U64,
+        };
+        let expected = vec![
+            SaveOp::Primitive {
+                path: path(&["next_entity"]),
+                ty: PrimitiveType::U64,
+            },
+            SaveOp::Repeat {
+                index: 0,
+                count: Count {
+                    path: path(&["Hp", "active_components"]),
+                    ty: PrimitiveType::U64,
+                    max: env::MAX_COMPONENTS,
+                },
+                ops: vec![
+                    entity("Hp"),
+                    SaveOp::Primitive {
+                        path: path(&["Hp", "components", "0", "value"]),
+                        ty: PrimitiveType::I32,
+                    },
+                ],
+            },
+            SaveOp::Repeat {
+                index: 0,
+                count: Count {
+                    path: path(&["Tag", "active_components"]),
+                    ty: PrimitiveType::U64,
+                    max: env::MAX_COMPONENTS,
+                },
+                ops: vec![entity("Tag")],
+            },
+        ];
+
+        assert_eq!(expected, ops);
+    }
+
+    #[test]
+    fn save_format_nests_structs_and_lists() {
+        let world = world("struct Name { [char 4] name }\ncomponent Person soa { Name name }");
+
+        match &world.save_format.ops[1] {
+            SaveOp::Repeat { ops, .. } => assert_eq!(
+                SaveOp::Repeat {
+                    index: 1,
+                    count: Count {
+                        path: path(&["Person", "components_name", "0", "name", "length"]),
+                        ty: PrimitiveType::U32,
+                        max: 4,
+                    },
+                    ops: vec![SaveOp::Primitive {
+                        path: path(&["Person", "components_name", "0", "name", "items", "1"]),
+                        ty: PrimitiveType::Char,
+                    }],
+                },
+                ops[1]
+            ),
+            op => panic!("Expected repeat, got {:?}", op),
+        }
+    }
+
+    #[test]
+    fn save_format_saves_single_component_instances() {
+        let world = world("single_component Settings { u32 volume [char 2] lang }");
+
+        let expected = vec![
+            SaveOp::Primitive {
+                path: path(&["next_entity"]),
+                ty: PrimitiveType::U64,
+            },
+            SaveOp::Primitive {
+                path: path(&["Settings", "instance", "volume"]),
+                ty: PrimitiveType::U32,
+            },
+            SaveOp::Repeat {
+                index: 0,
+                count: Count {
+                    path: path(&["Settings", "instance", "lang", "length"]),
+                    ty: PrimitiveType::U32,
+                    max: 2,
+                },
+                ops: vec![SaveOp::Primitive {
+                    path: path(&["Settings", "instance", "lang", "items", "0"]),
+                    ty: PrimitiveType::Char,
+                }],
+            },
+        ];
+
+        assert_eq!(expected, world.save_format.ops);
+    }
+
+    #[test]
+    fn save_format_saves_enum_tags_and_variant_values() {
+        let world = world("enum Status { Ok, Damaged(i32) }\nsingle_component State(Status);");
+
+        let expected = vec![
+            SaveOp::Primitive {
+                path: path(&["next_entity"]),
+                ty: PrimitiveType::U64,
+            },
+            SaveOp::Primitive {
+                path: path(&["State", "instance", "value", "tag"]),
+                ty: PrimitiveType::U32,
+            },
+            SaveOp::Match {
+                path: path(&["State", "instance", "value", "tag"]),
+                variants: vec![
+                    vec![],
+                    vec![SaveOp::Primitive {
+                        path: path(&["State", "instance", "value", "payload", "Damaged"]),
+                        ty: PrimitiveType::I32,
+                    }],
+                ],
+            },
+        ];
+
+        assert_eq!(expected, world.save_format.ops);
+    }
+
+    #[test]
+    fn schema_hash_ignores_storage() {
+        let aos = world("component Position { i32 x i32 y }");
+        let soa = world("component Position soa { i32 x i32 y }");
+        let changed = world("component Position { i32 x i64 y }");
+
+        assert_eq!(aos.save_format.schema_hash, soa.save_format.schema_hash);
+        assert_ne!(aos.save_format.schema_hash, changed.save_format.schema_hash);
+    }
+}
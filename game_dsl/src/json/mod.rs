@@ -0,0 +1,389 @@
+#[cfg(test)]
+mod tests;
+
+/// A JSON value. Object keys keep their insertion order so output is stable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Creates an object from the given key value pairs.
+    pub fn object(pairs: Vec<(&str, Json)>) -> Self {
+        Json::Object(
+            pairs
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// Returns the value for the given key if this is an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(pairs) => pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Walks the given keys, returning the nested value if it exists.
+    pub fn path(&self, keys: &[&str]) -> Option<&Json> {
+        let mut value = self;
+        for key in keys {
+            value = value.get(key)?;
+        }
+        Some(value)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|n| *n >= 0.0 && n.fract() == 0.0)
+            .map(|n| n as usize)
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Serializes the value without any whitespace.
+    pub fn to_compact_string(&self) -> String {
+        let mut output = String::new();
+        self.write(&mut output, None, 0);
+        output
+    }
+
+    /// Serializes the value with each nested element on its own line.
+    pub fn to_pretty_string(&self) -> String {
+        let mut output = String::new();
+        self.write(&mut output, Some("  "), 0);
+        output
+    }
+
+    fn write(&self, output: &mut String, indent: Option<&str>, depth: usize) {
+        let newline = |output: &mut String, depth: usize| {
+            if let Some(indent) = indent {
+                output.push('\n');
+                output.push_str(&indent.repeat(depth));
+            }
+        };
+
+        match self {
+            Json::Null => output.push_str("null"),
+            Json::Bool(b) => output.push_str(if *b { "true" } else { "false" }),
+            Json::Number(n) => output.push_str(&number_to_string(*n)),
+            Json::String(s) => write_string(s, output),
+            Json::Array(values) => {
+                output.push('[');
+                for (idx, value) in values.iter().enumerate() {
+                    if idx != 0 {
+                        output.push(',');
+                    }
+                    newline(output, depth + 1);
+                    value.write(output, indent, depth + 1);
+                }
+                if !values.is_empty() {
+                    newline(output, depth);
+                }
+                output.push(']');
+            }
+            Json::Object(pairs) => {
+                output.push('{');
+                for (idx, (key, value)) in pairs.iter().enumerate() {
+                    if idx != 0 {
+                        output.push(',');
+                    }
+                    newline(output, depth + 1);
+                    write_string(key, output);
+                    output.push(':');
+                    if indent.is_some() {
+                        output.push(' ');
+                    }
+                    value.write(output, indent, depth + 1);
+                }
+                if !pairs.is_empty() {
+                    newline(output, depth);
+                }
+                output.push('}');
+            }
+        }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Self {
+        Json::Number(value)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(value: Vec<Json>) -> Self {
+        Json::Array(value)
+    }
+}
+
+fn number_to_string(n: f64) -> String {
+    if !n.is_finite() {
+        "null".to_string()
+    } else if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        format!("{}", n)
+    }
+}
+
+fn write_string(s: &str, output: &mut String) {
+    output.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+/// Parses the given text into a JSON value.
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        index: 0,
+    };
+
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.index != parser.chars.len() {
+        return Err(format!(
+            "Unexpected trailing characters at {}",
+            parser.index
+        ));
+    }
+
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn pop(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.index += 1;
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.index += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn pop_expected(&mut self, expected: char) -> Result<(), String> {
+        match self.pop() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!(
+                "Expected '{}', got '{}' at {}",
+                expected,
+                c,
+                self.index - 1
+            )),
+            None => Err(format!("Expected '{}', got nothing!", expected)),
+        }
+    }
+
+    fn pop_keyword(&mut self, keyword: &str, value: Json) -> Result<Json, String> {
+        for expected in keyword.chars() {
+            self.pop_expected(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('n') => self.pop_keyword("null", Json::Null),
+            Some('t') => self.pop_keyword("true", Json::Bool(true)),
+            Some('f') => self.pop_keyword("false", Json::Bool(false)),
+            Some('"') => self.parse_string().map(Json::String),
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_object(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            Some(c) => Err(format!("Unexpected character '{}' at {}", c, self.index)),
+            None => Err("Expected value, got nothing!".to_string()),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let start = self.index;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E') {
+                self.index += 1;
+            } else {
+                break;
+            }
+        }
+
+        let s: String = self.chars[start..self.index].iter().collect();
+        s.parse::<f64>()
+            .map(Json::Number)
+            .map_err(|_| format!("Invalid number '{}' at {}", s, start))
+    }
+
+    fn parse_hex(&mut self) -> Result<u32, String> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = self
+                .pop()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| format!("Invalid unicode escape at {}", self.index))?;
+            value = value * 16 + digit;
+        }
+        Ok(value)
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.pop_expected('"')?;
+
+        let mut s = String::new();
+        loop {
+            match self.pop() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.pop() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => {
+                        let mut code = self.parse_hex()?;
+                        // Surrogate pairs
+                        if (0xD800..0xDC00).contains(&code) {
+                            self.pop_expected('\\')?;
+                            self.pop_expected('u')?;
+                            let low = self.parse_hex()?;
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00));
+                        }
+                        s.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                    }
+                    Some(c) => return Err(format!("Invalid escape '\\{}' at {}", c, self.index)),
+                    None => return Err("Unterminated string".to_string()),
+                },
+                Some(c) => s.push(c),
+                None => return Err("Unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Json, String> {
+        self.pop_expected('[')?;
+
+        let mut values = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pop();
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.pop() {
+                Some(',') => {}
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err(format!("Expected ',' or ']' at {}", self.index)),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, String> {
+        self.pop_expected('{')?;
+
+        let mut pairs = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pop();
+            return Ok(Json::Object(pairs));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.pop_expected(':')?;
+            let value = self.parse_value()?;
+            pairs.push((key, value));
+
+            self.skip_whitespace();
+            match self.pop() {
+                Some(',') => {}
+                Some('}') => return Ok(Json::Object(pairs)),
+                _ => return Err(format!("Expected ',' or '}}' at {}", self.index)),
+            }
+        }
+    }
+}
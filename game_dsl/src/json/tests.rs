@@ -0,0 +1,72 @@
+use super::*;
+
+#[test]
+fn parse_primitives() {
+    assert_eq!(Ok(Json::Null), parse("null"));
+    assert_eq!(Ok(Json::Bool(true)), parse("true"));
+    assert_eq!(Ok(Json::Bool(false)), parse(" false "));
+    assert_eq!(Ok(Json::Number(-12.5)), parse("-12.5"));
+    assert_eq!(Ok(Json::Number(1e3)), parse("1e3"));
+}
+
+#[test]
+fn parse_string_escapes() {
+    let result = parse(r#""a\"b\\c\nA😀""#);
+    assert_eq!(Ok(Json::String("a\"b\\c\nA\u{1F600}".to_string())), result);
+}
+
+#[test]
+fn parse_nested() {
+    let result = parse(r#"{"id": 1, "params": {"list": [1, "two", null]}}"#).unwrap();
+
+    assert_eq!(Some(1), result.get("id").and_then(|id| id.as_usize()));
+    assert_eq!(
+        Some(&vec![Json::Number(1.0), "two".into(), Json::Null]),
+        result.path(&["params", "list"]).and_then(|l| l.as_array())
+    );
+}
+
+#[test]
+fn parse_trailing_characters_returns_err() {
+    assert!(parse("{} a").is_err());
+}
+
+#[test]
+fn parse_unterminated_returns_err() {
+    assert!(parse(r#"{"a": [1, 2"#).is_err());
+    assert!(parse(r#""abc"#).is_err());
+}
+
+#[test]
+fn to_compact_string() {
+    let json = Json::object(vec![
+        ("a", 1usize.into()),
+        ("b", Json::Array(vec![true.into(), Json::Null])),
+        ("c", "quote\"".into()),
+        ("d", 0.5.into()),
+    ]);
+
+    assert_eq!(
+        r#"{"a":1,"b":[true,null],"c":"quote\"","d":0.5}"#,
+        json.to_compact_string()
+    );
+}
+
+#[test]
+fn to_pretty_string() {
+    let json = Json::object(vec![
+        ("a", Json::Array(vec![1usize.into()])),
+        ("b", Json::Array(vec![])),
+    ]);
+
+    assert_eq!(
+        "{\n  \"a\": [\n    1\n  ],\n  \"b\": []\n}",
+        json.to_pretty_string()
+    );
+}
+
+#[test]
+fn round_trip() {
+    let text = r#"{"jsonrpc":"2.0","id":3,"result":{"items":[{"label":"Position","kind":22}]}}"#;
+    assert_eq!(text, parse(text).unwrap().to_compact_string());
+}
@@ -0,0 +1,388 @@
+mod report;
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    compiler_ir::{Struct, IR},
+    parser::{ListType, Primitive, PrimitiveType},
+};
+pub use report::*;
+
+/// The memory footprint of a type, following C layout rules.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub size: usize,
+    pub alignment: usize,
+}
+
+impl Layout {
+    pub fn new(size: usize, alignment: usize) -> Self {
+        Self { size, alignment }
+    }
+}
+
+/// The placement of a field within its struct.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldLayout {
+    pub id: String,
+    pub offset: usize,
+    pub layout: Layout,
+}
+
+/// Layouts for all structs in the IR.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layouts {
+    structs: HashMap<String, Layout>,
+    fields: HashMap<String, Vec<FieldLayout>>,
+    unions: HashSet<String>,
+}
+
+impl Layouts {
+    /// Calculates the layout for every struct in the IR.
+    /// Structs that recursively contain themselves have no layout.
+    pub fn new(ir: &[IR]) -> Self {
+        let mut structs = HashMap::new();
+        let mut calculator = Calculator {
+            structs: ir
+                .iter()
+                .filter_map(|ir| match ir {
+                    IR::Struct(s) => Some((s.value.id.clone(), &s.value)),
+                    _ => None,
+                })
+                .collect(),
+            visiting: vec![],
+            fields: HashMap::new(),
+        };
+
+        for id in calculator.structs.keys().cloned().collect::<Vec<_>>() {
+            if let Some(layout) = calculator.strukt(&id, &mut structs) {
+                structs.insert(id, layout);
+            }
+        }
+
+        let unions = calculator
+            .structs
+            .values()
+            .filter(|s| s.union)
+            .map(|s| s.id.clone())
+            .collect();
+
+        Self {
+            structs,
+            fields: calculator.fields,
+            unions,
+        }
+    }
+
+    /// Returns the layout of the struct with the given IR id.
+    pub fn get(&self, id: &str) -> Option<Layout> {
+        self.structs.get(id).copied()
+    }
+
+    /// Returns the fields of the struct with the given IR id, in declaration order.
+    pub fn fields(&self, id: &str) -> Option<&[FieldLayout]> {
+        self.fields.get(id).map(|f| f.as_slice())
+    }
+
+    /// Returns the number of bytes of padding in the struct with the given IR id.
+    /// Unions only count the space past their largest field.
+    pub fn padding(&self, id: &str) -> Option<usize> {
+        let fields = self.fields(id)?.iter();
+        let used: usize = if self.unions.contains(id) {
+            fields.map(|f| f.layout.size).max().unwrap_or_default()
+        } else {
+            fields.map(|f| f.layout.size).sum()
+        };
+        Some(self.get(id)?.size - used)
+    }
+
+    /// Returns the layout of the given primitive.
+    pub fn primitive(&self, primitive: &PrimitiveType) -> Option<Layout> {
+        match primitive {
+            PrimitiveType::Identifier(id) => self.get(id),
+            primitive => Some(primitive_layout(primitive)),
+        }
+    }
+
+    /// Returns the layout of a single value or a statically allocated list.
+    pub fn list(&self, ty: &ListType<Primitive>) -> Option<Layout> {
+        match ty {
+            ListType::Single(ty) => self.primitive(&ty.primitive_type),
+            ListType::List { ty, max_size } => {
+                let element = self.primitive(&ty.primitive_type)?;
+                Some(Layout::new(element.size * max_size, element.alignment))
+            }
+        }
+    }
+}
+
+struct Calculator<'a> {
+    structs: HashMap<String, &'a Struct>,
+    visiting: Vec<String>,
+    fields: HashMap<String, Vec<FieldLayout>>,
+}
+
+impl<'a> Calculator<'a> {
+    fn strukt(&mut self, id: &str, cache: &mut HashMap<String, Layout>) -> Option<Layout> {
+        if let Some(layout) = cache.get(id) {
+            return Some(*layout);
+        }
+
+        if self.visiting.iter().any(|v| v == id) {
+            return None;
+        }
+
+        let strukt = *self.structs.get(id)?;
+        self.visiting.push(id.to_string());
+
+        let mut size = 0;
+        let mut alignment = 1;
+        let mut fields = vec![];
+        let mut result = Some(());
+        for field in strukt.fields.iter() {
+            let field_layout = match self.list(&field.ty, cache) {
+                Some(layout) => layout,
+                None => {
+                    result = None;
+                    break;
+                }
+            };
+
+            // The fields of a union all start at the beginning
+            let offset = if strukt.union {
+                0
+            } else {
+                align_to(size, field_layout.alignment)
+            };
+            fields.push(FieldLayout {
+                id: field.id.clone(),
+                offset,
+                layout: field_layout,
+            });
+
+            size = size.max(offset + field_layout.size);
+            alignment = alignment.max(field_layout.alignment);
+        }
+
+        self.visiting.pop();
+        result?;
+
+        let layout = Layout::new(align_to(size, alignment), alignment);
+        cache.insert(id.to_string(), layout);
+        self.fields.insert(id.to_string(), fields);
+        Some(layout)
+    }
+
+    fn list(
+        &mut self,
+        ty: &ListType<Primitive>,
+        cache: &mut HashMap<String, Layout>,
+    ) -> Option<Layout> {
+        let (primitive, count) = match ty {
+            ListType::Single(ty) => (ty, 1),
+            ListType::List { ty, max_size } => (ty, *max_size),
+        };
+
+        let element = match &primitive.primitive_type {
+            PrimitiveType::Identifier(id) => self.strukt(id, cache)?,
+            primitive => primitive_layout(primitive),
+        };
+
+        Some(Layout::new(element.size * count, element.alignment))
+    }
+}
+
+/// Reorders the fields of every struct that isn't `ordered` by descending alignment to reduce padding.
+/// Fields with the same alignment keep their declaration order.
+pub fn reorder_fields(ir: &mut [IR]) {
+    let layouts = Layouts::new(ir);
+
+    for ir in ir.iter_mut() {
+        if let IR::Struct(s) = ir {
+            if s.value.ordered {
+                continue;
+            }
+
+            // Structs without a layout are left alone
+            let alignments: Option<Vec<usize>> = s
+                .value
+                .fields
+                .iter()
+                .map(|f| layouts.list(&f.ty).map(|l| l.alignment))
+                .collect();
+
+            if let Some(alignments) = alignments {
+                let mut fields: Vec<_> = s.value.fields.drain(..).zip(alignments).collect();
+                fields.sort_by(|(_, a), (_, b)| b.cmp(a));
+                s.value.fields = fields.into_iter().map(|(f, _)| f).collect();
+            }
+        }
+    }
+}
+
+/// Returns the layout of a non identifier primitive.
+pub fn primitive_layout(primitive: &PrimitiveType) -> Layout {
+    match primitive {
+        PrimitiveType::U32 | PrimitiveType::I32 | PrimitiveType::F32 => Layout::new(4, 4),
+        PrimitiveType::U64 | PrimitiveType::I64 | PrimitiveType::F64 => Layout::new(8, 8),
+        PrimitiveType::Bool | PrimitiveType::Char => Layout::new(1, 1),
+        PrimitiveType::Identifier(id) => panic!("Identifier '{}' has no primitive layout", id),
+    }
+}
+
+/// Rounds the offset up to the next multiple of the alignment.
+pub fn align_to(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compiler_ir::{Commentable, StructField},
+        env, ir_builder, parser, unchecked_env,
+    };
+
+    fn layouts(code: &str) -> Layouts {
+        let ast = parser::parse(code, (0, 0).into()).unwrap();
+        let env = env::build(unchecked_env::build(ast)).unwrap();
+        Layouts::new(&ir_builder::build(env).unwrap())
+    }
+
+    #[test]
+    fn primitive_struct_is_padded() {
+        let layouts = layouts("struct A { bool a\n u64 b\n char c }");
+        assert_eq!(
+            Some(Layout::new(24, 8)),
+            layouts.get(&ir_builder::struct_id("A"))
+        );
+    }
+
+    #[test]
+    fn padding_is_reported_per_field() {
+        let layouts = layouts("struct A { bool a\n u64 b\n char c }");
+        let id = ir_builder::struct_id("A");
+        let offsets: Vec<usize> = layouts
+            .fields(&id)
+            .unwrap()
+            .iter()
+            .map(|f| f.offset)
+            .collect();
+
+        assert_eq!(vec![0, 8, 16], offsets);
+        assert_eq!(Some(14), layouts.padding(&id));
+    }
+
+    #[test]
+    fn component_store_uses_capacity() {
+        let layouts = layouts("component Hp(i32);\nsingle_component Score(u64);");
+        assert_eq!(
+            Some(Layout::new(8 + 4 * 1024 + 8 * 1024 + 4 * 1024 + 1024, 8)),
+            layouts.get(&ir_builder::struct_id(&env::store_id("Hp")))
+        );
+        assert_eq!(
+            Some(Layout::new(8 + 8, 8)),
+            layouts.get(&ir_builder::struct_id(&env::store_id("Score")))
+        );
+    }
+
+    #[test]
+    fn reorder_fields_removes_padding() {
+        let ast = parser::parse(
+            "struct A { bool a\n u64 b\n char c\n u32 d }\nstruct B ordered { bool a\n u64 b }",
+            (0, 0).into(),
+        )
+        .unwrap();
+        let env = env::build(unchecked_env::build(ast)).unwrap();
+        let mut ir = ir_builder::build(env).unwrap();
+        reorder_fields(&mut ir);
+
+        let fields = |id: &str| -> Vec<String> {
+            ir.iter()
+                .find_map(|ir| match ir {
+                    IR::Struct(s) if s.value.id == id => {
+                        Some(s.value.fields.iter().map(|f| f.id.clone()).collect())
+                    }
+                    _ => None,
+                })
+                .unwrap()
+        };
+        assert_eq!(
+            vec!["b", "d", "a", "c"],
+            fields(&ir_builder::struct_id("A"))
+        );
+        assert_eq!(vec!["a", "b"], fields(&ir_builder::struct_id("B")));
+
+        let layouts = Layouts::new(&ir);
+        assert_eq!(
+            Some(Layout::new(16, 8)),
+            layouts.get(&ir_builder::struct_id("A"))
+        );
+        assert_eq!(Some(2), layouts.padding(&ir_builder::struct_id("A")));
+    }
+
+    #[test]
+    fn nested_struct_list() {
+        let layouts = layouts("struct Aabb { i32 w\n i32 h }\ncomponent Boxes([Aabb 4]);");
+        assert_eq!(
+            Some(Layout::new(8, 4)),
+            layouts.get(&ir_builder::struct_id("Aabb"))
+        );
+        // The list holds its length before the items
+        assert_eq!(
+            Some(Layout::new(4 + 32, 4)),
+            layouts.get(&ir_builder::component_id("Boxes"))
+        );
+    }
+
+    #[test]
+    fn enum_payloads_share_memory() {
+        let layouts = layouts("enum Shape { Point, Circle(f32), Rect([f64 2]) }");
+        assert_eq!(
+            Some(Layout::new(24, 8)),
+            layouts.get(&ir_builder::payload_id("Shape"))
+        );
+        assert_eq!(
+            Some(Layout::new(32, 8)),
+            layouts.get(&ir_builder::struct_id("Shape"))
+        );
+        assert_eq!(Some(0), layouts.padding(&ir_builder::payload_id("Shape")));
+        assert_eq!(Some(4), layouts.padding(&ir_builder::struct_id("Shape")));
+    }
+
+    #[test]
+    fn empty_struct_has_no_size() {
+        let layouts = layouts("struct Empty;");
+        assert_eq!(
+            Some(Layout::new(0, 1)),
+            layouts.get(&ir_builder::struct_id("Empty"))
+        );
+    }
+
+    #[test]
+    fn recursive_struct_has_no_layout() {
+        // The env rejects recursive structs, so the IR is built by hand
+        let strukt = |id: &str, field: &str| {
+            IR::Struct(Commentable {
+                comments: vec![],
+                value: Struct {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    fields: vec![StructField {
+                        id: "field".to_string(),
+                        comments: vec![],
+                        ty: ListType::Single(field.into()),
+                    }],
+                    ordered: false,
+                    union: false,
+                    variants: vec![],
+                    list: None,
+                },
+            })
+        };
+        let layouts = Layouts::new(&[strukt("A", "B"), strukt("B", "A")]);
+
+        assert_eq!(None, layouts.get("A"));
+        assert_eq!(None, layouts.get("B"));
+    }
+}
@@ -0,0 +1,327 @@
+use super::{align_to, primitive_layout, Layout, Layouts};
+use crate::{
+    compiler_ir::{IR, SNAPSHOT_HEADER},
+    env::{self, Env, StructValue},
+    ir_builder,
+    json::Json,
+    parser::{ComponentType, COMPONENT_ID, ENUM_ID, EVENT_ID, SINGLE_COMPONENT_ID, STRUCT_ID},
+};
+
+/// The memory footprint of every declared type and component store.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub types: Vec<TypeReport>,
+    pub stores: Vec<StoreReport>,
+    /// The size of the world struct, which holds every store along with the id of the next entity.
+    pub world: usize,
+    /// The number of worlds kept in the ring of rollback snapshots.
+    pub snapshot_slots: usize,
+    /// The size of a snapshot, which is a copy of the world after a small header.
+    pub snapshot: usize,
+    /// The bytes needed for the world and its snapshots.
+    pub total: usize,
+}
+
+/// The layout of a struct, enum or component.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeReport {
+    pub keyword: &'static str,
+    pub id: String,
+    pub layout: Layout,
+    pub padding: usize,
+}
+
+/// The footprint of a component store at its capacity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoreReport {
+    pub component: String,
+    pub capacity: usize,
+    pub bytes: usize,
+}
+
+impl Report {
+    /// Builds the report for the env and its IR. Types without a layout are left out.
+    pub fn new(env: &Env, ir: &[IR]) -> Self {
+        let layouts = &Layouts::new(ir);
+        let mut types = vec![];
+        let mut stores = vec![];
+
+        for strukt in env.structs.values() {
+            if let StructValue::Struct(strukt) = strukt {
+                let ir_id = ir_builder::struct_id(&strukt.id);
+                if let Some(report) = type_report(STRUCT_ID, &strukt.id, &ir_id, layouts) {
+                    types.push(report);
+                }
+            }
+        }
+
+        for e in env.enums.values() {
+            let ir_id = ir_builder::struct_id(&e.id);
+            if let Some(report) = type_report(ENUM_ID, &e.id, &ir_id, layouts) {
+                types.push(report);
+            }
+        }
+
+        for component in env.components.values() {
+            let keyword = match component.component_type {
+                ComponentType::Component => COMPONENT_ID,
+                ComponentType::SingleComponent => SINGLE_COMPONENT_ID,
+                ComponentType::Event => EVENT_ID,
+            };
+
+            let ir_id = ir_builder::component_id(&component.id);
+            if let Some(report) = type_report(keyword, &component.id, &ir_id, layouts) {
+                types.push(report);
+            }
+
+            let store_id = ir_builder::struct_id(&env::store_id(&component.id));
+            if let Some(layout) = layouts.get(&store_id) {
+                stores.push(StoreReport {
+                    component: component.id.clone(),
+                    capacity: env::store_capacity(component),
+                    bytes: layout.size,
+                });
+            }
+        }
+
+        types.sort_by(|a, b| a.id.cmp(&b.id).then(a.keyword.cmp(b.keyword)));
+        stores.sort_by(|a, b| a.component.cmp(&b.component));
+        // The generated code keeps a copy of the world in each snapshot slot
+        let (world, snapshot_slots) = ir
+            .iter()
+            .find_map(|ir| match ir {
+                IR::World(world) => Some((
+                    layouts.get(&world.id).unwrap_or_else(|| Layout::new(0, 1)),
+                    world.snapshot_slots,
+                )),
+                _ => None,
+            })
+            .unwrap_or_else(|| (Layout::new(0, 1), 0));
+        let snapshot = snapshot_size(&world);
+
+        Self {
+            types,
+            stores,
+            world: world.size,
+            snapshot_slots,
+            snapshot,
+            total: world.size + snapshot * snapshot_slots,
+        }
+    }
+
+    /// Returns the report as aligned plain text tables.
+    pub fn to_table(&self) -> String {
+        let mut rows = vec![vec![
+            "Type".to_string(),
+            "Kind".to_string(),
+            "Size".to_string(),
+            "Align".to_string(),
+            "Padding".to_string(),
+        ]];
+        for t in self.types.iter() {
+            rows.push(vec![
+                t.id.clone(),
+                t.keyword.to_string(),
+                t.layout.size.to_string(),
+                t.layout.alignment.to_string(),
+                t.padding.to_string(),
+            ]);
+        }
+
+        let mut output = table(&rows, 2);
+        output.push('\n');
+
+        let mut rows = vec![vec![
+            "Store".to_string(),
+            "Capacity".to_string(),
+            "Bytes".to_string(),
+        ]];
+        for s in self.stores.iter() {
+            rows.push(vec![
+                s.component.clone(),
+                s.capacity.to_string(),
+                s.bytes.to_string(),
+            ]);
+        }
+
+        output.push_str(&table(&rows, 1));
+        output.push_str(&format!("\nWorld: {} bytes\n", self.world));
+        output.push_str(&format!(
+            "Snapshots: {} x {} bytes\n",
+            self.snapshot_slots, self.snapshot
+        ));
+        output.push_str(&format!("World total: {} bytes\n", self.total));
+        output
+    }
+
+    pub fn to_json(&self) -> Json {
+        let types = self
+            .types
+            .iter()
+            .map(|t| {
+                Json::object(vec![
+                    ("id", t.id.as_str().into()),
+                    ("kind", t.keyword.into()),
+                    ("size", t.layout.size.into()),
+                    ("alignment", t.layout.alignment.into()),
+                    ("padding", t.padding.into()),
+                ])
+            })
+            .collect::<Vec<_>>();
+
+        let stores = self
+            .stores
+            .iter()
+            .map(|s| {
+                Json::object(vec![
+                    ("component", s.component.as_str().into()),
+                    ("capacity", s.capacity.into()),
+                    ("bytes", s.bytes.into()),
+                ])
+            })
+            .collect::<Vec<_>>();
+
+        Json::object(vec![
+            ("types", types.into()),
+            ("stores", stores.into()),
+            ("world", self.world.into()),
+            ("snapshot_slots", self.snapshot_slots.into()),
+            ("snapshot", self.snapshot.into()),
+            ("total", self.total.into()),
+        ])
+    }
+}
+
+/// Returns the size of a snapshot of the world, laid out like the C struct holding it.
+fn snapshot_size(world: &Layout) -> usize {
+    let mut offset = 0;
+    let mut alignment = world.alignment;
+    for (_, ty) in SNAPSHOT_HEADER.iter() {
+        let layout = primitive_layout(ty);
+        offset = align_to(offset, layout.alignment) + layout.size;
+        alignment = alignment.max(layout.alignment);
+    }
+    offset = align_to(offset, world.alignment) + world.size;
+    align_to(offset, alignment)
+}
+
+fn type_report(
+    keyword: &'static str,
+    id: &str,
+    ir_id: &str,
+    layouts: &Layouts,
+) -> Option<TypeReport> {
+    Some(TypeReport {
+        keyword,
+        id: id.to_string(),
+        layout: layouts.get(ir_id)?,
+        padding: layouts.padding(ir_id)?,
+    })
+}
+
+/// Formats the rows into columns. Text columns are left aligned and the rest are right aligned.
+fn table(rows: &[Vec<String>], text_columns: usize) -> String {
+    let columns = rows.first().map(|r| r.len()).unwrap_or_default();
+    let widths: Vec<usize> = (0..columns)
+        .map(|c| rows.iter().map(|r| r[c].len()).max().unwrap_or_default())
+        .collect();
+
+    let mut output = String::new();
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(c, cell)| {
+                if c < text_columns {
+                    format!("{:<width$}", cell, width = widths[c])
+                } else {
+                    format!("{:>width$}", cell, width = widths[c])
+                }
+            })
+            .collect();
+
+        output.push_str(cells.join("  ").trim_end());
+        output.push('\n');
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{location::Location, parser, unchecked_env};
+
+    fn report(code: &str) -> Report {
+        let ast = parser::parse(code, Location::default()).unwrap();
+        let env = env::build(unchecked_env::build(ast)).unwrap();
+        let ir = ir_builder::build(env.clone()).unwrap();
+        Report::new(&env, &ir)
+    }
+
+    #[test]
+    fn report_totals_world_and_snapshots() {
+        let report = report(
+            "struct Pair { bool a u32 b }\ncomponent Hp(i32);\nsingle_component Score(u64);",
+        );
+
+        assert_eq!(
+            vec!["Entity", "Hp", "Pair", "Score"],
+            report
+                .types
+                .iter()
+                .map(|t| t.id.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(3, report.types[2].padding);
+        assert_eq!(
+            vec![1024, 1],
+            report.stores.iter().map(|s| s.capacity).collect::<Vec<_>>()
+        );
+        // The stores plus the next entity id and padding, in the world and in each of 8 snapshots
+        assert_eq!(17416 + 16 + 8, report.world);
+        assert_eq!(8, report.snapshot_slots);
+        // The taken flag, slot and checksum in front of each copy
+        assert_eq!(24 + 17440, report.snapshot);
+        assert_eq!(17440 + 17464 * 8, report.total);
+    }
+
+    #[test]
+    fn report_total_matches_compiled_size() {
+        let code = "struct Pair { bool a u32 b }\ncomponent Hp(i32);\nsingle_component Score(u64);\nworld {}";
+        let report = report(code);
+        let host = format!(
+            "int main(void) {{
+\tsize_t total = sizeof(D_WORLD) + sizeof(d_snapshots);
+\tif (sizeof(D_SNAPSHOT) != {} || total != {}) {{
+\t\tprintf(\"snapshot %zu, total %zu\\n\", sizeof(D_SNAPSHOT), total);
+\t\treturn 1;
+\t}}
+\treturn 0;
+}}
+",
+            report.snapshot, report.total
+        );
+
+        if let Some(result) = crate::compiler::tests::cc_run(code, &host) {
+            assert_eq!(Ok(()), result);
+        }
+    }
+
+    #[test]
+    fn table_is_aligned() {
+        let table = report("component Hp(i32);").to_table();
+        let expected = "Type    Kind       Size  Align  Padding
+Entity  struct        8      8        0
+Hp      component     4      4        0
+
+Store  Capacity  Bytes
+Hp         1024  17416
+
+World: 17424 bytes
+Snapshots: 8 x 17448 bytes
+World total: 157008 bytes
+";
+        assert_eq!(expected, table);
+    }
+}
@@ -71,6 +71,11 @@ static MERGE_TOKEN_CASES: &[(TokenValue, TokenValue, TokenValue)] = &[
         TokenValue::RChevron,
         TokenValue::RShift,
     ),
+    (
+        TokenValue::QuestionMark,
+        TokenValue::QuestionMark,
+        TokenValue::Coalesce,
+    ),
 ];
 
 #[derive(Debug, PartialEq, Clone)]
@@ -80,11 +85,6 @@ enum State {
     Identifier { s: String, location: Location },
 }
 
-#[cfg(test)]
-pub fn lex_str(input: &str) -> Result<Vec<Token>, Error> {
-    lex(input, (0, 0).into())
-}
-
 /// Lexes the given string into a set of tokens.
 pub fn lex(input: &str, mut working_location: Location) -> Result<Vec<Token>, Error> {
     let input = input.replace("\r\n", "\n");
@@ -111,7 +111,7 @@ pub fn lex(input: &str, mut working_location: Location) -> Result<Vec<Token>, Er
 
     let tokens: Vec<Token> = tokens.iter_mut().map(|t| t.finalize()).collect();
 
-    Ok(merge_tokens(tokens)?)
+    merge_tokens(tokens)
 }
 
 fn merge_tokens(tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
@@ -134,7 +134,7 @@ fn merge_tokens(tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
             while keep_going && next_index < tokens.len() {
                 let next_token = &tokens[next_index];
                 if let TokenValue::Comment(next_comments) = &next_token.value {
-                    let should_extend = last_token.is_subsequent_line(&next_token)
+                    let should_extend = last_token.is_subsequent_line(next_token)
                         && last_token.start_location.column() == next_token.start_location.column();
 
                     if should_extend {
@@ -181,14 +181,13 @@ fn merge_tokens(tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
 
             // Attempt to make a number
             if !merged_tokens {
-                if let TokenValue::Number(n) = token.value {
+                if let TokenValue::Number(n) = &token.value {
                     let start_location = token.start_location.clone();
                     let mut end_location = token.end_location.clone();
-                    let mut working_string = n.to_string();
+                    let mut working_string = n.clone();
 
                     // Merge next if it's a period
-                    if next_token.value == TokenValue::Period && token.is_back_to_back(&next_token)
-                    {
+                    if next_token.value == TokenValue::Period && token.is_back_to_back(next_token) {
                         end_location = next_token.end_location.clone();
                         working_string.push('.');
                         merged_tokens = true;
@@ -199,10 +198,12 @@ fn merge_tokens(tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
                             && next_token.is_back_to_back(&tokens[third_index])
                         {
                             let third_token = &tokens[third_index];
-                            if let TokenValue::Number(n) = third_token.value {
-                                working_string.push_str(&n.to_string());
+                            if let TokenValue::Number(n) = &third_token.value {
+                                working_string.push_str(n);
                                 end_location = third_token.end_location.clone();
                                 i += 3;
+                            } else {
+                                i += 2;
                             }
                         } else {
                             i += 2;
@@ -211,7 +212,7 @@ fn merge_tokens(tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
 
                     if merged_tokens {
                         let token = Token {
-                            value: TokenValue::Number(working_string.parse::<f64>().unwrap()),
+                            value: TokenValue::Number(working_string),
                             start_location,
                             end_location,
                         };
@@ -254,7 +255,7 @@ fn handle_quote(
                 if s.is_empty() {
                     false
                 } else {
-                    s.chars().last().unwrap() == '\\'
+                    s.ends_with('\\')
                 }
             };
 
@@ -330,7 +331,7 @@ fn handle_character(
 ) -> Result<(), Error> {
     type TokCtor = fn(Location, Location) -> token::Token;
 
-    const TERMINAL_TOKEN_CASES: [(char, fn(Location, Location) -> token::Token); 22] = [
+    const TERMINAL_TOKEN_CASES: [(char, TokCtor); 23] = [
         ('{', Token::lcurlybrace as TokCtor),
         ('}', Token::rcurlybrace as TokCtor),
         (',', Token::comma as TokCtor),
@@ -353,6 +354,7 @@ fn handle_character(
         ('|', Token::pipe as TokCtor),
         (';', Token::semicolon as TokCtor),
         ('^', Token::carrot as TokCtor),
+        ('?', Token::question_mark as TokCtor),
     ];
 
     match stack.pop() {
@@ -78,6 +78,7 @@ lexer_single_symbol!(Token::ampersand, lex_ampersand, "&", lex_ampersand_ends_id
 lexer_single_symbol!(Token::pipe, lex_pipe, "|", lex_pipe_ends_identifier);
 lexer_single_symbol!(Token::semicolon, lex_semicolon, ";", lex_semicolon_ends_identifier);
 lexer_single_symbol!(Token::carrot, lex_carrot, "^", lex_carrot_ends_identifier);
+lexer_single_symbol!(Token::question_mark, lex_question_mark, "?", lex_question_mark_ends_identifier);
 
 
 
@@ -113,6 +114,7 @@ lexer_double_symbol!(lex_equal, "==", TokenValue::Equal);
 lexer_double_symbol!(lex_not_equal, "!=", TokenValue::NotEqual);
 lexer_double_symbol!(lex_lshift, "<<", TokenValue::LShift);
 lexer_double_symbol!(lex_rshift, ">>", TokenValue::RShift);
+lexer_double_symbol!(lex_coalesce, "??", TokenValue::Coalesce);
 
 
 #[test]
@@ -171,7 +173,7 @@ fn zero_returns_number(){
     let result = lex(input, Location::default()).unwrap();
 
     let expected = vec![
-        Token::number(0.0, (0, 0).into(), (0, 1).into())
+        Token::number("0", (0, 0).into(), (0, 1).into())
     ];
 
     assert_eq!(expected, result);
@@ -184,7 +186,7 @@ fn positive_num_returns_number(){
     let result = lex(input, Location::default()).unwrap();
 
     let expected = vec![
-        Token::number(123.456, (0, 0).into(), (0, 7).into())
+        Token::number("123.456", (0, 0).into(), (0, 7).into())
     ];
 
     assert_eq!(expected, result);
@@ -196,11 +198,11 @@ fn complicated_nums(){
     let result = lex(input, Location::default()).unwrap();
 
     let expected = vec![
-        Token::number(123.456, (0, 0).into(), (0, 7).into()),
-        Token::number(78.0, (0, 8).into(), (0, 10).into()),
+        Token::number("123.456", (0, 0).into(), (0, 7).into()),
+        Token::number("78", (0, 8).into(), (0, 10).into()),
         Token::period((0, 11).into(), (0, 12).into()),
-        Token::number(9.0, (0, 13).into(), (0, 14).into()),
-        Token::number(10.0, (0, 15).into(), (0, 17).into()),
+        Token::number("9", (0, 13).into(), (0, 14).into()),
+        Token::number("10", (0, 15).into(), (0, 17).into()),
     ];
 
     assert_eq!(expected, result);
@@ -213,7 +215,22 @@ fn negative_num_returns_neg_and_number(){
 
     let expected = vec![
         Token::minus((0, 0).into(), (0, 1).into()),
-        Token::number(123.456, (0, 1).into(), (0, 8).into())
+        Token::number("123.456", (0, 1).into(), (0, 8).into())
+    ];
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn nums_keep_their_digits(){
+    let input = "2.0 2.05 1e3 inf";
+    let result = lex(input, Location::default()).unwrap();
+
+    let expected = vec![
+        Token::number("2.0", (0, 0).into(), (0, 3).into()),
+        Token::number("2.05", (0, 4).into(), (0, 8).into()),
+        Token::number("1e3", (0, 9).into(), (0, 12).into()),
+        Token::identifier("inf".to_string(), (0, 13).into(), (0, 16).into()),
     ];
 
     assert_eq!(expected, result);
@@ -31,7 +31,8 @@ pub enum TokenValue {
     String(String),
     Comment(Vec<String>),
     Identifier(String),
-    Number(f64),
+    /// A number as written, e.g. `2`, `2.0` or `1e3`, so its kind and digits are kept.
+    Number(String),
     LCurlyBrace,
     RCurlyBrace,
     Comma,
@@ -54,6 +55,7 @@ pub enum TokenValue {
     Pipe,
     Semicolon,
     Carrot,
+    QuestionMark,
     // Double token values
     PlusAssign,
     SubAssign,
@@ -74,6 +76,7 @@ pub enum TokenValue {
     NotEqual,
     LShift,
     RShift,
+    Coalesce,
 }
 impl TokenValue {
     pub fn display_name(&self) -> String {
@@ -104,6 +107,7 @@ impl TokenValue {
             TokenValue::Pipe => "|".to_string(),
             TokenValue::Semicolon => ";".to_string(),
             TokenValue::Carrot => "^".to_string(),
+            TokenValue::QuestionMark => "?".to_string(),
             TokenValue::PlusAssign => "+=".to_string(),
             TokenValue::SubAssign => "-=".to_string(),
             TokenValue::MulAssign => "*=".to_string(),
@@ -123,6 +127,7 @@ impl TokenValue {
             TokenValue::NotEqual => "!=".to_string(),
             TokenValue::LShift => "<<".to_string(),
             TokenValue::RShift => ">>".to_string(),
+            TokenValue::Coalesce => "??".to_string(),
         }
     }
 }
@@ -156,36 +161,11 @@ impl Token {
     symbol_constructor!(Pipe, pipe);
     symbol_constructor!(Semicolon, semicolon);
     symbol_constructor!(Carrot, carrot);
-    symbol_constructor!(PlusAssign, plus_assign);
-    symbol_constructor!(SubAssign, sub_assign);
-    symbol_constructor!(MulAssign, mul_assign);
-    symbol_constructor!(DivAssign, div_assign);
-    symbol_constructor!(ModAssign, mod_assign);
-    symbol_constructor!(PipeAssign, pipe_assign);
-    symbol_constructor!(AndAssign, and_assign);
-    symbol_constructor!(CarrotAssign, carrot_assign);
-    symbol_constructor!(PlusPlus, plus_plus);
-    symbol_constructor!(MinusMinus, minus_minus);
-    symbol_constructor!(NotNot, not_not);
-    symbol_constructor!(Or, or);
-    symbol_constructor!(And, and);
-    symbol_constructor!(LessThanEqual, less_than_equal);
-    symbol_constructor!(GreaterThanEqual, greater_than_equal);
-    symbol_constructor!(Equal, equal);
-    symbol_constructor!(NotEqual, not_equal);
-    symbol_constructor!(LShift, lshift);
-    symbol_constructor!(RShift, rshift);
+    symbol_constructor!(QuestionMark, question_mark);
 
     string_constructor!(String, string);
     string_constructor!(Identifier, identifier);
 
-    pub fn is_identifier(&self) -> bool {
-        match &self.value {
-            TokenValue::Identifier(_) => true,
-            _ => false,
-        }
-    }
-
     pub fn comment(s: String, start_location: Location, end_location: Location) -> Self {
         Self {
             value: TokenValue::Comment(vec![s]),
@@ -202,9 +182,10 @@ impl Token {
         }
     }
 
-    pub fn number(n: f64, start_location: Location, end_location: Location) -> Self {
+    #[cfg(test)]
+    pub fn number(n: &str, start_location: Location, end_location: Location) -> Self {
         Self {
-            value: TokenValue::Number(n),
+            value: TokenValue::Number(n.to_string()),
             start_location,
             end_location,
         }
@@ -234,9 +215,10 @@ impl Token {
             TokenValue::Identifier(s) => {
                 let id = s.trim();
 
-                // Check if it's a number
-                if let Ok(n) = id.parse::<f64>() {
-                    TokenValue::Number(n)
+                // Check if it's a number. Words such as 'inf' parse as floats but are identifiers.
+                let is_number = id.starts_with(|c: char| c.is_ascii_digit());
+                if is_number && id.parse::<f64>().is_ok() {
+                    TokenValue::Number(id.to_string())
                 } else {
                     TokenValue::Identifier(id.to_string())
                 }
@@ -284,13 +266,13 @@ mod tests {
     fn is_back_to_back_space_returns_false() {
         let token1 = Token::identifier("+".to_string(), (0, 0).into(), (0, 1).into());
         let token2 = Token::identifier("=".to_string(), (0, 2).into(), (1, 2).into());
-        assert_eq!(false, token1.is_back_to_back(&token2));
+        assert!(!token1.is_back_to_back(&token2));
     }
 
     #[test]
     fn is_back_to_back_newline_returns_false() {
         let token1 = Token::identifier("+".to_string(), (0, 0).into(), (0, 1).into());
         let token2 = Token::identifier("=".to_string(), (1, 1).into(), (1, 2).into());
-        assert_eq!(false, token1.is_back_to_back(&token2));
+        assert!(!token1.is_back_to_back(&token2));
     }
 }
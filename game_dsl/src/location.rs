@@ -44,7 +44,7 @@ impl Location {
     pub fn pretty_print_line_column(&self) -> String {
         match self {
             Location::Text { line, column } => format!("{}:{}", line, column),
-            Location::File { path, line, column } => {
+            Location::File { line, column, .. } => {
                 format!("{}:{}", line, column)
             }
             Location::SystemDefined => "System defined".to_string(),
@@ -0,0 +1,321 @@
+use crate::{
+    env,
+    error::Error,
+    ir_builder,
+    layout::{self, Layout, Layouts},
+    location::Location,
+    parser::{
+        self, Ast, ComponentProperties, ComponentType, Listable, Primitive, PrimitiveType,
+        StructProperties, COMPONENT_ID, ENUM_ID, EVENT_ID, SINGLE_COMPONENT_ID, STRUCT_ID,
+    },
+    unchecked_env,
+};
+
+/// An open text document along with everything the compiler knows about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub text: String,
+    pub asts: Vec<Ast>,
+    pub errors: Vec<Error>,
+    layouts: Option<Layouts>,
+}
+
+/// A struct, enum or component declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration<'a> {
+    pub keyword: &'static str,
+    pub id: &'a str,
+    pub fields: Vec<Field<'a>>,
+    pub start_location: &'a Location,
+    pub end_location: &'a Location,
+}
+
+impl<'a> Declaration<'a> {
+    /// Whether the declaration is a type that properties can use, rather than a component.
+    fn is_type(&self) -> bool {
+        self.keyword == STRUCT_ID || self.keyword == ENUM_ID
+    }
+
+    fn ir_id(&self) -> String {
+        if self.is_type() {
+            ir_builder::struct_id(self.id)
+        } else {
+            ir_builder::component_id(self.id)
+        }
+    }
+}
+
+/// A field of a declaration. Value types have a single field named `value` and the fields of an
+/// enum are the variants that hold a value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field<'a> {
+    pub id: &'a str,
+    pub ty: &'a Listable<Primitive>,
+    pub start_location: &'a Location,
+    pub end_location: &'a Location,
+}
+
+impl Document {
+    /// Parses and checks the given text.
+    pub fn new(text: String) -> Self {
+        let (asts, mut errors) = parser::parse_partial(&text, Location::default());
+
+        let mut layouts = None;
+        if errors.is_empty() {
+            match env::build(unchecked_env::build(asts.clone())) {
+                Ok(env) => {
+                    if let Ok(mut ir) = ir_builder::build(env) {
+                        layout::reorder_fields(&mut ir);
+                        layouts = Some(Layouts::new(&ir));
+                    }
+                }
+                Err(e) => errors = e,
+            }
+        }
+
+        Self {
+            text,
+            asts,
+            errors,
+            layouts,
+        }
+    }
+
+    /// Returns all struct, enum and component declarations in the document.
+    pub fn declarations(&self) -> Vec<Declaration<'_>> {
+        let mut declarations = vec![];
+        for ast in self.asts.iter() {
+            match ast {
+                Ast::Struct(s) => {
+                    let fields = match &s.properties {
+                        StructProperties::None => vec![],
+                        StructProperties::Value(value) => vec![value_field(value)],
+                        StructProperties::Multiple(properties) => properties
+                            .iter()
+                            .map(|p| Field {
+                                id: &p.identifier,
+                                ty: &p.ty,
+                                start_location: &p.start_location,
+                                end_location: &p.end_location,
+                            })
+                            .collect(),
+                    };
+
+                    declarations.push(Declaration {
+                        keyword: STRUCT_ID,
+                        id: &s.id,
+                        fields,
+                        start_location: &s.start_location,
+                        end_location: &s.end_location,
+                    });
+                }
+                Ast::Component(c) => {
+                    let fields = match &c.properties {
+                        ComponentProperties::None => vec![],
+                        ComponentProperties::Value(value) => vec![value_field(value)],
+                        ComponentProperties::Multiple(properties) => properties
+                            .iter()
+                            .map(|p| Field {
+                                id: &p.identifier,
+                                ty: &p.ty,
+                                start_location: &p.start_location,
+                                end_location: &p.end_location,
+                            })
+                            .collect(),
+                    };
+
+                    declarations.push(Declaration {
+                        keyword: match c.component_type {
+                            ComponentType::Component => COMPONENT_ID,
+                            ComponentType::SingleComponent => SINGLE_COMPONENT_ID,
+                            ComponentType::Event => EVENT_ID,
+                        },
+                        id: &c.id,
+                        fields,
+                        start_location: &c.start_location,
+                        end_location: &c.end_location,
+                    });
+                }
+                Ast::Enum(e) => {
+                    let fields = e
+                        .variants
+                        .iter()
+                        .filter_map(|v| {
+                            Some(Field {
+                                id: &v.id,
+                                ty: v.payload.as_ref()?,
+                                start_location: &v.start_location,
+                                end_location: &v.end_location,
+                            })
+                        })
+                        .collect();
+
+                    declarations.push(Declaration {
+                        keyword: ENUM_ID,
+                        id: &e.id,
+                        fields,
+                        start_location: &e.start_location,
+                        end_location: &e.end_location,
+                    });
+                }
+                Ast::Comment(_) | Ast::System(_) | Ast::World(_) => {}
+            }
+        }
+
+        declarations
+    }
+
+    /// Returns the declaration a type identifier refers to. Structs and enums take precedence over components.
+    pub fn find_declaration(&self, id: &str) -> Option<Declaration<'_>> {
+        let declarations = self.declarations();
+        let position = declarations
+            .iter()
+            .position(|d| d.id == id && d.is_type())
+            .or_else(|| declarations.iter().position(|d| d.id == id))?;
+
+        declarations.into_iter().nth(position)
+    }
+
+    /// Returns the location of the declaration for the type identifier at the given position.
+    pub fn definition(&self, position: &Location) -> Option<(Location, Location)> {
+        for declaration in self.declarations() {
+            for field in declaration.fields.iter() {
+                let ty = field.ty.inner_ty();
+                if let PrimitiveType::Identifier(id) = &ty.primitive_type {
+                    if contains(&ty.start_location, &ty.end_location, position) {
+                        let target = self.find_declaration(id)?;
+                        return Some((target.start_location.clone(), target.end_location.clone()));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns markdown describing the field or declaration at the given position.
+    pub fn hover(&self, position: &Location) -> Option<String> {
+        let declaration = self
+            .declarations()
+            .into_iter()
+            .find(|d| contains(d.start_location, d.end_location, position))?;
+
+        let field = declaration
+            .fields
+            .iter()
+            .find(|f| contains(f.start_location, f.end_location, position));
+
+        let mut lines = vec!["```game_dsl".to_string()];
+        let layout = match field {
+            Some(field) => {
+                lines.push(format!("{} {}", field.ty.ty, field.id));
+                lines.push("```".to_string());
+
+                if let PrimitiveType::Identifier(id) = &field.ty.inner_ty().primitive_type {
+                    match self.find_declaration(id) {
+                        Some(target) => lines.push(format!(
+                            "`{}` resolves to `{} {}` ({})",
+                            id,
+                            target.keyword,
+                            target.id,
+                            target.start_location.pretty_print_line_column()
+                        )),
+                        None => lines.push(format!("`{}` is not declared", id)),
+                    }
+                }
+
+                self.layouts
+                    .as_ref()
+                    .and_then(|l| l.list(&ir_builder::build_field_type(&field.ty.ty)))
+            }
+            None => {
+                lines.push(format!("{} {}", declaration.keyword, declaration.id));
+                lines.push("```".to_string());
+
+                self.layouts
+                    .as_ref()
+                    .and_then(|l| l.get(&declaration.ir_id()))
+            }
+        };
+
+        if let Some(Layout { size, alignment }) = layout {
+            lines.push(format!("Size: {} bytes, alignment: {}", size, alignment));
+        }
+
+        Some(lines.join("\n"))
+    }
+
+    /// Returns component names if the position is inside a `read [..]` or `write [..]` list.
+    pub fn completions(&self, position: &Location) -> Vec<Declaration<'_>> {
+        let offset = match self.offset(position) {
+            Some(offset) => offset,
+            None => return vec![],
+        };
+
+        let before: Vec<char> = self.text.chars().take(offset).collect();
+
+        // Find the opening bracket of the list the position is in
+        let mut idx = before.len();
+        loop {
+            if idx == 0 {
+                return vec![];
+            }
+            idx -= 1;
+
+            match before[idx] {
+                '[' => break,
+                ']' | '{' | '}' | '(' | ')' | '#' => return vec![],
+                _ => {}
+            }
+        }
+
+        // Check that the list belongs to a read or write
+        let keyword: String = before[..idx]
+            .iter()
+            .rev()
+            .skip_while(|c| c.is_whitespace())
+            .take_while(|c| !c.is_whitespace())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect();
+
+        if keyword != "read" && keyword != "write" {
+            return vec![];
+        }
+
+        self.declarations()
+            .into_iter()
+            .filter(|d| !d.is_type())
+            .collect()
+    }
+
+    /// Converts a line and column into a character offset into the text.
+    fn offset(&self, position: &Location) -> Option<usize> {
+        let mut offset = 0;
+        for (line_idx, line) in self.text.split('\n').enumerate() {
+            let line_len = line.chars().count();
+            if line_idx == position.line() {
+                return Some(offset + position.column().min(line_len));
+            }
+            offset += line_len + 1;
+        }
+
+        None
+    }
+}
+
+fn value_field(value: &Listable<Primitive>) -> Field<'_> {
+    Field {
+        id: "value",
+        ty: value,
+        start_location: &value.start_location,
+        end_location: &value.end_location,
+    }
+}
+
+/// Returns whether the position lies within the start and end, inclusive.
+pub fn contains(start: &Location, end: &Location, position: &Location) -> bool {
+    let key = |l: &Location| (l.line(), l.column());
+    key(start) <= key(position) && key(position) <= key(end)
+}
@@ -0,0 +1,431 @@
+mod document;
+#[cfg(test)]
+mod tests;
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use crate::{
+    json::{self, Json},
+    location::Location,
+    parser::{ENUM_ID, STRUCT_ID},
+};
+pub use document::*;
+
+const PARSE_ERROR: f64 = -32700.0;
+const INVALID_REQUEST: f64 = -32600.0;
+const METHOD_NOT_FOUND: f64 = -32601.0;
+
+/// The largest message body read, so a bad `Content-Length` can't make the server allocate any amount of memory.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+// LSP symbol kinds
+const SYMBOL_KIND_CLASS: usize = 5;
+const SYMBOL_KIND_FIELD: usize = 8;
+const SYMBOL_KIND_ENUM: usize = 10;
+const SYMBOL_KIND_ENUM_MEMBER: usize = 22;
+const SYMBOL_KIND_STRUCT: usize = 23;
+
+// LSP completion item kinds
+const COMPLETION_KIND_CLASS: usize = 7;
+
+/// Runs the language server over stdin and stdout until the client exits.
+pub fn run() -> io::Result<()> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    serve(&mut stdin.lock(), &mut stdout.lock())
+}
+
+/// Serves JSON-RPC messages from the input until the client exits or the input closes.
+pub fn serve<R: BufRead, W: Write>(input: &mut R, output: &mut W) -> io::Result<()> {
+    let mut server = Server::default();
+
+    while let Some(message) = read_message(input)? {
+        let responses = match json::parse(&message) {
+            Ok(message) => server.handle(&message),
+            Err(e) => vec![error_response(Json::Null, PARSE_ERROR, &e)],
+        };
+
+        for response in responses {
+            write_message(output, &response)?;
+        }
+
+        if server.exited {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a single `Content-Length` framed message. Returns `None` when the input is closed.
+/// Returns an error if the message is larger than `MAX_MESSAGE_SIZE`.
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut content_length = None;
+
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let content_length = content_length.unwrap_or_default();
+    if content_length > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Content-Length {} is over the limit of {} bytes",
+                content_length, MAX_MESSAGE_SIZE
+            ),
+        ));
+    }
+
+    let mut content = vec![0; content_length];
+    input.read_exact(&mut content)?;
+
+    Ok(Some(String::from_utf8_lossy(&content).to_string()))
+}
+
+/// Writes a single `Content-Length` framed message.
+pub fn write_message<W: Write>(output: &mut W, message: &Json) -> io::Result<()> {
+    let content = message.to_compact_string();
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
+
+/// The state of the language server.
+#[derive(Debug, Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+    exited: bool,
+}
+
+impl Server {
+    /// Handles a single message from the client, returning all messages to send back.
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").and_then(|m| m.as_str());
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+
+        match (method, message.get("id")) {
+            (Some(method), Some(id)) => vec![self.request(method, id.clone(), &params)],
+            (Some(method), None) => self.notification(method, &params),
+            // Responses from the client are not used.
+            (None, _) => vec![],
+        }
+    }
+
+    fn request(&mut self, method: &str, id: Json, params: &Json) -> Json {
+        if self.shutdown {
+            return error_response(id, INVALID_REQUEST, "Server is shutting down");
+        }
+
+        let result = match method {
+            "initialize" => initialize_result(),
+            "shutdown" => {
+                self.shutdown = true;
+                Json::Null
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/completion" => self.completion(params),
+            _ => {
+                return error_response(
+                    id,
+                    METHOD_NOT_FOUND,
+                    &format!("Unhandled method '{}'", method),
+                )
+            }
+        };
+
+        Json::object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("id", id),
+            ("result", result),
+        ])
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params
+            .path(&["textDocument", "uri"])
+            .and_then(|u| u.as_str())
+            .map(|u| u.to_string());
+
+        match (method, uri) {
+            ("exit", _) => {
+                self.exited = true;
+                vec![]
+            }
+            ("textDocument/didOpen", Some(uri)) => {
+                let text = params
+                    .path(&["textDocument", "text"])
+                    .and_then(|t| t.as_str())
+                    .unwrap_or_default();
+
+                self.open(uri, text)
+            }
+            ("textDocument/didChange", Some(uri)) => {
+                // Only full document syncs are supported so the last change is the full text.
+                let text = params
+                    .get("contentChanges")
+                    .and_then(|c| c.as_array())
+                    .and_then(|c| c.last())
+                    .and_then(|c| c.get("text"))
+                    .and_then(|t| t.as_str());
+
+                match text {
+                    Some(text) => self.open(uri, text),
+                    None => vec![],
+                }
+            }
+            ("textDocument/didClose", Some(uri)) => {
+                self.documents.remove(&uri);
+                vec![publish_diagnostics(&uri, vec![])]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn open(&mut self, uri: String, text: &str) -> Vec<Json> {
+        let document = Document::new(text.to_string());
+        let diagnostics = document
+            .errors
+            .iter()
+            .map(|e| {
+                Json::object(vec![
+                    ("range", range(&e.location, &next_column(&e.location))),
+                    ("severity", 1usize.into()),
+                    ("source", env!("CARGO_PKG_NAME").into()),
+                    ("message", e.message.clone().into()),
+                ])
+            })
+            .collect();
+
+        let notification = publish_diagnostics(&uri, diagnostics);
+        self.documents.insert(uri, document);
+        vec![notification]
+    }
+
+    /// Returns the document and position referenced in the params.
+    fn document_position<'a>(
+        &'a self,
+        params: &'a Json,
+    ) -> Option<(&'a str, &'a Document, Location)> {
+        let uri = params.path(&["textDocument", "uri"])?.as_str()?;
+        let document = self.documents.get(uri)?;
+        let line = params.path(&["position", "line"])?.as_usize()?;
+        let character = params.path(&["position", "character"])?.as_usize()?;
+
+        Some((uri, document, (line, character).into()))
+    }
+
+    fn definition(&self, params: &Json) -> Json {
+        let (uri, document, position) = match self.document_position(params) {
+            Some(d) => d,
+            None => return Json::Null,
+        };
+
+        match document.definition(&position) {
+            Some((start, end)) => {
+                Json::object(vec![("uri", uri.into()), ("range", range(&start, &end))])
+            }
+            None => Json::Null,
+        }
+    }
+
+    fn hover(&self, params: &Json) -> Json {
+        let (_, document, position) = match self.document_position(params) {
+            Some(d) => d,
+            None => return Json::Null,
+        };
+
+        match document.hover(&position) {
+            Some(markdown) => Json::object(vec![(
+                "contents",
+                Json::object(vec![
+                    ("kind", "markdown".into()),
+                    ("value", markdown.into()),
+                ]),
+            )]),
+            None => Json::Null,
+        }
+    }
+
+    fn document_symbols(&self, params: &Json) -> Json {
+        let document = params
+            .path(&["textDocument", "uri"])
+            .and_then(|u| u.as_str())
+            .and_then(|uri| self.documents.get(uri));
+
+        let document = match document {
+            Some(document) => document,
+            None => return Json::Null,
+        };
+
+        document
+            .declarations()
+            .iter()
+            .map(|d| {
+                let (kind, field_kind) = match d.keyword {
+                    STRUCT_ID => (SYMBOL_KIND_STRUCT, SYMBOL_KIND_FIELD),
+                    ENUM_ID => (SYMBOL_KIND_ENUM, SYMBOL_KIND_ENUM_MEMBER),
+                    _ => (SYMBOL_KIND_CLASS, SYMBOL_KIND_FIELD),
+                };
+
+                let children = d
+                    .fields
+                    .iter()
+                    .map(|f| {
+                        symbol(
+                            f.id,
+                            &f.ty.ty.to_string(),
+                            field_kind,
+                            f.start_location,
+                            f.end_location,
+                            vec![],
+                        )
+                    })
+                    .collect();
+
+                symbol(
+                    d.id,
+                    d.keyword,
+                    kind,
+                    d.start_location,
+                    d.end_location,
+                    children,
+                )
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    fn completion(&self, params: &Json) -> Json {
+        let (_, document, position) = match self.document_position(params) {
+            Some(d) => d,
+            None => return Json::Null,
+        };
+
+        document
+            .completions(&position)
+            .iter()
+            .map(|d| {
+                Json::object(vec![
+                    ("label", d.id.into()),
+                    ("kind", COMPLETION_KIND_CLASS.into()),
+                    ("detail", d.keyword.into()),
+                ])
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+fn initialize_result() -> Json {
+    Json::object(vec![
+        (
+            "capabilities",
+            Json::object(vec![
+                // Full document sync
+                ("textDocumentSync", 1usize.into()),
+                ("definitionProvider", true.into()),
+                ("hoverProvider", true.into()),
+                ("documentSymbolProvider", true.into()),
+                (
+                    "completionProvider",
+                    Json::object(vec![("triggerCharacters", vec!["[".into()].into())]),
+                ),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object(vec![
+                ("name", env!("CARGO_PKG_NAME").into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+    ])
+}
+
+fn error_response(id: Json, code: f64, message: &str) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        (
+            "error",
+            Json::object(vec![("code", code.into()), ("message", message.into())]),
+        ),
+    ])
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        (
+            "params",
+            Json::object(vec![
+                ("uri", uri.into()),
+                ("diagnostics", diagnostics.into()),
+            ]),
+        ),
+    ])
+}
+
+fn symbol(
+    name: &str,
+    detail: &str,
+    kind: usize,
+    start: &Location,
+    end: &Location,
+    children: Vec<Json>,
+) -> Json {
+    Json::object(vec![
+        ("name", name.into()),
+        ("detail", detail.into()),
+        ("kind", kind.into()),
+        ("range", range(start, end)),
+        ("selectionRange", range(start, end)),
+        ("children", children.into()),
+    ])
+}
+
+fn position(location: &Location) -> Json {
+    Json::object(vec![
+        ("line", location.line().into()),
+        ("character", location.column().into()),
+    ])
+}
+
+fn range(start: &Location, end: &Location) -> Json {
+    Json::object(vec![("start", position(start)), ("end", position(end))])
+}
+
+fn next_column(location: &Location) -> Location {
+    let mut location = location.clone();
+    location.increment_column();
+    location
+}
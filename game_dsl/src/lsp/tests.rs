@@ -0,0 +1,365 @@
+use super::*;
+
+const URI: &str = "file:///test.gdsl";
+
+const SOURCE: &str = "struct Name {
+    [char 10] name
+}
+
+component Person {
+    Name name
+    i32 age
+}
+
+single_component WorldState;
+";
+
+/// Frames the given messages, runs the server over them and returns all responses.
+fn run_script(messages: Vec<Json>) -> Vec<Json> {
+    let mut input = vec![];
+    for message in messages.iter() {
+        write_message(&mut input, message).unwrap();
+    }
+
+    let mut output = vec![];
+    serve(&mut input.as_slice(), &mut output).unwrap();
+
+    let mut output = output.as_slice();
+    let mut responses = vec![];
+    while let Some(message) = read_message(&mut output).unwrap() {
+        responses.push(json::parse(&message).unwrap());
+    }
+
+    responses
+}
+
+fn request(id: usize, method: &str, params: Json) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("id", id.into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn did_open(text: &str) -> Json {
+    notification(
+        "textDocument/didOpen",
+        Json::object(vec![(
+            "textDocument",
+            Json::object(vec![
+                ("uri", URI.into()),
+                ("languageId", "game_dsl".into()),
+                ("version", 1usize.into()),
+                ("text", text.into()),
+            ]),
+        )]),
+    )
+}
+
+fn text_document_position(line: usize, character: usize) -> Json {
+    Json::object(vec![
+        ("textDocument", Json::object(vec![("uri", URI.into())])),
+        (
+            "position",
+            Json::object(vec![("line", line.into()), ("character", character.into())]),
+        ),
+    ])
+}
+
+/// Opens the document, runs the request and returns its result.
+fn request_result(text: &str, method: &str, params: Json) -> Json {
+    let responses = run_script(vec![
+        request(1, "initialize", Json::object(vec![])),
+        did_open(text),
+        request(2, method, params),
+        notification("exit", Json::Null),
+    ]);
+
+    responses
+        .into_iter()
+        .find(|r| r.get("id").and_then(|id| id.as_usize()) == Some(2))
+        .and_then(|r| r.get("result").cloned())
+        .unwrap()
+}
+
+fn diagnostics(text: &str) -> Vec<Json> {
+    let responses = run_script(vec![did_open(text), notification("exit", Json::Null)]);
+    let notification = &responses[0];
+
+    assert_eq!(
+        Some("textDocument/publishDiagnostics"),
+        notification.get("method").and_then(|m| m.as_str())
+    );
+
+    notification
+        .path(&["params", "diagnostics"])
+        .and_then(|d| d.as_array())
+        .cloned()
+        .unwrap()
+}
+
+fn message(diagnostic: &Json) -> &str {
+    diagnostic.get("message").and_then(|m| m.as_str()).unwrap()
+}
+
+#[test]
+fn initialize_returns_capabilities() {
+    let responses = run_script(vec![
+        request(1, "initialize", Json::object(vec![])),
+        request(2, "shutdown", Json::Null),
+        notification("exit", Json::Null),
+    ]);
+
+    assert_eq!(2, responses.len());
+    let capabilities = responses[0].path(&["result", "capabilities"]).unwrap();
+    assert_eq!(Some(&Json::Bool(true)), capabilities.get("hoverProvider"));
+    assert_eq!(
+        Some(&Json::Bool(true)),
+        capabilities.get("definitionProvider")
+    );
+    assert_eq!(Some(&Json::Null), responses[1].get("result"));
+}
+
+#[test]
+fn exit_stops_reading_messages() {
+    let responses = run_script(vec![
+        notification("exit", Json::Null),
+        request(1, "initialize", Json::object(vec![])),
+    ]);
+
+    assert!(responses.is_empty());
+}
+
+#[test]
+fn unknown_method_returns_err() {
+    let responses = run_script(vec![
+        request(1, "textDocument/rename", Json::Null),
+        notification("exit", Json::Null),
+    ]);
+
+    assert_eq!(
+        Some(METHOD_NOT_FOUND),
+        responses[0]
+            .path(&["error", "code"])
+            .and_then(|c| c.as_f64())
+    );
+}
+
+#[test]
+fn invalid_json_returns_parse_error() {
+    let mut input = b"Content-Length: 3\r\n\r\n{a}".to_vec();
+    write_message(&mut input, &notification("exit", Json::Null)).unwrap();
+
+    let mut output = vec![];
+    serve(&mut input.as_slice(), &mut output).unwrap();
+
+    let message = read_message(&mut output.as_slice()).unwrap().unwrap();
+    let response = json::parse(&message).unwrap();
+    assert_eq!(
+        Some(PARSE_ERROR),
+        response.path(&["error", "code"]).and_then(|c| c.as_f64())
+    );
+}
+
+#[test]
+fn valid_document_publishes_no_diagnostics() {
+    assert!(diagnostics(SOURCE).is_empty());
+}
+
+#[test]
+fn syntax_errors_publish_all_diagnostics() {
+    let diagnostics = diagnostics("struct A { i32 }\nstruct B(;");
+
+    assert_eq!(2, diagnostics.len());
+    assert_eq!("Expected identifier, got }", message(&diagnostics[0]));
+    assert_eq!(
+        Some(&Json::object(vec![
+            ("line", 0usize.into()),
+            ("character", 15usize.into())
+        ])),
+        diagnostics[0].path(&["range", "start"])
+    );
+    assert_eq!("Expected identifier, got ;", message(&diagnostics[1]));
+}
+
+#[test]
+fn env_errors_publish_diagnostics() {
+    let diagnostics = diagnostics("component A { Missing m }");
+
+    assert_eq!(1, diagnostics.len());
+    assert_eq!(
+        "Referenced struct type 'Missing' does not exist for property 'm' for component 'A'",
+        message(&diagnostics[0])
+    );
+}
+
+#[test]
+fn did_change_republishes_diagnostics() {
+    let change = notification(
+        "textDocument/didChange",
+        Json::object(vec![
+            ("textDocument", Json::object(vec![("uri", URI.into())])),
+            (
+                "contentChanges",
+                vec![Json::object(vec![("text", "struct A;".into())])].into(),
+            ),
+        ]),
+    );
+    let responses = run_script(vec![
+        did_open("struct A {"),
+        change,
+        notification("exit", Json::Null),
+    ]);
+
+    let count = |r: &Json| {
+        r.path(&["params", "diagnostics"])
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .len()
+    };
+    assert_eq!(2, responses.len());
+    assert_eq!(1, count(&responses[0]));
+    assert_eq!(0, count(&responses[1]));
+}
+
+#[test]
+fn definition_of_field_type_returns_declaration() {
+    let result = request_result(
+        SOURCE,
+        "textDocument/definition",
+        text_document_position(5, 5),
+    );
+
+    assert_eq!(Some(URI), result.get("uri").and_then(|u| u.as_str()));
+    assert_eq!(
+        Some(&Json::object(vec![
+            ("line", 0usize.into()),
+            ("character", 0usize.into())
+        ])),
+        result.path(&["range", "start"])
+    );
+    assert_eq!(
+        Some(&Json::object(vec![
+            ("line", 2usize.into()),
+            ("character", 1usize.into())
+        ])),
+        result.path(&["range", "end"])
+    );
+}
+
+#[test]
+fn definition_of_primitive_returns_null() {
+    let result = request_result(
+        SOURCE,
+        "textDocument/definition",
+        text_document_position(6, 5),
+    );
+
+    assert_eq!(Json::Null, result);
+}
+
+#[test]
+fn hover_field_returns_type_and_size() {
+    let result = request_result(SOURCE, "textDocument/hover", text_document_position(5, 6));
+
+    assert_eq!(
+        Some("```game_dsl\nName name\n```\n`Name` resolves to `struct Name` (0:0)\nSize: 16 bytes, alignment: 4"),
+        result.path(&["contents", "value"]).and_then(|v| v.as_str())
+    );
+}
+
+#[test]
+fn hover_declaration_returns_size() {
+    let result = request_result(SOURCE, "textDocument/hover", text_document_position(4, 3));
+
+    assert_eq!(
+        Some("```game_dsl\ncomponent Person\n```\nSize: 20 bytes, alignment: 4"),
+        result.path(&["contents", "value"]).and_then(|v| v.as_str())
+    );
+}
+
+#[test]
+fn document_symbols_returns_declarations() {
+    let params = Json::object(vec![(
+        "textDocument",
+        Json::object(vec![("uri", URI.into())]),
+    )]);
+    let result = request_result(SOURCE, "textDocument/documentSymbol", params);
+    let symbols = result.as_array().unwrap();
+
+    let names: Vec<&str> = symbols
+        .iter()
+        .map(|s| s.get("name").and_then(|n| n.as_str()).unwrap())
+        .collect();
+    assert_eq!(vec!["Name", "Person", "WorldState"], names);
+    assert_eq!(
+        Some(SYMBOL_KIND_STRUCT),
+        symbols[0].get("kind").and_then(|k| k.as_usize())
+    );
+    assert_eq!(
+        Some(SYMBOL_KIND_CLASS),
+        symbols[1].get("kind").and_then(|k| k.as_usize())
+    );
+
+    let children = symbols[1]
+        .get("children")
+        .and_then(|c| c.as_array())
+        .unwrap();
+    assert_eq!(2, children.len());
+    assert_eq!(
+        Some("age"),
+        children[1].get("name").and_then(|n| n.as_str())
+    );
+    assert_eq!(
+        Some("i32"),
+        children[1].get("detail").and_then(|n| n.as_str())
+    );
+}
+
+#[test]
+fn completion_in_read_list_returns_components() {
+    let text = format!("{}\nsystem Aging {{\n    read [Wor", SOURCE);
+    let result = request_result(
+        &text,
+        "textDocument/completion",
+        text_document_position(12, 13),
+    );
+
+    let labels: Vec<&str> = result
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c.get("label").and_then(|l| l.as_str()).unwrap())
+        .collect();
+    assert_eq!(vec!["Person", "WorldState"], labels);
+}
+
+#[test]
+fn completion_outside_list_returns_nothing() {
+    let text = format!("{}\nsystem Aging {{\n    read [Person]\n    ", SOURCE);
+    let result = request_result(
+        &text,
+        "textDocument/completion",
+        text_document_position(13, 4),
+    );
+
+    assert_eq!(Some(&vec![]), result.as_array());
+}
+
+#[test]
+fn oversized_message_is_rejected() {
+    let input = format!("Content-Length: {}\r\n\r\n", MAX_MESSAGE_SIZE + 1);
+
+    let error = read_message(&mut input.as_bytes()).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, error.kind());
+}
@@ -1,19 +1,48 @@
+mod cli;
 mod compiler;
 /// Intermediate represation of all primitives for a compiler.
 mod compiler_ir;
 mod env;
 mod error;
+mod formatter;
+mod graph;
 mod ir_builder;
+mod json;
+mod layout;
 mod lexer;
 mod location;
+mod lsp;
 mod parser;
 mod unchecked_env;
 
-use std::path::Path;
+use std::{path::Path, process::ExitCode};
 
 use location::Location;
 
-fn main() {
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(|a| a.as_str()) {
+        Some("lsp") => match lsp::run() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("Language server error: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        Some("fmt") => cli::fmt(&args[1..]),
+        Some("layout") => cli::layout(&args[1..]),
+        Some("schema") => cli::schema(&args[1..]),
+        Some("graph") => cli::graph(&args[1..]),
+        _ => {
+            run_example();
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+/// Compiles and runs the example from the language docs.
+fn run_example() {
     let input = r#"
     # Structs can be empty with a semicolon.
     struct Empty;
@@ -71,6 +100,12 @@ fn main() {
         Name name
         i32 age
     }
+
+    # Stores keep each field of an 'soa' component in its own array.
+    component Velocity soa {
+        f32 x
+        f32 y
+    }
     
     # This is an example of a struct component.
     single_component WorldState {
@@ -87,7 +122,15 @@ fn main() {
     
     "#;
 
-    let ast = parser::parse(input, Location::default()).unwrap();
+    let ast = match parser::parse(input, Location::default()) {
+        Ok(ast) => ast,
+        Err(errors) => {
+            for error in errors {
+                println!("{}", error.message);
+            }
+            return;
+        }
+    };
     let unchecked_env = unchecked_env::build(ast);
     let env = match env::build(unchecked_env) {
         Ok(env) => env,
@@ -98,21 +141,27 @@ fn main() {
             return;
         }
     };
-    let compiler_ir = ir_builder::build(env).unwrap();
+    let mut compiler_ir = ir_builder::build(env).unwrap();
+    layout::reorder_fields(&mut compiler_ir);
     let artifacts = compiler::build(compiler_ir);
 
     // Save to file and compile
     std::fs::remove_dir_all(Path::new("../_generated/")).unwrap();
-    for artifact in artifacts.iter() {
-        println!("Compiling {:?}...", artifact.target);
+    let target_dir = |artifact: &compiler::Artifact| {
         let mut path = std::path::PathBuf::from("../_generated/");
-        path.push(&format!("{:?}", artifact.target));
+        path.push(format!("{:?}", artifact.target));
+        path
+    };
 
-        let src_dir = path.clone();
-        std::fs::create_dir_all(&path).unwrap();
+    // Stubs are included by the generated code, so everything is written before compiling
+    for artifact in artifacts.iter() {
+        compiler::write_artifact(artifact, &target_dir(artifact)).unwrap();
+    }
 
-        path.push(&artifact.path);
-        std::fs::write(&path, &artifact.contents).unwrap();
+    for artifact in artifacts.iter().filter(|a| !a.stub) {
+        println!("Compiling {:?}...", artifact.target);
+        let src_dir = target_dir(artifact);
+        let path = src_dir.join(&artifact.path);
 
         let cmd = match artifact.target {
             compiler::Target::C => "gcc",
@@ -122,7 +171,7 @@ fn main() {
         let mut cmd = std::process::Command::new(cmd);
         cmd.arg(&path);
         cmd.arg("-o");
-        cmd.arg(format!("{}/a.out", src_dir.to_str().unwrap().to_string()));
+        cmd.arg(format!("{}/a.out", src_dir.to_str().unwrap()));
 
         let output = cmd.output().unwrap();
         println!("{}", String::from_utf8_lossy(&output.stdout));
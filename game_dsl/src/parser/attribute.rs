@@ -0,0 +1,94 @@
+use crate::{error::Error, lexer::TokenValue};
+
+use super::Tokens;
+
+pub const AUTO_ID: &str = "auto";
+pub const ORDERED_ID: &str = "ordered";
+pub const SOA_ID: &str = "soa";
+
+/// A modifier written after the name of a declaration. E.g. `struct Header ordered { .. }`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attribute {
+    /// Derives the stages of the world from what its systems read and write.
+    Auto,
+    /// Keeps fields in declaration order instead of reordering them to reduce padding.
+    Ordered,
+    /// Stores each field of a component in its own array instead of an array of components.
+    Soa,
+}
+
+impl Attribute {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Attribute::Auto => AUTO_ID,
+            Attribute::Ordered => ORDERED_ID,
+            Attribute::Soa => SOA_ID,
+        }
+    }
+}
+
+impl std::fmt::Display for Attribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+/// Parses all attributes following a declaration name.
+/// Stops at the first token that isn't an attribute, leaving it for the declaration to handle.
+pub fn parse_attributes(tokens: &mut Tokens) -> Result<Vec<Attribute>, Error> {
+    let mut attributes = vec![];
+
+    loop {
+        let attribute = match tokens.peek() {
+            Some(TokenValue::Identifier(id)) if id == AUTO_ID => Attribute::Auto,
+            Some(TokenValue::Identifier(id)) if id == ORDERED_ID => Attribute::Ordered,
+            Some(TokenValue::Identifier(id)) if id == SOA_ID => Attribute::Soa,
+            _ => break,
+        };
+
+        let (id, token) = tokens.pop_identifier()?;
+        if attributes.contains(&attribute) {
+            return Err(Error::new(
+                format!("Duplicate attribute '{}'", id),
+                token.start_location,
+            ));
+        }
+
+        attributes.push(attribute);
+    }
+
+    Ok(attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::lex;
+
+    #[test]
+    fn parse_attributes_stops_at_body() {
+        let mut tokens = lex("ordered soa { i32 a }");
+        assert_eq!(
+            Ok(vec![Attribute::Ordered, Attribute::Soa]),
+            parse_attributes(&mut tokens)
+        );
+        assert!(tokens.peek_expected(TokenValue::LCurlyBrace));
+    }
+
+    #[test]
+    fn parse_attributes_leaves_other_identifiers() {
+        let mut tokens = lex("sorted;");
+        assert_eq!(Ok(vec![]), parse_attributes(&mut tokens));
+        assert!(tokens.peek_identifier("sorted"));
+    }
+
+    #[test]
+    fn parse_attributes_duplicate_returns_err() {
+        let mut tokens = lex("ordered ordered;");
+        let expected = Err(Error::new(
+            "Duplicate attribute 'ordered'".to_string(),
+            (0, 8).into(),
+        ));
+        assert_eq!(expected, parse_attributes(&mut tokens));
+    }
+}
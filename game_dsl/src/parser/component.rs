@@ -1,13 +1,17 @@
 use crate::{error::Error, lexer::TokenValue, location::Location};
 
 use super::{
-    is_reserved_word, parse_listable_primitive, Listable, Primitive, Tokens, COMPONENT_ID,
-    SINGLE_COMPONENT_ID,
+    parse_attributes, parse_listable_primitive, Attribute, Listable, Primitive, Tokens,
+    COMPONENT_ID, EVENT_ID, SINGLE_COMPONENT_ID,
 };
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Component {
     pub id: String,
+    pub comments: Vec<String>,
+    /// Comments after the last property, which stay at the end of the body.
+    pub trailing_comments: Vec<String>,
+    pub attributes: Vec<Attribute>,
     pub component_type: ComponentType,
     pub properties: ComponentProperties,
     pub start_location: Location,
@@ -18,6 +22,8 @@ pub struct Component {
 pub enum ComponentType {
     SingleComponent,
     Component,
+    /// Data that systems emit and read during a tick rather than attach to entities.
+    Event,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -30,25 +36,28 @@ pub enum ComponentProperties {
 #[derive(Debug, Clone, PartialEq)]
 pub struct ComponentProperty {
     pub identifier: String,
+    pub comments: Vec<String>,
+    /// A comment on the same line after the property. It isn't documentation.
+    pub trailing_comment: Option<String>,
     pub ty: Listable<Primitive>,
     pub start_location: Location,
     pub end_location: Location,
 }
 
 pub fn parse(tokens: &mut Tokens) -> Result<Component, Error> {
-    let start_location;
     let end_location;
 
     let (component_type, token) = tokens.pop_identifier()?;
-    start_location = token.start_location.clone();
+    let start_location = token.start_location.clone();
 
     let component_type = match component_type.as_str() {
         COMPONENT_ID => ComponentType::Component,
         SINGLE_COMPONENT_ID => ComponentType::SingleComponent,
+        EVENT_ID => ComponentType::Event,
         other => {
             return Err(Error::new(
                 format!(
-                    "Expected '{COMPONENT_ID}' or '{SINGLE_COMPONENT_ID}', got '{}'",
+                    "Expected '{COMPONENT_ID}', '{SINGLE_COMPONENT_ID}' or '{EVENT_ID}', got '{}'",
                     other
                 ),
                 token.start_location.clone(),
@@ -56,7 +65,9 @@ pub fn parse(tokens: &mut Tokens) -> Result<Component, Error> {
         }
     };
 
-    let (id, token) = tokens.pop_identifier()?;
+    let (id, _) = tokens.pop_identifier()?;
+    let attributes = parse_attributes(tokens)?;
+    let mut trailing_comments = vec![];
 
     // Get properties
     let properties = {
@@ -76,13 +87,29 @@ pub fn parse(tokens: &mut Tokens) -> Result<Component, Error> {
         else if tokens.peek_expected(TokenValue::LCurlyBrace) {
             tokens.pop_expected(TokenValue::LCurlyBrace)?;
 
-            let mut properties = vec![];
+            let mut properties: Vec<ComponentProperty> = vec![];
+            let mut property_comments = vec![];
             while !tokens.is_empty() && !tokens.peek_expected(TokenValue::RCurlyBrace) {
+                // Comments document the property that follows them, except a comment on the same
+                // line as a property, which trails it
+                if tokens.peek_comment() {
+                    let (mut lines, token) = tokens.pop_comment()?;
+                    if let Some(p) = properties.last_mut() {
+                        if p.end_location.line() == token.start_location.line() {
+                            p.trailing_comment = Some(lines.remove(0));
+                        }
+                    }
+                    property_comments.extend(lines);
+                    continue;
+                }
+
                 let prop_type = parse_listable_primitive(tokens)?;
                 let (identifier, token) = tokens.pop_identifier()?;
 
                 let property = ComponentProperty {
                     identifier,
+                    comments: std::mem::take(&mut property_comments),
+                    trailing_comment: None,
                     start_location: prop_type.start_location.clone(),
                     end_location: token.end_location.clone(),
                     ty: prop_type,
@@ -93,6 +120,8 @@ pub fn parse(tokens: &mut Tokens) -> Result<Component, Error> {
             let token = tokens.pop_expected(TokenValue::RCurlyBrace)?;
             end_location = token.end_location.clone();
 
+            trailing_comments = property_comments;
+
             if properties.is_empty() {
                 ComponentProperties::None
             } else {
@@ -109,6 +138,9 @@ pub fn parse(tokens: &mut Tokens) -> Result<Component, Error> {
 
     Ok(Component {
         id,
+        comments: vec![],
+        trailing_comments,
+        attributes,
         properties,
         component_type,
         start_location,
@@ -119,7 +151,7 @@ pub fn parse(tokens: &mut Tokens) -> Result<Component, Error> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::{lex, ListType, PrimitiveType, RESERVED_WORDS};
+    use crate::parser::{lex, ListType, PrimitiveType};
 
     #[test]
     fn not_component_returns_err() {
@@ -127,7 +159,7 @@ mod tests {
         let mut tokens = lex(input);
         let result = parse(&mut tokens);
         let expected = Err(Error::new(
-            "Expected 'component' or 'single_component', got 'not_component'".to_string(),
+            "Expected 'component', 'single_component' or 'event', got 'not_component'".to_string(),
             (0, 0).into(),
         ));
         assert_eq!(result, expected);
@@ -140,6 +172,9 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Component {
             id: "IsAlive".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: ComponentProperties::None,
             component_type: ComponentType::Component,
             start_location: (0, 0).into(),
@@ -155,6 +190,9 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Component {
             id: "IsAlive".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: ComponentProperties::None,
             component_type: ComponentType::SingleComponent,
             start_location: (0, 0).into(),
@@ -163,6 +201,19 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn event_type() {
+        let input = "event Collision { Entity a Entity b }";
+        let mut tokens = lex(input);
+        let component = parse(&mut tokens).unwrap();
+
+        assert_eq!(ComponentType::Event, component.component_type);
+        assert!(matches!(
+            component.properties,
+            ComponentProperties::Multiple(ref properties) if properties.len() == 2
+        ));
+    }
+
     #[test]
     fn tag_type_missing_semicolon_returns_err() {
         let input = "component IsAlive";
@@ -182,6 +233,9 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Component {
             id: "Hp".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: ComponentProperties::Value(Listable {
                 ty: ListType::Single(Primitive {
                     primitive_type: PrimitiveType::I32,
@@ -206,6 +260,9 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Component {
             id: "Hp".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: ComponentProperties::Value(Listable {
                 ty: ListType::List {
                     ty: Primitive {
@@ -259,6 +316,9 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Component {
             id: "Hp".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: ComponentProperties::None,
             component_type: ComponentType::Component,
             start_location: (0, 0).into(),
@@ -275,8 +335,13 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Component {
             id: "Hp".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: ComponentProperties::Multiple(vec![ComponentProperty {
                 identifier: "value".to_string(),
+                comments: vec![],
+                trailing_comment: None,
                 ty: Listable {
                     ty: ListType::Single(Primitive {
                         primitive_type: PrimitiveType::I32,
@@ -304,8 +369,13 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Component {
             id: "Hp".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: ComponentProperties::Multiple(vec![ComponentProperty {
                 identifier: "value".to_string(),
+                comments: vec![],
+                trailing_comment: None,
                 ty: Listable {
                     ty: ListType::List {
                         ty: Primitive {
@@ -336,9 +406,14 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Component {
             id: "Position".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: ComponentProperties::Multiple(vec![
                 ComponentProperty {
                     identifier: "x".to_string(),
+                    comments: vec![],
+                    trailing_comment: None,
                     ty: Listable {
                         ty: ListType::Single(Primitive {
                             primitive_type: PrimitiveType::I32,
@@ -353,6 +428,8 @@ mod tests {
                 },
                 ComponentProperty {
                     identifier: "y".to_string(),
+                    comments: vec![],
+                    trailing_comment: None,
                     ty: Listable {
                         ty: ListType::Single(Primitive {
                             primitive_type: PrimitiveType::I32,
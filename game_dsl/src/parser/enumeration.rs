@@ -0,0 +1,199 @@
+use super::{parse_listable_primitive, Listable, Primitive, Tokens, ENUM_ID};
+use crate::{error::Error, lexer::TokenValue, location::Location};
+
+/// A value that is exactly one of its variants, e.g. `enum Status { Ok, Damaged(i32) }`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enum {
+    pub id: String,
+    pub comments: Vec<String>,
+    pub variants: Vec<Variant>,
+    pub start_location: Location,
+    pub end_location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub id: String,
+    pub comments: Vec<String>,
+    /// A comment on the same line after the variant. It isn't documentation.
+    pub trailing_comment: Option<String>,
+    /// The value the variant holds, if any.
+    pub payload: Option<Listable<Primitive>>,
+    pub start_location: Location,
+    pub end_location: Location,
+}
+
+impl Enum {
+    pub fn variant(&self, id: &str) -> Option<&Variant> {
+        self.variants.iter().find(|v| v.id == id)
+    }
+
+    /// Whether no variant holds a value, so the enum is only its tag.
+    pub fn is_plain(&self) -> bool {
+        self.variants.iter().all(|v| v.payload.is_none())
+    }
+}
+
+pub fn parse(tokens: &mut Tokens) -> Result<Enum, Error> {
+    let (enum_ty, token) = tokens.pop_identifier()?;
+    let start_location = token.start_location.clone();
+
+    if enum_ty != ENUM_ID {
+        return Err(Error::new(
+            format!("Expected '{ENUM_ID}', got '{}'", enum_ty),
+            token.start_location.clone(),
+        ));
+    }
+
+    let (id, _) = tokens.pop_identifier()?;
+    tokens.pop_expected(TokenValue::LCurlyBrace)?;
+
+    let mut comments = vec![];
+    let mut variants: Vec<Variant> = vec![];
+    let mut variant_comments = vec![];
+    while !tokens.is_empty() && !tokens.peek_expected(TokenValue::RCurlyBrace) {
+        // Comments document the variant that follows them, except a comment on the same line as
+        // a variant, which trails it
+        if tokens.peek_comment() {
+            let (mut lines, token) = tokens.pop_comment()?;
+            if let Some(v) = variants.last_mut() {
+                if v.end_location.line() == token.start_location.line() {
+                    v.trailing_comment = Some(lines.remove(0));
+                }
+            }
+            variant_comments.extend(lines);
+            continue;
+        }
+
+        let (variant_id, token) = tokens.pop_identifier()?;
+        let mut end_location = token.end_location.clone();
+        let payload = if tokens.peek_expected(TokenValue::LParen) {
+            tokens.pop_expected(TokenValue::LParen)?;
+            let payload = parse_listable_primitive(tokens)?;
+            end_location = tokens.pop_expected(TokenValue::RParen)?.end_location;
+            Some(payload)
+        } else {
+            None
+        };
+
+        variants.push(Variant {
+            id: variant_id,
+            comments: std::mem::take(&mut variant_comments),
+            trailing_comment: None,
+            payload,
+            start_location: token.start_location,
+            end_location,
+        });
+
+        // Variants are separated by commas, with an optional one after the last
+        if tokens.peek_expected(TokenValue::Comma) {
+            tokens.pop();
+        } else if !tokens.is_empty()
+            && !tokens.peek_expected(TokenValue::RCurlyBrace)
+            && !tokens.peek_comment()
+        {
+            tokens.pop_expected(TokenValue::Comma)?;
+        }
+    }
+
+    let token = tokens.pop_expected(TokenValue::RCurlyBrace)?;
+
+    // Comments at the end of the body document the enum
+    comments.append(&mut variant_comments);
+
+    Ok(Enum {
+        id,
+        comments,
+        variants,
+        start_location,
+        end_location: token.end_location,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{lex, ListType, PrimitiveType};
+
+    #[test]
+    fn plain_enum() {
+        let input = "enum Status { Ok, Dead, }";
+        let mut tokens = lex(input);
+        let result = parse(&mut tokens);
+        let expected = Ok(Enum {
+            id: "Status".to_string(),
+            comments: vec![],
+            variants: vec![
+                Variant {
+                    id: "Ok".to_string(),
+                    comments: vec![],
+                    trailing_comment: None,
+                    payload: None,
+                    start_location: (0, 14).into(),
+                    end_location: (0, 16).into(),
+                },
+                Variant {
+                    id: "Dead".to_string(),
+                    comments: vec![],
+                    trailing_comment: None,
+                    payload: None,
+                    start_location: (0, 18).into(),
+                    end_location: (0, 22).into(),
+                },
+            ],
+            start_location: (0, 0).into(),
+            end_location: (0, 25).into(),
+        });
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn variants_with_payloads() {
+        let input = "enum Status {\n    # Lost hp\n    Damaged(i32)\n    Ok # Full hp\n}";
+        let mut tokens = lex(input);
+        let result = parse(&mut tokens);
+        let expected = Err(Error::new(
+            "Expected ,, got identifier: Ok".to_string(),
+            (3, 4).into(),
+        ));
+        assert_eq!(expected, result);
+
+        let input = "enum Status {\n    # Lost hp\n    Damaged([i32 2]),\n    Ok # Full hp\n}";
+        let mut tokens = lex(input);
+        let result = parse(&mut tokens).unwrap();
+
+        assert_eq!(vec!["Lost hp".to_string()], result.variants[0].comments);
+        assert!(result.variants[1].comments.is_empty());
+        assert_eq!(
+            Some("Full hp".to_string()),
+            result.variants[1].trailing_comment
+        );
+        assert_eq!(
+            Some(&ListType::List {
+                ty: Primitive {
+                    primitive_type: PrimitiveType::I32,
+                    start_location: (2, 13).into(),
+                    end_location: (2, 16).into(),
+                },
+                max_size: 2,
+            }),
+            result.variants[0].payload.as_ref().map(|p| &p.ty)
+        );
+        assert_eq!(None, result.variants[1].payload);
+        assert!(!result.is_plain());
+    }
+
+    #[test]
+    fn missing_closing_brace_returns_err() {
+        let input = "enum Status { Ok";
+        let mut tokens = lex(input);
+        let result = parse(&mut tokens);
+        let expected = Err(Error::new(
+            "Expected }, got nothing!".to_string(),
+            (0, 16).into(),
+        ));
+
+        assert_eq!(expected, result);
+    }
+}
@@ -0,0 +1,468 @@
+use super::Tokens;
+use crate::{
+    error::Error,
+    lexer::{Token, TokenValue},
+    location::Location,
+};
+
+pub const TRUE_ID: &str = "true";
+pub const FALSE_ID: &str = "false";
+pub const HAS_ID: &str = "has";
+pub const SINGLE_ID: &str = "single";
+
+/// A value computed in a system body, e.g. `1 + (e.SpeedModifier ?? 0)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub value: ExprValue,
+    pub start_location: Location,
+    pub end_location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprValue {
+    /// A number as written, e.g. `2` or `2.0`.
+    Number(String),
+    Bool(bool),
+    Identifier(String),
+    /// Access of a component on an entity or a field on a value, e.g. `e.Position.x`.
+    Field {
+        target: Box<Expr>,
+        field: String,
+    },
+    /// A method called on a value, e.g. `e.Collisions.push(other)`. Variants of an enum holding
+    /// a value are a call on the enum id, e.g. `Status.Damaged(5)`, while variants without a
+    /// value are a `Field` on it, e.g. `Status.Dead`.
+    Call {
+        target: Box<Expr>,
+        method: String,
+        args: Vec<Expr>,
+    },
+    /// Checks whether an entity has a component, e.g. `e has SpeedModifier`.
+    Has {
+        entity: String,
+        component: String,
+    },
+    /// The only instance of a single component, e.g. `single WorldState`.
+    Single {
+        component: String,
+    },
+    Unary {
+        op: UnaryOp,
+        expr: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// An expression in parentheses. Kept so formatting doesn't change the source.
+    Group(Box<Expr>),
+}
+
+/// A method of a list, e.g. `push` in `e.Collisions.push(other)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListMethod {
+    /// Adds an item to the end, unless the list is full.
+    Push,
+    /// Removes the last item and returns it, or the zero value if the list is empty.
+    Pop,
+    Clear,
+    Len,
+    /// Removes the item at an index, moving the items after it down. Indexes past the end are ignored.
+    RemoveAt,
+    Contains,
+}
+
+impl ListMethod {
+    pub fn from_id(id: &str) -> Option<Self> {
+        let method = match id {
+            "push" => ListMethod::Push,
+            "pop" => ListMethod::Pop,
+            "clear" => ListMethod::Clear,
+            "len" => ListMethod::Len,
+            "remove_at" => ListMethod::RemoveAt,
+            "contains" => ListMethod::Contains,
+            _ => return None,
+        };
+
+        Some(method)
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            ListMethod::Push => "push",
+            ListMethod::Pop => "pop",
+            ListMethod::Clear => "clear",
+            ListMethod::Len => "len",
+            ListMethod::RemoveAt => "remove_at",
+            ListMethod::Contains => "contains",
+        }
+    }
+
+    /// Whether the method changes the list, so it can only be called on lists that can be written to.
+    pub fn changes_list(&self) -> bool {
+        matches!(
+            self,
+            ListMethod::Push | ListMethod::Pop | ListMethod::Clear | ListMethod::RemoveAt
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Not,
+    Negate,
+}
+
+impl UnaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOp::Not => "!",
+            UnaryOp::Negate => "-",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    /// Uses the right side if the optional component on the left side is missing.
+    Coalesce,
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanEqual,
+    GreaterThan,
+    GreaterThanEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+}
+
+impl BinaryOp {
+    fn from_token(value: &TokenValue) -> Option<Self> {
+        let op = match value {
+            TokenValue::Coalesce => BinaryOp::Coalesce,
+            TokenValue::Or => BinaryOp::Or,
+            TokenValue::And => BinaryOp::And,
+            TokenValue::Equal => BinaryOp::Equal,
+            TokenValue::NotEqual => BinaryOp::NotEqual,
+            TokenValue::LChevron => BinaryOp::LessThan,
+            TokenValue::LessThanEqual => BinaryOp::LessThanEqual,
+            TokenValue::RChevron => BinaryOp::GreaterThan,
+            TokenValue::GreaterThanEqual => BinaryOp::GreaterThanEqual,
+            TokenValue::Plus => BinaryOp::Add,
+            TokenValue::Minus => BinaryOp::Subtract,
+            TokenValue::Asterisk => BinaryOp::Multiply,
+            TokenValue::Slash => BinaryOp::Divide,
+            TokenValue::Percent => BinaryOp::Modulo,
+            _ => return None,
+        };
+
+        Some(op)
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Coalesce => "??",
+            BinaryOp::Or => "||",
+            BinaryOp::And => "&&",
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::LessThan => "<",
+            BinaryOp::LessThanEqual => "<=",
+            BinaryOp::GreaterThan => ">",
+            BinaryOp::GreaterThanEqual => ">=",
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+        }
+    }
+
+    /// Operators with a higher precedence bind tighter. All operators are left associative.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Coalesce => 1,
+            BinaryOp::Or => 2,
+            BinaryOp::And => 3,
+            BinaryOp::Equal | BinaryOp::NotEqual => 4,
+            BinaryOp::LessThan
+            | BinaryOp::LessThanEqual
+            | BinaryOp::GreaterThan
+            | BinaryOp::GreaterThanEqual => 5,
+            BinaryOp::Add | BinaryOp::Subtract => 6,
+            BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo => 7,
+        }
+    }
+
+    /// Whether the operator compares values and results in a `bool`.
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Equal
+                | BinaryOp::NotEqual
+                | BinaryOp::LessThan
+                | BinaryOp::LessThanEqual
+                | BinaryOp::GreaterThan
+                | BinaryOp::GreaterThanEqual
+        )
+    }
+}
+
+/// Returns whether a number is written as a fraction, with a `.` or an exponent, e.g. `2.0` or `1e3`.
+pub fn is_fractional(number: &str) -> bool {
+    number.contains(['.', 'e', 'E'])
+}
+
+pub fn parse_expr(tokens: &mut Tokens) -> Result<Expr, Error> {
+    parse_binary(tokens, 0)
+}
+
+/// Parses binary operators by precedence climbing.
+fn parse_binary(tokens: &mut Tokens, min_precedence: u8) -> Result<Expr, Error> {
+    let mut lhs = parse_unary(tokens)?;
+
+    while let Some(op) = tokens.peek().and_then(BinaryOp::from_token) {
+        if op.precedence() <= min_precedence {
+            break;
+        }
+
+        tokens.pop();
+        let rhs = parse_binary(tokens, op.precedence())?;
+        lhs = Expr {
+            start_location: lhs.start_location.clone(),
+            end_location: rhs.end_location.clone(),
+            value: ExprValue::Binary {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+        };
+    }
+
+    Ok(lhs)
+}
+
+fn parse_unary(tokens: &mut Tokens) -> Result<Expr, Error> {
+    let op = match tokens.peek() {
+        Some(TokenValue::ExclamationMark) => UnaryOp::Not,
+        Some(TokenValue::Minus) => UnaryOp::Negate,
+        _ => return parse_postfix(tokens),
+    };
+
+    let start_location = tokens.pop().map(|t| t.start_location).unwrap_or_default();
+    let expr = parse_unary(tokens)?;
+    Ok(Expr {
+        start_location,
+        end_location: expr.end_location.clone(),
+        value: ExprValue::Unary {
+            op,
+            expr: Box::new(expr),
+        },
+    })
+}
+
+fn parse_postfix(tokens: &mut Tokens) -> Result<Expr, Error> {
+    let mut expr = parse_primary(tokens)?;
+
+    while tokens.peek_expected(TokenValue::Period) {
+        tokens.pop();
+        let (field, token) = tokens.pop_identifier()?;
+
+        if tokens.peek_expected(TokenValue::LParen) {
+            tokens.pop();
+            let mut args = vec![];
+            while !tokens.is_empty() && !tokens.peek_expected(TokenValue::RParen) {
+                if !args.is_empty() {
+                    tokens.pop_expected(TokenValue::Comma)?;
+                }
+                args.push(parse_expr(tokens)?);
+            }
+            let token = tokens.pop_expected(TokenValue::RParen)?;
+            expr = Expr {
+                start_location: expr.start_location.clone(),
+                end_location: token.end_location,
+                value: ExprValue::Call {
+                    target: Box::new(expr),
+                    method: field,
+                    args,
+                },
+            };
+            continue;
+        }
+
+        expr = Expr {
+            start_location: expr.start_location.clone(),
+            end_location: token.end_location,
+            value: ExprValue::Field {
+                target: Box::new(expr),
+                field,
+            },
+        };
+    }
+
+    Ok(expr)
+}
+
+fn parse_primary(tokens: &mut Tokens) -> Result<Expr, Error> {
+    let token = match tokens.pop() {
+        Some(token) => token,
+        None => {
+            return Err(Error::new(
+                "Expected expression, got nothing!".to_string(),
+                tokens.last_location(),
+            ))
+        }
+    };
+
+    let Token {
+        value,
+        start_location,
+        end_location,
+    } = token;
+    let value = match value {
+        TokenValue::Number(n) => ExprValue::Number(n),
+        TokenValue::Identifier(id) if id == TRUE_ID => ExprValue::Bool(true),
+        TokenValue::Identifier(id) if id == FALSE_ID => ExprValue::Bool(false),
+        TokenValue::Identifier(entity) if tokens.peek_identifier(HAS_ID) => {
+            tokens.pop();
+            let (component, token) = tokens.pop_identifier()?;
+            return Ok(Expr {
+                value: ExprValue::Has { entity, component },
+                start_location,
+                end_location: token.end_location,
+            });
+        }
+        TokenValue::Identifier(id)
+            if id == SINGLE_ID && matches!(tokens.peek(), Some(TokenValue::Identifier(_))) =>
+        {
+            let (component, token) = tokens.pop_identifier()?;
+            return Ok(Expr {
+                value: ExprValue::Single { component },
+                start_location,
+                end_location: token.end_location,
+            });
+        }
+        TokenValue::Identifier(id) => ExprValue::Identifier(id),
+        TokenValue::LParen => {
+            let expr = parse_expr(tokens)?;
+            let token = tokens.pop_expected(TokenValue::RParen)?;
+            return Ok(Expr {
+                value: ExprValue::Group(Box::new(expr)),
+                start_location,
+                end_location: token.end_location,
+            });
+        }
+        value => {
+            return Err(Error::new(
+                format!("Expected expression, got {}", value.display_name()),
+                start_location,
+            ))
+        }
+    };
+
+    Ok(Expr {
+        value,
+        start_location,
+        end_location,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::lex;
+
+    /// Writes the expression back out with parentheses around every operation.
+    fn grouped(expr: &Expr) -> String {
+        match &expr.value {
+            ExprValue::Number(n) => n.clone(),
+            ExprValue::Bool(b) => b.to_string(),
+            ExprValue::Identifier(id) => id.clone(),
+            ExprValue::Field { target, field } => format!("{}.{}", grouped(target), field),
+            ExprValue::Call {
+                target,
+                method,
+                args,
+            } => format!(
+                "{}.{}({})",
+                grouped(target),
+                method,
+                args.iter().map(grouped).collect::<Vec<_>>().join(", ")
+            ),
+            ExprValue::Has { entity, component } => format!("({} has {})", entity, component),
+            ExprValue::Single { component } => format!("(single {})", component),
+            ExprValue::Unary { op, expr } => format!("({}{})", op.symbol(), grouped(expr)),
+            ExprValue::Binary { op, lhs, rhs } => {
+                format!("({} {} {})", grouped(lhs), op.symbol(), grouped(rhs))
+            }
+            ExprValue::Group(expr) => grouped(expr),
+        }
+    }
+
+    #[test]
+    fn parse_expr_uses_precedence() {
+        let mut tokens = lex("1 + e.Speed.x * 2 < 3 && !done || e has Dead ?? false");
+        assert_eq!(
+            "(((((1 + (e.Speed.x * 2)) < 3) && (!done)) || (e has Dead)) ?? false)",
+            grouped(&parse_expr(&mut tokens).unwrap())
+        );
+    }
+
+    #[test]
+    fn parse_enum_variants() {
+        let mut tokens = lex("Status.Damaged(e.Hp.max - 1) == Status.Dead");
+        let expr = parse_expr(&mut tokens).unwrap();
+        assert_eq!(
+            "(Status.Damaged((e.Hp.max - 1)) == Status.Dead)",
+            grouped(&expr)
+        );
+    }
+
+    #[test]
+    fn parse_method_calls() {
+        let mut tokens = lex("e.Collisions.contains(other) && e.Collisions.len() < 4");
+        let expr = parse_expr(&mut tokens).unwrap();
+        assert_eq!(
+            "(e.Collisions.contains(other) && (e.Collisions.len() < 4))",
+            grouped(&expr)
+        );
+    }
+
+    #[test]
+    fn parse_single_component() {
+        let mut tokens = lex("single WorldState.frame + 1");
+        let expr = parse_expr(&mut tokens).unwrap();
+        assert_eq!("((single WorldState).frame + 1)", grouped(&expr));
+    }
+
+    #[test]
+    fn parse_expr_is_left_associative() {
+        let mut tokens = lex("a - b - (c - d)");
+        let expr = parse_expr(&mut tokens).unwrap();
+        assert_eq!("((a - b) - (c - d))", grouped(&expr));
+        assert_eq!(Location::from((0, 15)), expr.end_location);
+    }
+
+    #[test]
+    fn parse_expr_stops_at_block() {
+        let mut tokens = lex("a < b {");
+        parse_expr(&mut tokens).unwrap();
+        assert!(tokens.peek_expected(TokenValue::LCurlyBrace));
+    }
+
+    #[test]
+    fn parse_expr_missing_operand_returns_err() {
+        let mut tokens = lex("a + }");
+        let expected = Err(Error::new(
+            "Expected expression, got }".to_string(),
+            (0, 4).into(),
+        ));
+        assert_eq!(expected, parse_expr(&mut tokens));
+    }
+}
@@ -8,6 +8,14 @@ pub enum ListType<T> {
     Single(T),
     List { ty: T, max_size: usize },
 }
+impl<T> ListType<T> {
+    pub fn inner_ty(&self) -> &T {
+        match self {
+            ListType::Single(ty) => ty,
+            ListType::List { ty, .. } => ty,
+        }
+    }
+}
 
 /// An element that may be a list or a single element.
 #[derive(Debug, Clone, PartialEq)]
@@ -18,9 +26,15 @@ pub struct Listable<T> {
 }
 impl<T> Listable<T> {
     pub fn inner_ty(&self) -> &T {
-        match self.ty {
-            ListType::Single(ref ty) => ty,
-            ListType::List { ref ty, .. } => ty,
+        self.ty.inner_ty()
+    }
+}
+
+impl<T: std::fmt::Display> std::fmt::Display for ListType<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListType::Single(ty) => write!(f, "{}", ty),
+            ListType::List { ty, max_size } => write!(f, "[{} {}]", ty, max_size),
         }
     }
 }
@@ -59,11 +73,19 @@ pub fn parse_listable<T>(
     {
         let (value, token) = tokens.pop_number()?;
 
-        max_size = value as usize;
+        max_size = match value.parse::<usize>() {
+            Ok(max_size) => max_size,
+            Err(_) => {
+                return Err(Error {
+                    message: format!("List size must be a whole number, found '{}'", value),
+                    location: token.start_location.clone(),
+                })
+            }
+        };
 
         if max_size == 0 {
             return Err(Error {
-                message: format!("List size must be greater than 0"),
+                message: "List size must be greater than 0".to_string(),
                 location: token.start_location.clone(),
             });
         }
@@ -115,8 +137,7 @@ mod tests {
     fn lex(input: &str) -> Tokens {
         let start_location = Location::default();
         let tokens = lexer::lex(input, start_location.clone()).unwrap();
-        let tokens = Tokens::new(tokens, start_location);
-        tokens
+        Tokens::new(tokens, start_location)
     }
 
     #[test]
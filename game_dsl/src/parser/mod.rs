@@ -1,22 +1,41 @@
+mod attribute;
 mod comment;
 mod component;
+mod enumeration;
+mod expr;
 mod listable;
 mod primitives;
+mod reference;
+mod statement;
 mod strukt;
+mod system;
 mod tokens;
+mod world;
 
-use crate::lexer::{self, Token, TokenValue};
+use crate::lexer::{self, TokenValue};
 use crate::{error::Error, location::Location};
+pub use attribute::*;
 pub use comment::*;
 pub use component::*;
+pub use enumeration::*;
+pub use expr::*;
 pub use listable::*;
 pub use primitives::*;
+pub use reference::*;
+pub use statement::*;
 pub use strukt::*;
+pub use system::*;
 pub use tokens::*;
+pub use world::*;
 
 pub const COMPONENT_ID: &str = "component";
 pub const SINGLE_COMPONENT_ID: &str = "single_component";
+pub const EVENT_ID: &str = "event";
 pub const STRUCT_ID: &str = "struct";
+pub const ENUM_ID: &str = "enum";
+pub const SYSTEM_ID: &str = "system";
+pub const EXTERNAL_SYSTEM_ID: &str = "external_system";
+pub const WORLD_ID: &str = "world";
 pub const U32_ID: &str = "u32";
 pub const U64_ID: &str = "u64";
 pub const I32_ID: &str = "i32";
@@ -26,10 +45,12 @@ pub const F64_ID: &str = "f64";
 pub const BOOL_ID: &str = "bool";
 pub const CHAR_ID: &str = "char";
 
-pub const RESERVED_WORDS: [&str; 11] = [
+pub const RESERVED_WORDS: [&str; 13] = [
     COMPONENT_ID,
     SINGLE_COMPONENT_ID,
+    EVENT_ID,
     STRUCT_ID,
+    ENUM_ID,
     U32_ID,
     U64_ID,
     I32_ID,
@@ -50,51 +71,148 @@ pub fn is_reserved_word(id: &str) -> bool {
     false
 }
 
-pub fn parse(code: &str, start_location: Location) -> Result<Vec<Ast>, Error> {
-    let tokens = lexer::lex(code, start_location.clone())?;
+/// Keywords that start a top level declaration. Used as synchronization points when recovering from errors.
+pub const DECLARATION_IDS: [&str; 8] = [
+    COMPONENT_ID,
+    SINGLE_COMPONENT_ID,
+    EVENT_ID,
+    STRUCT_ID,
+    ENUM_ID,
+    SYSTEM_ID,
+    EXTERNAL_SYSTEM_ID,
+    WORLD_ID,
+];
+
+pub fn is_declaration_word(id: &str) -> bool {
+    DECLARATION_IDS.contains(&id)
+}
+
+/// Parses the given code, returning every syntax error found if any.
+pub fn parse(code: &str, start_location: Location) -> Result<Vec<Ast>, Vec<Error>> {
+    let (asts, errors) = parse_partial(code, start_location);
+
+    if errors.is_empty() {
+        Ok(asts)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses the given code, recovering from syntax errors at the next declaration or the end of the current one.
+/// Returns all ASTs that were successfully parsed along with all errors.
+pub fn parse_partial(code: &str, start_location: Location) -> (Vec<Ast>, Vec<Error>) {
+    let tokens = match lexer::lex(code, start_location.clone()) {
+        Ok(tokens) => tokens,
+        Err(error) => return (vec![], vec![error]),
+    };
 
     let mut asts = vec![];
-    let mut tokens = Tokens::new(tokens, start_location.clone());
+    let mut errors = vec![];
+    let mut tokens = Tokens::new(tokens, start_location);
 
     while let Some(token) = tokens.pop() {
-        match &token.value {
+        let result = match &token.value {
             TokenValue::Identifier(i) => {
-                if i == COMPONENT_ID || i == SINGLE_COMPONENT_ID {
+                if i == COMPONENT_ID || i == SINGLE_COMPONENT_ID || i == EVENT_ID {
                     tokens.insert_head(token.clone());
-
-                    let component = component::parse(&mut tokens)?;
-                    asts.push(Ast::Component(component));
+                    component::parse(&mut tokens).map(Ast::Component)
                 } else if i == STRUCT_ID {
                     tokens.insert_head(token.clone());
-
-                    let strukt = strukt::parse(&mut tokens)?;
-                    asts.push(Ast::Struct(strukt));
+                    strukt::parse(&mut tokens).map(Ast::Struct)
+                } else if i == ENUM_ID {
+                    tokens.insert_head(token.clone());
+                    enumeration::parse(&mut tokens).map(Ast::Enum)
+                } else if i == SYSTEM_ID || i == EXTERNAL_SYSTEM_ID {
+                    tokens.insert_head(token.clone());
+                    system::parse(&mut tokens).map(Ast::System)
+                } else if i == WORLD_ID {
+                    tokens.insert_head(token.clone());
+                    world::parse(&mut tokens).map(Ast::World)
                 } else {
-                    let token = tokens.pop().unwrap();
-                    return Err(Error::new(
+                    Err(Error::new(
                         format!("Unexpected identifier {:?}", i),
                         token.start_location.clone(),
-                    ));
+                    ))
                 }
             }
-            TokenValue::Comment(lines) => {
-                let comment = Comment {
-                    lines: lines.clone(),
-                    start_location: token.start_location.clone(),
-                    end_location: token.end_location.clone(),
-                };
-                asts.push(Ast::Comment(comment));
+            TokenValue::Comment(lines) => Ok(Ast::Comment(Comment {
+                lines: lines.clone(),
+                start_location: token.start_location.clone(),
+                end_location: token.end_location.clone(),
+            })),
+            _ => Err(Error::new(
+                format!("Unexpected token {:?}", token),
+                token.start_location.clone(),
+            )),
+        };
+
+        match result {
+            Ok(mut ast) => {
+                attach_doc_comment(&mut asts, &mut ast);
+                asts.push(ast);
+            }
+            Err(error) => {
+                errors.push(error);
+                synchronize(&mut tokens);
+            }
+        }
+    }
+
+    (asts, errors)
+}
+
+/// Moves a comment that ends on the line right before a declaration into the declaration's comments.
+/// Comments trailing a previous declaration on the same line are left alone.
+fn attach_doc_comment(asts: &mut Vec<Ast>, ast: &mut Ast) {
+    let (start_location, comments) = match ast {
+        Ast::Component(c) => (&c.start_location, &mut c.comments),
+        Ast::Struct(s) => (&s.start_location, &mut s.comments),
+        Ast::Enum(e) => (&e.start_location, &mut e.comments),
+        Ast::System(s) => (&s.start_location, &mut s.comments),
+        Ast::World(w) => (&w.start_location, &mut w.comments),
+        Ast::Comment(_) => return,
+    };
+
+    let comment = match asts.last() {
+        Some(Ast::Comment(comment)) => comment,
+        _ => return,
+    };
+
+    let is_trailing = match asts.iter().rev().nth(1) {
+        Some(Ast::Component(c)) => c.end_location.line() == comment.start_location.line(),
+        Some(Ast::Struct(s)) => s.end_location.line() == comment.start_location.line(),
+        Some(Ast::Enum(e)) => e.end_location.line() == comment.start_location.line(),
+        Some(Ast::System(s)) => s.end_location.line() == comment.start_location.line(),
+        Some(Ast::World(w)) => w.end_location.line() == comment.start_location.line(),
+        _ => false,
+    };
+
+    if is_trailing || comment.end_location.line() + 1 != start_location.line() {
+        return;
+    }
+
+    if let Some(Ast::Comment(comment)) = asts.pop() {
+        comments.splice(0..0, comment.lines);
+    }
+}
+
+/// Skips tokens until the start of the next declaration or just past the curly brace closing the declaration the error was in.
+fn synchronize(tokens: &mut Tokens) {
+    while let Some(value) = tokens.peek() {
+        match value {
+            TokenValue::Identifier(i) if is_declaration_word(i) => {
+                tokens.reset_depth();
+                return;
+            }
+            TokenValue::RCurlyBrace if tokens.depth() <= 1 => {
+                tokens.pop();
+                return;
             }
             _ => {
-                return Err(Error::new(
-                    format!("Unexpected token {:?}", token),
-                    token.start_location.clone(),
-                ));
+                tokens.pop();
             }
         }
     }
-
-    Ok(asts)
 }
 
 #[cfg(test)]
@@ -110,4 +228,144 @@ pub enum Ast {
     Component(Component),
     Comment(Comment),
     Struct(Struct),
+    Enum(Enum),
+    System(System),
+    World(World),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(asts: &[Ast]) -> Vec<String> {
+        asts.iter()
+            .filter_map(|ast| match ast {
+                Ast::Component(c) => Some(c.id.clone()),
+                Ast::Struct(s) => Some(s.id.clone()),
+                Ast::Enum(e) => Some(e.id.clone()),
+                Ast::System(s) => Some(s.id.clone()),
+                Ast::World(_) => Some(WORLD_ID.to_string()),
+                Ast::Comment(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_returns_all_errors() {
+        let input = "component A { i32 }\nstruct B;\nstruct C(;";
+        let result = parse(input, Location::default());
+        let expected = Err(vec![
+            Error::new("Expected identifier, got }".to_string(), (0, 18).into()),
+            Error::new("Expected identifier, got ;".to_string(), (2, 9).into()),
+        ]);
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn parse_partial_returns_asts_after_errors() {
+        let input = "component A { i32 }\nstruct B;\nstruct C(;\ncomponent D;";
+        let (asts, errors) = parse_partial(input, Location::default());
+
+        assert_eq!(vec!["B".to_string(), "D".to_string()], ids(&asts));
+        assert_eq!(2, errors.len());
+    }
+
+    #[test]
+    fn parse_partial_syncs_on_closing_curly_brace() {
+        let input = "struct A { i32 ; } component B;";
+        let (asts, errors) = parse_partial(input, Location::default());
+
+        assert_eq!(vec!["B".to_string()], ids(&asts));
+        assert_eq!(
+            vec![Error::new(
+                "Expected identifier, got ;".to_string(),
+                (0, 15).into()
+            )],
+            errors
+        );
+    }
+
+    #[test]
+    fn parse_partial_skips_nested_blocks() {
+        let input = "system S {\n    if true {\n        x = )\n    }\n    y = 1\n}\nstruct B;";
+        let (asts, errors) = parse_partial(input, Location::default());
+
+        assert_eq!(vec!["B".to_string()], ids(&asts));
+        assert_eq!(
+            vec![Error::new(
+                "Expected expression, got )".to_string(),
+                (2, 12).into()
+            )],
+            errors
+        );
+    }
+
+    #[test]
+    fn parse_partial_unexpected_identifier_returns_err() {
+        let input = "foo bar\nstruct A;";
+        let (asts, errors) = parse_partial(input, Location::default());
+
+        assert_eq!(vec!["A".to_string()], ids(&asts));
+        assert_eq!(
+            vec![Error::new(
+                "Unexpected identifier \"foo\"".to_string(),
+                (0, 0).into()
+            )],
+            errors
+        );
+    }
+
+    #[test]
+    fn parse_attaches_preceding_comment_to_declaration() {
+        let input = "# Detached\n\n# The player's name.\nstruct Name;";
+        let asts = parse(input, Location::default()).unwrap();
+
+        assert_eq!(2, asts.len());
+        match &asts[1] {
+            Ast::Struct(s) => assert_eq!(vec!["The player's name.".to_string()], s.comments),
+            ast => panic!("Expected struct, got {:?}", ast),
+        }
+    }
+
+    #[test]
+    fn parse_does_not_attach_trailing_comment() {
+        let input = "struct A; # About A\nstruct B;";
+        let asts = parse(input, Location::default()).unwrap();
+
+        assert_eq!(3, asts.len());
+        match &asts[2] {
+            Ast::Struct(s) => assert!(s.comments.is_empty()),
+            ast => panic!("Expected struct, got {:?}", ast),
+        }
+    }
+
+    #[test]
+    fn parse_attaches_comments_to_properties() {
+        let input = "component Health {\n    # Current value\n    i32 current\n    i32 max # Upper bound\n}";
+        let asts = parse(input, Location::default()).unwrap();
+
+        let properties = match &asts[0] {
+            Ast::Component(Component {
+                properties: ComponentProperties::Multiple(properties),
+                ..
+            }) => properties,
+            ast => panic!("Expected component with properties, got {:?}", ast),
+        };
+        assert_eq!(vec!["Current value".to_string()], properties[0].comments);
+        assert!(properties[1].comments.is_empty());
+        assert_eq!(
+            Some("Upper bound".to_string()),
+            properties[1].trailing_comment
+        );
+    }
+
+    #[test]
+    fn parse_partial_lex_error_returns_err() {
+        let input = "struct A; \"unterminated";
+        let (asts, errors) = parse_partial(input, Location::default());
+
+        assert!(asts.is_empty());
+        assert_eq!(1, errors.len());
+    }
 }
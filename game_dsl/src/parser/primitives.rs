@@ -9,15 +9,6 @@ pub struct Primitive {
     pub end_location: Location,
 }
 
-impl Primitive {
-    pub fn is_identifier(&self) -> bool {
-        match &self.primitive_type {
-            PrimitiveType::Identifier(_) => true,
-            _ => false,
-        }
-    }
-}
-
 impl From<&str> for Primitive {
     fn from(value: &str) -> Self {
         Primitive {
@@ -49,21 +40,21 @@ impl From<&String> for Listable<Primitive> {
         Primitive::from(value).into()
     }
 }
-impl Into<Listable<Primitive>> for Primitive {
-    fn into(self) -> Listable<Primitive> {
+impl From<Primitive> for Listable<Primitive> {
+    fn from(value: Primitive) -> Self {
         Listable {
-            ty: ListType::Single(self),
+            ty: ListType::Single(value),
             start_location: Location::SystemDefined,
             end_location: Location::SystemDefined,
         }
     }
 }
 
-impl Into<Listable<Primitive>> for PrimitiveType {
-    fn into(self) -> Listable<Primitive> {
+impl From<PrimitiveType> for Listable<Primitive> {
+    fn from(value: PrimitiveType) -> Self {
         Listable {
             ty: ListType::Single(Primitive {
-                primitive_type: self,
+                primitive_type: value,
                 start_location: Location::SystemDefined,
                 end_location: Location::SystemDefined,
             }),
@@ -73,13 +64,6 @@ impl Into<Listable<Primitive>> for PrimitiveType {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Identifier {
-    pub name: String,
-    pub start_location: Location,
-    pub end_location: Location,
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub enum PrimitiveType {
     U32,
@@ -93,6 +77,29 @@ pub enum PrimitiveType {
     Identifier(String),
 }
 
+impl std::fmt::Display for PrimitiveType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let id = match self {
+            PrimitiveType::U32 => U32_ID,
+            PrimitiveType::U64 => U64_ID,
+            PrimitiveType::I32 => I32_ID,
+            PrimitiveType::I64 => I64_ID,
+            PrimitiveType::F32 => F32_ID,
+            PrimitiveType::F64 => F64_ID,
+            PrimitiveType::Bool => BOOL_ID,
+            PrimitiveType::Char => CHAR_ID,
+            PrimitiveType::Identifier(id) => id,
+        };
+        write!(f, "{}", id)
+    }
+}
+
+impl std::fmt::Display for Primitive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.primitive_type)
+    }
+}
+
 pub fn parse(tokens: &mut Tokens) -> Result<Primitive, Error> {
     let (id, token) = tokens.pop_identifier()?;
 
@@ -124,8 +131,7 @@ mod tests {
     fn lex(input: &str) -> Tokens {
         let start_location = Location::default();
         let tokens = lexer::lex(input, start_location.clone()).unwrap();
-        let tokens = Tokens::new(tokens, start_location);
-        tokens
+        Tokens::new(tokens, start_location)
     }
 
     #[test]
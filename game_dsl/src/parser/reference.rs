@@ -0,0 +1,66 @@
+use super::Tokens;
+use crate::{error::Error, lexer::TokenValue, location::Location};
+
+/// A use of a declaration by id, e.g. a component in the read list of a system.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub id: String,
+    pub start_location: Location,
+    pub end_location: Location,
+}
+
+impl From<&str> for Reference {
+    fn from(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            start_location: Location::SystemDefined,
+            end_location: Location::SystemDefined,
+        }
+    }
+}
+
+/// Parses a list of ids in square brackets, e.g. `[Position Velocity]`.
+/// Returns the references along with the location of the closing bracket.
+pub fn parse_references(tokens: &mut Tokens) -> Result<(Vec<Reference>, Location), Error> {
+    tokens.pop_expected(TokenValue::LSquareBracket)?;
+
+    let mut references = vec![];
+    while !tokens.is_empty() && !tokens.peek_expected(TokenValue::RSquareBracket) {
+        let (id, token) = tokens.pop_identifier()?;
+        references.push(Reference {
+            id,
+            start_location: token.start_location,
+            end_location: token.end_location,
+        });
+    }
+
+    let token = tokens.pop_expected(TokenValue::RSquareBracket)?;
+    Ok((references, token.end_location))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::lex;
+
+    #[test]
+    fn parse_references_returns_ids() {
+        let mut tokens = lex("[Position Velocity]");
+        let (references, end_location) = parse_references(&mut tokens).unwrap();
+
+        let ids: Vec<&str> = references.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(vec!["Position", "Velocity"], ids);
+        assert_eq!(Location::from((0, 10)), references[1].start_location);
+        assert_eq!(Location::from((0, 19)), end_location);
+    }
+
+    #[test]
+    fn parse_references_unclosed_returns_err() {
+        let mut tokens = lex("[Position");
+        let expected = Err(Error::new(
+            "Expected ], got nothing!".to_string(),
+            (0, 9).into(),
+        ));
+        assert_eq!(expected, parse_references(&mut tokens));
+    }
+}
@@ -0,0 +1,752 @@
+use super::{parse_expr, Expr, ExprValue, Reference, Tokens};
+use crate::{error::Error, lexer::TokenValue, location::Location};
+
+pub const FOR_ID: &str = "for";
+pub const WITH_ID: &str = "with";
+pub const WITHOUT_ID: &str = "without";
+pub const CHANGED_ID: &str = "changed";
+pub const IN_ID: &str = "in";
+pub const EVENTS_ID: &str = "events";
+pub const EMIT_ID: &str = "emit";
+pub const CREATE_ID: &str = "create";
+pub const IF_ID: &str = "if";
+pub const ELSE_ID: &str = "else";
+pub const MATCH_ID: &str = "match";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub value: StatementValue,
+    pub start_location: Location,
+    pub end_location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementValue {
+    Comment(Vec<String>),
+    /// Runs the body for every entity with the given components, e.g. `for e with Position, Hp? { .. }`.
+    /// Entities with a `without` component, or whose `changed` components weren't written this tick, are skipped.
+    Query {
+        entity: Reference,
+        terms: Vec<QueryTerm>,
+        without: Vec<Reference>,
+        changed: Vec<Reference>,
+        body: Vec<Statement>,
+    },
+    /// Runs the body for every event emitted this tick, e.g. `for ev in events Collision { .. }`.
+    Events {
+        event: Reference,
+        component: Reference,
+        body: Vec<Statement>,
+    },
+    /// Runs the body for every item of a list, oldest first, e.g. `for other in e.Collisions { .. }`.
+    Iterate {
+        item: Reference,
+        list: Expr,
+        body: Vec<Statement>,
+    },
+    /// Queues an event with a value for each of its fields, e.g. `emit Collision(a, b)`.
+    Emit {
+        component: Reference,
+        args: Vec<Expr>,
+    },
+    /// Creates an entity with the given components, e.g. `create [Position(1, 2) Hp(100) IsAlive]`.
+    Create {
+        components: Vec<Constructor>,
+    },
+    /// An `else if` is an `If` as the only statement of `otherwise`.
+    If {
+        condition: Expr,
+        then: Vec<Statement>,
+        otherwise: Vec<Statement>,
+    },
+    /// Runs the arm for the variant of an enum, e.g. `match e.Status { Ok {} Damaged(amount) { .. } }`.
+    Match {
+        value: Expr,
+        arms: Vec<MatchArm>,
+        /// Comments after the last arm.
+        comments: Vec<String>,
+    },
+    /// Assigning to an identifier that isn't declared yet declares a local.
+    Assign {
+        target: Expr,
+        op: AssignOp,
+        value: Expr,
+    },
+    /// A method called for its effect, e.g. `e.Collisions.clear()`.
+    Call(Expr),
+}
+
+/// A component given a value for each of its fields, e.g. `Position(1, 2)`.
+/// Components without fields can leave out the parentheses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constructor {
+    pub component: Reference,
+    pub args: Vec<Expr>,
+    pub start_location: Location,
+    pub end_location: Location,
+}
+
+/// A component a query iterates over. Optional components don't have to exist on the entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryTerm {
+    pub component: Reference,
+    pub optional: bool,
+}
+
+/// An arm of a `match`, which binds the value of its variant if it has a binding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub comments: Vec<String>,
+    /// The variant the arm runs for, or `None` for the `else` arm that runs for every other variant.
+    pub variant: Option<Reference>,
+    pub binding: Option<Reference>,
+    pub body: Vec<Statement>,
+    pub start_location: Location,
+    pub end_location: Location,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AssignOp {
+    Assign,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl AssignOp {
+    fn from_token(value: &TokenValue) -> Option<Self> {
+        let op = match value {
+            TokenValue::Assign => AssignOp::Assign,
+            TokenValue::PlusAssign => AssignOp::Add,
+            TokenValue::SubAssign => AssignOp::Subtract,
+            TokenValue::MulAssign => AssignOp::Multiply,
+            TokenValue::DivAssign => AssignOp::Divide,
+            _ => return None,
+        };
+
+        Some(op)
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            AssignOp::Assign => "=",
+            AssignOp::Add => "+=",
+            AssignOp::Subtract => "-=",
+            AssignOp::Multiply => "*=",
+            AssignOp::Divide => "/=",
+        }
+    }
+}
+
+/// Parses statements in curly braces. Returns the statements and the location of the closing brace.
+pub fn parse_block(tokens: &mut Tokens) -> Result<(Vec<Statement>, Location), Error> {
+    tokens.pop_expected(TokenValue::LCurlyBrace)?;
+
+    let mut statements = vec![];
+    while !tokens.is_empty() && !tokens.peek_expected(TokenValue::RCurlyBrace) {
+        statements.push(parse_statement(tokens)?);
+    }
+
+    let token = tokens.pop_expected(TokenValue::RCurlyBrace)?;
+    Ok((statements, token.end_location))
+}
+
+pub fn parse_statement(tokens: &mut Tokens) -> Result<Statement, Error> {
+    if tokens.peek_comment() {
+        let (lines, token) = tokens.pop_comment()?;
+        return Ok(Statement {
+            value: StatementValue::Comment(lines),
+            start_location: token.start_location,
+            end_location: token.end_location,
+        });
+    }
+
+    if tokens.peek_identifier(FOR_ID) {
+        return parse_query(tokens);
+    }
+
+    if tokens.peek_identifier(IF_ID) {
+        return parse_if(tokens);
+    }
+
+    // `match` can still be used as a local
+    let is_match = tokens.peek_identifier(MATCH_ID)
+        && tokens.peek_nth(1).is_some_and(|t| {
+            AssignOp::from_token(&t.value).is_none() && t.value != TokenValue::Period
+        });
+    if is_match {
+        return parse_match(tokens);
+    }
+
+    // `emit` can still be used as a local
+    if tokens.peek_identifier(EMIT_ID)
+        && matches!(
+            tokens.peek_nth(1).map(|t| &t.value),
+            Some(TokenValue::Identifier(_))
+        )
+    {
+        return parse_emit(tokens);
+    }
+
+    // `create` can still be used as a local
+    if tokens.peek_identifier(CREATE_ID) && tokens.peek_expected_nth(1, TokenValue::LSquareBracket)
+    {
+        return parse_create(tokens);
+    }
+
+    let target = parse_expr(tokens)?;
+    let is_assign = tokens
+        .peek()
+        .is_some_and(|t| AssignOp::from_token(t).is_some());
+    if matches!(target.value, ExprValue::Call { .. }) && !is_assign {
+        return Ok(Statement {
+            start_location: target.start_location.clone(),
+            end_location: target.end_location.clone(),
+            value: StatementValue::Call(target),
+        });
+    }
+
+    let op = match tokens.pop() {
+        Some(token) => match AssignOp::from_token(&token.value) {
+            Some(op) => op,
+            None => {
+                return Err(Error::new(
+                    format!("Expected assignment, got {}", token.display_name()),
+                    token.start_location,
+                ))
+            }
+        },
+        None => {
+            return Err(Error::new(
+                "Expected assignment, got nothing!".to_string(),
+                tokens.last_location(),
+            ))
+        }
+    };
+    let value = parse_expr(tokens)?;
+
+    Ok(Statement {
+        start_location: target.start_location.clone(),
+        end_location: value.end_location.clone(),
+        value: StatementValue::Assign { target, op, value },
+    })
+}
+
+fn parse_query(tokens: &mut Tokens) -> Result<Statement, Error> {
+    let (_, token) = tokens.pop_identifier()?;
+    let start_location = token.start_location;
+
+    let (id, token) = tokens.pop_identifier()?;
+    let entity = Reference {
+        id,
+        start_location: token.start_location,
+        end_location: token.end_location,
+    };
+
+    let (keyword, token) = tokens.pop_identifier()?;
+    // `events` can still be a list when it isn't followed by the event
+    let is_events = tokens.peek_identifier(EVENTS_ID)
+        && matches!(
+            tokens.peek_nth(1).map(|t| &t.value),
+            Some(TokenValue::Identifier(_))
+        );
+    if keyword == IN_ID && is_events {
+        return parse_events(tokens, entity, start_location);
+    }
+    if keyword == IN_ID {
+        let list = parse_expr(tokens)?;
+        let (body, end_location) = parse_block(tokens)?;
+        return Ok(Statement {
+            value: StatementValue::Iterate {
+                item: entity,
+                list,
+                body,
+            },
+            start_location,
+            end_location,
+        });
+    }
+    if keyword != WITH_ID {
+        return Err(Error::new(
+            format!("Expected '{WITH_ID}' or '{IN_ID}', got '{}'", keyword),
+            token.start_location,
+        ));
+    }
+
+    let mut terms = vec![parse_query_term(tokens)?];
+    while tokens.peek_expected(TokenValue::Comma) {
+        tokens.pop();
+        terms.push(parse_query_term(tokens)?);
+    }
+
+    // Filters can come in either order
+    let mut without = vec![];
+    let mut changed = vec![];
+    while tokens.peek_identifier(WITHOUT_ID) || tokens.peek_identifier(CHANGED_ID) {
+        let (keyword, token) = tokens.pop_identifier()?;
+        let filter = if keyword == WITHOUT_ID {
+            &mut without
+        } else {
+            &mut changed
+        };
+        if !filter.is_empty() {
+            return Err(Error::new(
+                format!("'{}' can only be used once per query", keyword),
+                token.start_location,
+            ));
+        }
+
+        filter.push(parse_filter_reference(tokens)?);
+        while tokens.peek_expected(TokenValue::Comma) {
+            tokens.pop();
+            filter.push(parse_filter_reference(tokens)?);
+        }
+    }
+
+    let (body, end_location) = parse_block(tokens)?;
+    Ok(Statement {
+        value: StatementValue::Query {
+            entity,
+            terms,
+            without,
+            changed,
+            body,
+        },
+        start_location,
+        end_location,
+    })
+}
+
+/// Parses the rest of `for ev in events Collision { .. }` after `in`.
+fn parse_events(
+    tokens: &mut Tokens,
+    event: Reference,
+    start_location: Location,
+) -> Result<Statement, Error> {
+    tokens.pop_identifier()?;
+    let component = parse_filter_reference(tokens)?;
+    let (body, end_location) = parse_block(tokens)?;
+    Ok(Statement {
+        value: StatementValue::Events {
+            event,
+            component,
+            body,
+        },
+        start_location,
+        end_location,
+    })
+}
+
+/// Parses `emit Collision(a, b)`. Events without fields can leave out the parentheses.
+fn parse_emit(tokens: &mut Tokens) -> Result<Statement, Error> {
+    let (_, token) = tokens.pop_identifier()?;
+    let start_location = token.start_location;
+
+    let Constructor {
+        component,
+        args,
+        end_location,
+        ..
+    } = parse_constructor(tokens)?;
+
+    Ok(Statement {
+        value: StatementValue::Emit { component, args },
+        start_location,
+        end_location,
+    })
+}
+
+/// Parses `create [Position(1, 2) IsAlive]`.
+fn parse_create(tokens: &mut Tokens) -> Result<Statement, Error> {
+    let (_, token) = tokens.pop_identifier()?;
+    let start_location = token.start_location;
+
+    tokens.pop_expected(TokenValue::LSquareBracket)?;
+    let mut components = vec![];
+    while !tokens.is_empty() && !tokens.peek_expected(TokenValue::RSquareBracket) {
+        components.push(parse_constructor(tokens)?);
+    }
+    let end_location = tokens
+        .pop_expected(TokenValue::RSquareBracket)?
+        .end_location;
+
+    Ok(Statement {
+        value: StatementValue::Create { components },
+        start_location,
+        end_location,
+    })
+}
+
+fn parse_constructor(tokens: &mut Tokens) -> Result<Constructor, Error> {
+    let component = parse_filter_reference(tokens)?;
+    let start_location = component.start_location.clone();
+    let mut end_location = component.end_location.clone();
+    let mut args = vec![];
+    if tokens.peek_expected(TokenValue::LParen) {
+        tokens.pop();
+        while !tokens.is_empty() && !tokens.peek_expected(TokenValue::RParen) {
+            if !args.is_empty() {
+                tokens.pop_expected(TokenValue::Comma)?;
+            }
+            args.push(parse_expr(tokens)?);
+        }
+        end_location = tokens.pop_expected(TokenValue::RParen)?.end_location;
+    }
+
+    Ok(Constructor {
+        component,
+        args,
+        start_location,
+        end_location,
+    })
+}
+
+fn parse_query_term(tokens: &mut Tokens) -> Result<QueryTerm, Error> {
+    let (id, token) = tokens.pop_identifier()?;
+    let mut component = Reference {
+        id,
+        start_location: token.start_location,
+        end_location: token.end_location,
+    };
+
+    let optional = tokens.peek_expected(TokenValue::QuestionMark);
+    if optional {
+        let token = tokens.pop_expected(TokenValue::QuestionMark)?;
+        component.end_location = token.end_location;
+    }
+
+    Ok(QueryTerm {
+        component,
+        optional,
+    })
+}
+
+fn parse_filter_reference(tokens: &mut Tokens) -> Result<Reference, Error> {
+    let (id, token) = tokens.pop_identifier()?;
+    Ok(Reference {
+        id,
+        start_location: token.start_location,
+        end_location: token.end_location,
+    })
+}
+
+fn parse_if(tokens: &mut Tokens) -> Result<Statement, Error> {
+    let (_, token) = tokens.pop_identifier()?;
+    let start_location = token.start_location;
+
+    let condition = parse_expr(tokens)?;
+    let (then, mut end_location) = parse_block(tokens)?;
+
+    let mut otherwise = vec![];
+    if tokens.peek_identifier(ELSE_ID) {
+        tokens.pop();
+        if tokens.peek_identifier(IF_ID) {
+            let statement = parse_if(tokens)?;
+            end_location = statement.end_location.clone();
+            otherwise.push(statement);
+        } else {
+            let (statements, location) = parse_block(tokens)?;
+            otherwise = statements;
+            end_location = location;
+        }
+    }
+
+    Ok(Statement {
+        value: StatementValue::If {
+            condition,
+            then,
+            otherwise,
+        },
+        start_location,
+        end_location,
+    })
+}
+
+fn parse_match(tokens: &mut Tokens) -> Result<Statement, Error> {
+    let (_, token) = tokens.pop_identifier()?;
+    let start_location = token.start_location;
+
+    let value = parse_expr(tokens)?;
+    tokens.pop_expected(TokenValue::LCurlyBrace)?;
+
+    let mut arms: Vec<MatchArm> = vec![];
+    let mut comments = vec![];
+    while !tokens.is_empty() && !tokens.peek_expected(TokenValue::RCurlyBrace) {
+        // Comments document the arm that follows them
+        if tokens.peek_comment() {
+            let (mut lines, _) = tokens.pop_comment()?;
+            comments.append(&mut lines);
+            continue;
+        }
+
+        let (id, token) = tokens.pop_identifier()?;
+        if arms.last().is_some_and(|a| a.variant.is_none()) {
+            return Err(Error::new(
+                format!("'{ELSE_ID}' must be the last arm of '{MATCH_ID}'"),
+                token.start_location,
+            ));
+        }
+
+        let variant = (id != ELSE_ID).then(|| Reference {
+            id,
+            start_location: token.start_location.clone(),
+            end_location: token.end_location.clone(),
+        });
+
+        let mut binding = None;
+        if variant.is_some() && tokens.peek_expected(TokenValue::LParen) {
+            tokens.pop();
+            binding = Some(parse_filter_reference(tokens)?);
+            tokens.pop_expected(TokenValue::RParen)?;
+        }
+
+        let (body, end_location) = parse_block(tokens)?;
+        arms.push(MatchArm {
+            comments: std::mem::take(&mut comments),
+            variant,
+            binding,
+            body,
+            start_location: token.start_location,
+            end_location,
+        });
+    }
+
+    let token = tokens.pop_expected(TokenValue::RCurlyBrace)?;
+    Ok(Statement {
+        value: StatementValue::Match {
+            value,
+            arms,
+            comments,
+        },
+        start_location,
+        end_location: token.end_location,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{lex, ExprValue};
+
+    #[test]
+    fn parse_query_with_optional_terms() {
+        let mut tokens = lex(
+            "for e with Position, SpeedModifier? {\n    e.Position.x += e.SpeedModifier ?? 1\n}",
+        );
+        let statement = parse_statement(&mut tokens).unwrap();
+
+        let (entity, terms, body) = match statement.value {
+            StatementValue::Query {
+                entity,
+                terms,
+                body,
+                ..
+            } => (entity, terms, body),
+            value => panic!("Expected query, got {:?}", value),
+        };
+        assert_eq!("e", entity.id);
+        let terms: Vec<(&str, bool)> = terms
+            .iter()
+            .map(|t| (t.component.id.as_str(), t.optional))
+            .collect();
+        assert_eq!(vec![("Position", false), ("SpeedModifier", true)], terms);
+        assert!(matches!(
+            body[0].value,
+            StatementValue::Assign {
+                op: AssignOp::Add,
+                ..
+            }
+        ));
+        assert_eq!(Location::from((2, 1)), statement.end_location);
+    }
+
+    #[test]
+    fn parse_query_filters() {
+        let mut tokens = lex("for e with Position changed Position without IsDead, Frozen {}");
+        let statement = parse_statement(&mut tokens).unwrap();
+
+        let ids = |references: &[Reference]| -> Vec<String> {
+            references.iter().map(|r| r.id.clone()).collect()
+        };
+        match statement.value {
+            StatementValue::Query {
+                without, changed, ..
+            } => {
+                assert_eq!(vec!["IsDead", "Frozen"], ids(&without));
+                assert_eq!(vec!["Position"], ids(&changed));
+            }
+            value => panic!("Expected query, got {:?}", value),
+        }
+    }
+
+    #[test]
+    fn parse_repeated_query_filter_returns_err() {
+        let mut tokens = lex("for e with Position without A without B {}");
+        let expected = Err(Error::new(
+            "'without' can only be used once per query".to_string(),
+            (0, 30).into(),
+        ));
+        assert_eq!(expected, parse_statement(&mut tokens));
+    }
+
+    #[test]
+    fn parse_else_if_chain() {
+        let mut tokens = lex("if a { x = 1 } else if b { x = 2 } else { x = 3 }");
+        let statement = parse_statement(&mut tokens).unwrap();
+
+        let otherwise = match statement.value {
+            StatementValue::If { otherwise, .. } => otherwise,
+            value => panic!("Expected if, got {:?}", value),
+        };
+        match &otherwise[0].value {
+            StatementValue::If {
+                condition,
+                otherwise,
+                ..
+            } => {
+                assert_eq!(ExprValue::Identifier("b".to_string()), condition.value);
+                assert_eq!(1, otherwise.len());
+            }
+            value => panic!("Expected else if, got {:?}", value),
+        }
+    }
+
+    #[test]
+    fn parse_statement_without_assignment_returns_err() {
+        let mut tokens = lex("e.Position }");
+        let expected = Err(Error::new(
+            "Expected assignment, got }".to_string(),
+            (0, 11).into(),
+        ));
+        assert_eq!(expected, parse_statement(&mut tokens));
+    }
+
+    #[test]
+    fn parse_query_without_with_returns_err() {
+        let mut tokens = lex("for e of Position {}");
+        let expected = Err(Error::new(
+            "Expected 'with' or 'in', got 'of'".to_string(),
+            (0, 6).into(),
+        ));
+        assert_eq!(expected, parse_statement(&mut tokens));
+    }
+
+    #[test]
+    fn parse_events_and_emit() {
+        let mut tokens =
+            lex("for ev in events Collision {\n    emit Hit(ev.a, 2)\n    emit Reset\n}");
+        let statement = parse_statement(&mut tokens).unwrap();
+
+        let body = match statement.value {
+            StatementValue::Events {
+                event,
+                component,
+                body,
+            } => {
+                assert_eq!("ev", event.id);
+                assert_eq!("Collision", component.id);
+                body
+            }
+            value => panic!("Expected events, got {:?}", value),
+        };
+        match &body[0].value {
+            StatementValue::Emit { component, args } => {
+                assert_eq!("Hit", component.id);
+                assert_eq!(2, args.len());
+            }
+            value => panic!("Expected emit, got {:?}", value),
+        }
+        assert!(matches!(
+            &body[1].value,
+            StatementValue::Emit { args, .. } if args.is_empty()
+        ));
+        assert_eq!(Location::from((2, 14)), body[1].end_location);
+    }
+
+    #[test]
+    fn parse_create() {
+        let mut tokens = lex("create [Position(1, 2) IsAlive]\ncreate = 1");
+        let statement = parse_statement(&mut tokens).unwrap();
+
+        match statement.value {
+            StatementValue::Create { components } => {
+                assert_eq!(
+                    vec!["Position", "IsAlive"],
+                    components
+                        .iter()
+                        .map(|c| c.component.id.as_str())
+                        .collect::<Vec<_>>()
+                );
+                assert_eq!(2, components[0].args.len());
+                assert!(components[1].args.is_empty());
+            }
+            value => panic!("Expected create, got {:?}", value),
+        }
+        assert_eq!(Location::from((0, 31)), statement.end_location);
+        assert!(matches!(
+            parse_statement(&mut tokens).unwrap().value,
+            StatementValue::Assign { .. }
+        ));
+    }
+
+    #[test]
+    fn parse_list_iteration_and_calls() {
+        let mut tokens = lex("for other in e.Collisions {\n    e.Collisions.clear()\n}");
+        let statement = parse_statement(&mut tokens).unwrap();
+
+        let (item, body) = match &statement.value {
+            StatementValue::Iterate { item, body, .. } => (item, body),
+            value => panic!("Expected iterate, got {:?}", value),
+        };
+        assert_eq!("other", item.id);
+        assert!(matches!(
+            &body[0].value,
+            StatementValue::Call(Expr { value: ExprValue::Call { method, args, .. }, .. })
+                if method == "clear" && args.is_empty()
+        ));
+        assert_eq!(Location::from((1, 24)), body[0].end_location);
+    }
+
+    #[test]
+    fn parse_match_arms() {
+        let mut tokens = lex(
+            "match e.Status {\n  Ok {}\n  # Hurt\n  Damaged(amount) { x = amount }\n  else {}\n}",
+        );
+        let statement = parse_statement(&mut tokens).unwrap();
+
+        let arms = match statement.value {
+            StatementValue::Match { arms, .. } => arms,
+            value => panic!("Expected match, got {:?}", value),
+        };
+        let variants: Vec<Option<&str>> = arms
+            .iter()
+            .map(|a| a.variant.as_ref().map(|v| v.id.as_str()))
+            .collect();
+        assert_eq!(vec![Some("Ok"), Some("Damaged"), None], variants);
+        assert_eq!(vec!["Hurt".to_string()], arms[1].comments);
+        assert_eq!(
+            Some("amount"),
+            arms[1].binding.as_ref().map(|b| b.id.as_str())
+        );
+        assert_eq!(1, arms[1].body.len());
+        assert_eq!(Location::from((5, 1)), statement.end_location);
+
+        // `match` is still a valid local
+        let mut tokens = lex("match = 1");
+        assert!(matches!(
+            parse_statement(&mut tokens).unwrap().value,
+            StatementValue::Assign { .. }
+        ));
+    }
+
+    #[test]
+    fn parse_match_else_before_arm_returns_err() {
+        let mut tokens = lex("match s { else {} Ok {} }");
+        let expected = Err(Error::new(
+            "'else' must be the last arm of 'match'".to_string(),
+            (0, 18).into(),
+        ));
+        assert_eq!(expected, parse_statement(&mut tokens));
+    }
+}
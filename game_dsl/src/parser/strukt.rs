@@ -1,12 +1,16 @@
 use super::{
-    is_reserved_word, parse_listable_primitive, Listable, Primitive, PrimitiveType, Tokens,
-    STRUCT_ID,
+    parse_attributes, parse_listable_primitive, Attribute, ListType, Listable, Primitive,
+    PrimitiveType, Tokens, STRUCT_ID,
 };
 use crate::{error::Error, lexer::TokenValue, location::Location};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Struct {
     pub id: String,
+    pub comments: Vec<String>,
+    /// Comments after the last property, which stay at the end of the body.
+    pub trailing_comments: Vec<String>,
+    pub attributes: Vec<Attribute>,
     pub properties: StructProperties,
     pub start_location: Location,
     pub end_location: Location,
@@ -22,6 +26,9 @@ pub enum StructProperties {
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructProperty {
     pub identifier: String,
+    pub comments: Vec<String>,
+    /// A comment on the same line after the property. It isn't documentation.
+    pub trailing_comment: Option<String>,
     pub ty: Listable<Primitive>,
     pub start_location: Location,
     pub end_location: Location,
@@ -31,6 +38,8 @@ impl From<(&str, &str)> for StructProperty {
     fn from((id, ty): (&str, &str)) -> Self {
         Self {
             identifier: id.to_string(),
+            comments: vec![],
+            trailing_comment: None,
             ty: ty.into(),
             start_location: Location::SystemDefined,
             end_location: Location::SystemDefined,
@@ -42,6 +51,8 @@ impl From<(&str, &String)> for StructProperty {
     fn from((id, ty): (&str, &String)) -> Self {
         Self {
             identifier: id.to_string(),
+            comments: vec![],
+            trailing_comment: None,
             ty: ty.into(),
             start_location: Location::SystemDefined,
             end_location: Location::SystemDefined,
@@ -49,10 +60,56 @@ impl From<(&str, &String)> for StructProperty {
     }
 }
 
+impl From<(&str, &str, usize)> for StructProperty {
+    fn from((id, ty, max_size): (&str, &str, usize)) -> Self {
+        Self {
+            identifier: id.to_string(),
+            comments: vec![],
+            trailing_comment: None,
+            ty: Listable {
+                ty: ListType::List {
+                    ty: ty.into(),
+                    max_size,
+                },
+                start_location: Location::SystemDefined,
+                end_location: Location::SystemDefined,
+            },
+            start_location: Location::SystemDefined,
+            end_location: Location::SystemDefined,
+        }
+    }
+}
+
+impl From<(&str, PrimitiveType, usize)> for StructProperty {
+    fn from((id, ty, max_size): (&str, PrimitiveType, usize)) -> Self {
+        Self {
+            identifier: id.to_string(),
+            comments: vec![],
+            trailing_comment: None,
+            ty: Listable {
+                ty: ListType::List {
+                    ty: Primitive {
+                        primitive_type: ty,
+                        start_location: Location::SystemDefined,
+                        end_location: Location::SystemDefined,
+                    },
+                    max_size,
+                },
+                start_location: Location::SystemDefined,
+                end_location: Location::SystemDefined,
+            },
+            start_location: Location::SystemDefined,
+            end_location: Location::SystemDefined,
+        }
+    }
+}
+
 impl From<(&str, PrimitiveType)> for StructProperty {
     fn from((id, ty): (&str, PrimitiveType)) -> Self {
         Self {
             identifier: id.to_string(),
+            comments: vec![],
+            trailing_comment: None,
             ty: ty.into(),
             start_location: Location::SystemDefined,
             end_location: Location::SystemDefined,
@@ -61,11 +118,10 @@ impl From<(&str, PrimitiveType)> for StructProperty {
 }
 
 pub fn parse(tokens: &mut Tokens) -> Result<Struct, Error> {
-    let start_location;
     let end_location;
 
     let (struct_ty, token) = tokens.pop_identifier()?;
-    start_location = token.start_location.clone();
+    let start_location = token.start_location.clone();
 
     if struct_ty != STRUCT_ID {
         return Err(Error::new(
@@ -75,6 +131,8 @@ pub fn parse(tokens: &mut Tokens) -> Result<Struct, Error> {
     }
 
     let (id, _) = tokens.pop_identifier()?;
+    let attributes = parse_attributes(tokens)?;
+    let mut trailing_comments = vec![];
 
     let properties = {
         // Value struct
@@ -92,13 +150,29 @@ pub fn parse(tokens: &mut Tokens) -> Result<Struct, Error> {
         else if tokens.peek_expected(TokenValue::LCurlyBrace) {
             tokens.pop_expected(TokenValue::LCurlyBrace)?;
 
-            let mut properties = vec![];
+            let mut properties: Vec<StructProperty> = vec![];
+            let mut property_comments = vec![];
             while !tokens.is_empty() && !tokens.peek_expected(TokenValue::RCurlyBrace) {
+                // Comments document the property that follows them, except a comment on the same
+                // line as a property, which trails it
+                if tokens.peek_comment() {
+                    let (mut lines, token) = tokens.pop_comment()?;
+                    if let Some(p) = properties.last_mut() {
+                        if p.end_location.line() == token.start_location.line() {
+                            p.trailing_comment = Some(lines.remove(0));
+                        }
+                    }
+                    property_comments.extend(lines);
+                    continue;
+                }
+
                 let prop_type = parse_listable_primitive(tokens)?;
                 let (identifier, token) = tokens.pop_identifier()?;
 
                 let property = StructProperty {
                     identifier,
+                    comments: std::mem::take(&mut property_comments),
+                    trailing_comment: None,
                     start_location: prop_type.start_location.clone(),
                     end_location: token.end_location.clone(),
                     ty: prop_type,
@@ -109,6 +183,8 @@ pub fn parse(tokens: &mut Tokens) -> Result<Struct, Error> {
             let token = tokens.pop_expected(TokenValue::RCurlyBrace)?;
             end_location = token.end_location.clone();
 
+            trailing_comments = property_comments;
+
             if properties.is_empty() {
                 StructProperties::None
             } else {
@@ -125,6 +201,9 @@ pub fn parse(tokens: &mut Tokens) -> Result<Struct, Error> {
 
     Ok(Struct {
         id,
+        comments: vec![],
+        trailing_comments,
+        attributes,
         properties,
         start_location,
         end_location,
@@ -134,7 +213,7 @@ pub fn parse(tokens: &mut Tokens) -> Result<Struct, Error> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::{lex, ListType, PrimitiveType, RESERVED_WORDS};
+    use crate::parser::{lex, ListType, PrimitiveType};
 
     #[test]
     fn empty_struct() {
@@ -143,6 +222,9 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Struct {
             id: "Empty".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: StructProperties::None,
             start_location: (0, 0).into(),
             end_location: (0, 13).into(),
@@ -184,6 +266,9 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Struct {
             id: "Dollar".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: StructProperties::Value(Listable {
                 ty: ListType::Single(Primitive {
                     primitive_type: PrimitiveType::I32,
@@ -207,6 +292,9 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Struct {
             id: "Dollar".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: StructProperties::Value(Listable {
                 ty: ListType::List {
                     ty: Primitive {
@@ -256,6 +344,9 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Struct {
             id: "Dollar".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: StructProperties::None,
             start_location: (0, 0).into(),
             end_location: (0, 16).into(),
@@ -270,8 +361,13 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Struct {
             id: "Dollar".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: StructProperties::Multiple(vec![StructProperty {
                 identifier: "amount".to_string(),
+                comments: vec![],
+                trailing_comment: None,
                 ty: Listable {
                     ty: ListType::Single(Primitive {
                         primitive_type: PrimitiveType::I32,
@@ -298,8 +394,13 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Struct {
             id: "Dollar".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: StructProperties::Multiple(vec![StructProperty {
                 identifier: "amounts".to_string(),
+                comments: vec![],
+                trailing_comment: None,
                 ty: Listable {
                     ty: ListType::List {
                         ty: Primitive {
@@ -328,9 +429,14 @@ mod tests {
         let result = parse(&mut tokens);
         let expected = Ok(Struct {
             id: "Dollar".to_string(),
+            comments: vec![],
+            trailing_comments: vec![],
+            attributes: vec![],
             properties: StructProperties::Multiple(vec![
                 StructProperty {
                     identifier: "amount".to_string(),
+                    comments: vec![],
+                    trailing_comment: None,
                     ty: Listable {
                         ty: ListType::Single(Primitive {
                             primitive_type: PrimitiveType::I32,
@@ -345,6 +451,8 @@ mod tests {
                 },
                 StructProperty {
                     identifier: "amounts".to_string(),
+                    comments: vec![],
+                    trailing_comment: None,
                     ty: Listable {
                         ty: ListType::List {
                             ty: Primitive {
@@ -0,0 +1,369 @@
+use super::{
+    parse_references, parse_statement, Reference, Statement, StatementValue, Tokens, CREATE_ID,
+    EXTERNAL_SYSTEM_ID, SYSTEM_ID,
+};
+use crate::{error::Error, lexer::TokenValue, location::Location};
+
+pub const READ_ID: &str = "read";
+pub const WRITE_ID: &str = "write";
+
+// The files implementing an external system for each target
+pub const TARGET_C_IMPL_ID: &str = "target_c_impl";
+pub const TARGET_CPP_IMPL_ID: &str = "target_cpp_impl";
+pub const TARGET_CSHARP_IMPL_ID: &str = "target_csharp_impl";
+pub const TARGET_JS_IMPL_ID: &str = "target_js_impl";
+pub const TARGET_IMPL_IDS: [&str; 4] = [
+    TARGET_C_IMPL_ID,
+    TARGET_CPP_IMPL_ID,
+    TARGET_CSHARP_IMPL_ID,
+    TARGET_JS_IMPL_ID,
+];
+
+/// A system runs over the components it declares. Components that are written can also be read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct System {
+    pub id: String,
+    pub comments: Vec<String>,
+    pub system_type: SystemType,
+    pub reads: Vec<Reference>,
+    pub writes: Vec<Reference>,
+    /// Comments above the `read` and `write` sections.
+    pub read_comments: Vec<String>,
+    pub write_comments: Vec<String>,
+    pub body: Vec<Statement>,
+    /// The host files implementing an external system.
+    pub impls: Vec<TargetImpl>,
+    pub start_location: Location,
+    pub end_location: Location,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SystemType {
+    System,
+    /// Implemented by the host rather than by a body, e.g. `external_system Graphics { .. }`.
+    ExternalSystem,
+}
+
+/// The file implementing an external system for a target, e.g. `target_c_impl "graphics.c"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetImpl {
+    /// The section declaring the file, e.g. `target_c_impl`.
+    pub target: String,
+    pub path: String,
+    pub comments: Vec<String>,
+    pub start_location: Location,
+    pub end_location: Location,
+}
+
+pub fn parse(tokens: &mut Tokens) -> Result<System, Error> {
+    let (keyword, token) = tokens.pop_identifier()?;
+    let start_location = token.start_location.clone();
+    let system_type = match keyword.as_str() {
+        SYSTEM_ID => SystemType::System,
+        EXTERNAL_SYSTEM_ID => SystemType::ExternalSystem,
+        _ => {
+            return Err(Error::new(
+                format!(
+                    "Expected '{SYSTEM_ID}' or '{EXTERNAL_SYSTEM_ID}', got '{}'",
+                    keyword
+                ),
+                start_location,
+            ))
+        }
+    };
+
+    let (id, _) = tokens.pop_identifier()?;
+    tokens.pop_expected(TokenValue::LCurlyBrace)?;
+
+    let mut reads = None;
+    let mut writes = None;
+    let mut read_comments = vec![];
+    let mut write_comments = vec![];
+    let mut body = vec![];
+    let mut impls: Vec<TargetImpl> = vec![];
+    // Comments belong to the section or statement that follows them
+    let mut pending = vec![];
+    while !tokens.is_empty() && !tokens.peek_expected(TokenValue::RCurlyBrace) {
+        if tokens.peek_comment() {
+            pending.push(tokens.pop_comment()?);
+            continue;
+        }
+
+        // Target implementations are an identifier followed by a path
+        if system_type == SystemType::ExternalSystem
+            && matches!(
+                tokens.peek_nth(1).map(|t| &t.value),
+                Some(TokenValue::String(_))
+            )
+        {
+            let mut target_impl = parse_target_impl(tokens)?;
+            for (mut lines, _) in pending.drain(..) {
+                target_impl.comments.append(&mut lines);
+            }
+            if impls.iter().any(|i| i.target == target_impl.target) {
+                return Err(Error::new(
+                    format!("Duplicate '{}' in system '{}'", target_impl.target, id),
+                    target_impl.start_location,
+                ));
+            }
+            impls.push(target_impl);
+            continue;
+        }
+
+        // Sections are an identifier followed by a list, anything else is a statement
+        if !tokens.peek_expected_nth(1, TokenValue::LSquareBracket)
+            || tokens.peek_identifier(CREATE_ID)
+        {
+            if system_type == SystemType::ExternalSystem {
+                let location = tokens
+                    .peek_nth(0)
+                    .map(|t| t.start_location.clone())
+                    .unwrap_or_else(|| tokens.last_location());
+                return Err(Error::new(
+                    format!(
+                        "External system '{}' is implemented by the host and can't have statements",
+                        id
+                    ),
+                    location,
+                ));
+            }
+
+            for (lines, token) in pending.drain(..) {
+                body.push(Statement {
+                    value: StatementValue::Comment(lines),
+                    start_location: token.start_location,
+                    end_location: token.end_location,
+                });
+            }
+            body.push(parse_statement(tokens)?);
+            continue;
+        }
+
+        let (section, token) = tokens.pop_identifier()?;
+        let (references, comments) = match section.as_str() {
+            READ_ID => (&mut reads, &mut read_comments),
+            WRITE_ID => (&mut writes, &mut write_comments),
+            other => {
+                return Err(Error::new(
+                    format!("Expected '{READ_ID}' or '{WRITE_ID}', got '{}'", other),
+                    token.start_location,
+                ))
+            }
+        };
+
+        if !body.is_empty() {
+            return Err(Error::new(
+                format!(
+                    "'{}' must come before the statements of system '{}'",
+                    section, id
+                ),
+                token.start_location,
+            ));
+        }
+
+        if references.is_some() {
+            return Err(Error::new(
+                format!("Duplicate '{}' in system '{}'", section, id),
+                token.start_location,
+            ));
+        }
+
+        let (list, _) = parse_references(tokens)?;
+        *references = Some(list);
+        for (mut lines, _) in pending.drain(..) {
+            comments.append(&mut lines);
+        }
+    }
+
+    // Comments after everything else stay at the end of the body
+    for (lines, token) in pending {
+        body.push(Statement {
+            value: StatementValue::Comment(lines),
+            start_location: token.start_location,
+            end_location: token.end_location,
+        });
+    }
+
+    let token = tokens.pop_expected(TokenValue::RCurlyBrace)?;
+
+    Ok(System {
+        id,
+        comments: vec![],
+        system_type,
+        reads: reads.unwrap_or_default(),
+        writes: writes.unwrap_or_default(),
+        read_comments,
+        write_comments,
+        body,
+        impls,
+        start_location,
+        end_location: token.end_location,
+    })
+}
+
+fn parse_target_impl(tokens: &mut Tokens) -> Result<TargetImpl, Error> {
+    let (target, token) = tokens.pop_identifier()?;
+    let start_location = token.start_location;
+    if !TARGET_IMPL_IDS.contains(&target.as_str()) {
+        return Err(Error::new(
+            format!(
+                "Expected a target such as '{}', got '{}'",
+                TARGET_C_IMPL_ID, target
+            ),
+            start_location,
+        ));
+    }
+
+    let token = tokens.pop().ok_or_else(|| {
+        Error::new(
+            "Expected a path, got nothing!".to_string(),
+            tokens.last_location(),
+        )
+    })?;
+    let path = match token.value {
+        TokenValue::String(path) => path,
+        value => {
+            return Err(Error::new(
+                format!("Expected a path, got {}", value.display_name()),
+                token.start_location,
+            ))
+        }
+    };
+
+    Ok(TargetImpl {
+        target,
+        path,
+        comments: vec![],
+        start_location,
+        end_location: token.end_location,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::lex;
+
+    fn ids(references: &[Reference]) -> Vec<&str> {
+        references.iter().map(|r| r.id.as_str()).collect()
+    }
+
+    #[test]
+    fn system_with_read_and_write() {
+        let mut tokens = lex(
+            "system Move {\n    # Moves things.\n    write [Position]\n    read [Velocity Mass]\n}",
+        );
+        let system = parse(&mut tokens).unwrap();
+
+        assert_eq!("Move", system.id);
+        assert_eq!(vec!["Moves things.".to_string()], system.write_comments);
+        assert_eq!(vec!["Velocity", "Mass"], ids(&system.reads));
+        assert_eq!(vec!["Position"], ids(&system.writes));
+        assert_eq!(Location::from((4, 1)), system.end_location);
+    }
+
+    #[test]
+    fn empty_system() {
+        let mut tokens = lex("system Idle {}");
+        let system = parse(&mut tokens).unwrap();
+
+        assert!(system.reads.is_empty());
+        assert!(system.writes.is_empty());
+    }
+
+    #[test]
+    fn system_with_body() {
+        let mut tokens = lex("system Move {\n    write [Position]\n\n    # Every entity.\n    for e with Position {\n        e.Position.x += 1\n    }\n}");
+        let system = parse(&mut tokens).unwrap();
+
+        assert!(system.comments.is_empty());
+        assert_eq!(2, system.body.len());
+        assert_eq!(
+            StatementValue::Comment(vec!["Every entity.".to_string()]),
+            system.body[0].value
+        );
+        assert!(matches!(system.body[1].value, StatementValue::Query { .. }));
+    }
+
+    #[test]
+    fn section_after_body_returns_err() {
+        let mut tokens = lex("system Move { x = 1 read [A] }");
+        let expected = Err(Error::new(
+            "'read' must come before the statements of system 'Move'".to_string(),
+            (0, 20).into(),
+        ));
+        assert_eq!(expected, parse(&mut tokens));
+    }
+
+    #[test]
+    fn duplicate_section_returns_err() {
+        let mut tokens = lex("system Move { read [A] read [B] }");
+        let expected = Err(Error::new(
+            "Duplicate 'read' in system 'Move'".to_string(),
+            (0, 23).into(),
+        ));
+        assert_eq!(expected, parse(&mut tokens));
+    }
+
+    #[test]
+    fn unknown_section_returns_err() {
+        let mut tokens = lex("system Move { reads [A] }");
+        let expected = Err(Error::new(
+            "Expected 'read' or 'write', got 'reads'".to_string(),
+            (0, 14).into(),
+        ));
+        assert_eq!(expected, parse(&mut tokens));
+    }
+
+    #[test]
+    fn external_system_with_impls() {
+        let mut tokens = lex("external_system Render {
+    write [Sprite]
+
+    target_c_impl \"graphics.c\"
+    target_js_impl \"graphics.js\"
+}");
+        let system = parse(&mut tokens).unwrap();
+
+        assert_eq!(SystemType::ExternalSystem, system.system_type);
+        assert_eq!(vec!["Sprite"], ids(&system.writes));
+        let impls: Vec<(&str, &str)> = system
+            .impls
+            .iter()
+            .map(|i| (i.target.as_str(), i.path.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                (TARGET_C_IMPL_ID, "graphics.c"),
+                (TARGET_JS_IMPL_ID, "graphics.js")
+            ],
+            impls
+        );
+    }
+
+    #[test]
+    fn external_system_misuse_returns_err() {
+        let mut tokens = lex("external_system Render { x = 1 }");
+        let expected = Err(Error::new(
+            "External system 'Render' is implemented by the host and can't have statements"
+                .to_string(),
+            (0, 25).into(),
+        ));
+        assert_eq!(expected, parse(&mut tokens));
+
+        let mut tokens =
+            lex("external_system Render { target_c_impl \"a.c\" target_c_impl \"b.c\" }");
+        let expected = Err(Error::new(
+            "Duplicate 'target_c_impl' in system 'Render'".to_string(),
+            (0, 45).into(),
+        ));
+        assert_eq!(expected, parse(&mut tokens));
+
+        let mut tokens = lex("external_system Render { target_rust_impl \"a.rs\" }");
+        let expected = Err(Error::new(
+            "Expected a target such as 'target_c_impl', got 'target_rust_impl'".to_string(),
+            (0, 25).into(),
+        ));
+        assert_eq!(expected, parse(&mut tokens));
+    }
+}
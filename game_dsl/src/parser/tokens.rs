@@ -8,12 +8,15 @@ use crate::{
 pub struct Tokens {
     tokens: Vec<Token>,
     last_location: Location,
+    /// The number of curly braces popped that haven't been closed yet.
+    depth: usize,
 }
 impl Tokens {
     pub fn new(tokens: Vec<Token>, start_location: Location) -> Self {
         Self {
             tokens,
             last_location: start_location,
+            depth: 0,
         }
     }
 
@@ -21,6 +24,15 @@ impl Tokens {
         self.last_location.clone()
     }
 
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Treats every open curly brace as closed, e.g. when a declaration is missing its closing brace.
+    pub fn reset_depth(&mut self) {
+        self.depth = 0;
+    }
+
     pub fn is_empty(&self) -> bool {
         self.tokens.is_empty()
     }
@@ -31,6 +43,11 @@ impl Tokens {
         } else {
             let token = self.tokens.remove(0);
             self.last_location = token.end_location.clone();
+            match token.value {
+                TokenValue::LCurlyBrace => self.depth += 1,
+                TokenValue::RCurlyBrace => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
             Some(token)
         }
     }
@@ -65,7 +82,7 @@ impl Tokens {
 
     pub fn peek_expected_nth(&self, n: usize, expected: TokenValue) -> bool {
         match self.peek_nth(n) {
-            Some(t) => &t.value == &expected,
+            Some(t) => t.value == expected,
             None => false,
         }
     }
@@ -93,10 +110,7 @@ impl Tokens {
     }
 
     pub fn peek_comment(&self) -> bool {
-        match self.peek() {
-            Some(TokenValue::Comment(_)) => true,
-            _ => false,
-        }
+        matches!(self.peek(), Some(TokenValue::Comment(_)))
     }
 
     pub fn pop_comment(&mut self) -> Result<(Vec<String>, Token), Error> {
@@ -127,7 +141,7 @@ impl Tokens {
         }
     }
 
-    pub fn pop_number(&mut self) -> Result<(f64, Token), Error> {
+    pub fn pop_number(&mut self) -> Result<(String, Token), Error> {
         match self.pop() {
             Some(Token {
                 value: TokenValue::Number(n),
@@ -156,6 +170,11 @@ impl Tokens {
     }
 
     pub fn insert_head(&mut self, token: Token) {
+        match token.value {
+            TokenValue::LCurlyBrace => self.depth = self.depth.saturating_sub(1),
+            TokenValue::RCurlyBrace => self.depth += 1,
+            _ => {}
+        }
         self.tokens.insert(0, token);
     }
 
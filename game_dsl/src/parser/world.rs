@@ -0,0 +1,110 @@
+use super::{parse_attributes, parse_references, Attribute, Reference, Tokens, WORLD_ID};
+use crate::{error::Error, lexer::TokenValue, location::Location};
+
+/// The world dispatches its stages in order. Systems within a stage may run in parallel.
+/// With the `auto` attribute the stages only give the order of systems, and the compiler groups them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct World {
+    pub comments: Vec<String>,
+    pub attributes: Vec<Attribute>,
+    pub stages: Vec<Stage>,
+    pub start_location: Location,
+    pub end_location: Location,
+}
+
+/// A group of systems that run at the same time, e.g. `[Gravity Controllables]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stage {
+    pub comments: Vec<String>,
+    pub systems: Vec<Reference>,
+    pub start_location: Location,
+    pub end_location: Location,
+}
+
+pub fn parse(tokens: &mut Tokens) -> Result<World, Error> {
+    let (keyword, token) = tokens.pop_identifier()?;
+    let start_location = token.start_location.clone();
+    if keyword != WORLD_ID {
+        return Err(Error::new(
+            format!("Expected '{WORLD_ID}', got '{}'", keyword),
+            start_location,
+        ));
+    }
+
+    let attributes = parse_attributes(tokens)?;
+    tokens.pop_expected(TokenValue::LCurlyBrace)?;
+
+    let mut comments = vec![];
+    let mut stages = vec![];
+    while !tokens.is_empty() && !tokens.peek_expected(TokenValue::RCurlyBrace) {
+        // Comments document the stage that follows them
+        if tokens.peek_comment() {
+            let (mut lines, _) = tokens.pop_comment()?;
+            comments.append(&mut lines);
+            continue;
+        }
+
+        let stage_start = tokens
+            .peek_nth(0)
+            .map(|t| t.start_location.clone())
+            .unwrap_or_else(|| tokens.last_location());
+        let (systems, end_location) = parse_references(tokens)?;
+        stages.push(Stage {
+            comments: std::mem::take(&mut comments),
+            systems,
+            start_location: stage_start,
+            end_location,
+        });
+    }
+
+    let token = tokens.pop_expected(TokenValue::RCurlyBrace)?;
+
+    Ok(World {
+        // Comments at the end of the body document the world
+        comments,
+        attributes,
+        stages,
+        start_location,
+        end_location: token.end_location,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::lex;
+
+    #[test]
+    fn world_with_stages() {
+        let mut tokens = lex("world {\n    # Physics first.\n    [Gravity Move]\n    [Render]\n}");
+        let world = parse(&mut tokens).unwrap();
+
+        let stages: Vec<Vec<&str>> = world
+            .stages
+            .iter()
+            .map(|s| s.systems.iter().map(|r| r.id.as_str()).collect())
+            .collect();
+        assert_eq!(vec![vec!["Gravity", "Move"], vec!["Render"]], stages);
+        assert_eq!(vec!["Physics first.".to_string()], world.stages[0].comments);
+        assert_eq!(Location::from((2, 4)), world.stages[0].start_location);
+    }
+
+    #[test]
+    fn auto_world() {
+        let mut tokens = lex("world auto { [Move Render] }");
+        let world = parse(&mut tokens).unwrap();
+
+        assert_eq!(vec![Attribute::Auto], world.attributes);
+        assert_eq!(1, world.stages.len());
+    }
+
+    #[test]
+    fn world_stage_without_brackets_returns_err() {
+        let mut tokens = lex("world { Gravity }");
+        let expected = Err(Error::new(
+            "Expected [, got identifier: Gravity".to_string(),
+            (0, 8).into(),
+        ));
+        assert_eq!(expected, parse(&mut tokens));
+    }
+}
@@ -1,15 +1,21 @@
-use crate::parser::{Ast, Component, Struct};
+use crate::parser::{Ast, Component, Enum, Struct, System, World};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct UncheckedEnv {
     pub components: Vec<Component>,
     pub structs: Vec<Struct>,
+    pub enums: Vec<Enum>,
+    pub systems: Vec<System>,
+    pub worlds: Vec<World>,
 }
 
 pub fn build(asts: Vec<Ast>) -> UncheckedEnv {
     let mut env = UncheckedEnv {
         components: vec![],
         structs: vec![],
+        enums: vec![],
+        systems: vec![],
+        worlds: vec![],
     };
 
     for ast in asts {
@@ -17,12 +23,21 @@ pub fn build(asts: Vec<Ast>) -> UncheckedEnv {
             Ast::Component(c) => {
                 env.components.push(c);
             }
-            Ast::Comment(c) => {
+            Ast::Comment(_) => {
                 // skip for now
             }
             Ast::Struct(s) => {
                 env.structs.push(s);
             }
+            Ast::Enum(e) => {
+                env.enums.push(e);
+            }
+            Ast::System(s) => {
+                env.systems.push(s);
+            }
+            Ast::World(w) => {
+                env.worlds.push(w);
+            }
         }
     }
 